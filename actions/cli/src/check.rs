@@ -0,0 +1,52 @@
+use argparse::{ArgumentParser, List, StoreTrue};
+use core_lang::checker::{Diagnostic, Severity, check_with};
+use core_lang::object::Arity;
+use core_lang::syntax::Source;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// `core check [--deny-warnings] [FILES...]`: reports problems in config
+/// files, or stdin when no files are given. Returns the exit code.
+pub fn check(args: Vec<String>) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut deny_warnings: bool = false;
+    let mut files: Vec<PathBuf> = Vec::new();
+    {
+        let mut parser = ArgumentParser::new();
+        parser.set_description("Check .core files for mistakes");
+        parser.refer(&mut deny_warnings).add_option(
+            &["--deny-warnings"],
+            StoreTrue,
+            "Fail on warnings too",
+        );
+        parser.refer(&mut files).add_argument("FILES", List, "");
+        if let Err(code) = parser.parse(args, &mut std::io::stdout(), &mut std::io::stderr()) {
+            return Ok(code);
+        }
+    }
+
+    let mut sources: Vec<Source> = Vec::new();
+    if files.is_empty() {
+        let mut text: String = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        sources.push(Source::new("<stdin>", &text));
+    }
+    for file in files {
+        let text: String = std::fs::read_to_string(&file)?;
+        sources.push(Source::new(&file.display().to_string(), &text));
+    }
+
+    // Config files may call what the editor defines
+    let natives: Vec<(String, Arity)> = core_editor::script::natives();
+    let mut code: i32 = 0;
+    for source in sources {
+        let diagnostics: Vec<Diagnostic> = check_with(&source, &natives);
+        for diagnostic in diagnostics {
+            eprintln!("{}", diagnostic.render(&source));
+            if deny_warnings || diagnostic.severity() == Severity::Error {
+                code = 1;
+            }
+        }
+    }
+
+    Ok(code)
+}
@@ -0,0 +1,72 @@
+use argparse::{ArgumentParser, List, StoreTrue};
+use core_lang::formatter::{FormatOptions, format};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// `core fmt [--check] [FILES...]`: formats files in place, or stdin to stdout
+/// when no files are given. Returns the exit code.
+pub fn fmt(args: Vec<String>) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut check: bool = false;
+    let mut max_width: usize = FormatOptions::default().max_width;
+    let mut files: Vec<PathBuf> = Vec::new();
+    {
+        let mut parser = ArgumentParser::new();
+        parser.set_description("Format .core files");
+        parser.refer(&mut check).add_option(
+            &["--check"],
+            StoreTrue,
+            "Only report files which are not formatted",
+        );
+        parser.refer(&mut max_width).add_option(
+            &["--max-width"],
+            argparse::Store,
+            "Maximum line width",
+        );
+        parser.refer(&mut files).add_argument("FILES", List, "");
+        if let Err(code) = parser.parse(args, &mut std::io::stdout(), &mut std::io::stderr()) {
+            return Ok(code);
+        }
+    }
+
+    let options = FormatOptions {
+        max_width,
+        ..FormatOptions::default()
+    };
+
+    if files.is_empty() {
+        let mut text: String = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        let formatted: String = format(&text, &options)?;
+        if check {
+            return Ok((formatted != text) as i32);
+        }
+        std::io::stdout().write_all(formatted.as_bytes())?;
+        return Ok(0);
+    }
+
+    let mut code: i32 = 0;
+    for file in files {
+        let text: String = std::fs::read_to_string(&file)?;
+        let formatted: String = match format(&text, &options) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                code = 1;
+                continue;
+            }
+        };
+
+        if formatted == text {
+            continue;
+        }
+        match check {
+            true => {
+                println!("{}", file.display());
+                code = 1;
+            }
+            false => std::fs::write(&file, formatted)?,
+        }
+    }
+
+    Ok(code)
+}
@@ -1,25 +1,76 @@
-use argparse::{ArgumentParser, Store};
+use argparse::{ArgumentParser, List};
 use core_lang::ast::AST;
 use core_lang::ast::Boolean;
 use core_lang::ast::BuiltinWord;
 use core_lang::ast::Constant;
 use core_lang::ast::Value;
+use core_lang::syntax::{Source, Syntax};
+use core_lang::{evaluator, parser};
 use std::path::PathBuf;
 
-pub fn cli(ast: &AST) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let constants: Vec<Constant> = ast.0.clone();
-    let cli_options: CLIOption = judge_cli_option(constants)?;
+mod check;
+mod fmt;
+mod test;
 
-    let mut path: PathBuf = PathBuf::new();
+/// Runs a subcommand such as `core fmt`, `core check` or `core lsp` instead of the editor, if `args` name
+/// one. Returns the exit code.
+pub fn subcommand(args: &[String]) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+    let Some(name) = args.get(1) else {
+        return Ok(None);
+    };
+    // The rest is parsed as if `core <name>` were the program
+    let mut rest: Vec<String> = vec![format!("{} {}", args[0], name)];
+    rest.extend(args[2..].iter().cloned());
+
+    match name.as_str() {
+        "check" => Ok(Some(check::check(rest)?)),
+        "fmt" => Ok(Some(fmt::fmt(rest)?)),
+        "lsp" => Ok(Some(core_lsp::run(
+            std::io::stdin().lock(),
+            std::io::stdout().lock(),
+        )?)),
+        "test" => Ok(Some(test::test(rest)?)),
+        _ => Ok(None),
+    }
+}
+
+/// The files to open, from the command line if the `cli` definition of
+/// `init.core` enables it.
+pub fn cli(config: &Source) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let ast: AST = options(config)?;
+    let cli_options: CLIOption = judge_cli_option(ast.0)?;
+
+    let mut paths: Vec<PathBuf> = Vec::new();
 
     if cli_options.enable {
         let mut parser = ArgumentParser::new();
         parser.set_description(env!("CARGO_PKG_DESCRIPTION"));
-        parser.refer(&mut path).add_argument("FILENAME", Store, "");
+        parser.refer(&mut paths).add_argument("FILES", List, "");
         parser.parse_args_or_exit();
     }
 
-    Ok(path)
+    Ok(paths)
+}
+
+/// Reads the `cli` definitions of `config`. Everything else is left to the
+/// editor, which also reports syntax errors.
+fn options(config: &Source) -> Result<AST, Box<dyn std::error::Error>> {
+    let Ok(forms) = parser::parse_spanned(&config.text) else {
+        return Ok(AST(Vec::new()));
+    };
+    let definitions: Vec<Syntax> = forms
+        .into_iter()
+        .filter(|v| match v.as_sexpr() {
+            Some([head, name, ..]) => {
+                head.as_word().as_deref() == Some("define")
+                    && name.as_word().as_deref() == Some("cli")
+            }
+            _ => false,
+        })
+        .collect();
+
+    evaluator::eval_syntax(definitions)
+        .map_err(|e| format!("CONFIG_LOAD_ERROR: {}: {}", config.name, e).into())
 }
 
 fn judge_cli_option(constants: Vec<Constant>) -> Result<CLIOption, Box<dyn std::error::Error>> {
@@ -28,29 +79,30 @@ fn judge_cli_option(constants: Vec<Constant>) -> Result<CLIOption, Box<dyn std::
     for constant in constants {
         if constant.name() == Value::BuiltinWord(BuiltinWord::Cli) {
             let (_args, expr): (Vec<Value>, Vec<Value>) = match constant.value() {
-                Value::SExpression(values) => match &values[0] {
-                    Value::Lambda((args, expr)) => (args.clone(), expr.clone()),
-                    _ => panic!(),
+                Value::SExpression(values) => match values.first() {
+                    Some(Value::Lambda((args, expr))) => (args.clone(), expr.clone()),
+                    _ => return Err("CONFIG_LOAD_ERROR: cli must be a lambda".into()),
                 },
-                _ => panic!(),
+                _ => return Err("CONFIG_LOAD_ERROR: cli must be a lambda".into()),
             };
 
             for value in expr {
                 let function = value.car()?;
 
                 match function {
-                    Value::Word(v) => match v.as_str() {
-                        "enable" => {
+                    Value::Word(v) => {
+                        if v == "enable" {
                             let mut v: Vec<Value> = value.cdr()?;
-                            result.enable = read_boolean(&v.pop().unwrap())?;
+                            let value: Value =
+                                v.pop().ok_or("CONFIG_LOAD_ERROR: enable expects a value")?;
+                            result.enable = read_boolean(&value)?;
 
                             if v.pop().is_some() {
-                                panic!();
+                                return Err("CONFIG_LOAD_ERROR: enable expects 1 value".into());
                             }
                         }
-                        _ => (),
-                    },
-                    _ => panic!(),
+                    }
+                    _ => return Err("CONFIG_LOAD_ERROR: expected an option name".into()),
                 }
             }
         }
@@ -65,7 +117,7 @@ fn read_boolean(value: &Value) -> Result<bool, Box<dyn std::error::Error>> {
             Boolean::T => Ok(true),
             Boolean::Nil => Ok(false),
         },
-        _ => panic!(),
+        _ => Err("CONFIG_LOAD_ERROR: expected t or nil".into()),
     }
 }
 
@@ -0,0 +1,78 @@
+use argparse::{ArgumentParser, Store};
+use core_lang::debugger::TraceEvent;
+use core_lang::interpreter::Interpreter;
+use core_lang::syntax::Source;
+use core_lang::testing::{TestResult, discover, run_source_in};
+use std::path::PathBuf;
+
+/// `core test [DIR]`: runs the tests of every `*.core` file under `DIR`.
+/// Returns the exit code.
+pub fn test(args: Vec<String>) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut directory: PathBuf = PathBuf::from(".");
+    {
+        let mut parser = ArgumentParser::new();
+        parser.set_description("Run the tests of .core files");
+        parser.refer(&mut directory).add_argument(
+            "DIR",
+            Store,
+            "Directory to search for *.core files",
+        );
+        if let Err(code) = parser.parse(args, &mut std::io::stdout(), &mut std::io::stderr()) {
+            return Ok(code);
+        }
+    }
+
+    let mut passed: usize = 0;
+    let mut failures: Vec<String> = Vec::new();
+    for path in discover(&directory)? {
+        let name: String = path.display().to_string();
+        let source: Source = Source::new(&name, &std::fs::read_to_string(&path)?);
+
+        let mut interpreter: Interpreter = Interpreter::new();
+        let results = run_source_in(&mut interpreter, source.clone());
+        // What `trace` collected goes to stderr
+        if let Some(debugger) = interpreter.debugger_mut() {
+            let events: Vec<TraceEvent> = debugger.take_trace();
+            events.iter().for_each(|v| eprintln!("{}", v));
+        }
+        let results: Vec<TestResult> = match results {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{} ... FAILED to load", name);
+                failures.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        println!("running {} tests from {}", results.len(), name);
+        for result in results {
+            let Some(failure) = result.failure else {
+                println!("test {} ... ok", result.name);
+                passed += 1;
+                continue;
+            };
+
+            println!("test {} ... FAILED", result.name);
+            let (line, column) = source.line_col(failure.span.start);
+            failures.push(format!(
+                "{}:{}:{}: {}: {}",
+                name, line, column, result.name, failure.message
+            ));
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for failure in &failures {
+            println!("    {}", failure);
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        passed,
+        failures.len()
+    );
+
+    Ok(if failures.is_empty() { 0 } else { 1 })
+}
@@ -0,0 +1,333 @@
+//! Where the screen is drawn and input comes from: a real terminal, or an
+//! in-memory screen for tests.
+
+use crate::grid::{Cell, Color, Grid, Style};
+use crate::key::{Key, KeyCode};
+use crossterm::{cursor, event, style, terminal};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+    Resize(u16, u16),
+}
+
+pub trait Backend {
+    fn size(&self) -> Result<(u16, u16), Box<dyn std::error::Error>>;
+
+    /// Blanks the whole screen.
+    fn clear(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Draws cells at the given columns and lines.
+    fn draw(&mut self, cells: &[(u16, u16, &Cell)]) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Shows the cursor at a column and line, or hides it.
+    fn set_cursor(&mut self, cursor: Option<(u16, u16)>) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Waits for the next event, for at most `timeout` if given. `None` means
+    /// the timeout passed, or without a timeout that input has ended.
+    fn poll(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Event>, Box<dyn std::error::Error>>;
+}
+
+/// The terminal the editor runs in. It is switched to the alternate screen
+/// and raw mode while this exists.
+pub struct CrosstermBackend {
+    out: std::io::Stdout,
+}
+
+impl CrosstermBackend {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut out: std::io::Stdout = std::io::stdout();
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(out, terminal::EnterAlternateScreen)?;
+
+        Ok(CrosstermBackend { out })
+    }
+}
+
+impl Drop for CrosstermBackend {
+    fn drop(&mut self) {
+        // Nothing can be done about a terminal which cannot be restored
+        let _ = crossterm::execute!(
+            self.out,
+            style::ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn size(&self) -> Result<(u16, u16), Box<dyn std::error::Error>> {
+        Ok(terminal::size()?)
+    }
+
+    fn clear(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        crossterm::queue!(
+            self.out,
+            style::SetAttribute(style::Attribute::Reset),
+            style::ResetColor,
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+
+        Ok(())
+    }
+
+    fn draw(&mut self, cells: &[(u16, u16, &Cell)]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut next: Option<(u16, u16)> = None;
+        let mut current: Option<Style> = None;
+        for (x, y, cell) in cells {
+            // The second half of a wide character is drawn with the first
+            if cell.symbol.is_empty() {
+                continue;
+            }
+            if next != Some((*x, *y)) {
+                crossterm::queue!(self.out, cursor::MoveTo(*x, *y))?;
+            }
+            if current != Some(cell.style) {
+                set_style(&mut self.out, cell.style)?;
+                current = Some(cell.style);
+            }
+            crossterm::queue!(self.out, style::Print(&cell.symbol))?;
+            let width: u16 = unicode_width::UnicodeWidthStr::width(cell.symbol.as_str()) as u16;
+            next = Some((x + width.max(1), *y));
+        }
+
+        Ok(())
+    }
+
+    fn set_cursor(
+        &mut self,
+        position: Option<(u16, u16)>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match position {
+            Some((x, y)) => crossterm::queue!(self.out, cursor::MoveTo(x, y), cursor::Show)?,
+            None => crossterm::queue!(self.out, cursor::Hide)?,
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(self.out.flush()?)
+    }
+
+    fn poll(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Event>, Box<dyn std::error::Error>> {
+        loop {
+            if let Some(timeout) = timeout {
+                if !event::poll(timeout)? {
+                    return Ok(None);
+                }
+            }
+            // Key releases and mouse events are read and dropped
+            match event::read()? {
+                event::Event::Key(v) if v.kind != event::KeyEventKind::Release => {
+                    if let Some(key) = key(v) {
+                        return Ok(Some(Event::Key(key)));
+                    }
+                }
+                event::Event::Resize(width, height) => {
+                    return Ok(Some(Event::Resize(width, height)));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn set_style(out: &mut std::io::Stdout, value: Style) -> Result<(), Box<dyn std::error::Error>> {
+    use style::{Attribute, SetAttribute, SetBackgroundColor, SetForegroundColor};
+
+    crossterm::queue!(out, SetAttribute(Attribute::Reset))?;
+    if value.bold {
+        crossterm::queue!(out, SetAttribute(Attribute::Bold))?;
+    }
+    if value.underline {
+        crossterm::queue!(out, SetAttribute(Attribute::Underlined))?;
+    }
+    if value.reverse {
+        crossterm::queue!(out, SetAttribute(Attribute::Reverse))?;
+    }
+    crossterm::queue!(
+        out,
+        SetForegroundColor(value.fg.map_or(style::Color::Reset, color)),
+        SetBackgroundColor(value.bg.map_or(style::Color::Reset, color))
+    )?;
+
+    Ok(())
+}
+
+fn color(value: Color) -> style::Color {
+    match value {
+        Color::Black => style::Color::Black,
+        Color::Red => style::Color::DarkRed,
+        Color::Green => style::Color::DarkGreen,
+        Color::Yellow => style::Color::DarkYellow,
+        Color::Blue => style::Color::DarkBlue,
+        Color::Magenta => style::Color::DarkMagenta,
+        Color::Cyan => style::Color::DarkCyan,
+        Color::White => style::Color::Grey,
+        Color::Grey => style::Color::DarkGrey,
+    }
+}
+
+fn key(value: event::KeyEvent) -> Option<Key> {
+    use event::KeyCode as Code;
+
+    let code: KeyCode = match value.code {
+        Code::Char(v) => KeyCode::Char(v),
+        Code::Enter => KeyCode::Enter,
+        Code::Tab | Code::BackTab => KeyCode::Tab,
+        Code::Backspace => KeyCode::Backspace,
+        Code::Delete => KeyCode::Delete,
+        Code::Insert => KeyCode::Insert,
+        Code::Esc => KeyCode::Esc,
+        Code::Left => KeyCode::Left,
+        Code::Right => KeyCode::Right,
+        Code::Up => KeyCode::Up,
+        Code::Down => KeyCode::Down,
+        Code::Home => KeyCode::Home,
+        Code::End => KeyCode::End,
+        Code::PageUp => KeyCode::PageUp,
+        Code::PageDown => KeyCode::PageDown,
+        Code::F(n) => KeyCode::F(n),
+        _ => return None,
+    };
+    let modifiers: event::KeyModifiers = value.modifiers;
+
+    Some(Key {
+        code,
+        ctrl: modifiers.contains(event::KeyModifiers::CONTROL),
+        alt: modifiers.contains(event::KeyModifiers::ALT),
+        shift: !matches!(code, KeyCode::Char(_))
+            && (value.code == Code::BackTab || modifiers.contains(event::KeyModifiers::SHIFT)),
+    })
+}
+
+/// A screen in memory, fed with scripted events, so that the editor can run
+/// in tests without a terminal.
+pub struct HeadlessBackend {
+    screen: Grid,
+    cursor: Option<(u16, u16)>,
+    /// `None` is a pause long enough for any timeout to pass.
+    events: VecDeque<Option<Event>>,
+    drawn: usize,
+}
+
+impl HeadlessBackend {
+    pub fn new(width: u16, height: u16) -> Self {
+        HeadlessBackend {
+            screen: Grid::new(width, height),
+            cursor: None,
+            events: VecDeque::new(),
+            drawn: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.events.push_back(Some(event));
+    }
+
+    /// Waits without input, so that a poll with a timeout times out.
+    pub fn pause(&mut self) {
+        self.events.push_back(None);
+    }
+
+    pub fn push_keys(&mut self, keys: impl IntoIterator<Item = Key>) {
+        self.events
+            .extend(keys.into_iter().map(|v| Some(Event::Key(v))));
+    }
+
+    /// Types each character of `text` as a key.
+    pub fn type_str(&mut self, text: &str) {
+        self.push_keys(text.chars().map(|v| match v {
+            '\n' => Key::new(KeyCode::Enter),
+            '\t' => Key::new(KeyCode::Tab),
+            v => Key::new(KeyCode::Char(v)),
+        }));
+    }
+
+    /// Changes the size of the screen, as a terminal window would.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.push(Event::Resize(width, height));
+    }
+
+    pub fn screen(&self) -> &Grid {
+        &self.screen
+    }
+
+    pub fn cursor(&self) -> Option<(u16, u16)> {
+        self.cursor
+    }
+
+    /// The number of cells drawn since this was last called.
+    pub fn take_drawn(&mut self) -> usize {
+        std::mem::take(&mut self.drawn)
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn size(&self) -> Result<(u16, u16), Box<dyn std::error::Error>> {
+        Ok((self.screen.width(), self.screen.height()))
+    }
+
+    fn clear(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.screen = Grid::new(self.screen.width(), self.screen.height());
+
+        Ok(())
+    }
+
+    fn draw(&mut self, cells: &[(u16, u16, &Cell)]) -> Result<(), Box<dyn std::error::Error>> {
+        for (x, y, cell) in cells {
+            if !cell.symbol.is_empty() {
+                self.screen.put(*x, *y, &cell.symbol, cell.style);
+            }
+        }
+        self.drawn += cells.len();
+
+        Ok(())
+    }
+
+    fn set_cursor(
+        &mut self,
+        position: Option<(u16, u16)>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.cursor = position;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn poll(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Event>, Box<dyn std::error::Error>> {
+        loop {
+            match self.events.pop_front() {
+                Some(Some(event)) => {
+                    if let Event::Resize(width, height) = event {
+                        self.screen = Grid::new(width, height);
+                    }
+                    return Ok(Some(event));
+                }
+                // Without a timeout, waiting ends only with the next event
+                Some(None) if timeout.is_none() => (),
+                Some(None) | None => return Ok(None),
+            }
+        }
+    }
+}
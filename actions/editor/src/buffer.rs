@@ -0,0 +1,494 @@
+//! The text of an open file.
+//!
+//! Offsets are byte offsets into the UTF-8 text and must fall on char
+//! boundaries. Lines and columns count from 0, and columns count chars.
+
+use crate::file::{self, FileStamp, SaveOptions};
+use crate::history::{Edit, History, Travel};
+use crate::rope::Rope;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+
+const NO_FILE_NAME: &str = "BUFFER_ERROR: the buffer has no file name";
+
+#[derive(Debug, Clone, Default)]
+pub struct Buffer {
+    rope: Rope,
+    path: Option<PathBuf>,
+    /// The file as it was when last read or written, if it existed.
+    stamp: Option<FileStamp>,
+    history: History,
+}
+
+/// A line and column, both counting from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Buffer {
+    /// An empty buffer which is not backed by a file.
+    pub fn new() -> Self {
+        Buffer::default()
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        Buffer {
+            rope: Rope::new(text),
+            ..Buffer::default()
+        }
+    }
+
+    /// Fails unless `bytes` is valid UTF-8.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let text: &str = std::str::from_utf8(bytes)
+            .map_err(|e| format!("BUFFER_ERROR: invalid UTF-8 at byte {}", e.valid_up_to()))?;
+
+        Ok(Buffer::from_text(text))
+    }
+
+    /// Reads the file at `path`. A file which does not exist yet opens as an
+    /// empty buffer, and is created when saved.
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut buffer: Buffer = Buffer::new();
+        buffer.path = Some(path.to_path_buf());
+        buffer.reload()?;
+        buffer.history = History::default();
+
+        Ok(buffer)
+    }
+
+    /// Replaces the text with what is on disk. This is one change in the
+    /// undo history, so it can be undone.
+    pub fn reload(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let path: &Path = self.path.as_deref().ok_or(NO_FILE_NAME)?;
+        let (rope, stamp): (Rope, Option<FileStamp>) = match file::read(path)? {
+            Some(file::Contents { bytes, stamp }) => {
+                let text: &str = std::str::from_utf8(&bytes).map_err(|e| {
+                    format!(
+                        "BUFFER_ERROR: invalid UTF-8 at byte {} in {}",
+                        e.valid_up_to(),
+                        path.display()
+                    )
+                })?;
+                (Rope::new(text), Some(stamp))
+            }
+            None => (Rope::default(), None),
+        };
+        let text: String = rope.to_string();
+        if text != self.text() {
+            let deleted: String = self.text();
+            self.rope = rope;
+            self.history.record(Edit {
+                offset: 0,
+                deleted,
+                inserted: text,
+            });
+            self.history.commit(0, 0);
+        }
+        self.stamp = stamp;
+        self.history.mark_saved();
+
+        Ok(())
+    }
+
+    /// Writes the text to the buffer's file, unless the file was changed by
+    /// something else since it was read.
+    pub fn save(&mut self, options: &SaveOptions) -> Result<(), Box<dyn std::error::Error>> {
+        if self.changed_on_disk()? {
+            return Err(format!(
+                "BUFFER_ERROR: {} changed on disk since it was read",
+                self.path.as_deref().unwrap_or(Path::new("")).display()
+            )
+            .into());
+        }
+
+        self.overwrite(options)
+    }
+
+    /// Writes the text to the buffer's file, even if it changed on disk.
+    pub fn overwrite(&mut self, options: &SaveOptions) -> Result<(), Box<dyn std::error::Error>> {
+        let path: &Path = self.path.as_deref().ok_or(NO_FILE_NAME)?;
+        let stamp: FileStamp = file::write_atomic(path, self.rope.chunks(), options)?;
+        self.stamp = Some(stamp);
+        self.history.mark_saved();
+
+        Ok(())
+    }
+
+    /// Writes the undo history into `directory`, for [`Buffer::read_history`]
+    /// to read back once the file is opened again.
+    pub fn write_history(&self, directory: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let (Some(path), Some(stamp)) = (&self.path, &self.stamp) else {
+            return Ok(());
+        };
+        let text: String = self.history.to_text(stamp.hash);
+        std::fs::create_dir_all(directory)
+            .map_err(|e| format!("BUFFER_ERROR: cannot create {}: {}", directory.display(), e))?;
+        file::write_atomic(
+            &history_file(directory, path),
+            std::iter::once(text.as_str()),
+            &SaveOptions::default(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Reads the undo history saved with the file into `directory`, if there
+    /// is one and it was saved for the text the file has now. Returns whether
+    /// there was.
+    pub fn read_history(&mut self, directory: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+        let (Some(path), Some(stamp)) = (&self.path, &self.stamp) else {
+            return Ok(false);
+        };
+        if self.history.len() > 1 || self.history.pending() {
+            return Ok(false);
+        }
+        let Some(contents) = file::read(&history_file(directory, path))? else {
+            return Ok(false);
+        };
+        let text: &str = std::str::from_utf8(&contents.bytes)
+            .map_err(|_| "HISTORY_ERROR: the undo history is not UTF-8")?;
+
+        match History::from_text(text, stamp.hash)? {
+            Some(history) => {
+                self.history = history;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Whether the file was changed, created or removed by something else
+    /// since the buffer last read or wrote it.
+    pub fn changed_on_disk(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+
+        match &self.stamp {
+            Some(stamp) => stamp.changed(path),
+            None => Ok(path.exists()),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Whether the text was changed since the buffer was read or saved.
+    /// Undoing back to the saved text makes it unmodified again.
+    pub fn modified(&self) -> bool {
+        self.history.modified()
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Ends the current transaction, so that the edits made since the last
+    /// one are undone together. `before` and `after` are where the cursor
+    /// was before and after them.
+    pub fn commit(&mut self, before: usize, after: usize) {
+        self.history.commit(before, after);
+    }
+
+    /// Undoes the last transaction. Returns where the cursor goes, or `None`
+    /// if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<usize> {
+        let travel: Option<Travel> = self.history.undo();
+        travel.map(|v| self.apply(v))
+    }
+
+    /// Redoes the transaction undone last.
+    pub fn redo(&mut self) -> Option<usize> {
+        let travel: Option<Travel> = self.history.redo();
+        travel.map(|v| self.apply(v))
+    }
+
+    /// Goes to the text before the last transaction made, whichever branch of
+    /// the history it is on.
+    pub fn earlier(&mut self) -> Option<usize> {
+        let travel: Option<Travel> = self.history.earlier();
+        travel.map(|v| self.apply(v))
+    }
+
+    /// Goes to the text after the next transaction made.
+    pub fn later(&mut self) -> Option<usize> {
+        let travel: Option<Travel> = self.history.later();
+        travel.map(|v| self.apply(v))
+    }
+
+    /// Goes back to the text as it was `ago` before now.
+    pub fn travel_back(&mut self, ago: Duration) -> usize {
+        let travel: Travel = self.history.travel_back(ago);
+        self.apply(travel)
+    }
+
+    fn apply(&mut self, travel: Travel) -> usize {
+        for edit in travel.edits {
+            self.rope
+                .delete(edit.offset, edit.offset + edit.deleted.len());
+            self.rope.insert(edit.offset, &edit.inserted);
+        }
+
+        travel.cursor.min(self.len())
+    }
+
+    /// The length in bytes.
+    pub fn len(&self) -> usize {
+        self.rope.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rope.is_empty()
+    }
+
+    /// The number of lines. Text ending in a newline has an empty last line.
+    pub fn len_lines(&self) -> usize {
+        self.rope.newlines() + 1
+    }
+
+    pub fn text(&self) -> String {
+        self.rope.to_string()
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> Result<String, Box<dyn std::error::Error>> {
+        self.check(range.start)?;
+        self.check(range.end)?;
+
+        Ok(self.rope.slice(range.start, range.end.max(range.start)))
+    }
+
+    pub fn insert(&mut self, offset: usize, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.check(offset)?;
+        self.rope.insert(offset, text);
+        if !text.is_empty() {
+            self.history.record(Edit {
+                offset,
+                deleted: String::new(),
+                inserted: text.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn delete(&mut self, range: Range<usize>) -> Result<(), Box<dyn std::error::Error>> {
+        self.check(range.start)?;
+        self.check(range.end)?;
+        if range.start < range.end {
+            self.history.record(Edit {
+                offset: range.start,
+                deleted: self.rope.slice(range.start, range.end),
+                inserted: String::new(),
+            });
+        }
+        self.rope.delete(range.start, range.end);
+
+        Ok(())
+    }
+
+    /// Replaces the text in `range` with `text`.
+    pub fn replace(
+        &mut self,
+        range: Range<usize>,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.delete(range.clone())?;
+        self.insert(range.start, text)
+    }
+
+    /// The offset where `line` starts.
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        match line {
+            0 => Some(0),
+            n => self.rope.after_newline(n - 1),
+        }
+    }
+
+    /// The offsets of `line`, without its line ending.
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start: usize = self.line_start(line)?;
+        let end: usize = match self.rope.after_newline(line) {
+            Some(v) if v >= start + 2 && self.ends_with_cr(v - 1) => v - 2,
+            Some(v) => v - 1,
+            None => self.len(),
+        };
+
+        Some(start..end)
+    }
+
+    /// The text of `line`, without its line ending.
+    pub fn line(&self, line: usize) -> Option<String> {
+        let range: Range<usize> = self.line_range(line)?;
+
+        Some(self.rope.slice(range.start, range.end))
+    }
+
+    /// The text of `line`, with its line ending.
+    fn line_with_ending(&self, line: usize) -> Option<String> {
+        let start: usize = self.line_start(line)?;
+        let end: usize = self.rope.after_newline(line).unwrap_or(self.len());
+
+        Some(self.rope.slice(start, end))
+    }
+
+    /// The lines of the text, without their line endings.
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.len_lines()).filter_map(|v| self.line(v))
+    }
+
+    /// The chars from `offset` to the end of the text, with their offsets.
+    /// Lines are read as they are reached, so stopping early reads little.
+    pub fn chars_from(&self, offset: usize) -> impl Iterator<Item = (usize, char)> + '_ {
+        let first: usize = self.rope.newlines_before(offset.min(self.len()));
+        (first..self.len_lines())
+            .flat_map(|v| self.line_chars(v))
+            .skip_while(move |(i, _)| *i < offset)
+    }
+
+    /// The chars before `offset`, nearest first, with their offsets.
+    pub fn chars_before(&self, offset: usize) -> impl Iterator<Item = (usize, char)> + '_ {
+        let last: usize = self.rope.newlines_before(offset.min(self.len()));
+        (0..=last)
+            .rev()
+            .flat_map(|v| self.line_chars(v).into_iter().rev())
+            .skip_while(move |(i, _)| *i >= offset)
+    }
+
+    fn line_chars(&self, line: usize) -> Vec<(usize, char)> {
+        let start: usize = self.line_start(line).unwrap_or(0);
+        let text: String = self.line_with_ending(line).unwrap_or_default();
+
+        text.char_indices().map(|(i, v)| (start + i, v)).collect()
+    }
+
+    /// The extended grapheme clusters of the text, which is what a user sees
+    /// as one character. `\r\n` is a single cluster.
+    pub fn graphemes(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.len_lines())
+            .filter_map(|v| self.line_with_ending(v))
+            .flat_map(|v| {
+                v.graphemes(true)
+                    .map(str::to_string)
+                    .collect::<Vec<String>>()
+            })
+    }
+
+    pub fn offset_to_position(
+        &self,
+        offset: usize,
+    ) -> Result<Position, Box<dyn std::error::Error>> {
+        self.check(offset)?;
+        let line: usize = self.rope.newlines_before(offset);
+        let start: usize = self.line_start(line).unwrap_or(0);
+
+        Ok(Position {
+            line,
+            column: self.rope.slice(start, offset).chars().count(),
+        })
+    }
+
+    /// The offset of `position`. Columns past the end of the line are moved
+    /// to its end.
+    pub fn position_to_offset(
+        &self,
+        position: Position,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let range: Range<usize> = self.line_range(position.line).ok_or_else(|| {
+            format!(
+                "BUFFER_ERROR: line {} is past the end of the buffer",
+                position.line
+            )
+        })?;
+        let line: String = self.rope.slice(range.start, range.end);
+        let column: usize = line
+            .char_indices()
+            .nth(position.column)
+            .map_or(line.len(), |(i, _)| i);
+
+        Ok(range.start + column)
+    }
+
+    /// The first grapheme boundary after `offset`, or the end of the text.
+    pub fn next_grapheme_boundary(
+        &self,
+        offset: usize,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        self.check(offset)?;
+        let line: usize = self.rope.newlines_before(offset);
+        let start: usize = self.line_start(line).unwrap_or(0);
+        let text: String = self.line_with_ending(line).unwrap_or_default();
+
+        Ok(text
+            .grapheme_indices(true)
+            .map(|(i, v)| start + i + v.len())
+            .find(|v| *v > offset)
+            .unwrap_or(offset))
+    }
+
+    /// The last grapheme boundary before `offset`, or 0.
+    pub fn prev_grapheme_boundary(
+        &self,
+        offset: usize,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        self.check(offset)?;
+        let line: usize = self.rope.newlines_before(offset);
+        // At the start of a line, the previous cluster is the line ending before it
+        let line: usize = match self.line_start(line) == Some(offset) {
+            true if line > 0 => line - 1,
+            _ => line,
+        };
+        let start: usize = self.line_start(line).unwrap_or(0);
+        let text: String = self.line_with_ending(line).unwrap_or_default();
+
+        Ok(text
+            .grapheme_indices(true)
+            .map(|(i, _)| start + i)
+            .rfind(|v| *v < offset)
+            .unwrap_or(0))
+    }
+
+    /// Whether the char before `offset` is `\r`.
+    fn ends_with_cr(&self, offset: usize) -> bool {
+        self.rope.is_char_boundary(offset - 1) && self.rope.slice(offset - 1, offset) == "\r"
+    }
+
+    fn check(&self, offset: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if offset > self.len() {
+            return Err(format!(
+                "BUFFER_ERROR: offset {} is past the end of the buffer ({} bytes)",
+                offset,
+                self.len()
+            )
+            .into());
+        }
+        if offset < self.len() && !self.rope.is_char_boundary(offset) {
+            return Err(format!("BUFFER_ERROR: offset {} is inside a character", offset).into());
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Buffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.rope.fmt(f)
+    }
+}
+
+/// Where the undo history of the file at `path` is kept in `directory`. The
+/// name is the hash of the file's absolute path, so it is short and differs
+/// between files.
+fn history_file(directory: &Path, path: &Path) -> PathBuf {
+    let path: PathBuf = std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    let hash: u64 = file::hash([path.as_os_str().as_encoded_bytes()]);
+
+    directory.join(format!("{:016x}", hash))
+}
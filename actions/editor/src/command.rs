@@ -0,0 +1,396 @@
+//! The commands built into the editor, which keys are bound to by name.
+//! `init.core` adds more with `define-command`.
+
+use crate::editor::Editor;
+use crate::window::{Direction, Side};
+
+pub struct Command {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub run: fn(&mut Editor) -> Result<(), Box<dyn std::error::Error>>,
+}
+
+pub static BUILTIN: &[Command] = &[
+    Command {
+        name: "quit",
+        doc: "Leaves the editor, asking first if any buffer is modified.",
+        run: |editor| {
+            editor.quit();
+            Ok(())
+        },
+    },
+    Command {
+        name: "save",
+        doc: "Writes the buffer to its file.",
+        run: Editor::save,
+    },
+    Command {
+        name: "reload",
+        doc: "Replaces the buffer with the contents of its file.",
+        run: Editor::reload,
+    },
+    Command {
+        name: "execute-command",
+        doc: "Runs a command, asking for its name.",
+        run: |editor| {
+            editor.execute_command();
+            Ok(())
+        },
+    },
+    Command {
+        name: "describe-symbol",
+        doc: "Shows what a name defined in init.core or by the editor does.",
+        run: |editor| {
+            editor.describe_symbol();
+            Ok(())
+        },
+    },
+    Command {
+        name: "find-file",
+        doc: "Opens a file, asking for its name.",
+        run: |editor| {
+            editor.find_file();
+            Ok(())
+        },
+    },
+    Command {
+        name: "new-buffer",
+        doc: "Opens an empty buffer without a file.",
+        run: |editor| {
+            editor.new_buffer();
+            Ok(())
+        },
+    },
+    Command {
+        name: "switch-buffer",
+        doc: "Shows another open buffer, picked by part of its name.",
+        run: Editor::switch_buffer,
+    },
+    Command {
+        name: "list-buffers",
+        doc: "Lists the open buffers.",
+        run: |editor| {
+            editor.list_buffers();
+            Ok(())
+        },
+    },
+    Command {
+        name: "close-buffer",
+        doc: "Closes the buffer, asking first if it has unsaved changes.",
+        run: Editor::close_buffer,
+    },
+    Command {
+        name: "split-window-below",
+        doc: "Divides the window in two, one above the other.",
+        run: |editor| {
+            editor.split_window(Direction::Below);
+            Ok(())
+        },
+    },
+    Command {
+        name: "split-window-right",
+        doc: "Divides the window in two, side by side.",
+        run: |editor| {
+            editor.split_window(Direction::Right);
+            Ok(())
+        },
+    },
+    Command {
+        name: "close-window",
+        doc: "Closes the window, leaving its buffer open.",
+        run: Editor::close_window,
+    },
+    Command {
+        name: "only-window",
+        doc: "Closes every other window.",
+        run: |editor| {
+            editor.only_window();
+            Ok(())
+        },
+    },
+    Command {
+        name: "maximize-window",
+        doc: "Gives the window the whole tab, or gives the other windows back their places.",
+        run: Editor::maximize_window,
+    },
+    Command {
+        name: "other-window",
+        doc: "Moves the cursor to the next window.",
+        run: |editor| {
+            editor.other_window(1);
+            Ok(())
+        },
+    },
+    Command {
+        name: "window-left",
+        doc: "Moves the cursor to the window to the left.",
+        run: |editor| editor.window_toward(Side::Left),
+    },
+    Command {
+        name: "window-right",
+        doc: "Moves the cursor to the window to the right.",
+        run: |editor| editor.window_toward(Side::Right),
+    },
+    Command {
+        name: "window-up",
+        doc: "Moves the cursor to the window above.",
+        run: |editor| editor.window_toward(Side::Up),
+    },
+    Command {
+        name: "window-down",
+        doc: "Moves the cursor to the window below.",
+        run: |editor| editor.window_toward(Side::Down),
+    },
+    Command {
+        name: "swap-window",
+        doc: "Exchanges the place of the window with the next one.",
+        run: Editor::swap_window,
+    },
+    Command {
+        name: "enlarge-window",
+        doc: "Makes the window a line taller.",
+        run: |editor| editor.resize_window(Direction::Below, 1),
+    },
+    Command {
+        name: "shrink-window",
+        doc: "Makes the window a line shorter.",
+        run: |editor| editor.resize_window(Direction::Below, -1),
+    },
+    Command {
+        name: "enlarge-window-horizontally",
+        doc: "Makes the window a column wider.",
+        run: |editor| editor.resize_window(Direction::Right, 1),
+    },
+    Command {
+        name: "shrink-window-horizontally",
+        doc: "Makes the window a column narrower.",
+        run: |editor| editor.resize_window(Direction::Right, -1),
+    },
+    Command {
+        name: "new-tab",
+        doc: "Opens a tab with one window, showing the buffer.",
+        run: |editor| {
+            editor.new_tab();
+            Ok(())
+        },
+    },
+    Command {
+        name: "next-tab",
+        doc: "Shows the next tab.",
+        run: |editor| editor.next_tab(1),
+    },
+    Command {
+        name: "previous-tab",
+        doc: "Shows the previous tab.",
+        run: |editor| editor.next_tab(-1),
+    },
+    Command {
+        name: "close-tab",
+        doc: "Closes the tab and its windows, leaving their buffers open.",
+        run: Editor::close_tab,
+    },
+    Command {
+        name: "save-layout",
+        doc: "Keeps the windows of the tab, and what they show, in the data directory.",
+        run: Editor::save_layout,
+    },
+    Command {
+        name: "restore-layout",
+        doc: "Shows the windows kept by save-layout.",
+        run: Editor::restore_layout,
+    },
+    Command {
+        name: "keyboard-quit",
+        doc: "Cancels the keys typed so far.",
+        run: |editor| {
+            editor.set_message("Quit");
+            Ok(())
+        },
+    },
+    Command {
+        name: "newline",
+        doc: "Inserts a line break.",
+        run: |editor| editor.insert("\n"),
+    },
+    Command {
+        name: "insert-tab",
+        doc: "Inserts a tab.",
+        run: |editor| editor.insert("\t"),
+    },
+    Command {
+        name: "delete-backward-char",
+        doc: "Deletes the character before the cursor.",
+        run: Editor::delete_backward,
+    },
+    Command {
+        name: "delete-char",
+        doc: "Deletes the character after the cursor.",
+        run: Editor::delete_forward,
+    },
+    Command {
+        name: "backward-char",
+        doc: "Moves the cursor one character left.",
+        run: Editor::move_left,
+    },
+    Command {
+        name: "forward-char",
+        doc: "Moves the cursor one character right.",
+        run: Editor::move_right,
+    },
+    Command {
+        name: "previous-line",
+        doc: "Moves the cursor up a line.",
+        run: |editor| {
+            editor.move_lines(-1);
+            Ok(())
+        },
+    },
+    Command {
+        name: "next-line",
+        doc: "Moves the cursor down a line.",
+        run: |editor| {
+            editor.move_lines(1);
+            Ok(())
+        },
+    },
+    Command {
+        name: "page-up",
+        doc: "Moves the cursor up a screen.",
+        run: |editor| {
+            editor.move_pages(-1);
+            Ok(())
+        },
+    },
+    Command {
+        name: "page-down",
+        doc: "Moves the cursor down a screen.",
+        run: |editor| {
+            editor.move_pages(1);
+            Ok(())
+        },
+    },
+    Command {
+        name: "beginning-of-line",
+        doc: "Moves the cursor to the start of the line.",
+        run: |editor| {
+            editor.move_to_line_start();
+            Ok(())
+        },
+    },
+    Command {
+        name: "end-of-line",
+        doc: "Moves the cursor to the end of the line.",
+        run: |editor| {
+            editor.move_to_line_end();
+            Ok(())
+        },
+    },
+    Command {
+        name: "undo",
+        doc: "Undoes the last change.",
+        run: Editor::undo,
+    },
+    Command {
+        name: "redo",
+        doc: "Redoes the change undone last.",
+        run: Editor::redo,
+    },
+    Command {
+        name: "undo-earlier",
+        doc: "Goes to the text before the last change made, on any branch of the undo tree.",
+        run: Editor::undo_earlier,
+    },
+    Command {
+        name: "undo-later",
+        doc: "Goes to the text after the next change made, on any branch of the undo tree.",
+        run: Editor::undo_later,
+    },
+    Command {
+        name: "search-forward",
+        doc: "Searches forward for text as it is typed.",
+        run: |editor| {
+            editor.search(true, false);
+            Ok(())
+        },
+    },
+    Command {
+        name: "search-backward",
+        doc: "Searches backward for text as it is typed.",
+        run: |editor| {
+            editor.search(false, false);
+            Ok(())
+        },
+    },
+    Command {
+        name: "search-forward-regex",
+        doc: "Searches forward for a regular expression as it is typed.",
+        run: |editor| {
+            editor.search(true, true);
+            Ok(())
+        },
+    },
+    Command {
+        name: "search-backward-regex",
+        doc: "Searches backward for a regular expression as it is typed.",
+        run: |editor| {
+            editor.search(false, true);
+            Ok(())
+        },
+    },
+    Command {
+        name: "search-next",
+        doc: "Goes to the next match of the last search.",
+        run: |editor| editor.search_next(false),
+    },
+    Command {
+        name: "search-previous",
+        doc: "Goes to the previous match of the last search.",
+        run: |editor| editor.search_next(true),
+    },
+    Command {
+        name: "query-replace",
+        doc: "Replaces text after the cursor, asking about each match.",
+        run: |editor| {
+            editor.query_replace(false);
+            Ok(())
+        },
+    },
+    Command {
+        name: "query-replace-regex",
+        doc: "Replaces matches of a regular expression after the cursor, asking about each.",
+        run: |editor| {
+            editor.query_replace(true);
+            Ok(())
+        },
+    },
+    Command {
+        name: "enable-persistent-undo",
+        doc: "Keeps the undo history of saved files between sessions.",
+        run: |editor| editor.set_persistent_undo(true),
+    },
+    Command {
+        name: "disable-persistent-undo",
+        doc: "Stops keeping the undo history of saved files.",
+        run: |editor| editor.set_persistent_undo(false),
+    },
+    Command {
+        name: "modal-editing",
+        doc: "Edits with vi commands in normal mode, typing only in insert mode.",
+        run: |editor| {
+            editor.set_modal_editing(true);
+            Ok(())
+        },
+    },
+    Command {
+        name: "modeless-editing",
+        doc: "Types text with every key which is not bound.",
+        run: |editor| {
+            editor.set_modal_editing(false);
+            Ok(())
+        },
+    },
+];
+
+pub fn builtin(name: &str) -> Option<&'static Command> {
+    BUILTIN.iter().find(|v| v.name == name)
+}
@@ -0,0 +1,2549 @@
+//! The state of a running editor, how keys change it, and how it is shown.
+
+use crate::backend::Event;
+use crate::buffer::{Buffer, Position};
+use crate::command;
+use crate::file::SaveOptions;
+use crate::fuzzy;
+use crate::grid::{Color, Grid, Style};
+use crate::key::{Key, KeyCode, keys_to_string};
+use crate::keymap::{Binding, Lookup};
+use crate::minibuffer::{self, Input, Minibuffer, Reading, input};
+use crate::modal::{self, Action, Insert, Modal, Motion, Operator, Register};
+use crate::script::{Context, Effect, Script};
+use crate::search::{Match, Pattern};
+use crate::window::{Direction, Layout, Rect, Side};
+use core_lang::help::{self, Help};
+use core_lang::object::Object;
+use core_lang::syntax::Source;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const TAB_WIDTH: usize = 4;
+
+/// The name of a buffer without a file. More of them are numbered, as
+/// `*scratch*<2>`.
+const SCRATCH: &str = "*scratch*";
+
+/// How deeply commands may run other commands, so that a command which runs
+/// itself stops.
+const MAX_COMMAND_DEPTH: usize = 32;
+
+/// Matches of a search other than the one the cursor is on.
+const MATCH: Style = Style {
+    fg: Some(Color::Black),
+    bg: Some(Color::Yellow),
+    bold: false,
+    underline: false,
+    reverse: false,
+};
+
+/// The status lines of the windows without the cursor.
+const INACTIVE: Style = Style {
+    fg: Some(Color::Grey),
+    bg: None,
+    bold: false,
+    underline: false,
+    reverse: true,
+};
+
+/// Where windows are drawn until the screen is first drawn.
+const DEFAULT_AREA: Rect = Rect {
+    x: 0,
+    y: 0,
+    width: 80,
+    height: 23,
+};
+
+pub struct Editor {
+    buffer: Buffer,
+    /// The name of the buffer, which is its file name, or `*scratch*` for
+    /// one without a file.
+    name: String,
+    /// The other open buffers, the one shown last first.
+    buffers: Vec<Background>,
+    /// Whether the open buffers are listed on screen, until the next key.
+    listing: bool,
+    /// How the screen is shared between the windows of the tab shown.
+    layout: Layout,
+    /// The window with the cursor, which shows `buffer`.
+    window: usize,
+    /// The other windows of the tab shown.
+    views: Vec<View>,
+    /// The layout from before `maximize-window`, which it goes back to.
+    zoomed: Option<Layout>,
+    /// The tabs which are not shown, and where the one shown goes among
+    /// them.
+    tabs: Vec<Tab>,
+    tab: usize,
+    /// The number of the next window made.
+    next_window: usize,
+    /// Where the windows were drawn last time.
+    area: Rect,
+    /// The byte offset of the cursor in the buffer.
+    cursor: usize,
+    /// The column the cursor keeps to when moving between lines.
+    goal: Option<usize>,
+    /// The first line and column on screen.
+    top: usize,
+    left: usize,
+    /// The number of lines of text the screen showed last time.
+    page: usize,
+    message: Option<String>,
+    /// A question in the message area, which the next key answers.
+    prompt: Option<Prompt>,
+    /// What the minibuffer read before, oldest first, for each thing it
+    /// reads.
+    answers: HashMap<Reading, Vec<String>>,
+    /// The last search, which `search-next` repeats, and whether it went
+    /// forward.
+    last_search: Option<(Pattern, bool)>,
+    save_options: SaveOptions,
+    /// Where data such as undo history is kept between sessions.
+    data_directory: Option<PathBuf>,
+    /// The cursor when the transaction in progress started.
+    transaction: Option<usize>,
+    script: Script,
+    /// The editing mode, which picks a keymap. Without modal editing this
+    /// stays `insert`.
+    mode: String,
+    /// Whether keys in the modes other than insert are read as vi commands.
+    modal_editing: bool,
+    modal: Modal,
+    /// Where the selection started, in the visual modes.
+    anchor: Option<usize>,
+    /// The text deleted or yanked last, which `p` puts.
+    register: Register,
+    /// The keys of a sequence typed so far.
+    pending: Vec<Key>,
+    /// Whether the keys which can follow `pending` are listed on screen.
+    which_key: bool,
+    depth: usize,
+    quit: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Prompt {
+    /// Saving found that the file changed on disk since it was read.
+    ChangedOnDisk,
+    /// An incremental search, which moves the cursor as the query is typed.
+    Search(Search),
+    /// Reading what query-replace looks for, and then what replaces it.
+    ReplaceFrom {
+        regex: bool,
+        text: String,
+    },
+    ReplaceWith {
+        pattern: Pattern,
+        text: String,
+    },
+    /// Closing a buffer with changes which are not saved.
+    CloseModified,
+    /// Quitting with buffers whose changes are not saved.
+    QuitModified,
+    /// Reading the name of a command, file, buffer or symbol.
+    Read(Reading, Minibuffer),
+    /// Asking whether to replace the match at `range`.
+    Replace {
+        pattern: Pattern,
+        with: String,
+        range: Range<usize>,
+        replaced: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Search {
+    query: String,
+    forward: bool,
+    regex: bool,
+    /// The cursor when the search started, which cancelling goes back to.
+    start: usize,
+    /// The query compiled, unless it is empty or does not compile.
+    pattern: Option<Pattern>,
+    error: Option<String>,
+    found: Option<Match>,
+}
+
+/// A buffer which is open but not shown, and where it was scrolled to.
+#[derive(Debug, Clone)]
+struct Background {
+    buffer: Buffer,
+    name: String,
+    cursor: usize,
+    top: usize,
+    left: usize,
+}
+
+/// A window other than the one with the cursor. Its cursor is kept as a
+/// line and column, which still make sense after its buffer is edited in
+/// another window.
+#[derive(Debug, Clone)]
+struct View {
+    window: usize,
+    name: String,
+    position: Position,
+    top: usize,
+    left: usize,
+}
+
+/// A tab which is not shown, with all of its windows.
+#[derive(Debug, Clone)]
+struct Tab {
+    layout: Layout,
+    window: usize,
+    views: Vec<View>,
+    zoomed: Option<Layout>,
+}
+
+impl Editor {
+    pub fn new(buffer: Buffer) -> Self {
+        let name: String = match buffer.path() {
+            Some(v) => v.display().to_string(),
+            None => String::from(SCRATCH),
+        };
+
+        Editor {
+            buffer,
+            name,
+            buffers: Vec::new(),
+            listing: false,
+            layout: Layout::Window(0),
+            window: 0,
+            views: Vec::new(),
+            zoomed: None,
+            tabs: Vec::new(),
+            tab: 0,
+            next_window: 1,
+            area: DEFAULT_AREA,
+            cursor: 0,
+            goal: None,
+            top: 0,
+            left: 0,
+            page: 1,
+            message: None,
+            prompt: None,
+            answers: HashMap::new(),
+            last_search: None,
+            save_options: SaveOptions::default(),
+            data_directory: None,
+            transaction: None,
+            script: Script::new(),
+            mode: String::from(modal::INSERT),
+            modal_editing: false,
+            modal: Modal::default(),
+            anchor: None,
+            register: Register::default(),
+            pending: Vec::new(),
+            which_key: false,
+            depth: 0,
+            quit: false,
+        }
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The line and column of the cursor, counting columns as chars.
+    pub fn position(&self) -> Position {
+        self.buffer
+            .offset_to_position(self.cursor)
+            .unwrap_or_default()
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Shows `message` in the message area until the next key.
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Leaves the editor, first asking whether to discard the changes of
+    /// buffers which are not saved.
+    pub fn quit(&mut self) {
+        let modified: Vec<&str> = std::iter::once((&self.name, &self.buffer))
+            .chain(self.buffers.iter().map(|v| (&v.name, &v.buffer)))
+            .filter(|(_, buffer)| buffer.modified())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if modified.is_empty() {
+            self.quit = true;
+            return;
+        }
+
+        let message: String = format!(
+            "{} {} modified; quit without saving (d) or cancel?",
+            modified.join(", "),
+            if modified.len() == 1 { "is" } else { "are" }
+        );
+        self.prompt = Some(Prompt::QuitModified);
+        self.set_message(message);
+    }
+
+    pub fn set_save_options(&mut self, options: SaveOptions) {
+        self.save_options = options;
+    }
+
+    pub fn set_data_directory(&mut self, directory: PathBuf) {
+        self.data_directory = Some(directory);
+    }
+
+    /// Saves the undo history of files in the data directory when they are
+    /// saved, and reads it back for the file being edited.
+    pub fn set_persistent_undo(&mut self, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let directory: Option<PathBuf> = match enabled {
+            true => Some(
+                self.data_directory
+                    .as_ref()
+                    .ok_or("there is no data directory to keep undo history in")?
+                    .join("undo"),
+            ),
+            false => None,
+        };
+        if let Some(directory) = &directory {
+            // A history which cannot be read only loses the undo of its own buffer
+            let mut unreadable: Vec<String> = Vec::new();
+            if let Err(e) = self.buffer.read_history(directory) {
+                unreadable.push(format!("{}: {}", self.name, e));
+            }
+            for background in &mut self.buffers {
+                if let Err(e) = background.buffer.read_history(directory) {
+                    unreadable.push(format!("{}: {}", background.name, e));
+                }
+            }
+            if !unreadable.is_empty() {
+                self.set_message(unreadable.join("; "));
+            }
+        }
+        self.save_options.undo_directory = directory;
+
+        Ok(())
+    }
+
+    pub fn script(&self) -> &Script {
+        &self.script
+    }
+
+    /// Evaluates `init.core`, which binds keys and defines commands, and
+    /// runs its `main` procedure.
+    pub fn load_config(&mut self, source: Source) -> Result<(), Box<dyn std::error::Error>> {
+        let effects: Vec<Effect> = self.script.load(source, self.context())?;
+
+        self.apply(effects)
+    }
+
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: impl Into<String>) {
+        self.mode = mode.into();
+    }
+
+    pub fn modal_editing(&self) -> bool {
+        self.modal_editing
+    }
+
+    /// Switches between modal editing, which starts in normal mode, and
+    /// modeless editing, which always inserts.
+    pub fn set_modal_editing(&mut self, enabled: bool) {
+        self.modal_editing = enabled;
+        self.modal.escape();
+        self.anchor = None;
+        self.mode = String::from(match enabled {
+            true => modal::NORMAL,
+            false => modal::INSERT,
+        });
+        self.keep_on_char();
+    }
+
+    pub fn register(&self) -> &Register {
+        &self.register
+    }
+
+    /// The file type of the buffer, which is the extension of its file.
+    pub fn filetype(&self) -> Option<String> {
+        let extension = self.buffer.path()?.extension()?;
+
+        Some(extension.to_string_lossy().into_owned())
+    }
+
+    /// The keys of a sequence typed so far.
+    pub fn pending(&self) -> &[Key] {
+        &self.pending
+    }
+
+    /// How long to wait for the next key before calling
+    /// [`Editor::timed_out`], if at all.
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.pending.is_empty() || self.which_key {
+            true => None,
+            false => Some(self.script.host().keymaps.timeout),
+        }
+    }
+
+    /// Called when no key came within [`Editor::timeout`] of a prefix key.
+    /// Runs what the prefix is bound to on its own, or else lists the keys
+    /// which can follow it.
+    pub fn timed_out(&mut self) {
+        let lookup: Lookup = self.lookup(&self.pending, true);
+        match lookup {
+            Lookup::Prefix(Some(binding)) => {
+                self.pending.clear();
+                self.report(|editor| editor.run(&binding));
+            }
+            _ => self.which_key = !self.pending.is_empty(),
+        }
+    }
+
+    pub fn handle(&mut self, event: &Event) {
+        if let Event::Key(key) = event {
+            self.message = None;
+            self.listing = false;
+            // A run of typed chars, or a whole visit to insert mode, is undone at once
+            let typing: bool = self.mode == modal::INSERT
+                && (self.modal_editing || (key.char().is_some() && self.prompt.is_none()));
+            if !typing {
+                self.commit();
+            }
+            self.transaction.get_or_insert(self.cursor);
+
+            self.report(|editor| match editor.prompt.take() {
+                Some(prompt) => editor.answer(prompt, *key),
+                None => editor.key(*key),
+            });
+            // Keys which start insert mode, like `c`, begin the visit to it
+            if self.mode != modal::INSERT || !(typing || self.modal_editing) {
+                self.commit();
+            }
+        }
+    }
+
+    /// Ends the transaction in progress, so that its edits are undone
+    /// together.
+    fn commit(&mut self) {
+        let before: usize = self.transaction.take().unwrap_or(self.cursor);
+        self.buffer.commit(before, self.cursor);
+    }
+
+    pub fn undo(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit();
+        let cursor: Option<usize> = self.buffer.undo();
+        self.travelled(cursor, "Already at the oldest change")
+    }
+
+    pub fn redo(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit();
+        let cursor: Option<usize> = self.buffer.redo();
+        self.travelled(cursor, "Already at the newest change")
+    }
+
+    /// Goes to the text before the last change made, on whichever branch
+    /// of the undo tree.
+    pub fn undo_earlier(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit();
+        let cursor: Option<usize> = self.buffer.earlier();
+        self.travelled(cursor, "Already at the oldest change")
+    }
+
+    /// Goes to the text after the next change made.
+    pub fn undo_later(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit();
+        let cursor: Option<usize> = self.buffer.later();
+        self.travelled(cursor, "Already at the newest change")
+    }
+
+    /// Goes back to the text as it was `ago` before now.
+    pub fn undo_to(&mut self, ago: Duration) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit();
+        let cursor: usize = self.buffer.travel_back(ago);
+        self.travelled(Some(cursor), "")
+    }
+
+    fn travelled(
+        &mut self,
+        cursor: Option<usize>,
+        otherwise: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cursor: usize = cursor.ok_or(otherwise)?;
+        self.cursor = cursor;
+        self.goal = None;
+        self.keep_on_char();
+
+        Ok(())
+    }
+
+    /// Shows the error of `f` in the message area.
+    fn report(&mut self, f: impl FnOnce(&mut Self) -> Result<(), Box<dyn std::error::Error>>) {
+        if let Err(e) = f(self) {
+            self.set_message(e.to_string());
+        }
+    }
+
+    fn answer(&mut self, prompt: Prompt, key: Key) -> Result<(), Box<dyn std::error::Error>> {
+        match prompt {
+            Prompt::ChangedOnDisk => match key.char() {
+                Some('o') => {
+                    self.buffer.overwrite(&self.save_options)?;
+                    self.saved();
+                }
+                Some('r') => self.reload()?,
+                _ => self.set_message("Cancelled"),
+            },
+            Prompt::Search(search) => self.answer_search(search, key)?,
+            Prompt::CloseModified => match key.char() {
+                Some('s') => {
+                    self.save()?;
+                    if !self.buffer.modified() {
+                        self.discard_buffer();
+                    }
+                }
+                Some('d') => self.discard_buffer(),
+                _ => self.set_message("Cancelled"),
+            },
+            Prompt::QuitModified => match key.char() {
+                Some('d') => self.quit = true,
+                _ => self.set_message("Cancelled"),
+            },
+            Prompt::Read(reading, mut minibuffer) => {
+                let candidates: Vec<String> = self.candidates(reading, &minibuffer.text);
+                let answers: &[String] = self.answers.get(&reading).map_or(&[], |v| &v[..]);
+                match minibuffer.key(key, &candidates, answers) {
+                    Input::Editing(_) => self.prompt = Some(Prompt::Read(reading, minibuffer)),
+                    Input::Done(text) => self.accept(reading, text, minibuffer.selected)?,
+                    Input::Cancelled => self.set_message("Quit"),
+                }
+            }
+            Prompt::ReplaceFrom { regex, text } => match input(text, key) {
+                Input::Editing(text) => self.prompt = Some(Prompt::ReplaceFrom { regex, text }),
+                Input::Done(text) => {
+                    let pattern: Pattern = Pattern::new(&text, regex)?;
+                    self.prompt = Some(Prompt::ReplaceWith {
+                        pattern,
+                        text: String::new(),
+                    });
+                }
+                Input::Cancelled => self.set_message("Quit"),
+            },
+            Prompt::ReplaceWith { pattern, text } => match input(text, key) {
+                Input::Editing(text) => self.prompt = Some(Prompt::ReplaceWith { pattern, text }),
+                Input::Done(with) => self.replace_next(pattern, with, self.cursor, 0),
+                Input::Cancelled => self.set_message("Quit"),
+            },
+            Prompt::Replace {
+                pattern,
+                with,
+                range,
+                replaced,
+            } => match (key.code, key.char()) {
+                (_, Some('y' | ' ')) => {
+                    let next: usize = self.replace_match(&pattern, range, &with)?;
+                    self.replace_next(pattern, with, next, replaced + 1);
+                }
+                (KeyCode::Backspace, _) | (_, Some('n')) => {
+                    let next: usize = match range.is_empty() {
+                        true => next_char(&self.buffer, range.start),
+                        false => range.end,
+                    };
+                    self.replace_next(pattern, with, next, replaced);
+                }
+                (_, Some('!')) => {
+                    let mut next: usize = self.replace_match(&pattern, range, &with)?;
+                    let mut replaced: usize = replaced + 1;
+                    while let Some(found) = pattern.find_forward(&self.buffer, next) {
+                        if found.wrapped {
+                            break;
+                        }
+                        self.cursor = found.range.start;
+                        next = self.replace_match(&pattern, found.range, &with)?;
+                        replaced += 1;
+                    }
+                    self.replace_next(pattern, with, next, replaced);
+                }
+                _ => self.replace_next(pattern, with, self.buffer.len() + 1, replaced),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Starts an incremental search, for literal text or a regular
+    /// expression.
+    pub fn search(&mut self, forward: bool, regex: bool) {
+        self.prompt = Some(Prompt::Search(Search {
+            query: String::new(),
+            forward,
+            regex,
+            start: self.cursor,
+            pattern: None,
+            error: None,
+            found: None,
+        }));
+    }
+
+    /// Reads a key of an incremental search. Keys which mean nothing to it
+    /// end the search where it is, and then do what they usually do.
+    fn answer_search(
+        &mut self,
+        mut search: Search,
+        key: Key,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let current: usize = search
+            .found
+            .as_ref()
+            .map_or(search.start, |v| v.range.start);
+        let from: usize = match (key.code, key.ctrl, key.alt) {
+            (KeyCode::Char(c), false, false) => {
+                search.query.push(c);
+                search.start
+            }
+            (KeyCode::Backspace, false, false) => {
+                search.query.pop();
+                search.start
+            }
+            (KeyCode::Char('r'), false, true) => {
+                search.regex = !search.regex;
+                search.start
+            }
+            (KeyCode::Char('f'), true, false) | (KeyCode::Down, false, false) => {
+                search.forward = true;
+                current
+            }
+            (KeyCode::Char('r'), true, false) | (KeyCode::Up, false, false) => {
+                search.forward = false;
+                current
+            }
+            (KeyCode::Enter, false, false) => {
+                self.end_search(search);
+                return Ok(());
+            }
+            (KeyCode::Esc, ..) | (KeyCode::Char('g'), true, false) => {
+                self.cursor = search.start;
+                self.goal = None;
+                return Ok(());
+            }
+            _ => {
+                self.end_search(search);
+                return self.key(key);
+            }
+        };
+
+        search.find(&self.buffer, from);
+        self.cursor = search
+            .found
+            .as_ref()
+            .map_or(search.start, |v| v.range.start);
+        self.goal = None;
+        self.prompt = Some(Prompt::Search(search));
+
+        Ok(())
+    }
+
+    /// Leaves the cursor where an incremental search got to, and keeps the
+    /// search for `search-next`.
+    fn end_search(&mut self, search: Search) {
+        if let Some(pattern) = search.pattern {
+            self.last_search = Some((pattern, search.forward));
+        }
+        self.keep_on_char();
+    }
+
+    /// Goes to the next match of the last search, in the direction it went,
+    /// or the other way if `reverse`.
+    pub fn search_next(&mut self, reverse: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let (pattern, forward) = self
+            .last_search
+            .as_ref()
+            .ok_or("there is no search to repeat")?;
+        let forward: bool = *forward != reverse;
+        let found: Match = match forward {
+            true => pattern.find_forward(&self.buffer, next_char(&self.buffer, self.cursor)),
+            false => pattern.find_backward(&self.buffer, self.cursor),
+        }
+        .ok_or_else(|| format!("Not found: {}", pattern.query()))?;
+
+        self.cursor = found.range.start;
+        self.goal = None;
+        self.keep_on_char();
+        if found.wrapped {
+            self.set_message("Search wrapped around");
+        }
+
+        Ok(())
+    }
+
+    /// Asks for text to look for and what to replace it with, then goes
+    /// through the matches from the cursor to the end, asking about each.
+    pub fn query_replace(&mut self, regex: bool) {
+        self.prompt = Some(Prompt::ReplaceFrom {
+            regex,
+            text: String::new(),
+        });
+    }
+
+    /// Asks about the first match at or after `offset`, or else says how
+    /// many were replaced.
+    fn replace_next(&mut self, pattern: Pattern, with: String, offset: usize, replaced: usize) {
+        match pattern.find_forward(&self.buffer, offset) {
+            Some(found) if !found.wrapped && offset <= self.buffer.len() => {
+                self.cursor = found.range.start;
+                self.goal = None;
+                self.set_message(format!(
+                    "Replace {} with {}? (y, n, !, q)",
+                    pattern.query(),
+                    with
+                ));
+                self.prompt = Some(Prompt::Replace {
+                    pattern,
+                    with,
+                    range: found.range,
+                    replaced,
+                });
+            }
+            _ => {
+                self.keep_on_char();
+                self.set_message(match replaced {
+                    1 => String::from("Replaced 1 occurrence"),
+                    n => format!("Replaced {} occurrences", n),
+                });
+            }
+        }
+    }
+
+    /// Replaces the match at `range`. Returns where to look for the next
+    /// match, which is after the replacement.
+    fn replace_match(
+        &mut self,
+        pattern: &Pattern,
+        range: Range<usize>,
+        with: &str,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let text: String = pattern.replacement(&self.buffer, range.clone(), with)?;
+        self.buffer.replace(range.clone(), &text)?;
+        let end: usize = range.start + text.len();
+
+        // An empty match would be found again at the same place
+        Ok(match range.is_empty() {
+            true => next_char(&self.buffer, end),
+            false => end,
+        })
+    }
+
+    /// The text of a prompt which reads text, shown in the message area with
+    /// the cursor after it.
+    fn prompt_line(&self) -> Option<String> {
+        match self.prompt.as_ref()? {
+            Prompt::Search(search) => {
+                let mut words: Vec<&str> = Vec::new();
+                match &search.found {
+                    None if search.pattern.is_some() => words.push("failing"),
+                    Some(found) if found.wrapped => words.push("wrapped"),
+                    _ => (),
+                }
+                if search.regex {
+                    words.push("regex");
+                }
+                words.push("search");
+                if !search.forward {
+                    words.push("backward");
+                }
+                let mut label: String = words.join(" ");
+                label[..1].make_ascii_uppercase();
+                let error: String = search
+                    .error
+                    .as_ref()
+                    .map_or(String::new(), |v| format!(" [{}]", v));
+
+                Some(format!("{}: {}{}", label, search.query, error))
+            }
+            Prompt::ReplaceFrom { regex, text } => Some(format!(
+                "Query replace{}: {}",
+                if *regex { " regex" } else { "" },
+                text
+            )),
+            Prompt::ReplaceWith { pattern, text } => Some(format!(
+                "Query replace{} {} with: {}",
+                if pattern.is_regex() { " regex" } else { "" },
+                pattern.query(),
+                text
+            )),
+            Prompt::Read(reading, minibuffer) => {
+                Some(format!("{}{}", reading.prompt(), minibuffer.text))
+            }
+            Prompt::ChangedOnDisk
+            | Prompt::CloseModified
+            | Prompt::QuitModified
+            | Prompt::Replace { .. } => None,
+        }
+    }
+
+    /// The pattern whose matches are highlighted, and the match the cursor
+    /// is on.
+    fn highlighted(&self) -> Option<(&Pattern, Option<Range<usize>>)> {
+        match self.prompt.as_ref()? {
+            Prompt::Search(search) => Some((
+                search.pattern.as_ref()?,
+                search.found.as_ref().map(|v| v.range.clone()),
+            )),
+            Prompt::Replace { pattern, range, .. } => Some((pattern, Some(range.clone()))),
+            _ => None,
+        }
+    }
+
+    fn key(&mut self, key: Key) -> Result<(), Box<dyn std::error::Error>> {
+        self.which_key = false;
+        // The char after keys such as `f` is read as it is, whatever it is bound to
+        if self.modal_editing && self.modal.awaiting() {
+            let action: Option<Action> = self.modal.key(key, &self.mode);
+            return action.map_or(Ok(()), |v| self.perform(v));
+        }
+
+        self.pending.push(key);
+        let mut lookup: Lookup = self.lookup(&self.pending, false);
+        // Keys bound for a mode come before vi commands, and global keys after
+        if self.modal_editing && matches!(lookup, Lookup::Undefined) {
+            if let [key] = self.pending[..] {
+                if let Some(action) = self.modal.key(key, &self.mode) {
+                    self.pending.clear();
+                    return self.perform(action);
+                }
+            }
+            lookup = self.lookup(&self.pending, true);
+        }
+
+        match lookup {
+            Lookup::Found(binding) => {
+                self.pending.clear();
+                self.end_operator();
+                self.run(&binding)
+            }
+            // Keys typed so far are echoed, as they are waiting for more
+            Lookup::Prefix(_) => {
+                let message: String = format!("{}-", keys_to_string(&self.pending));
+                self.set_message(message);
+                Ok(())
+            }
+            Lookup::Undefined => {
+                self.end_operator();
+                let keys: Vec<Key> = std::mem::take(&mut self.pending);
+                match (&keys[..], key.char()) {
+                    ([_], Some(v)) if self.mode == modal::INSERT => {
+                        self.insert(v.encode_utf8(&mut [0; 4]))
+                    }
+                    // C-g after a prefix cancels it, rather than being undefined
+                    ([_, ..], _) if key == Key::ctrl(KeyCode::Char('g')) => {
+                        self.set_message("Quit");
+                        Ok(())
+                    }
+                    _ => Err(format!("{} is undefined", keys_to_string(&keys)).into()),
+                }
+            }
+        }
+    }
+
+    /// Looks `keys` up in the keymaps. With modal editing the global keymap
+    /// is only tried if `global`, after the vi commands.
+    fn lookup(&self, keys: &[Key], global: bool) -> Lookup {
+        let keymaps = &self.script.host().keymaps;
+        match self.modal_editing && !global {
+            true => keymaps.lookup_mode(&self.mode, self.filetype().as_deref(), keys),
+            false => keymaps.lookup(&self.mode, self.filetype().as_deref(), keys),
+        }
+    }
+
+    /// The keys which can follow the keys typed so far, with what they run.
+    pub fn continuations(&self) -> Vec<(Key, String)> {
+        self.script.host().keymaps.continuations(
+            &self.mode,
+            self.filetype().as_deref(),
+            &self.pending,
+        )
+    }
+
+    fn run(&mut self, binding: &Binding) -> Result<(), Box<dyn std::error::Error>> {
+        match binding {
+            Binding::Command(name) => self.run_command(name),
+            Binding::Procedure(procedure) => self.call(procedure),
+        }
+    }
+
+    /// Runs a built-in command, or one defined by `init.core`.
+    pub fn run_command(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(command) = command::builtin(name) {
+            return (command.run)(self);
+        }
+        let procedure: Object = self
+            .script
+            .host()
+            .commands
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown command {}", name))?;
+
+        self.call(&procedure)
+    }
+
+    /// Calls a procedure from `init.core`, then does what it asked for.
+    fn call(&mut self, procedure: &Object) -> Result<(), Box<dyn std::error::Error>> {
+        if self.depth >= MAX_COMMAND_DEPTH {
+            return Err("commands are nested too deeply".into());
+        }
+
+        let context: Context = self.context();
+        let effects: Vec<Effect> = self.script.call(procedure, context)?;
+        self.depth += 1;
+        let result: Result<(), Box<dyn std::error::Error>> = self.apply(effects);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn apply(&mut self, effects: Vec<Effect>) -> Result<(), Box<dyn std::error::Error>> {
+        for effect in effects {
+            match effect {
+                Effect::Insert(v) => self.insert(&v)?,
+                Effect::Message(v) => self.set_message(v),
+                Effect::Command(v) => self.run_command(&v)?,
+                Effect::UndoTo(v) => self.undo_to(v)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn context(&self) -> Context {
+        Context {
+            file_name: self.buffer.path().map(|v| v.display().to_string()),
+            mode: self.mode.clone(),
+            filetype: self.filetype(),
+        }
+    }
+
+    /// Saves the buffer, first asking whether to overwrite or reload if the
+    /// file changed on disk.
+    pub fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.buffer.changed_on_disk()? {
+            self.prompt = Some(Prompt::ChangedOnDisk);
+            self.set_message(format!(
+                "{} changed on disk; overwrite (o), reload (r) or cancel?",
+                self.name
+            ));
+            return Ok(());
+        }
+
+        self.buffer.save(&self.save_options)?;
+        self.saved();
+
+        Ok(())
+    }
+
+    /// Reports a save, and saves the undo history too if it is kept. The file
+    /// is saved even if its history cannot be.
+    fn saved(&mut self) {
+        let written = self
+            .save_options
+            .undo_directory
+            .as_ref()
+            .map(|v| self.buffer.write_history(v));
+        match written {
+            Some(Err(e)) => self.set_message(format!(
+                "Wrote {}, but not its undo history: {}",
+                self.name, e
+            )),
+            _ => self.set_message(format!("Wrote {}", self.name)),
+        }
+    }
+
+    /// Replaces the buffer's text with what is on disk, keeping the cursor on
+    /// the same line and column where possible.
+    pub fn reload(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let position: Position = self.position();
+        self.buffer.reload()?;
+        let line: usize = position.line.min(self.buffer.len_lines() - 1);
+        self.cursor = self
+            .buffer
+            .position_to_offset(Position { line, ..position })?;
+        self.goal = None;
+        self.set_message(format!("Reverted {}", self.name));
+
+        Ok(())
+    }
+
+    /// The name of the buffer shown.
+    pub fn buffer_name(&self) -> &str {
+        &self.name
+    }
+
+    /// The names of the open buffers: the one shown, then the others from
+    /// the one shown last.
+    pub fn buffer_names(&self) -> Vec<String> {
+        std::iter::once(&self.name)
+            .chain(self.buffers.iter().map(|v| &v.name))
+            .cloned()
+            .collect()
+    }
+
+    /// Shows the file at `path`, reading it unless a buffer has it already.
+    pub fn open(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let has = |buffer: &Buffer| buffer.path().is_some_and(|v| same_file(v, path));
+        if has(&self.buffer) {
+            return Ok(());
+        }
+        if let Some(i) = self.buffers.iter().position(|v| has(&v.buffer)) {
+            let background: Background = self.buffers.remove(i);
+            self.show(background);
+            return Ok(());
+        }
+
+        let buffer: Buffer = self.read(path)?;
+        self.show(Background::new(buffer, path.display().to_string()));
+
+        Ok(())
+    }
+
+    /// Reads the file at `path`, with its undo history if that is kept.
+    fn read(&mut self, path: &Path) -> Result<Buffer, Box<dyn std::error::Error>> {
+        let mut buffer: Buffer = Buffer::open(path)?;
+        if let Some(directory) = self.save_options.undo_directory.clone() {
+            if let Err(e) = buffer.read_history(&directory) {
+                self.set_message(format!("{}: {}", path.display(), e));
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    /// Shows a new buffer without a file.
+    pub fn new_buffer(&mut self) {
+        let name: String = (1..)
+            .map(|n| match n {
+                1 => String::from(SCRATCH),
+                n => format!("{}<{}>", SCRATCH, n),
+            })
+            .find(|v| *v != self.name && self.buffers.iter().all(|b| b.name != *v))
+            .unwrap_or_default();
+
+        self.show(Background::new(Buffer::new(), name));
+    }
+
+    /// Shows the open buffer called `name`.
+    pub fn switch_to(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if name == self.name {
+            return Ok(());
+        }
+        let i: usize = self
+            .buffers
+            .iter()
+            .position(|v| v.name == name)
+            .ok_or_else(|| format!("there is no buffer named {}", name))?;
+        let background: Background = self.buffers.remove(i);
+        self.show(background);
+
+        Ok(())
+    }
+
+    /// Asks for the buffer to switch to, matching its name fuzzily. With
+    /// nothing typed it is the one shown before this one.
+    pub fn switch_buffer(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.buffers.is_empty() {
+            return Err("There is no other buffer".into());
+        }
+        self.prompt = Some(Prompt::Read(Reading::Buffer, Minibuffer::new()));
+
+        Ok(())
+    }
+
+    /// Lists the open buffers until the next key.
+    pub fn list_buffers(&mut self) {
+        self.listing = true;
+    }
+
+    /// Asks for the name of a file to open, completing it from the files
+    /// on disk.
+    pub fn find_file(&mut self) {
+        self.prompt = Some(Prompt::Read(Reading::File, Minibuffer::new()));
+    }
+
+    /// Asks for a command to run, matching its name fuzzily. With nothing
+    /// typed the commands run this way come first, the last run first.
+    pub fn execute_command(&mut self) {
+        self.prompt = Some(Prompt::Read(Reading::Command, Minibuffer::new()));
+    }
+
+    /// Asks for a name defined in `init.core` or by the editor, and shows
+    /// the first line of its documentation.
+    pub fn describe_symbol(&mut self) {
+        self.prompt = Some(Prompt::Read(Reading::Symbol, Minibuffer::new()));
+    }
+
+    /// The names of the commands, built in or from `init.core`, sorted.
+    pub fn command_names(&self) -> Vec<String> {
+        let host = self.script.host();
+        let mut names: Vec<String> = command::BUILTIN
+            .iter()
+            .map(|v| v.name.to_string())
+            .chain(host.commands.keys().cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// The candidates for `reading` which `text` matches, best first.
+    fn candidates(&self, reading: Reading, text: &str) -> Vec<String> {
+        let names: Vec<String> = match reading {
+            Reading::File => return minibuffer::files(text),
+            Reading::Buffer => return self.buffer_matches(text),
+            Reading::Command => {
+                let recent: Vec<String> = self
+                    .answers
+                    .get(&reading)
+                    .map_or(Vec::new(), |v| v.iter().rev().cloned().collect());
+                let rest: Vec<String> = self
+                    .command_names()
+                    .into_iter()
+                    .filter(|v| !recent.contains(v))
+                    .collect();
+                recent.into_iter().chain(rest).collect()
+            }
+            Reading::Symbol => help::apropos(self.script.interpreter(), "")
+                .into_iter()
+                .map(|v| v.name)
+                .collect(),
+        };
+
+        fuzzy::rank(text, &names)
+            .into_iter()
+            .map(|i| names[i].clone())
+            .collect()
+    }
+
+    /// Does what the minibuffer read `text` for. Files are opened as typed,
+    /// and otherwise the `selected` candidate is taken.
+    fn accept(
+        &mut self,
+        reading: Reading,
+        text: String,
+        selected: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let answer: String = match reading {
+            Reading::File if text.is_empty() => return Ok(()),
+            Reading::File => text,
+            _ => self
+                .candidates(reading, &text)
+                .into_iter()
+                .nth(selected)
+                .ok_or_else(|| format!("No {} matches {}", reading.noun(), text))?,
+        };
+        let answers: &mut Vec<String> = self.answers.entry(reading).or_default();
+        answers.retain(|v| *v != answer);
+        answers.push(answer.clone());
+
+        match reading {
+            Reading::Command => self.run_command(&answer),
+            Reading::File => self.open(Path::new(&answer)),
+            Reading::Buffer => self.switch_to(&answer),
+            Reading::Symbol => {
+                let help: Option<Help> = help::describe(self.script.interpreter(), &answer);
+                let message: String = match help.as_ref().and_then(Help::summary) {
+                    Some(v) => format!("{}: {}", answer, v),
+                    None => format!("{} is not documented", answer),
+                };
+                self.set_message(message);
+                Ok(())
+            }
+        }
+    }
+
+    /// The shortest keys which run the command `name` in the global
+    /// keymap.
+    fn keys_for(&self, name: &str) -> Option<String> {
+        let host = self.script.host();
+
+        host.keymaps
+            .global
+            .bindings()
+            .filter(|(_, v)| matches!(v, Binding::Command(v) if v == name))
+            .map(|(keys, _)| keys)
+            .min_by_key(|v| v.len())
+            .map(keys_to_string)
+    }
+
+    /// Closes the buffer shown, first asking whether to save it if it has
+    /// changes which are not saved.
+    pub fn close_buffer(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.buffer.modified() {
+            self.prompt = Some(Prompt::CloseModified);
+            self.set_message(format!(
+                "{} is modified; save (s), discard (d) or cancel?",
+                self.name
+            ));
+            return Ok(());
+        }
+
+        self.discard_buffer();
+        Ok(())
+    }
+
+    /// Closes the buffer shown without saving it, and shows the one shown
+    /// before it, or a new scratch buffer if it was the last.
+    fn discard_buffer(&mut self) {
+        let next: Background = match self.buffers.is_empty() {
+            true => Background::new(Buffer::new(), String::from(SCRATCH)),
+            false => self.buffers.remove(0),
+        };
+        let closed: Background = self.replace_buffer(next);
+        // Other windows which showed it show this one instead
+        let views = self
+            .views
+            .iter_mut()
+            .chain(self.tabs.iter_mut().flat_map(|v| v.views.iter_mut()));
+        for view in views.filter(|v| v.name == closed.name) {
+            *view = View {
+                window: view.window,
+                name: self.name.clone(),
+                position: Position::default(),
+                top: 0,
+                left: 0,
+            };
+        }
+        self.set_message(format!("Closed {}", closed.name));
+    }
+
+    /// Shows `background`, keeping the buffer shown now as the first of the
+    /// others.
+    fn show(&mut self, background: Background) {
+        let previous: Background = self.replace_buffer(background);
+        self.buffers.insert(0, previous);
+    }
+
+    fn replace_buffer(&mut self, next: Background) -> Background {
+        self.commit();
+        // A selection belongs to the buffer it was made in
+        if self.anchor.is_some() {
+            self.escape();
+        }
+
+        let previous: Background = Background {
+            buffer: std::mem::replace(&mut self.buffer, next.buffer),
+            name: std::mem::replace(&mut self.name, next.name),
+            cursor: self.cursor,
+            top: self.top,
+            left: self.left,
+        };
+        self.cursor = next.cursor;
+        self.top = next.top;
+        self.left = next.left;
+        self.goal = None;
+        self.keep_on_char();
+
+        previous
+    }
+
+    /// The names of the other buffers which `text` matches, best first.
+    fn buffer_matches(&self, text: &str) -> Vec<String> {
+        let names: Vec<&str> = self.buffers.iter().map(|v| v.name.as_str()).collect();
+
+        fuzzy::rank(text, &names)
+            .into_iter()
+            .map(|i| names[i].to_string())
+            .collect()
+    }
+
+    /// The window with the cursor.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// How the screen is shared between the windows of the tab shown.
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// The name of the buffer each window of the tab shown shows, from the
+    /// top left.
+    pub fn window_buffers(&self) -> Vec<(usize, String)> {
+        self.layout
+            .windows()
+            .into_iter()
+            .map(
+                |window| match self.views.iter().find(|v| v.window == window) {
+                    Some(view) => (window, view.name.clone()),
+                    None => (window, self.name.clone()),
+                },
+            )
+            .collect()
+    }
+
+    /// The tab shown, counting from 0, and how many tabs there are.
+    pub fn tab(&self) -> (usize, usize) {
+        (self.tab, self.tabs.len() + 1)
+    }
+
+    /// Divides the window with the cursor in two, both showing its buffer.
+    /// The cursor stays in the top or left one.
+    pub fn split_window(&mut self, direction: Direction) {
+        self.unzoom();
+        let window: usize = self.next_window;
+        self.next_window += 1;
+        self.layout.split(self.window, window, direction);
+        self.views.push(View {
+            window,
+            ..self.view()
+        });
+    }
+
+    /// Closes the window with the cursor, which goes to the next window.
+    pub fn close_window(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.unzoom();
+        if self.views.is_empty() {
+            return Err("Cannot close the only window".into());
+        }
+
+        let closed: usize = self.window;
+        self.other_window(1);
+        self.views.retain(|v| v.window != closed);
+        self.layout.remove(closed);
+
+        Ok(())
+    }
+
+    /// Closes every window but the one with the cursor.
+    pub fn only_window(&mut self) {
+        self.zoomed = None;
+        self.views.clear();
+        self.layout = Layout::Window(self.window);
+    }
+
+    /// Gives the whole tab to the window with the cursor, or gives the other
+    /// windows back their places if it has it already.
+    pub fn maximize_window(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.zoomed.is_some() {
+            self.unzoom();
+            return Ok(());
+        }
+        if self.views.is_empty() {
+            return Err("There is only one window".into());
+        }
+
+        let layout: Layout = std::mem::replace(&mut self.layout, Layout::Window(self.window));
+        self.zoomed = Some(layout);
+
+        Ok(())
+    }
+
+    fn unzoom(&mut self) {
+        if let Some(layout) = self.zoomed.take() {
+            self.layout = layout;
+        }
+    }
+
+    /// Moves the cursor `count` windows on, from the top left, or back if
+    /// it is negative.
+    pub fn other_window(&mut self, count: isize) {
+        self.unzoom();
+        let windows: Vec<usize> = self.layout.windows();
+        let i: usize = self.layout_index(self.window);
+        let next: usize = (i as isize + count).rem_euclid(windows.len() as isize) as usize;
+        self.focus(windows[next]);
+    }
+
+    /// Moves the cursor to the window on `side` of the one it is in.
+    pub fn window_toward(&mut self, side: Side) -> Result<(), Box<dyn std::error::Error>> {
+        let window: usize = self
+            .layout
+            .neighbor(self.window, side, self.area)
+            .ok_or_else(|| {
+                let place: &str = match side {
+                    Side::Left => "to the left",
+                    Side::Right => "to the right",
+                    Side::Up => "above",
+                    Side::Down => "below",
+                };
+                format!("There is no window {}", place)
+            })?;
+        self.focus(window);
+
+        Ok(())
+    }
+
+    /// Exchanges the place of the window with the cursor with the next one,
+    /// which the cursor goes along with.
+    pub fn swap_window(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.unzoom();
+        let windows: Vec<usize> = self.layout.windows();
+        if windows.len() < 2 {
+            return Err("There is only one window".into());
+        }
+
+        let i: usize = self.layout_index(self.window);
+        self.layout
+            .swap(self.window, windows[(i + 1) % windows.len()]);
+
+        Ok(())
+    }
+
+    /// Makes the window with the cursor `cells` taller, or wider if
+    /// `direction` is `Right`, or smaller if `cells` is negative.
+    pub fn resize_window(
+        &mut self,
+        direction: Direction,
+        cells: i32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.unzoom();
+        if !self.layout.resize(self.window, direction, cells, self.area) {
+            return Err(match direction {
+                Direction::Below => "There is no window above or below",
+                Direction::Right => "There is no window to the left or right",
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Where `window` comes among the windows of the layout shown.
+    fn layout_index(&self, window: usize) -> usize {
+        self.layout
+            .windows()
+            .iter()
+            .position(|v| *v == window)
+            .unwrap_or(0)
+    }
+
+    /// The window with the cursor, as it is left when the cursor goes to
+    /// another.
+    fn view(&self) -> View {
+        View {
+            window: self.window,
+            name: self.name.clone(),
+            position: self.position(),
+            top: self.top,
+            left: self.left,
+        }
+    }
+
+    /// Moves the cursor to `window` of the tab shown.
+    fn focus(&mut self, window: usize) {
+        let Some(i) = self.views.iter().position(|v| v.window == window) else {
+            return;
+        };
+        let view: View = self.views.remove(i);
+        let previous: View = self.view();
+        self.views.push(previous);
+        self.enter(view);
+    }
+
+    /// Shows `view` in the window with the cursor, and gives the cursor to
+    /// it.
+    fn enter(&mut self, view: View) {
+        self.commit();
+        if self.anchor.is_some() {
+            self.escape();
+        }
+        // A buffer which was closed meanwhile leaves the one shown
+        if view.name != self.name && self.switch_to(&view.name).is_err() {
+            self.window = view.window;
+            return;
+        }
+
+        self.window = view.window;
+        self.cursor = offset_of(&self.buffer, view.position);
+        self.top = view.top;
+        self.left = view.left;
+        self.goal = None;
+        self.keep_on_char();
+    }
+
+    /// Opens a new tab after the one shown, with one window showing the
+    /// buffer.
+    pub fn new_tab(&mut self) {
+        let tab: Tab = self.pack();
+        self.tabs.insert(self.tab, tab);
+        self.tab += 1;
+
+        let window: usize = self.next_window;
+        self.next_window += 1;
+        self.layout = Layout::Window(window);
+        self.window = window;
+    }
+
+    /// Shows the tab `count` tabs on, or back if it is negative.
+    pub fn next_tab(&mut self, count: isize) -> Result<(), Box<dyn std::error::Error>> {
+        if self.tabs.is_empty() {
+            return Err("There is only one tab".into());
+        }
+
+        let next: usize =
+            (self.tab as isize + count).rem_euclid(self.tabs.len() as isize + 1) as usize;
+        let tab: Tab = self.pack();
+        self.tabs.insert(self.tab, tab);
+        let tab: Tab = self.tabs.remove(next);
+        self.tab = next;
+        self.unpack(tab);
+
+        Ok(())
+    }
+
+    /// Closes the tab shown and its windows, showing the next tab.
+    pub fn close_tab(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.tabs.is_empty() {
+            return Err("Cannot close the only tab".into());
+        }
+
+        if self.tab == self.tabs.len() {
+            self.tab -= 1;
+        }
+        let tab: Tab = self.tabs.remove(self.tab);
+        self.unpack(tab);
+
+        Ok(())
+    }
+
+    /// Takes the windows of the tab shown, leaving it with no other windows.
+    fn pack(&mut self) -> Tab {
+        let mut views: Vec<View> = std::mem::take(&mut self.views);
+        views.push(self.view());
+
+        Tab {
+            layout: self.layout.clone(),
+            window: self.window,
+            views,
+            zoomed: self.zoomed.take(),
+        }
+    }
+
+    fn unpack(&mut self, mut tab: Tab) {
+        let i: usize = tab
+            .views
+            .iter()
+            .position(|v| v.window == tab.window)
+            .unwrap_or(0);
+        let view: View = tab.views.remove(i);
+        self.layout = tab.layout;
+        self.views = tab.views;
+        self.zoomed = tab.zoomed;
+        self.enter(view);
+    }
+
+    /// The windows of the tab shown and what each one shows, as text which
+    /// [`Editor::apply_layout`] reads. The first line is the layout, and
+    /// each line after it a window, starting with the one with the cursor:
+    /// its number, line and column, the first line and column on screen,
+    /// and the name of its buffer.
+    pub fn layout_text(&self) -> String {
+        let mut text: String = format!("{}\n", self.zoomed.as_ref().unwrap_or(&self.layout));
+        for view in std::iter::once(&self.view()).chain(&self.views) {
+            text.push_str(&format!(
+                "{} {}:{} {} {} {}\n",
+                view.window,
+                view.position.line,
+                view.position.column,
+                view.top,
+                view.left,
+                view.name
+            ));
+        }
+
+        text
+    }
+
+    /// Replaces the windows of the tab shown with those `text` describes,
+    /// opening the buffers they show.
+    pub fn apply_layout(&mut self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut lines = text.lines();
+        let layout: Layout = lines
+            .next()
+            .ok_or("LAYOUT_ERROR: the layout is empty")?
+            .parse()?;
+        let mut views: Vec<View> = lines.map(parse_view).collect::<Result<_, _>>()?;
+        let mut windows: Vec<usize> = views.iter().map(|v| v.window).collect();
+        windows.sort();
+        let mut expected: Vec<usize> = layout.windows();
+        expected.sort();
+        if windows != expected {
+            return Err("LAYOUT_ERROR: the windows do not match the layout".into());
+        }
+
+        for view in &views {
+            if view.name != self.name && self.buffers.iter().all(|v| v.name != view.name) {
+                let buffer: Buffer = match view.name.starts_with(SCRATCH) {
+                    true => Buffer::new(),
+                    false => self.read(Path::new(&view.name))?,
+                };
+                self.buffers
+                    .push(Background::new(buffer, view.name.clone()));
+            }
+        }
+        self.next_window = self
+            .next_window
+            .max(windows.iter().max().map_or(0, |v| v + 1));
+        let view: View = views.remove(0);
+        self.layout = layout;
+        self.views = views;
+        self.zoomed = None;
+        self.enter(view);
+
+        Ok(())
+    }
+
+    /// Writes the windows of the tab shown to the data directory, for
+    /// `restore-layout` to read back, in this session or a later one.
+    pub fn save_layout(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let path: PathBuf = self.layout_path()?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(&path, self.layout_text())?;
+        self.set_message("Saved the layout");
+
+        Ok(())
+    }
+
+    /// Shows the windows which `save-layout` wrote last.
+    pub fn restore_layout(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let path: PathBuf = self.layout_path()?;
+        let text: String =
+            std::fs::read_to_string(&path).map_err(|_| "There is no saved layout")?;
+
+        self.apply_layout(&text)
+    }
+
+    fn layout_path(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let directory: &PathBuf = self
+            .data_directory
+            .as_ref()
+            .ok_or("there is no data directory to keep the layout in")?;
+
+        Ok(directory.join("layout"))
+    }
+
+    /// Inserts `text` before the cursor.
+    pub fn insert(&mut self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.buffer.insert(self.cursor, text)?;
+        self.cursor += text.len();
+        self.goal = None;
+
+        Ok(())
+    }
+
+    pub fn delete_backward(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let start: usize = self.buffer.prev_grapheme_boundary(self.cursor)?;
+        self.buffer.delete(start..self.cursor)?;
+        self.cursor = start;
+        self.goal = None;
+
+        Ok(())
+    }
+
+    pub fn delete_forward(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let end: usize = self.buffer.next_grapheme_boundary(self.cursor)?;
+        self.buffer.delete(self.cursor..end)?;
+        self.goal = None;
+
+        Ok(())
+    }
+
+    pub fn move_left(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.cursor = self.buffer.prev_grapheme_boundary(self.cursor)?;
+        self.goal = None;
+
+        Ok(())
+    }
+
+    pub fn move_right(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.cursor = self.buffer.next_grapheme_boundary(self.cursor)?;
+        self.goal = None;
+
+        Ok(())
+    }
+
+    /// Moves the cursor down by `count` screens, or up if it is negative.
+    pub fn move_pages(&mut self, count: isize) {
+        self.move_lines(count * self.page as isize);
+    }
+
+    /// Moves the cursor down by `count` lines, or up if it is negative,
+    /// keeping to the same column on screen where the line is long enough.
+    pub fn move_lines(&mut self, count: isize) {
+        let position: Position = self.position();
+        let goal: usize = *self.goal.get_or_insert(self.column());
+        let line: usize = position
+            .line
+            .saturating_add_signed(count)
+            .min(self.buffer.len_lines() - 1);
+
+        if let Some(range) = self.buffer.line_range(line) {
+            let text: String = self.buffer.line(line).unwrap_or_default();
+            self.cursor = range.start + offset_at_column(&text, goal);
+        }
+    }
+
+    pub fn move_to_line_start(&mut self) {
+        if let Some(range) = self.buffer.line_range(self.position().line) {
+            self.cursor = range.start;
+        }
+        self.goal = None;
+    }
+
+    pub fn move_to_line_end(&mut self) {
+        if let Some(range) = self.buffer.line_range(self.position().line) {
+            self.cursor = range.end;
+        }
+        self.goal = None;
+    }
+
+    /// The column of the cursor on screen, before scrolling.
+    fn column(&self) -> usize {
+        self.column_of(self.cursor)
+    }
+
+    /// The column on screen of `offset`, before scrolling.
+    fn column_of(&self, offset: usize) -> usize {
+        column_in(&self.buffer, offset)
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.buffer
+            .offset_to_position(offset)
+            .unwrap_or_default()
+            .line
+    }
+
+    fn line_range_of(&self, offset: usize) -> Range<usize> {
+        self.buffer
+            .line_range(self.line_of(offset))
+            .unwrap_or_default()
+    }
+
+    /// Carries out a vi command.
+    fn perform(&mut self, action: Action) -> Result<(), Box<dyn std::error::Error>> {
+        if self.mode == modal::OPERATOR_PENDING {
+            self.mode = String::from(modal::NORMAL);
+        }
+
+        match action {
+            Action::Pending => (),
+            Action::Operator(_) => self.mode = String::from(modal::OPERATOR_PENDING),
+            Action::Move(Motion::Up, count) => self.move_lines(-(count as isize)),
+            Action::Move(Motion::Down, count) => self.move_lines(count as isize),
+            Action::Move(motion, count) => {
+                if let Some(target) = modal::target(&self.buffer, self.cursor, motion, count)? {
+                    self.cursor = target.offset;
+                    self.goal = None;
+                }
+            }
+            Action::Operate(operator, motion, count) => self.operate(operator, motion, count)?,
+            Action::OperateLines(operator, count) => {
+                let line: usize = self.position().line;
+                let last: usize = (line + count - 1).min(self.buffer.len_lines() - 1);
+                self.operate_lines(operator, line, last)?;
+            }
+            Action::Insert(at) => self.start_insert(at)?,
+            Action::Put { before, count } => self.put(before, count)?,
+            Action::Replace(c, count) => self.replace_chars(c, count)?,
+            Action::Visual(mode) if self.mode == mode => self.escape(),
+            Action::Visual(mode) => {
+                self.anchor.get_or_insert(self.cursor);
+                self.mode = String::from(mode);
+            }
+            Action::SwapAnchor => {
+                if let Some(anchor) = self.anchor.replace(self.cursor) {
+                    self.cursor = anchor;
+                    self.goal = None;
+                }
+            }
+            Action::OperateSelection(operator) => self.operate_selection(operator)?,
+            Action::Undo(count) => (0..count).try_for_each(|_| self.undo())?,
+            Action::Redo(count) => (0..count).try_for_each(|_| self.redo())?,
+            Action::Earlier(count) => (0..count).try_for_each(|_| self.undo_earlier())?,
+            Action::Later(count) => (0..count).try_for_each(|_| self.undo_later())?,
+            Action::Escape if self.mode == modal::INSERT => {
+                self.escape();
+                // Leaving insert mode steps back onto the last char typed
+                if self.cursor > self.line_range_of(self.cursor).start {
+                    self.cursor = self.buffer.prev_grapheme_boundary(self.cursor)?;
+                }
+            }
+            Action::Search { forward } => self.search(forward, true),
+            Action::SearchNext { reverse, count } => {
+                (0..count).try_for_each(|_| self.search_next(reverse))?
+            }
+            Action::Escape => self.escape(),
+        }
+        self.keep_on_char();
+
+        Ok(())
+    }
+
+    /// Cancels an operator waiting for a motion, when a key other than a
+    /// motion came.
+    fn end_operator(&mut self) {
+        if self.mode == modal::OPERATOR_PENDING {
+            self.escape();
+        }
+    }
+
+    fn escape(&mut self) {
+        self.modal.escape();
+        self.anchor = None;
+        self.mode = String::from(modal::NORMAL);
+    }
+
+    /// In normal mode the cursor is on a char, so it cannot be at the end of
+    /// a line unless the line is empty.
+    fn keep_on_char(&mut self) {
+        let range: Range<usize> = self.line_range_of(self.cursor);
+        if self.mode == modal::NORMAL && self.cursor >= range.end && range.end > range.start {
+            self.cursor = self
+                .buffer
+                .prev_grapheme_boundary(range.end)
+                .unwrap_or(range.start);
+        }
+    }
+
+    fn operate(
+        &mut self,
+        operator: Operator,
+        motion: Motion,
+        count: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let on_blank: bool = self
+            .buffer
+            .chars_from(self.cursor)
+            .next()
+            .is_none_or(|(_, v)| v.is_whitespace());
+        let motion: Motion = match motion {
+            // `cw` changes to the end of the word, keeping the blank after it
+            Motion::WordForward { big } if operator == Operator::Change && !on_blank => {
+                Motion::WordEnd { big }
+            }
+            v => v,
+        };
+        let Some(target) = modal::target(&self.buffer, self.cursor, motion, count)? else {
+            return Ok(());
+        };
+        if target.linewise {
+            let (line, other) = (self.position().line, self.line_of(target.offset));
+            return self.operate_lines(operator, line.min(other), line.max(other));
+        }
+
+        let start: usize = self.cursor.min(target.offset);
+        let mut end: usize = self.cursor.max(target.offset);
+        if target.inclusive {
+            end = self.buffer.next_grapheme_boundary(end)?;
+        }
+        // `dw` on the last word of a line stops at the end of the line
+        let line_end: usize = self.line_range_of(start).end;
+        if matches!(motion, Motion::WordForward { .. }) && end > line_end && line_end > start {
+            end = line_end;
+        }
+        let text: String = self.buffer.slice(start..end)?;
+
+        self.take(operator, start..end, Register::Chars(text))
+    }
+
+    /// Applies `operator` to the lines from `first` to `last`.
+    fn operate_lines(
+        &mut self,
+        operator: Operator,
+        first: usize,
+        last: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let start: usize = self.buffer.line_start(first).unwrap_or_default();
+        let after: Option<usize> = self.buffer.line_start(last + 1);
+        let mut text: String = self
+            .buffer
+            .slice(start..after.unwrap_or(self.buffer.len()))?;
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+
+        let range: Range<usize> = match (operator, after) {
+            (Operator::Yank, _) => {
+                self.register = Register::Lines(text);
+                if first < self.position().line {
+                    self.cursor = start;
+                }
+                return Ok(());
+            }
+            // Changing lines keeps one, empty, to type into
+            (Operator::Change, _) => {
+                let end: usize = self.buffer.line_range(last).unwrap_or_default().end;
+                let indent: usize = self.line_indent(first);
+                start + indent..end
+            }
+            (Operator::Delete, Some(v)) => start..v,
+            // The last line has no line break after it, so the one before it goes
+            (Operator::Delete, None) if first > 0 => {
+                self.buffer.line_range(first - 1).unwrap_or_default().end..self.buffer.len()
+            }
+            (Operator::Delete, None) => start..self.buffer.len(),
+        };
+        self.take(operator, range, Register::Lines(text))?;
+        if operator == Operator::Delete {
+            let line: usize = first.min(modal::last_line(&self.buffer));
+            let start: usize = self.buffer.line_start(line).unwrap_or_default();
+            self.cursor = start + self.line_indent(line);
+        }
+
+        Ok(())
+    }
+
+    /// The bytes of blank at the start of `line`.
+    fn line_indent(&self, line: usize) -> usize {
+        let text: String = self.buffer.line(line).unwrap_or_default();
+
+        text.len() - text.trim_start().len()
+    }
+
+    /// Moves `range` into the register, deleting it unless yanking.
+    fn take(
+        &mut self,
+        operator: Operator,
+        range: Range<usize>,
+        register: Register,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.register = register;
+        if operator != Operator::Yank {
+            self.buffer.delete(range.clone())?;
+        }
+        if operator == Operator::Change {
+            self.mode = String::from(modal::INSERT);
+        }
+        self.cursor = range.start;
+        self.goal = None;
+
+        Ok(())
+    }
+
+    fn operate_selection(&mut self, operator: Operator) -> Result<(), Box<dyn std::error::Error>> {
+        let anchor: usize = self.anchor.take().unwrap_or(self.cursor);
+        let mode: String = std::mem::replace(&mut self.mode, String::from(modal::NORMAL));
+        let (start, end) = (anchor.min(self.cursor), anchor.max(self.cursor));
+
+        match mode.as_str() {
+            modal::VISUAL_LINE => {
+                self.operate_lines(operator, self.line_of(start), self.line_of(end))
+            }
+            modal::VISUAL_BLOCK => {
+                let columns: Range<usize> = self.block_columns(anchor);
+                let mut ranges: Vec<Range<usize>> = Vec::new();
+                for line in self.line_of(start)..=self.line_of(end) {
+                    let range: Range<usize> = self.buffer.line_range(line).unwrap_or_default();
+                    let text: String = self.buffer.line(line).unwrap_or_default();
+                    ranges.push(
+                        range.start + offset_at_column(&text, columns.start)
+                            ..range.start + offset_at_column(&text, columns.end),
+                    );
+                }
+                let rows: Vec<String> = ranges
+                    .iter()
+                    .map(|v| self.buffer.slice(v.clone()))
+                    .collect::<Result<Vec<String>, _>>()?;
+                // From the bottom up, so that the offsets above stay put
+                if operator != Operator::Yank {
+                    for range in ranges[1..].iter().rev() {
+                        self.buffer.delete(range.clone())?;
+                    }
+                }
+                self.take(operator, ranges[0].clone(), Register::Block(rows))
+            }
+            _ => {
+                let end: usize = self.buffer.next_grapheme_boundary(end)?;
+                let text: String = self.buffer.slice(start..end)?;
+                self.take(operator, start..end, Register::Chars(text))
+            }
+        }
+    }
+
+    /// The columns on screen between the cursor and `anchor`, including the
+    /// char under each.
+    fn block_columns(&self, anchor: usize) -> Range<usize> {
+        let edges: [usize; 2] = [self.column_of(anchor), self.column_of(self.cursor)];
+        let right: usize = *edges.iter().max().unwrap_or(&0);
+
+        *edges.iter().min().unwrap_or(&0)..right + 1
+    }
+
+    fn start_insert(&mut self, at: Insert) -> Result<(), Box<dyn std::error::Error>> {
+        let range: Range<usize> = self.line_range_of(self.cursor);
+        self.mode = String::from(modal::INSERT);
+        self.goal = None;
+
+        match at {
+            Insert::Before => (),
+            Insert::After if self.cursor < range.end => self.move_right()?,
+            Insert::After => (),
+            Insert::LineStart => {
+                self.cursor = range.start + self.line_indent(self.line_of(range.start))
+            }
+            Insert::LineEnd => self.cursor = range.end,
+            Insert::LineBelow => {
+                self.cursor = range.end;
+                self.insert("\n")?;
+            }
+            Insert::LineAbove => {
+                self.cursor = range.start;
+                self.insert("\n")?;
+                self.cursor = range.start;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Puts the register after the cursor, or before it, `count` times.
+    fn put(&mut self, before: bool, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let range: Range<usize> = self.line_range_of(self.cursor);
+        let after: usize = match self.cursor < range.end {
+            true => self.buffer.next_grapheme_boundary(self.cursor)?,
+            false => self.cursor,
+        };
+
+        match self.register.clone() {
+            Register::Chars(text) => {
+                let at: usize = if before { self.cursor } else { after };
+                let text: String = text.repeat(count);
+                self.buffer.insert(at, &text)?;
+                self.cursor = match text.is_empty() {
+                    true => at,
+                    false => self.buffer.prev_grapheme_boundary(at + text.len())?,
+                };
+            }
+            Register::Lines(text) => {
+                let line: usize = self.line_of(self.cursor);
+                let text: String = text.repeat(count);
+                let at: Option<usize> = match before {
+                    true => self.buffer.line_start(line),
+                    false => self.buffer.line_start(line + 1),
+                };
+                let at: usize = match at {
+                    Some(v) => {
+                        self.buffer.insert(v, &text)?;
+                        v
+                    }
+                    // After the last line, which has no line break to put them after
+                    None => {
+                        let end: usize = self.buffer.len();
+                        let text: &str = text.strip_suffix('\n').unwrap_or(&text);
+                        self.buffer.insert(end, &format!("\n{}", text))?;
+                        end + 1
+                    }
+                };
+                self.cursor = at + self.line_indent(self.line_of(at));
+            }
+            Register::Block(rows) => {
+                let column: usize = match before {
+                    true => self.column(),
+                    false => self.column_of(after),
+                };
+                let first: usize = self.line_of(self.cursor);
+                for (i, row) in rows.iter().enumerate() {
+                    let line: usize = first + i;
+                    // Rows past the last line go on new lines, and a text which ends
+                    // with a line break still does
+                    match self.buffer.line_start(line) {
+                        None => self.buffer.insert(self.buffer.len(), "\n")?,
+                        Some(v) if i > 0 && v == self.buffer.len() => {
+                            self.buffer.insert(v, "\n")?
+                        }
+                        _ => (),
+                    }
+                    let range: Range<usize> = self.buffer.line_range(line).unwrap_or_default();
+                    let text: String = self.buffer.line(line).unwrap_or_default();
+                    // Short lines are padded out to the column of the block
+                    let padding: String = " ".repeat(column.saturating_sub(width(&text)));
+                    let at: usize = range.start + offset_at_column(&text, column);
+                    self.buffer
+                        .insert(at, &format!("{}{}", padding, row.repeat(count)))?;
+                }
+                let text: String = self.buffer.line(first).unwrap_or_default();
+                self.cursor =
+                    self.line_range_of(self.cursor).start + offset_at_column(&text, column);
+            }
+        }
+        self.goal = None;
+
+        Ok(())
+    }
+
+    /// Replaces `count` chars from the cursor with `c`, if the line has them.
+    fn replace_chars(&mut self, c: char, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let line_end: usize = self.line_range_of(self.cursor).end;
+        let mut end: usize = self.cursor;
+        for _ in 0..count {
+            if end >= line_end {
+                return Ok(());
+            }
+            end = self.buffer.next_grapheme_boundary(end)?;
+        }
+        let text: String = c.to_string().repeat(count);
+        self.buffer.replace(self.cursor..end, &text)?;
+        self.cursor += text.len() - c.len_utf8();
+
+        Ok(())
+    }
+
+    /// The selection of a visual mode, as the offsets selected on each line.
+    fn selected(&self, line: usize) -> Range<usize> {
+        let Some(anchor) = self.anchor else {
+            return 0..0;
+        };
+        let (start, end) = (anchor.min(self.cursor), anchor.max(self.cursor));
+        let range: Range<usize> = self.buffer.line_range(line).unwrap_or_default();
+        if line < self.line_of(start) || line > self.line_of(end) {
+            return 0..0;
+        }
+
+        match self.mode.as_str() {
+            modal::VISUAL_LINE => range,
+            modal::VISUAL_BLOCK => {
+                let columns: Range<usize> = self.block_columns(anchor);
+                let text: String = self.buffer.line(line).unwrap_or_default();
+                range.start + offset_at_column(&text, columns.start)
+                    ..range.start + offset_at_column(&text, columns.end)
+            }
+            _ => {
+                let end: usize = self.buffer.next_grapheme_boundary(end).unwrap_or(end);
+                start.max(range.start)..end.min(range.end)
+            }
+        }
+    }
+
+    /// Draws the windows, the tabs when there are several, and the message
+    /// area, scrolling each window so that its cursor is on screen. Returns
+    /// where the cursor is shown.
+    pub fn render(&mut self, width: u16, height: u16) -> (Grid, Option<(u16, u16)>) {
+        let mut grid: Grid = Grid::new(width, height);
+        let tab_line: u16 = u16::from(!self.tabs.is_empty() && height > 2);
+        self.area = Rect {
+            x: 0,
+            y: tab_line,
+            width,
+            height: height.saturating_sub(1 + tab_line),
+        };
+
+        let rects: Vec<(usize, Rect)> = self.layout.rects(self.area);
+        for (window, rect) in &rects {
+            self.scroll(*window, *rect);
+        }
+        let mut cursor: Option<(u16, u16)> = None;
+        for (window, rect) in &rects {
+            let shown: Option<(u16, u16)> = self.render_window(&mut grid, *window, *rect);
+            if *window == self.window {
+                cursor = shown;
+            }
+        }
+        if tab_line > 0 {
+            self.render_tabs(&mut grid);
+        }
+        if let Some((entries, selected)) = self.popup() {
+            let bottom: u16 = (self.area.y + self.area.height).saturating_sub(1);
+            render_popup(&mut grid, bottom, &entries, selected);
+        }
+
+        let prompt: Option<String> = self.prompt_line();
+        if let Some(message) = prompt.as_ref().or(self.message.as_ref()) {
+            grid.put_str(0, height.saturating_sub(1), message, Style::default());
+        }
+        if let Some(text) = &prompt {
+            cursor = Some((
+                (self::width(text) as u16).min(grid.width().saturating_sub(1)),
+                height.saturating_sub(1),
+            ));
+        }
+
+        (grid, cursor)
+    }
+
+    /// Scrolls `window`, which is drawn in `rect`, so that its cursor is on
+    /// screen.
+    fn scroll(&mut self, window: usize, rect: Rect) {
+        let rows: usize = (rect.height.saturating_sub(1) as usize).max(1);
+        let columns: usize = rect.width.max(1) as usize;
+        if window == self.window {
+            self.page = rows;
+            let (line, column): (usize, usize) = (self.position().line, self.column());
+            scroll_to(&mut self.top, &mut self.left, line, column, rows, columns);
+            return;
+        }
+
+        let Some(i) = self.views.iter().position(|v| v.window == window) else {
+            return;
+        };
+        let Some(buffer) = self.buffer_named(&self.views[i].name) else {
+            return;
+        };
+        let offset: usize = offset_of(buffer, self.views[i].position);
+        let line: usize = buffer.offset_to_position(offset).unwrap_or_default().line;
+        let column: usize = column_in(buffer, offset);
+        let view: &mut View = &mut self.views[i];
+        scroll_to(&mut view.top, &mut view.left, line, column, rows, columns);
+    }
+
+    /// The open buffer called `name`.
+    fn buffer_named(&self, name: &str) -> Option<&Buffer> {
+        match name == self.name {
+            true => Some(&self.buffer),
+            false => self
+                .buffers
+                .iter()
+                .find(|v| v.name == name)
+                .map(|v| &v.buffer),
+        }
+    }
+
+    /// Draws `window` in `rect`: its text, its status line, and the
+    /// separator to the right of it. Returns where its cursor is shown.
+    fn render_window(&self, grid: &mut Grid, window: usize, rect: Rect) -> Option<(u16, u16)> {
+        let focused: bool = window == self.window;
+        let (buffer, name, cursor, top, left): (&Buffer, &str, usize, usize, usize) = match focused
+        {
+            true => (&self.buffer, &self.name, self.cursor, self.top, self.left),
+            false => {
+                let view: &View = self.views.iter().find(|v| v.window == window)?;
+                let buffer: &Buffer = self.buffer_named(&view.name)?;
+                let cursor: usize = offset_of(buffer, view.position);
+                (buffer, &view.name, cursor, view.top, view.left)
+            }
+        };
+        let rows: usize = rect.height.saturating_sub(1) as usize;
+
+        // Only the window with the cursor shows matches and the selection
+        let highlighted = self.highlighted().filter(|_| focused);
+        for (y, line) in (top..top + rows).enumerate() {
+            let Some(text) = buffer.line(line) else {
+                break;
+            };
+            let start: usize = buffer.line_start(line).unwrap_or_default();
+            let mut styles: Vec<(Range<usize>, Style)> = Vec::new();
+            if let Some((pattern, current)) = &highlighted {
+                for range in pattern.matches(buffer, line) {
+                    let style: Style = match current.as_ref() == Some(&range) {
+                        true => Style::reverse(),
+                        false => MATCH,
+                    };
+                    styles.push((range.start - start..range.end - start, style));
+                }
+            }
+            if focused {
+                let selected: Range<usize> = self.selected(line);
+                styles.push((
+                    selected.start.saturating_sub(start)..selected.end.saturating_sub(start),
+                    Style::reverse(),
+                ));
+            }
+            render_line(grid, rect, rect.y + y as u16, left, &text, &styles);
+        }
+
+        let position: Position = buffer.offset_to_position(cursor).unwrap_or_default();
+        let column: usize = column_in(buffer, cursor);
+        if rect.height >= 1 {
+            let y: u16 = rect.y + rows as u16;
+            let style: Style = if focused { Style::reverse() } else { INACTIVE };
+            let modified: &str = if buffer.modified() { " [+]" } else { "" };
+            let location: String = format!("{}:{} ", position.line + 1, column + 1);
+            let mode: String = match self.modal_editing && focused {
+                true => format!(" {} ", self.mode.to_uppercase()),
+                false => String::new(),
+            };
+            let status: String = format!("{} {}{}", mode, name, modified);
+            let fits = |text: &str| -> usize { offset_at_column(text, rect.width as usize) };
+            grid.put_str(rect.x, y, &" ".repeat(rect.width as usize), style);
+            grid.put_str(rect.x, y, &status[..fits(&status)], style);
+            let x: u16 = rect.width.saturating_sub(self::width(&location) as u16);
+            grid.put_str(rect.x + x, y, &location[..fits(&location)], style);
+        }
+        if rect.x + rect.width < grid.width() {
+            for y in rect.y..rect.y + rect.height {
+                grid.put_str(rect.x + rect.width, y, "│", Style::default());
+            }
+        }
+
+        match position.line.checked_sub(top) {
+            Some(row) if focused && row < rows => {
+                Some((rect.x + (column - left) as u16, rect.y + row as u16))
+            }
+            _ => None,
+        }
+    }
+
+    /// Draws the tabs across the top of the screen, each named after the
+    /// buffer with the cursor, with the one shown standing out.
+    fn render_tabs(&self, grid: &mut Grid) {
+        let mut names: Vec<&str> = self
+            .tabs
+            .iter()
+            .map(|tab| {
+                tab.views
+                    .iter()
+                    .find(|v| v.window == tab.window)
+                    .map_or("", |v| v.name.as_str())
+            })
+            .collect();
+        names.insert(self.tab, &self.name);
+
+        grid.put_str(0, 0, &" ".repeat(grid.width() as usize), Style::reverse());
+        let mut x: u16 = 0;
+        for (i, name) in names.into_iter().enumerate() {
+            let style: Style = match i == self.tab {
+                true => Style::default(),
+                false => Style::reverse(),
+            };
+            x = grid.put_str(x, 0, &format!(" {} {} ", i + 1, name), style);
+        }
+    }
+
+    /// What is listed over the bottom of the text area, if anything: the
+    /// keys which can follow the pending prefix, the open buffers, or the
+    /// candidates of the minibuffer with the one picked. Commands are
+    /// listed with the keys which run them.
+    fn popup(&self) -> Option<(Vec<String>, Option<usize>)> {
+        if let Some(Prompt::Read(reading, minibuffer)) = &self.prompt {
+            let mut entries: Vec<String> = self.candidates(*reading, &minibuffer.text);
+            if *reading == Reading::Command {
+                for entry in &mut entries {
+                    if let Some(keys) = self.keys_for(entry) {
+                        *entry = format!("{} ({})", entry, keys);
+                    }
+                }
+            }
+            return Some((entries, Some(minibuffer.selected)));
+        }
+        if self.listing {
+            let entries: Vec<String> = std::iter::once((&self.name, &self.buffer))
+                .chain(self.buffers.iter().map(|v| (&v.name, &v.buffer)))
+                .enumerate()
+                .map(|(i, (name, buffer))| {
+                    let shown: char = if i == 0 { '.' } else { ' ' };
+                    let modified: char = if buffer.modified() { '*' } else { ' ' };
+                    format!("{}{} {}", shown, modified, name)
+                })
+                .collect();
+            return Some((entries, None));
+        }
+        if self.which_key && !self.pending.is_empty() {
+            let entries: Vec<String> = self
+                .continuations()
+                .into_iter()
+                .map(|(key, description)| format!("{} → {}", key, description))
+                .collect();
+            return Some((entries, None));
+        }
+
+        None
+    }
+}
+
+impl Search {
+    /// Compiles the query, and looks for it after `from` going forward, or
+    /// before it going backward.
+    fn find(&mut self, buffer: &Buffer, from: usize) {
+        let pattern: Result<Option<Pattern>, Box<dyn std::error::Error>> =
+            match self.query.is_empty() {
+                true => Ok(None),
+                false => Pattern::new(&self.query, self.regex).map(Some),
+            };
+        self.error = pattern.as_ref().err().map(|e| e.to_string());
+        self.pattern = pattern.unwrap_or(None);
+        self.found = self.pattern.as_ref().and_then(|v| match self.forward {
+            true => v.find_forward(buffer, next_char(buffer, from)),
+            false => v.find_backward(buffer, from),
+        });
+    }
+}
+
+impl Background {
+    fn new(buffer: Buffer, name: String) -> Self {
+        Background {
+            buffer,
+            name,
+            cursor: 0,
+            top: 0,
+            left: 0,
+        }
+    }
+}
+
+/// Draws a line of text in `rect` at row `y`, scrolled `left` columns, in
+/// the style of the last range of `styles` which covers each byte.
+fn render_line(
+    grid: &mut Grid,
+    rect: Rect,
+    y: u16,
+    left: usize,
+    text: &str,
+    styles: &[(Range<usize>, Style)],
+) {
+    let mut column: usize = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        let style: Style = styles
+            .iter()
+            .rfind(|(range, _)| range.contains(&i))
+            .map_or(Style::default(), |(_, style)| *style);
+        let width: usize = grapheme_width(grapheme, column);
+        let visible: Range<usize> = column.max(left)..column + width;
+        column += width;
+        if visible.start >= visible.end {
+            continue;
+        }
+        let x: usize = visible.start - left;
+        if x + visible.len() > rect.width as usize {
+            break;
+        }
+
+        // Tabs, and wide characters cut by the left edge, are shown as spaces
+        let x: u16 = rect.x + x as u16;
+        if grapheme == "\t" || visible.len() < width {
+            grid.put_str(x, y, &" ".repeat(visible.len()), style);
+        } else if grid.put(x, y, grapheme, style) == 0 {
+            break;
+        }
+    }
+}
+
+/// Moves `top` and `left` so that `line` and `column` are within the `rows`
+/// lines and `columns` columns from them.
+fn scroll_to(
+    top: &mut usize,
+    left: &mut usize,
+    line: usize,
+    column: usize,
+    rows: usize,
+    columns: usize,
+) {
+    if line < *top {
+        *top = line;
+    } else if line >= *top + rows {
+        *top = line + 1 - rows;
+    }
+    if column < *left {
+        *left = column;
+    } else if column >= *left + columns {
+        *left = column + 1 - columns;
+    }
+}
+
+/// The column on screen of `offset` in `buffer`, before scrolling.
+fn column_in(buffer: &Buffer, offset: usize) -> usize {
+    let line: usize = buffer.offset_to_position(offset).unwrap_or_default().line;
+    let range: Range<usize> = buffer.line_range(line).unwrap_or_default();
+    let before: String = buffer
+        .slice(range.start..offset.min(range.end))
+        .unwrap_or_default();
+
+    width(&before)
+}
+
+/// Draws `entries` in columns over the bottom of the text area, which ends
+/// at `bottom`, with the `selected` one reversed.
+fn render_popup(grid: &mut Grid, bottom: u16, entries: &[String], selected: Option<usize>) {
+    let column: usize = entries.iter().map(|v| width(v)).max().unwrap_or(0) + 2;
+    let columns: usize = (grid.width() as usize / column).max(1);
+    let lines: u16 = (entries.len().div_ceil(columns) as u16).min(bottom);
+
+    let top: u16 = bottom - lines;
+    for y in top..bottom {
+        grid.put_str(0, y, &" ".repeat(grid.width() as usize), Style::default());
+    }
+    for (i, entry) in entries.iter().enumerate().take(lines as usize * columns) {
+        let x: usize = i % columns * column + 1;
+        let style: Style = match selected == Some(i) {
+            true => Style::reverse(),
+            false => Style::default(),
+        };
+        grid.put_str(x as u16, top + (i / columns) as u16, entry, style);
+    }
+}
+
+/// Whether `a` and `b` are the same file, comparing them as they are when
+/// they do not exist.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// The offset of `position` in `buffer`, or the end of the text if the
+/// buffer has fewer lines now.
+fn offset_of(buffer: &Buffer, position: Position) -> usize {
+    buffer.position_to_offset(position).unwrap_or(buffer.len())
+}
+
+/// Reads a window of a layout, as [`Editor::layout_text`] writes it.
+fn parse_view(line: &str) -> Result<View, Box<dyn std::error::Error>> {
+    let invalid = || format!("LAYOUT_ERROR: invalid window {}", line);
+    let fields: Vec<&str> = line.splitn(5, ' ').collect();
+    let [window, position, top, left, name] = fields[..] else {
+        return Err(invalid().into());
+    };
+    let (line, column) = position.split_once(':').ok_or_else(invalid)?;
+    let number = |v: &str| v.parse::<usize>().map_err(|_| invalid());
+
+    Ok(View {
+        window: number(window)?,
+        name: name.to_string(),
+        position: Position {
+            line: number(line)?,
+            column: number(column)?,
+        },
+        top: number(top)?,
+        left: number(left)?,
+    })
+}
+
+/// The offset after the char at `offset`, or past the end of the text if
+/// there is none.
+fn next_char(buffer: &Buffer, offset: usize) -> usize {
+    buffer
+        .chars_from(offset)
+        .next()
+        .map_or(buffer.len() + 1, |(i, c)| i + c.len_utf8())
+}
+
+/// The columns taken by `grapheme` when shown starting at `column`.
+fn grapheme_width(grapheme: &str, column: usize) -> usize {
+    match grapheme {
+        "\t" => TAB_WIDTH - column % TAB_WIDTH,
+        v => v.width().clamp(1, 2),
+    }
+}
+
+/// The columns taken by `text`, starting from the left edge.
+fn width(text: &str) -> usize {
+    text.graphemes(true)
+        .fold(0, |column, v| column + grapheme_width(v, column))
+}
+
+/// The byte offset of the grapheme in `text` which covers `goal` on screen,
+/// or the end of `text`.
+fn offset_at_column(text: &str, goal: usize) -> usize {
+    let mut column: usize = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        let width: usize = grapheme_width(grapheme, column);
+        if column + width > goal {
+            return i;
+        }
+        column += width;
+    }
+
+    text.len()
+}
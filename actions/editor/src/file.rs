@@ -0,0 +1,147 @@
+//! Reading and saving files.
+//!
+//! Saving writes a temporary file next to the original and renames it over
+//! the original, so that a crash leaves either the old or the new contents
+//! and never a mix.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// What a file looked like when it was last read or written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    pub hash: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// Copies the previous contents to `<name>~` before overwriting them.
+    pub backup: bool,
+    /// Where the editor saves the undo history of files it saves, so that it
+    /// can be read back when the file is opened again.
+    pub undo_directory: Option<PathBuf>,
+}
+
+impl FileStamp {
+    /// Whether the file at `path` differs from what was stamped. Its contents
+    /// are only hashed if its size or modification time changed, so touching
+    /// a file does not count as changing it.
+    pub fn changed(&self, path: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+        let metadata: std::fs::Metadata = match std::fs::metadata(path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(error("cannot read", path, e)),
+        };
+        if metadata.len() != self.len {
+            return Ok(true);
+        }
+        if metadata.modified().ok() == self.modified {
+            return Ok(false);
+        }
+
+        let bytes: Vec<u8> = std::fs::read(path).map_err(|e| error("cannot read", path, e))?;
+
+        Ok(hash([bytes.as_slice()]) != self.hash)
+    }
+}
+
+/// The contents of a file, and its stamp at the time they were read.
+pub(crate) struct Contents {
+    pub bytes: Vec<u8>,
+    pub stamp: FileStamp,
+}
+
+/// Reads the file at `path`, or `None` if it does not exist.
+pub(crate) fn read(path: &Path) -> Result<Option<Contents>, Box<dyn std::error::Error>> {
+    let bytes: Vec<u8> = match std::fs::read(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(error("cannot read", path, e)),
+    };
+    let stamp: FileStamp = FileStamp {
+        modified: std::fs::metadata(path).and_then(|v| v.modified()).ok(),
+        len: bytes.len() as u64,
+        hash: hash([bytes.as_slice()]),
+    };
+
+    Ok(Some(Contents { bytes, stamp }))
+}
+
+/// Replaces the contents of the file at `path` with `chunks`, keeping its
+/// permissions. Saving through a symbolic link replaces the file it points
+/// to rather than the link.
+pub(crate) fn write_atomic<'a>(
+    path: &Path,
+    chunks: impl Iterator<Item = &'a str> + Clone,
+    options: &SaveOptions,
+) -> Result<FileStamp, Box<dyn std::error::Error>> {
+    let path: PathBuf = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let previous: Option<std::fs::Metadata> = std::fs::metadata(&path).ok();
+
+    if options.backup && previous.is_some() {
+        let backup: PathBuf = sibling(&path, |name| format!("{}~", name));
+        std::fs::copy(&path, &backup).map_err(|e| error("cannot back up", &path, e))?;
+    }
+
+    let temporary: PathBuf = sibling(&path, |name| {
+        format!(".{}.{}.tmp", name, std::process::id())
+    });
+    let written = || -> std::io::Result<()> {
+        let mut file: std::fs::File = std::fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)?;
+        for chunk in chunks.clone() {
+            file.write_all(chunk.as_bytes())?;
+        }
+        if let Some(previous) = &previous {
+            file.set_permissions(previous.permissions())?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&temporary, &path)
+    };
+    if let Err(e) = written() {
+        let _ = std::fs::remove_file(&temporary);
+        return Err(error("cannot save", &path, e));
+    }
+    // The rename itself is only durable once the directory is synced
+    if let Some(directory) = path.parent().and_then(|v| std::fs::File::open(v).ok()) {
+        let _ = directory.sync_all();
+    }
+
+    Ok(FileStamp {
+        modified: std::fs::metadata(&path).and_then(|v| v.modified()).ok(),
+        len: chunks.clone().map(|v| v.len() as u64).sum(),
+        hash: hash(chunks.map(str::as_bytes)),
+    })
+}
+
+/// Hashes bytes given in pieces, as if they were one slice. This is 64-bit
+/// FNV-1a, since the hash is saved with the undo history and has to stay the
+/// same across builds.
+pub(crate) fn hash<'a>(pieces: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut result: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in pieces.into_iter().flatten() {
+        result ^= u64::from(*byte);
+        result = result.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    result
+}
+
+/// A path in the same directory as `path`, named after it.
+fn sibling(path: &Path, name: impl Fn(&str) -> String) -> PathBuf {
+    let file_name: String = path
+        .file_name()
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    path.with_file_name(name(&file_name))
+}
+
+fn error(action: &str, path: &Path, e: std::io::Error) -> Box<dyn std::error::Error> {
+    format!("BUFFER_ERROR: {} {}: {}", action, path.display(), e).into()
+}
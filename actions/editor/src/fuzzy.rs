@@ -0,0 +1,55 @@
+//! Fuzzy matching of names, where the chars of a query have to appear in a
+//! name in order but not next to each other, so `nb` matches `new-buffer`.
+
+/// Chars after which a new word starts.
+const SEPARATORS: &[char] = &['-', '_', ' ', '/', '\\', '.', '*', '<'];
+
+/// How well `query` matches `candidate`, higher being better, or `None` if
+/// its chars do not all appear in it in order. Case is ignored. Matches at
+/// the start of words and runs of chars next to each other score more, and
+/// chars skipped over less.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let mut score: i64 = 0;
+    let mut previous: Option<char> = None;
+    // Where the last matched char was, as a count of chars
+    let mut last: Option<usize> = None;
+    let mut chars = candidate.chars().enumerate();
+    for wanted in query.chars() {
+        loop {
+            let (i, c) = chars.next()?;
+            let before: Option<char> = previous.replace(c);
+            if !c.to_lowercase().eq(wanted.to_lowercase()) {
+                continue;
+            }
+
+            let word_start: bool = match before {
+                None => true,
+                Some(v) => SEPARATORS.contains(&v) || (v.is_lowercase() && c.is_uppercase()),
+            };
+            let skipped: usize = last.map_or(i, |v| i - v - 1);
+            score += 1 + if word_start { 8 } else { 0 };
+            score += if skipped == 0 {
+                5
+            } else {
+                -(skipped.min(10) as i64)
+            };
+            last = Some(i);
+            break;
+        }
+    }
+
+    Some(score)
+}
+
+/// The indices of the `candidates` which `query` matches, best first, and in
+/// their order when they score the same.
+pub fn rank<S: AsRef<str>>(query: &str, candidates: &[S]) -> Vec<usize> {
+    let mut scored: Vec<(usize, i64)> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| Some((i, score(query, v.as_ref())?)))
+        .collect();
+    scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    scored.into_iter().map(|(i, _)| i).collect()
+}
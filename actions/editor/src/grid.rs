@@ -0,0 +1,171 @@
+//! A screen's worth of styled cells, which the editor draws into and the
+//! terminal compares against what it drew last time.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Grey,
+}
+
+/// One column of the screen. A character two columns wide is followed by a
+/// cell with an empty symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: String,
+    pub style: Style,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            symbol: String::from(" "),
+            style: Style::default(),
+        }
+    }
+}
+
+impl Style {
+    pub fn reverse() -> Self {
+        Style {
+            reverse: true,
+            ..Style::default()
+        }
+    }
+}
+
+impl Grid {
+    pub fn new(width: u16, height: u16) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        self.index(x, y).map(|v| &self.cells[v])
+    }
+
+    /// Puts one grapheme at `x`, `y`, and returns its width. Nothing is put
+    /// if it would not fit on the line.
+    pub fn put(&mut self, x: u16, y: u16, symbol: &str, style: Style) -> u16 {
+        // Control characters would move the terminal's cursor
+        let symbol: &str = match symbol.chars().any(char::is_control) {
+            true => "\u{fffd}",
+            false => symbol,
+        };
+        let width: u16 = symbol.width().clamp(1, 2) as u16;
+        let Some(index) = self.index(x, y) else {
+            return 0;
+        };
+        if x + width > self.width {
+            return 0;
+        }
+
+        self.cells[index] = Cell {
+            symbol: symbol.to_string(),
+            style,
+        };
+        if width == 2 {
+            self.cells[index + 1] = Cell {
+                symbol: String::new(),
+                style,
+            };
+        }
+
+        width
+    }
+
+    /// Puts `text` from `x` onwards, cutting it at the end of the line.
+    /// Returns the column after it.
+    pub fn put_str(&mut self, x: u16, y: u16, text: &str, style: Style) -> u16 {
+        let mut x: u16 = x;
+        for grapheme in text.graphemes(true) {
+            match self.put(x, y, grapheme, style) {
+                0 => break,
+                width => x += width,
+            }
+        }
+
+        x
+    }
+
+    /// Sets the style of the whole of line `y`, keeping its symbols.
+    pub fn style_line(&mut self, y: u16, style: Style) {
+        for x in 0..self.width {
+            if let Some(index) = self.index(x, y) {
+                self.cells[index].style = style;
+            }
+        }
+    }
+
+    /// The cells of `other` which differ from this grid, which must be the
+    /// same size.
+    pub fn diff<'a>(&self, other: &'a Grid) -> Vec<(u16, u16, &'a Cell)> {
+        let mut result: Vec<(u16, u16, &Cell)> = Vec::new();
+        for (i, (old, new)) in self.cells.iter().zip(&other.cells).enumerate() {
+            if old != new {
+                let (x, y): (usize, usize) = (i % self.width as usize, i / self.width as usize);
+                result.push((x as u16, y as u16, new));
+            }
+        }
+
+        result
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        match x < self.width && y < self.height {
+            true => Some(y as usize * self.width as usize + x as usize),
+            false => None,
+        }
+    }
+}
+
+/// The symbols of the grid, one line per row, without trailing spaces.
+impl std::fmt::Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            let line: String = (0..self.width)
+                .filter_map(|x| self.get(x, y))
+                .map(|v| v.symbol.as_str())
+                .collect();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
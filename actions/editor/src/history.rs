@@ -0,0 +1,381 @@
+//! Undo history, kept as a tree. Undoing and then editing starts a branch
+//! instead of throwing away what was undone, so every state the text was in
+//! can be gone back to, by undoing and redoing or by time.
+//!
+//! Each node is a transaction: the edits one command made, which are undone
+//! and redone together.
+
+use std::time::{Duration, SystemTime};
+
+/// Text `deleted` at `offset` and replaced with `inserted`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub offset: usize,
+    pub deleted: String,
+    pub inserted: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    /// In the order they were made. The first is the text before any edits.
+    nodes: Vec<Node>,
+    current: usize,
+    /// The node the file on disk matches.
+    saved: usize,
+    /// Edits of the transaction which is not committed yet.
+    pending: Vec<Edit>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    parent: usize,
+    /// The edits which lead from the parent to this node.
+    edits: Vec<Edit>,
+    time: SystemTime,
+    /// The cursor before and after the edits.
+    before: usize,
+    after: usize,
+    /// The child redo goes to, which is the one made or visited last.
+    redo: Option<usize>,
+}
+
+/// What to do to the text to move through the history, and where the cursor
+/// goes afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Travel {
+    pub edits: Vec<Edit>,
+    pub cursor: usize,
+}
+
+const MAGIC: &str = "core-undo 1";
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            nodes: vec![Node {
+                parent: 0,
+                edits: Vec::new(),
+                time: SystemTime::now(),
+                before: 0,
+                after: 0,
+                redo: None,
+            }],
+            current: 0,
+            saved: 0,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl Edit {
+    /// The edit which takes the text back to how it was before this one.
+    pub fn inverse(&self) -> Edit {
+        Edit {
+            offset: self.offset,
+            deleted: self.inserted.clone(),
+            inserted: self.deleted.clone(),
+        }
+    }
+}
+
+impl History {
+    /// Adds an edit to the transaction in progress. Typing one char after
+    /// another is kept as one edit.
+    pub fn record(&mut self, edit: Edit) {
+        if let Some(last) = self.pending.last_mut() {
+            if last.deleted.is_empty()
+                && edit.deleted.is_empty()
+                && last.offset + last.inserted.len() == edit.offset
+            {
+                last.inserted.push_str(&edit.inserted);
+                return;
+            }
+        }
+
+        self.pending.push(edit);
+    }
+
+    /// Ends the transaction in progress, if it made any edits. `before` and
+    /// `after` are the cursor before and after it.
+    pub fn commit(&mut self, before: usize, after: usize) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let node: usize = self.nodes.len();
+        self.nodes.push(Node {
+            parent: self.current,
+            edits: std::mem::take(&mut self.pending),
+            time: SystemTime::now(),
+            before,
+            after,
+            redo: None,
+        });
+        self.nodes[self.current].redo = Some(node);
+        self.current = node;
+    }
+
+    /// Whether edits were made since the last commit.
+    pub fn pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Notes that the file on disk now matches the text. The transaction in
+    /// progress ends here, with the cursor put where its edits were.
+    pub fn mark_saved(&mut self) {
+        if let (Some(first), Some(last)) = (self.pending.first(), self.pending.last()) {
+            let (before, after) = (first.offset, last.offset + last.inserted.len());
+            self.commit(before, after);
+        }
+        self.saved = self.current;
+    }
+
+    /// Whether the text differs from the file on disk.
+    pub fn modified(&self) -> bool {
+        self.saved != self.current || !self.pending.is_empty()
+    }
+
+    /// The number of the current node. The text before any edits is 0, and
+    /// each commit makes the next number.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    /// When the current node was made.
+    pub fn time(&self) -> SystemTime {
+        self.nodes[self.current].time
+    }
+
+    /// Undoes the current transaction, or `None` at the start of history.
+    pub fn undo(&mut self) -> Option<Travel> {
+        let node: usize = self.current;
+        if node == 0 {
+            return None;
+        }
+
+        Some(self.travel(self.nodes[node].parent))
+    }
+
+    /// Redoes the transaction last undone from here, or `None` if there is
+    /// none.
+    pub fn redo(&mut self) -> Option<Travel> {
+        let child: usize = self.nodes[self.current].redo?;
+
+        Some(self.travel(child))
+    }
+
+    /// Goes to the node made just before the current one, whichever branch
+    /// it is on.
+    pub fn earlier(&mut self) -> Option<Travel> {
+        let node: usize = self.current.checked_sub(1)?;
+
+        Some(self.travel(node))
+    }
+
+    /// Goes to the node made just after the current one.
+    pub fn later(&mut self) -> Option<Travel> {
+        let node: usize = self.current + 1;
+
+        (node < self.nodes.len()).then(|| self.travel(node))
+    }
+
+    /// Goes to how the text was `ago` before now: the last node made by then,
+    /// or the start of history.
+    pub fn travel_back(&mut self, ago: Duration) -> Travel {
+        let time: SystemTime = SystemTime::now()
+            .checked_sub(ago)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let node: usize = self.nodes.iter().rposition(|v| v.time <= time).unwrap_or(0);
+
+        self.travel(node)
+    }
+
+    /// Goes to `node` by undoing up to where its branch meets the current
+    /// one, then redoing down to it.
+    fn travel(&mut self, node: usize) -> Travel {
+        let up: Vec<usize> = self.ancestors(self.current);
+        let down: Vec<usize> = self.ancestors(node);
+        let common: usize = *up.iter().find(|v| down.contains(v)).unwrap_or(&0);
+
+        let mut travel: Travel = Travel {
+            edits: Vec::new(),
+            cursor: self.nodes[self.current].after,
+        };
+        for v in up.into_iter().take_while(|v| *v != common) {
+            let node: &Node = &self.nodes[v];
+            travel
+                .edits
+                .extend(node.edits.iter().rev().map(Edit::inverse));
+            travel.cursor = node.before;
+            let parent: usize = node.parent;
+            self.nodes[parent].redo = Some(v);
+        }
+        let path: Vec<usize> = down.into_iter().take_while(|v| *v != common).collect();
+        for v in path.into_iter().rev() {
+            let node: &Node = &self.nodes[v];
+            travel.edits.extend(node.edits.iter().cloned());
+            travel.cursor = node.after;
+            let parent: usize = node.parent;
+            self.nodes[parent].redo = Some(v);
+        }
+        self.current = node;
+
+        travel
+    }
+
+    /// `node` and the nodes above it, up to and including the first one.
+    fn ancestors(&self, node: usize) -> Vec<usize> {
+        let mut result: Vec<usize> = vec![node];
+        let mut node: usize = node;
+        while node != 0 {
+            node = self.nodes[node].parent;
+            result.push(node);
+        }
+
+        result
+    }
+
+    /// Writes the history, for text whose hash is `hash`. Uncommitted edits
+    /// are left out.
+    pub fn to_text(&self, hash: u64) -> String {
+        let mut out: String = format!(
+            "{}\n{} {} {}\n",
+            MAGIC,
+            hash,
+            self.current,
+            self.nodes.len()
+        );
+        for node in &self.nodes {
+            let time: u128 = node
+                .time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            out.push_str(&format!(
+                "{} {} {} {} {}\n",
+                node.parent,
+                time,
+                node.before,
+                node.after,
+                node.edits.len()
+            ));
+            // Text is written as it is, after its length, so it needs no escaping
+            for edit in &node.edits {
+                out.push_str(&format!(
+                    "{} {} {}\n{}{}\n",
+                    edit.offset,
+                    edit.deleted.len(),
+                    edit.inserted.len(),
+                    edit.deleted,
+                    edit.inserted
+                ));
+            }
+        }
+
+        out
+    }
+
+    /// Reads history written by [`History::to_text`]. Returns `None` if it
+    /// was written for text other than the text whose hash is `hash`.
+    pub fn from_text(text: &str, hash: u64) -> Result<Option<History>, Box<dyn std::error::Error>> {
+        let mut reader: Reader = Reader { text, offset: 0 };
+        if reader.line()? != MAGIC {
+            return Err("HISTORY_ERROR: not an undo history file".into());
+        }
+        let [written, current, count] = reader.numbers::<3>()?;
+        if written != hash as u128 {
+            return Ok(None);
+        }
+
+        let mut nodes: Vec<Node> = Vec::new();
+        for _ in 0..count {
+            let [parent, time, before, after, edits] = reader.numbers::<5>()?;
+            let mut node: Node = Node {
+                parent: parent as usize,
+                edits: Vec::new(),
+                time: SystemTime::UNIX_EPOCH + Duration::from_millis(time as u64),
+                before: before as usize,
+                after: after as usize,
+                redo: None,
+            };
+            for _ in 0..edits {
+                let [offset, deleted, inserted] = reader.numbers::<3>()?;
+                node.edits.push(Edit {
+                    offset: offset as usize,
+                    deleted: reader.take(deleted as usize)?.to_string(),
+                    inserted: reader.take(inserted as usize)?.to_string(),
+                });
+                reader.line()?;
+            }
+            if !nodes.is_empty() && node.parent >= nodes.len() {
+                return Err("HISTORY_ERROR: a change comes before its parent".into());
+            }
+            nodes.push(node);
+        }
+        let current: usize = current as usize;
+        if current >= nodes.len() {
+            return Err("HISTORY_ERROR: the current change does not exist".into());
+        }
+        for i in 1..nodes.len() {
+            let parent: usize = nodes[i].parent;
+            nodes[parent].redo = Some(i);
+        }
+
+        Ok(Some(History {
+            nodes,
+            current,
+            saved: current,
+            pending: Vec::new(),
+        }))
+    }
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn line(&mut self) -> Result<&'a str, Box<dyn std::error::Error>> {
+        let rest: &str = &self.text[self.offset..];
+        let end: usize = rest
+            .find('\n')
+            .ok_or("HISTORY_ERROR: unexpected end of file")?;
+        self.offset += end + 1;
+
+        Ok(&rest[..end])
+    }
+
+    fn numbers<const N: usize>(&mut self) -> Result<[u128; N], Box<dyn std::error::Error>> {
+        let line: &str = self.line()?;
+        let mut result: [u128; N] = [0; N];
+        let mut words = line.split(' ');
+        for v in result.iter_mut() {
+            *v = words
+                .next()
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| format!("HISTORY_ERROR: expected {} numbers, got {:?}", N, line))?;
+        }
+
+        Ok(result)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a str, Box<dyn std::error::Error>> {
+        let text: &str = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.text.get(self.offset..end))
+            .ok_or("HISTORY_ERROR: an edit is cut short")?;
+        self.offset += len;
+
+        Ok(text)
+    }
+}
@@ -0,0 +1,185 @@
+//! Key presses, written the way keymaps name them, such as `C-x` or `M-<left>`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// Only set for keys other than characters, whose case already shows it.
+    pub shift: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Esc,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    F(u8),
+}
+
+impl Key {
+    /// A key pressed without modifiers.
+    pub fn new(code: KeyCode) -> Self {
+        Key {
+            code,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    pub fn ctrl(code: KeyCode) -> Self {
+        Key {
+            ctrl: true,
+            ..Key::new(code)
+        }
+    }
+
+    pub fn alt(code: KeyCode) -> Self {
+        Key {
+            alt: true,
+            ..Key::new(code)
+        }
+    }
+
+    /// The character this key types, if it types one.
+    pub fn char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(v) if !self.ctrl && !self.alt => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Parses keys separated by spaces, such as `C-x C-s`.
+pub fn parse_keys(text: &str) -> Result<Vec<Key>, Box<dyn std::error::Error>> {
+    let keys: Vec<Key> = text
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<Key>, _>>()?;
+    if keys.is_empty() {
+        return Err("KEYMAP_ERROR: no keys given".into());
+    }
+
+    Ok(keys)
+}
+
+/// Writes keys the way [`parse_keys`] reads them.
+pub fn keys_to_string(keys: &[Key]) -> String {
+    keys.iter()
+        .map(Key::to_string)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl std::str::FromStr for Key {
+    type Err = Box<dyn std::error::Error>;
+
+    /// Parses one key, such as `a`, `C-x`, `M-<left>` or `RET`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut key: Key = Key::new(KeyCode::Esc);
+        let mut rest: &str = text;
+        // A lone `-`, or `C--`, names the minus key itself
+        while rest.len() > 2 {
+            let modifier: &mut bool = match rest.get(..2) {
+                Some("C-") => &mut key.ctrl,
+                Some("M-") => &mut key.alt,
+                Some("S-") => &mut key.shift,
+                _ => break,
+            };
+            *modifier = true;
+            rest = &rest[2..];
+        }
+
+        key.code = match rest {
+            "SPC" => KeyCode::Char(' '),
+            "RET" => KeyCode::Enter,
+            "TAB" => KeyCode::Tab,
+            "DEL" => KeyCode::Backspace,
+            "ESC" => KeyCode::Esc,
+            "<delete>" => KeyCode::Delete,
+            "<insert>" => KeyCode::Insert,
+            "<left>" => KeyCode::Left,
+            "<right>" => KeyCode::Right,
+            "<up>" => KeyCode::Up,
+            "<down>" => KeyCode::Down,
+            "<home>" => KeyCode::Home,
+            "<end>" => KeyCode::End,
+            "<prior>" => KeyCode::PageUp,
+            "<next>" => KeyCode::PageDown,
+            v => match (
+                v.strip_prefix("<f").and_then(|v| v.strip_suffix('>')),
+                single(v),
+            ) {
+                (Some(n), _) => KeyCode::F(
+                    n.parse()
+                        .map_err(|_| format!("KEYMAP_ERROR: unknown key {}", text))?,
+                ),
+                (None, Some(c)) => KeyCode::Char(c),
+                (None, None) => return Err(format!("KEYMAP_ERROR: unknown key {}", text).into()),
+            },
+        };
+
+        // The case of a character already says whether shift was held
+        if let KeyCode::Char(c) = key.code {
+            if key.shift {
+                key.code = KeyCode::Char(c.to_uppercase().next().unwrap_or(c));
+                key.shift = false;
+            }
+        }
+
+        Ok(key)
+    }
+}
+
+fn single(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    let c: char = chars.next()?;
+
+    chars.next().is_none().then_some(c)
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "C-")?;
+        }
+        if self.alt {
+            write!(f, "M-")?;
+        }
+        if self.shift {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "SPC"),
+            KeyCode::Char(v) => write!(f, "{}", v),
+            KeyCode::Enter => write!(f, "RET"),
+            KeyCode::Tab => write!(f, "TAB"),
+            KeyCode::Backspace => write!(f, "DEL"),
+            KeyCode::Esc => write!(f, "ESC"),
+            KeyCode::Delete => write!(f, "<delete>"),
+            KeyCode::Insert => write!(f, "<insert>"),
+            KeyCode::Left => write!(f, "<left>"),
+            KeyCode::Right => write!(f, "<right>"),
+            KeyCode::Up => write!(f, "<up>"),
+            KeyCode::Down => write!(f, "<down>"),
+            KeyCode::Home => write!(f, "<home>"),
+            KeyCode::End => write!(f, "<end>"),
+            KeyCode::PageUp => write!(f, "<prior>"),
+            KeyCode::PageDown => write!(f, "<next>"),
+            KeyCode::F(n) => write!(f, "<f{}>", n),
+        }
+    }
+}
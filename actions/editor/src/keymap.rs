@@ -0,0 +1,235 @@
+//! Key bindings, looked up by sequences of keys such as `C-x C-s`.
+//!
+//! Keymaps are layered: the one for the buffer's file type is tried first,
+//! then the one for the editing mode, then the global one.
+
+use crate::key::{Key, parse_keys};
+use core_lang::object::Object;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::time::Duration;
+
+/// What a key sequence runs.
+#[derive(Clone)]
+pub enum Binding {
+    /// A command, by name.
+    Command(String),
+    /// A procedure from `init.core`, called without arguments.
+    Procedure(Object),
+}
+
+/// The result of looking up keys typed so far.
+#[derive(Clone)]
+pub enum Lookup {
+    Found(Binding),
+    /// More keys may follow. The keys may also be bound on their own, which
+    /// is run if no other key comes before the prefix timeout.
+    Prefix(Option<Binding>),
+    Undefined,
+}
+
+#[derive(Clone, Default)]
+pub struct Keymap {
+    bindings: BTreeMap<Vec<Key>, Binding>,
+}
+
+#[derive(Clone)]
+pub struct Keymaps {
+    pub global: Keymap,
+    pub modes: HashMap<String, Keymap>,
+    pub filetypes: HashMap<String, Keymap>,
+    /// How long to wait after a prefix key before showing its continuations.
+    pub timeout: Duration,
+}
+
+/// The bindings of the global keymap before `init.core` changes them.
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("C-q", "quit"),
+    ("C-x C-c", "quit"),
+    ("C-s", "save"),
+    ("C-x C-s", "save"),
+    ("C-g", "keyboard-quit"),
+    ("M-x", "execute-command"),
+    ("C-h o", "describe-symbol"),
+    ("C-x C-f", "find-file"),
+    ("C-x b", "switch-buffer"),
+    ("C-x C-b", "list-buffers"),
+    ("C-x k", "close-buffer"),
+    ("C-x 2", "split-window-below"),
+    ("C-x 3", "split-window-right"),
+    ("C-x 0", "close-window"),
+    ("C-x 1", "only-window"),
+    ("C-x z", "maximize-window"),
+    ("C-x o", "other-window"),
+    ("C-x <left>", "window-left"),
+    ("C-x <right>", "window-right"),
+    ("C-x <up>", "window-up"),
+    ("C-x <down>", "window-down"),
+    ("C-x x", "swap-window"),
+    ("C-x ^", "enlarge-window"),
+    ("C-x -", "shrink-window"),
+    ("C-x }", "enlarge-window-horizontally"),
+    ("C-x {", "shrink-window-horizontally"),
+    ("C-x t 2", "new-tab"),
+    ("C-x t o", "next-tab"),
+    ("C-x t O", "previous-tab"),
+    ("C-x t 0", "close-tab"),
+    ("C-/", "undo"),
+    ("C-_", "undo"),
+    ("M-_", "redo"),
+    ("C-f", "search-forward"),
+    ("C-r", "search-backward"),
+    ("M-%", "query-replace"),
+    ("C-M-%", "query-replace-regex"),
+    ("RET", "newline"),
+    ("TAB", "insert-tab"),
+    ("DEL", "delete-backward-char"),
+    ("<delete>", "delete-char"),
+    ("<left>", "backward-char"),
+    ("<right>", "forward-char"),
+    ("<up>", "previous-line"),
+    ("<down>", "next-line"),
+    ("<prior>", "page-up"),
+    ("<next>", "page-down"),
+    ("<home>", "beginning-of-line"),
+    ("<end>", "end-of-line"),
+];
+
+impl Binding {
+    /// The name shown for this binding, e.g. in the list of continuations.
+    pub fn describe(&self) -> String {
+        match self {
+            Binding::Command(v) => v.clone(),
+            Binding::Procedure(Object::Procedure(v)) => {
+                v.name.clone().unwrap_or_else(|| String::from("lambda"))
+            }
+            Binding::Procedure(Object::Native(v)) => v.name.clone(),
+            Binding::Procedure(v) => v.to_string(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn bind(&mut self, keys: Vec<Key>, binding: Binding) {
+        self.bindings.insert(keys, binding);
+    }
+
+    /// Removes a binding. Returns `false` if there was none.
+    pub fn unbind(&mut self, keys: &[Key]) -> bool {
+        self.bindings.remove(keys).is_some()
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let exact: Option<Binding> = self.bindings.get(keys).cloned();
+        let longer: bool = self
+            .bindings
+            .range::<[Key], _>((Bound::Excluded(keys), Bound::Unbounded))
+            .next()
+            .is_some_and(|(k, _)| k.starts_with(keys));
+
+        match (exact, longer) {
+            (exact, true) => Lookup::Prefix(exact),
+            (Some(v), false) => Lookup::Found(v),
+            (None, false) => Lookup::Undefined,
+        }
+    }
+
+    /// The keys which can follow `prefix`, with what each one runs, or
+    /// `+prefix` for keys which need more keys after them.
+    pub fn continuations(&self, prefix: &[Key]) -> Vec<(Key, String)> {
+        let mut result: Vec<(Key, String)> = Vec::new();
+        let after = self
+            .bindings
+            .range::<[Key], _>((Bound::Excluded(prefix), Bound::Unbounded))
+            .take_while(|(k, _)| k.starts_with(prefix));
+        for (keys, binding) in after {
+            let key: Key = keys[prefix.len()];
+            // A key bound on its own sorts before the longer sequences it starts
+            if result.last().is_some_and(|(v, _)| *v == key) {
+                continue;
+            }
+            result.push(match keys.len() == prefix.len() + 1 {
+                true => (key, binding.describe()),
+                false => (key, String::from("+prefix")),
+            });
+        }
+
+        result
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (&[Key], &Binding)> {
+        self.bindings.iter().map(|(k, v)| (k.as_slice(), v))
+    }
+}
+
+impl Default for Keymaps {
+    fn default() -> Self {
+        let mut global: Keymap = Keymap::default();
+        for (keys, command) in DEFAULT_BINDINGS {
+            if let Ok(keys) = parse_keys(keys) {
+                global.bind(keys, Binding::Command(command.to_string()));
+            }
+        }
+
+        Keymaps {
+            global,
+            modes: HashMap::new(),
+            filetypes: HashMap::new(),
+            timeout: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl Keymaps {
+    /// Looks `keys` up in the keymap of `filetype`, then of `mode`, then in
+    /// the global keymap. The first keymap which knows the keys decides.
+    pub fn lookup(&self, mode: &str, filetype: Option<&str>, keys: &[Key]) -> Lookup {
+        match self.lookup_mode(mode, filetype, keys) {
+            Lookup::Undefined => self.global.lookup(keys),
+            v => v,
+        }
+    }
+
+    /// Looks `keys` up like [`Keymaps::lookup`], but not in the global keymap.
+    pub fn lookup_mode(&self, mode: &str, filetype: Option<&str>, keys: &[Key]) -> Lookup {
+        let mut layers: Vec<&Keymap> = self.layers(mode, filetype);
+        layers.pop();
+        for keymap in layers {
+            match keymap.lookup(keys) {
+                Lookup::Undefined => (),
+                v => return v,
+            }
+        }
+
+        Lookup::Undefined
+    }
+
+    /// The continuations of `prefix` in every layer, sorted by key.
+    pub fn continuations(
+        &self,
+        mode: &str,
+        filetype: Option<&str>,
+        prefix: &[Key],
+    ) -> Vec<(Key, String)> {
+        let mut result: Vec<(Key, String)> = Vec::new();
+        for keymap in self.layers(mode, filetype) {
+            for (key, description) in keymap.continuations(prefix) {
+                if !result.iter().any(|(v, _)| *v == key) {
+                    result.push((key, description));
+                }
+            }
+        }
+        result.sort_by_key(|(v, _)| *v);
+
+        result
+    }
+
+    fn layers(&self, mode: &str, filetype: Option<&str>) -> Vec<&Keymap> {
+        let mut result: Vec<&Keymap> = Vec::new();
+        result.extend(filetype.and_then(|v| self.filetypes.get(v)));
+        result.extend(self.modes.get(mode));
+        result.push(&self.global);
+
+        result
+    }
+}
@@ -1,8 +1,81 @@
-use core_lang::ast::AST;
+use core_lang::syntax::Source;
 use std::path::PathBuf;
+use std::time::Duration;
 
-pub fn editor(ast: AST, path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    dbg!(ast);
-    dbg!(path);
-    todo!()
+pub mod backend;
+pub mod buffer;
+pub mod command;
+pub mod editor;
+pub mod file;
+pub mod fuzzy;
+pub mod grid;
+pub mod history;
+pub mod key;
+pub mod keymap;
+pub mod minibuffer;
+pub mod modal;
+mod rope;
+pub mod script;
+pub mod search;
+pub mod terminal;
+pub mod window;
+
+use backend::{Backend, CrosstermBackend, Event};
+use buffer::Buffer;
+use editor::Editor;
+use terminal::Terminal;
+
+/// Opens `paths` in the editor, showing the first, configured by `config`,
+/// which is the source of `init.core`. Data kept between sessions goes in
+/// `data_directory`.
+pub fn editor(
+    config: Source,
+    paths: Vec<PathBuf>,
+    data_directory: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Without a file name there is nothing to read, so start from an empty buffer
+    let buffer: Buffer = match paths.first() {
+        Some(path) => Buffer::open(path)?,
+        None => Buffer::new(),
+    };
+    let mut editor: Editor = Editor::new(buffer);
+    // Opening the rest last to first, then going back to the first, leaves
+    // the others in the order given
+    if let Some((first, rest)) = paths.split_first() {
+        for path in rest.iter().rev() {
+            editor.open(path)?;
+        }
+        editor.open(first)?;
+    }
+    editor.set_data_directory(data_directory);
+    // A broken config should not lock anyone out of the editor that fixes it
+    if let Err(e) = editor.load_config(config) {
+        editor.set_message(e.to_string());
+    }
+    let mut terminal: Terminal<CrosstermBackend> = Terminal::new(CrosstermBackend::new()?)?;
+
+    run(&mut editor, &mut terminal)
+}
+
+/// Draws the editor and handles events until it quits or input ends.
+pub fn run<B: Backend>(
+    editor: &mut Editor,
+    terminal: &mut Terminal<B>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        let (width, height) = terminal.size();
+        let (grid, cursor) = editor.render(width, height);
+        terminal.draw(&grid, cursor)?;
+        if editor.should_quit() {
+            return Ok(());
+        }
+
+        let timeout: Option<Duration> = editor.timeout();
+        match terminal.backend_mut().poll(timeout)? {
+            Some(Event::Resize(width, height)) => terminal.resize(width, height)?,
+            Some(event) => editor.handle(&event),
+            None if timeout.is_some() => editor.timed_out(),
+            None => return Ok(()),
+        }
+    }
 }
@@ -0,0 +1,170 @@
+//! Reading text in the message area: a line typed into a prompt, or a name
+//! picked from the candidates which match what is typed, with earlier
+//! answers a key away.
+
+use crate::fuzzy;
+use crate::key::{Key, KeyCode};
+use std::path::Path;
+
+/// What the minibuffer reads, which decides its candidates and what is done
+/// with the answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reading {
+    /// A command to run.
+    Command,
+    /// A file to open, which need not exist.
+    File,
+    /// An open buffer to show.
+    Buffer,
+    /// A name defined in `init.core` or by the editor, to describe.
+    Symbol,
+}
+
+/// A name being read.
+#[derive(Debug, Clone, Default)]
+pub struct Minibuffer {
+    pub text: String,
+    /// Which of the candidates the text matches is picked, the best match
+    /// being 0.
+    pub selected: usize,
+    /// How many answers back the text was recalled from, 0 being the last.
+    recalled: Option<usize>,
+}
+
+/// Text typed into a prompt, after a key.
+pub enum Input {
+    Editing(String),
+    Done(String),
+    Cancelled,
+}
+
+impl Reading {
+    /// What the minibuffer asks.
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            Reading::Command => "M-x ",
+            Reading::File => "Find file: ",
+            Reading::Buffer => "Switch to buffer: ",
+            Reading::Symbol => "Describe symbol: ",
+        }
+    }
+
+    /// What one candidate is called, in messages.
+    pub fn noun(&self) -> &'static str {
+        match self {
+            Reading::Command => "command",
+            Reading::File => "file",
+            Reading::Buffer => "buffer",
+            Reading::Symbol => "symbol",
+        }
+    }
+}
+
+impl Minibuffer {
+    pub fn new() -> Self {
+        Minibuffer::default()
+    }
+
+    /// Changes the text, or the candidate picked, by `key`. `candidates` are
+    /// those the text matches now, best first, and `answers` those given
+    /// before, oldest first.
+    ///
+    /// Down and C-n pick the next candidate, and Up and C-p the one before.
+    /// TAB completes the text to the candidate picked. M-p recalls the
+    /// answer before the one shown, and M-n the one after.
+    pub fn key(&mut self, key: Key, candidates: &[String], answers: &[String]) -> Input {
+        match (key.code, key.ctrl, key.alt) {
+            (KeyCode::Down, false, false) | (KeyCode::Char('n'), true, false) => {
+                self.selected = (self.selected + 1).min(candidates.len().saturating_sub(1));
+            }
+            (KeyCode::Up, false, false) | (KeyCode::Char('p'), true, false) => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            (KeyCode::Tab, false, false) => {
+                if let Some(candidate) = candidates.get(self.selected) {
+                    self.text = candidate.clone();
+                    self.selected = 0;
+                }
+            }
+            (KeyCode::Char('p'), false, true) => {
+                let back: usize = self.recalled.map_or(0, |v| v + 1);
+                if back < answers.len() {
+                    self.recall(Some(back), answers);
+                }
+            }
+            (KeyCode::Char('n'), false, true) => match self.recalled {
+                Some(0) | None => self.recall(None, answers),
+                Some(back) => self.recall(Some(back - 1), answers),
+            },
+            _ => match input(std::mem::take(&mut self.text), key) {
+                Input::Editing(text) => {
+                    self.selected = 0;
+                    self.text = text;
+                }
+                v => return v,
+            },
+        }
+
+        Input::Editing(self.text.clone())
+    }
+
+    fn recall(&mut self, back: Option<usize>, answers: &[String]) {
+        self.text = match back {
+            Some(v) => answers[answers.len() - 1 - v].clone(),
+            None => String::new(),
+        };
+        self.recalled = back;
+        self.selected = 0;
+    }
+}
+
+/// What a key does to the text typed into a prompt.
+pub fn input(mut text: String, key: Key) -> Input {
+    match (key.code, key.char()) {
+        (_, Some(c)) => {
+            text.push(c);
+            Input::Editing(text)
+        }
+        (KeyCode::Backspace, _) => {
+            text.pop();
+            Input::Editing(text)
+        }
+        (KeyCode::Enter, _) => Input::Done(text),
+        (KeyCode::Esc, _) => Input::Cancelled,
+        _ if key == Key::ctrl(KeyCode::Char('g')) => Input::Cancelled,
+        _ => Input::Editing(text),
+    }
+}
+
+/// The files which `text` may be completed to: those in the directory it
+/// names up to its last `/` whose names match the rest fuzzily, best first.
+/// Directories end in `/`, and hidden files are left out unless the name
+/// typed starts with a dot.
+pub fn files(text: &str) -> Vec<String> {
+    let (directory, name) = match text.rfind('/') {
+        Some(i) => text.split_at(i + 1),
+        None => ("", text),
+    };
+    let path: &Path = Path::new(if directory.is_empty() { "." } else { directory });
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|v| {
+            let mut file: String = v.file_name().to_string_lossy().into_owned();
+            if v.path().is_dir() {
+                file.push('/');
+            }
+            file
+        })
+        .filter(|v| !v.starts_with('.') || name.starts_with('.'))
+        .collect();
+    names.sort();
+
+    fuzzy::rank(name, &names)
+        .into_iter()
+        .map(|i| format!("{}{}", directory, names[i]))
+        .collect()
+}
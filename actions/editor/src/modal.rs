@@ -0,0 +1,615 @@
+//! Modal editing in the manner of vi. In normal mode keys move the cursor
+//! and operate on text rather than type it.
+//!
+//! Keys are read as `[count] [operator [count]] motion`, so `d2w` deletes
+//! two words and `3j` moves down three lines. [`Modal`] only reads them; the
+//! editor carries out the [`Action`] they add up to.
+
+use crate::buffer::Buffer;
+use crate::key::{Key, KeyCode};
+
+pub const NORMAL: &str = "normal";
+pub const INSERT: &str = "insert";
+pub const VISUAL: &str = "visual";
+pub const VISUAL_LINE: &str = "visual-line";
+pub const VISUAL_BLOCK: &str = "visual-block";
+pub const OPERATOR_PENDING: &str = "operator-pending";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `w`, or `W` for words separated only by whitespace.
+    WordForward {
+        big: bool,
+    },
+    WordBackward {
+        big: bool,
+    },
+    WordEnd {
+        big: bool,
+    },
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    /// `gg`, or the line of the count.
+    FirstLine,
+    /// `G`, or the line of the count.
+    LastLine,
+    ParagraphForward,
+    ParagraphBackward,
+    /// `f`, `F`, `t` and `T`, which look for a char on the current line.
+    Find {
+        target: char,
+        forward: bool,
+        till: bool,
+    },
+}
+
+/// Where a motion ends, and how an operator treats the text it crosses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub offset: usize,
+    /// Operators act on whole lines.
+    pub linewise: bool,
+    /// Operators include the char at `offset`.
+    pub inclusive: bool,
+}
+
+/// Where `i`, `a`, `o` and the like start inserting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Insert {
+    Before,
+    After,
+    LineStart,
+    LineEnd,
+    LineBelow,
+    LineAbove,
+}
+
+/// What a sequence of keys in a modal state asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// More keys are needed.
+    Pending,
+    /// An operator was typed, and waits for its motion.
+    Operator(Operator),
+    Move(Motion, usize),
+    Operate(Operator, Motion, usize),
+    /// `dd`, `cc` and `yy`, which act on the count of lines from the cursor.
+    OperateLines(Operator, usize),
+    Insert(Insert),
+    Put {
+        before: bool,
+        count: usize,
+    },
+    Replace(char, usize),
+    /// Starts, switches or leaves the visual mode of this name.
+    Visual(&'static str),
+    /// Moves the cursor to the other end of the selection.
+    SwapAnchor,
+    OperateSelection(Operator),
+    Undo(usize),
+    Redo(usize),
+    /// `g-` and `g+`, which go through changes in the order they were made.
+    Earlier(usize),
+    Later(usize),
+    /// `/` and `?`, which start a search for a regular expression.
+    Search {
+        forward: bool,
+    },
+    /// `n`, or `N` to go the other way.
+    SearchNext {
+        reverse: bool,
+        count: usize,
+    },
+    /// Goes back to normal mode.
+    Escape,
+}
+
+/// The keys read so far of a command which is not complete.
+#[derive(Debug, Clone, Default)]
+pub struct Modal {
+    count: Option<usize>,
+    operator: Option<(Operator, usize)>,
+    awaiting: Option<Awaiting>,
+    /// The last `f`, `F`, `t` or `T`, which `;` and `,` repeat.
+    last_find: Option<Motion>,
+}
+
+/// A key which needs one more key to mean anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Awaiting {
+    Find { forward: bool, till: bool },
+    Replace,
+    G,
+}
+
+/// What the text of a register was taken from, which decides how it is put.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Register {
+    Chars(String),
+    /// Whole lines, each ending with a line break.
+    Lines(String),
+    /// A rectangle, one string for each line.
+    Block(Vec<String>),
+}
+
+impl Default for Register {
+    fn default() -> Self {
+        Register::Chars(String::new())
+    }
+}
+
+impl Modal {
+    /// Reads `key` in `mode`. Returns `None` if the key means nothing here,
+    /// after forgetting the keys read before it.
+    pub fn key(&mut self, key: Key, mode: &str) -> Option<Action> {
+        if mode == INSERT {
+            return (key == Key::new(KeyCode::Esc)).then_some(Action::Escape);
+        }
+
+        let visual: bool = matches!(mode, VISUAL | VISUAL_LINE | VISUAL_BLOCK);
+        let motion: Option<Motion> = match self.awaiting.take() {
+            Some(awaiting) => match (awaiting, key.char()) {
+                (Awaiting::Find { forward, till }, Some(target)) => {
+                    let find: Motion = Motion::Find {
+                        target,
+                        forward,
+                        till,
+                    };
+                    self.last_find = Some(find);
+                    Some(find)
+                }
+                (Awaiting::Replace, Some(c)) => {
+                    let count: usize = self.take_count();
+                    return Some(Action::Replace(c, count));
+                }
+                (Awaiting::G, Some('g')) => Some(Motion::FirstLine),
+                (Awaiting::G, Some('-')) if self.operator.is_none() => {
+                    return Some(Action::Earlier(self.take_count()));
+                }
+                (Awaiting::G, Some('+')) if self.operator.is_none() => {
+                    return Some(Action::Later(self.take_count()));
+                }
+                _ => return Some(self.escape()),
+            },
+            None => None,
+        };
+
+        let motion: Option<Motion> = motion.or_else(|| self.motion(key));
+        if let Some(motion) = motion {
+            let count: usize = self.take_count();
+            return Some(match self.operator.take() {
+                Some((operator, before)) => Action::Operate(operator, motion, before * count),
+                None => Action::Move(motion, count),
+            });
+        }
+
+        let c: char = match (key.code, key.ctrl, key.alt) {
+            (KeyCode::Esc, _, _) => return Some(self.escape()),
+            (KeyCode::Char('v'), true, false) => {
+                return Some(self.reset(Action::Visual(VISUAL_BLOCK)));
+            }
+            (KeyCode::Char('r'), true, false) if !visual && self.operator.is_none() => {
+                let count: usize = self.take_count();
+                return Some(Action::Redo(count));
+            }
+            (KeyCode::Delete, false, false) => 'x',
+            _ => key.char()?,
+        };
+        let count: Option<usize> = self.count;
+        match c {
+            '1'..='9' => return Some(self.digit(c)),
+            '0' if count.is_some() => return Some(self.digit(c)),
+            'f' | 'F' | 't' | 'T' => {
+                self.awaiting = Some(Awaiting::Find {
+                    forward: c.is_lowercase(),
+                    till: c.eq_ignore_ascii_case(&'t'),
+                });
+                return Some(Action::Pending);
+            }
+            'g' => {
+                self.awaiting = Some(Awaiting::G);
+                return Some(Action::Pending);
+            }
+            _ => (),
+        }
+
+        let count: usize = self.take_count();
+        let action: Option<Action> = match (c, self.operator.take(), visual) {
+            ('d' | 'c' | 'y', Some((operator, before)), false) => {
+                match operator == operator_of(c)? {
+                    true => Some(Action::OperateLines(operator, before * count)),
+                    false => None,
+                }
+            }
+            (_, Some(_), _) => None,
+            ('d' | 'x', None, true) => Some(Action::OperateSelection(Operator::Delete)),
+            ('c' | 's', None, true) => Some(Action::OperateSelection(Operator::Change)),
+            ('y', None, true) => Some(Action::OperateSelection(Operator::Yank)),
+            ('o', None, true) => Some(Action::SwapAnchor),
+            ('d' | 'c' | 'y', None, false) => {
+                let operator: Operator = operator_of(c)?;
+                self.operator = Some((operator, count));
+                Some(Action::Operator(operator))
+            }
+            ('x', None, false) => Some(Action::Operate(Operator::Delete, Motion::Right, count)),
+            ('X', None, false) => Some(Action::Operate(Operator::Delete, Motion::Left, count)),
+            ('s', None, false) => Some(Action::Operate(Operator::Change, Motion::Right, count)),
+            ('D', None, false) => Some(Action::Operate(Operator::Delete, Motion::LineEnd, 1)),
+            ('C', None, false) => Some(Action::Operate(Operator::Change, Motion::LineEnd, 1)),
+            ('Y', None, false) => Some(Action::OperateLines(Operator::Yank, count)),
+            ('p', None, false) => Some(Action::Put {
+                before: false,
+                count,
+            }),
+            ('P', None, false) => Some(Action::Put {
+                before: true,
+                count,
+            }),
+            ('r', None, false) => {
+                self.count = Some(count);
+                self.awaiting = Some(Awaiting::Replace);
+                Some(Action::Pending)
+            }
+            ('u', None, false) => Some(Action::Undo(count)),
+            ('i', None, false) => Some(Action::Insert(Insert::Before)),
+            ('a', None, false) => Some(Action::Insert(Insert::After)),
+            ('I', None, false) => Some(Action::Insert(Insert::LineStart)),
+            ('A', None, false) => Some(Action::Insert(Insert::LineEnd)),
+            ('o', None, false) => Some(Action::Insert(Insert::LineBelow)),
+            ('O', None, false) => Some(Action::Insert(Insert::LineAbove)),
+            ('/', None, _) => Some(Action::Search { forward: true }),
+            ('?', None, _) => Some(Action::Search { forward: false }),
+            ('n', None, _) => Some(Action::SearchNext {
+                reverse: false,
+                count,
+            }),
+            ('N', None, _) => Some(Action::SearchNext {
+                reverse: true,
+                count,
+            }),
+            ('v', None, _) => Some(Action::Visual(VISUAL)),
+            ('V', None, _) => Some(Action::Visual(VISUAL_LINE)),
+            _ => None,
+        };
+        if action.is_none() {
+            self.reset(Action::Escape);
+        }
+
+        action
+    }
+
+    /// Forgets the keys read so far.
+    pub fn escape(&mut self) -> Action {
+        self.reset(Action::Escape)
+    }
+
+    /// Whether the next key is read as a char, such as the one after `f`,
+    /// whatever it is bound to.
+    pub fn awaiting(&self) -> bool {
+        self.awaiting.is_some()
+    }
+
+    fn reset(&mut self, action: Action) -> Action {
+        self.count = None;
+        self.operator = None;
+        self.awaiting = None;
+
+        action
+    }
+
+    fn digit(&mut self, c: char) -> Action {
+        let digit: usize = c.to_digit(10).unwrap_or(0) as usize;
+        self.count = Some(
+            self.count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit),
+        );
+
+        Action::Pending
+    }
+
+    fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1)
+    }
+
+    fn motion(&self, key: Key) -> Option<Motion> {
+        if key.ctrl || key.alt {
+            return None;
+        }
+        let motion: Motion = match key.code {
+            KeyCode::Left | KeyCode::Backspace => Motion::Left,
+            KeyCode::Right => Motion::Right,
+            KeyCode::Up => Motion::Up,
+            KeyCode::Down | KeyCode::Enter => Motion::Down,
+            KeyCode::Home => Motion::LineStart,
+            KeyCode::End => Motion::LineEnd,
+            KeyCode::Char(c) => match c {
+                'h' => Motion::Left,
+                'l' | ' ' => Motion::Right,
+                'k' => Motion::Up,
+                'j' => Motion::Down,
+                'w' | 'W' => Motion::WordForward { big: c == 'W' },
+                'b' | 'B' => Motion::WordBackward { big: c == 'B' },
+                'e' | 'E' => Motion::WordEnd { big: c == 'E' },
+                '0' if self.count.is_none() => Motion::LineStart,
+                '^' => Motion::FirstNonBlank,
+                '$' => Motion::LineEnd,
+                'G' => Motion::LastLine,
+                '}' => Motion::ParagraphForward,
+                '{' => Motion::ParagraphBackward,
+                ';' => self.last_find?,
+                ',' => match self.last_find? {
+                    Motion::Find {
+                        target,
+                        forward,
+                        till,
+                    } => Motion::Find {
+                        target,
+                        forward: !forward,
+                        till,
+                    },
+                    v => v,
+                },
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(motion)
+    }
+}
+
+fn operator_of(c: char) -> Option<Operator> {
+    match c {
+        'd' => Some(Operator::Delete),
+        'c' => Some(Operator::Change),
+        'y' => Some(Operator::Yank),
+        _ => None,
+    }
+}
+
+/// Where `motion` repeated `count` times goes from `cursor`, or `None` if it
+/// cannot move, such as `f` for a char which is not on the line.
+pub fn target(
+    buffer: &Buffer,
+    cursor: usize,
+    motion: Motion,
+    count: usize,
+) -> Result<Option<Target>, Box<dyn std::error::Error>> {
+    let line: usize = buffer.offset_to_position(cursor)?.line;
+    let last: usize = buffer.len_lines() - 1;
+    let range = |line: usize| buffer.line_range(line).unwrap_or_default();
+    let exclusive = |offset: usize| Target {
+        offset,
+        linewise: false,
+        inclusive: false,
+    };
+    let linewise = |line: usize| Target {
+        offset: first_non_blank(buffer, line),
+        linewise: true,
+        inclusive: false,
+    };
+
+    let target: Target = match motion {
+        Motion::Left => {
+            let mut offset: usize = cursor;
+            for _ in 0..count {
+                if offset > range(line).start {
+                    offset = buffer.prev_grapheme_boundary(offset)?;
+                }
+            }
+            exclusive(offset)
+        }
+        Motion::Right => {
+            let mut offset: usize = cursor;
+            for _ in 0..count {
+                if offset < range(line).end {
+                    offset = buffer.next_grapheme_boundary(offset)?;
+                }
+            }
+            exclusive(offset)
+        }
+        Motion::Up if count > line => return Ok(None),
+        Motion::Up => linewise(line - count),
+        Motion::Down if line + count > last => return Ok(None),
+        Motion::Down => linewise(line + count),
+        Motion::WordForward { big } => {
+            exclusive(repeat(cursor, count, |v| word_forward(buffer, v, big)))
+        }
+        Motion::WordBackward { big } => {
+            exclusive(repeat(cursor, count, |v| word_backward(buffer, v, big)))
+        }
+        Motion::WordEnd { big } => Target {
+            offset: repeat(cursor, count, |v| word_end(buffer, v, big)),
+            linewise: false,
+            inclusive: true,
+        },
+        Motion::LineStart => exclusive(range(line).start),
+        Motion::FirstNonBlank => exclusive(first_non_blank(buffer, line)),
+        Motion::LineEnd => exclusive(range((line + count - 1).min(last)).end),
+        Motion::FirstLine if count > 1 => linewise((count - 1).min(last)),
+        Motion::FirstLine => linewise(0),
+        Motion::LastLine if count > 1 => linewise((count - 1).min(last)),
+        Motion::LastLine => linewise(last_line(buffer)),
+        Motion::ParagraphForward | Motion::ParagraphBackward => {
+            let forward: bool = motion == Motion::ParagraphForward;
+            let line: usize = repeat(line, count, |v| paragraph(buffer, v, forward));
+            match line > last_line(buffer) {
+                true => exclusive(range(last_line(buffer)).end),
+                false => exclusive(range(line).start),
+            }
+        }
+        Motion::Find {
+            target,
+            forward,
+            till,
+        } => match find(buffer, cursor, target, forward, till, count)? {
+            Some(offset) => Target {
+                offset,
+                linewise: false,
+                inclusive: forward,
+            },
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(target))
+}
+
+fn repeat(start: usize, count: usize, step: impl Fn(usize) -> usize) -> usize {
+    (0..count).fold(start, |v, _| step(v))
+}
+
+/// Words are runs of letters, digits and `_`, or runs of other non-blank
+/// chars. Big words are any runs of non-blank chars.
+fn class(c: char, big: bool) -> u8 {
+    match c {
+        c if c.is_whitespace() => 0,
+        _ if big => 1,
+        c if c.is_alphanumeric() || c == '_' => 2,
+        _ => 1,
+    }
+}
+
+/// The start of the next word after `offset`. An empty line counts as a word.
+fn word_forward(buffer: &Buffer, offset: usize, big: bool) -> usize {
+    let mut chars = buffer.chars_from(offset);
+    let Some((_, first)) = chars.next() else {
+        return offset;
+    };
+    let mut word: Option<u8> = Some(class(first, big)).filter(|v| *v != 0);
+    let mut previous: char = first;
+    for (i, c) in chars {
+        let k: u8 = class(c, big);
+        match word {
+            Some(v) if v == k => continue,
+            _ if k != 0 => return i,
+            _ if c == '\n' && previous == '\n' => return i,
+            _ => word = None,
+        }
+        previous = c;
+    }
+
+    buffer.len()
+}
+
+/// The start of the word before `offset`.
+fn word_backward(buffer: &Buffer, offset: usize, big: bool) -> usize {
+    let mut chars = buffer.chars_before(offset).peekable();
+    let mut word: Option<(u8, usize)> = None;
+    while let Some((i, c)) = chars.next() {
+        let k: u8 = class(c, big);
+        match word {
+            Some((v, _)) if v == k => word = Some((v, i)),
+            Some((_, start)) => return start,
+            None if k != 0 => word = Some((k, i)),
+            None if c == '\n' && chars.peek().is_none_or(|(_, v)| *v == '\n') => return i,
+            None => (),
+        }
+    }
+
+    word.map_or(0, |(_, start)| start)
+}
+
+/// The last char of the word after `offset`.
+fn word_end(buffer: &Buffer, offset: usize, big: bool) -> usize {
+    let mut chars = buffer.chars_from(offset).skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        let k: u8 = class(c, big);
+        if k == 0 {
+            continue;
+        }
+        let mut end: usize = i;
+        while let Some((j, _)) = chars.next_if(|(_, v)| class(*v, big) == k) {
+            end = j;
+        }
+        return end;
+    }
+
+    offset
+}
+
+/// The last line of text. The empty line after a final line break does not
+/// count, as vi does not show it.
+pub fn last_line(buffer: &Buffer) -> usize {
+    let last: usize = buffer.len_lines() - 1;
+    match buffer.line_range(last) {
+        Some(v) if v.is_empty() && last > 0 => last - 1,
+        _ => last,
+    }
+}
+
+/// The next empty line after `line`, or the previous one, past any empty
+/// lines next to it. Past the last line is `len_lines`.
+fn paragraph(buffer: &Buffer, line: usize, forward: bool) -> usize {
+    let empty = |v: usize| buffer.line(v).is_some_and(|v| v.trim().is_empty());
+    let mut line: usize = line;
+    let mut seen_text: bool = !empty(line);
+    loop {
+        match forward {
+            true if line + 1 > last_line(buffer) => return buffer.len_lines(),
+            true => line += 1,
+            false if line == 0 => return 0,
+            false => line -= 1,
+        }
+        match empty(line) {
+            true if seen_text => return line,
+            true => (),
+            false => seen_text = true,
+        }
+    }
+}
+
+fn first_non_blank(buffer: &Buffer, line: usize) -> usize {
+    let range = buffer.line_range(line).unwrap_or_default();
+    let text: String = buffer.line(line).unwrap_or_default();
+    let blank: usize = text.len() - text.trim_start().len();
+
+    (range.start + blank).min(range.end)
+}
+
+/// The offset of the `count`th `target` on the line of `cursor`, or just
+/// before it for `till`.
+fn find(
+    buffer: &Buffer,
+    cursor: usize,
+    target: char,
+    forward: bool,
+    till: bool,
+    count: usize,
+) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+    let found: Option<usize> = match forward {
+        true => buffer
+            .chars_from(cursor)
+            .skip(1)
+            .take_while(|(_, v)| *v != '\n')
+            .filter(|(_, v)| *v == target)
+            .nth(count - 1)
+            .map(|(i, _)| i),
+        false => buffer
+            .chars_before(cursor)
+            .take_while(|(_, v)| *v != '\n')
+            .filter(|(_, v)| *v == target)
+            .nth(count - 1)
+            .map(|(i, _)| i),
+    };
+
+    Ok(match (found, till, forward) {
+        (Some(v), true, true) => Some(buffer.prev_grapheme_boundary(v)?),
+        (Some(v), true, false) => Some(buffer.next_grapheme_boundary(v)?),
+        (v, _, _) => v,
+    })
+}
@@ -0,0 +1,422 @@
+//! A height-balanced tree of text chunks, so that edits anywhere in a large
+//! file only touch O(log n) nodes.
+//!
+//! Text lives in the leaves. Branches cache the length and the number of
+//! newlines below them, which is what offset and line lookups descend by.
+
+/// Leaves are split to at most this many bytes, and neighbours are merged
+/// while they fit.
+const MAX_LEAF: usize = 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct Rope {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf(String),
+    Branch {
+        left: Box<Node>,
+        right: Box<Node>,
+        len: usize,
+        newlines: usize,
+        height: usize,
+    },
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Node::Leaf(String::new())
+    }
+}
+
+impl Rope {
+    pub fn new(text: &str) -> Self {
+        let mut leaves: Vec<Node> = Vec::new();
+        let mut rest: &str = text;
+        while !rest.is_empty() {
+            let mut end: usize = rest.len().min(MAX_LEAF);
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            leaves.push(Node::Leaf(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+
+        Rope {
+            root: balanced(leaves),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of `\n` in the text.
+    pub fn newlines(&self) -> usize {
+        self.root.newlines()
+    }
+
+    /// Whether `offset` is within the text and not inside a UTF-8 sequence.
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        let mut node: &Node = &self.root;
+        let mut offset: usize = offset;
+        loop {
+            match node {
+                Node::Leaf(v) => return v.is_char_boundary(offset),
+                Node::Branch { left, right, .. } => match offset < left.len() {
+                    true => node = left,
+                    false => {
+                        offset -= left.len();
+                        node = right;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Inserts `text` at `offset`, which must be a char boundary.
+    pub fn insert(&mut self, offset: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let (left, right) = split(std::mem::take(&mut self.root), offset);
+        self.root = join(join(left, Rope::new(text).root), right);
+    }
+
+    /// Removes the bytes in `start..end`, which must be char boundaries.
+    pub fn delete(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        let (left, rest) = split(std::mem::take(&mut self.root), start);
+        let (_, right) = split(rest, end - start);
+        self.root = join(left, right);
+    }
+
+    /// The text in `start..end`.
+    pub fn slice(&self, start: usize, end: usize) -> String {
+        let mut result: String = String::with_capacity(end.saturating_sub(start));
+        collect(&self.root, start, end, &mut result);
+
+        result
+    }
+
+    /// The offset just after the `n`th newline, counting from 0.
+    pub fn after_newline(&self, n: usize) -> Option<usize> {
+        let mut node: &Node = &self.root;
+        let mut n: usize = n;
+        let mut base: usize = 0;
+        loop {
+            match node {
+                Node::Leaf(v) => {
+                    let (i, _) = v.match_indices('\n').nth(n)?;
+                    return Some(base + i + 1);
+                }
+                Node::Branch { left, right, .. } => match n < left.newlines() {
+                    true => node = left,
+                    false => {
+                        n -= left.newlines();
+                        base += left.len();
+                        node = right;
+                    }
+                },
+            }
+        }
+    }
+
+    /// The number of newlines before `offset`.
+    pub fn newlines_before(&self, offset: usize) -> usize {
+        let mut node: &Node = &self.root;
+        let mut offset: usize = offset;
+        let mut result: usize = 0;
+        loop {
+            match node {
+                Node::Leaf(v) => {
+                    let end: usize = offset.min(v.len());
+                    return result + v.as_bytes()[..end].iter().filter(|b| **b == b'\n').count();
+                }
+                Node::Branch { left, right, .. } => match offset < left.len() {
+                    true => node = left,
+                    false => {
+                        offset -= left.len();
+                        result += left.newlines();
+                        node = right;
+                    }
+                },
+            }
+        }
+    }
+
+    /// The leaves of the tree, in order.
+    pub fn chunks(&self) -> Chunks<'_> {
+        Chunks {
+            stack: vec![&self.root],
+        }
+    }
+}
+
+impl std::fmt::Display for Rope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.chunks().try_for_each(|v| f.write_str(v))
+    }
+}
+
+#[derive(Clone)]
+pub struct Chunks<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Node::Leaf(v) if v.is_empty() => (),
+                Node::Leaf(v) => return Some(v),
+                Node::Branch { left, right, .. } => {
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+            }
+        }
+    }
+}
+
+impl Node {
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf(v) => v.len(),
+            Node::Branch { len, .. } => *len,
+        }
+    }
+
+    fn newlines(&self) -> usize {
+        match self {
+            Node::Leaf(v) => v.bytes().filter(|b| *b == b'\n').count(),
+            Node::Branch { newlines, .. } => *newlines,
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Node::Leaf(_) => 0,
+            Node::Branch { height, .. } => *height,
+        }
+    }
+
+    /// Puts two nodes side by side, merging small leaves.
+    fn branch(left: Node, right: Node) -> Node {
+        match (left, right) {
+            (Node::Leaf(mut a), Node::Leaf(b)) if a.len() + b.len() <= MAX_LEAF => {
+                a.push_str(&b);
+                Node::Leaf(a)
+            }
+            (left, right) => Node::Branch {
+                len: left.len() + right.len(),
+                newlines: left.newlines() + right.newlines(),
+                height: left.height().max(right.height()) + 1,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        }
+    }
+}
+
+/// Builds a tree of minimal height over `leaves`.
+fn balanced(mut leaves: Vec<Node>) -> Node {
+    match leaves.len() {
+        0 => Node::default(),
+        1 => leaves.remove(0),
+        n => {
+            let right: Vec<Node> = leaves.split_off(n / 2);
+            Node::branch(balanced(leaves), balanced(right))
+        }
+    }
+}
+
+/// Concatenates two trees, keeping the heights of siblings within one of
+/// each other.
+fn join(left: Node, right: Node) -> Node {
+    if left.len() == 0 {
+        return right;
+    }
+    if right.len() == 0 {
+        return left;
+    }
+
+    let (hl, hr): (usize, usize) = (left.height(), right.height());
+    match (left, right) {
+        (
+            Node::Branch {
+                left: a, right: b, ..
+            },
+            right,
+        ) if hl > hr + 1 => rebalance(*a, join(*b, right)),
+        (
+            left,
+            Node::Branch {
+                left: a, right: b, ..
+            },
+        ) if hr > hl + 1 => rebalance(join(left, *a), *b),
+        (left, right) => Node::branch(left, right),
+    }
+}
+
+/// Joins siblings whose heights differ by up to two, rotating if needed.
+fn rebalance(left: Node, right: Node) -> Node {
+    let (hl, hr): (usize, usize) = (left.height(), right.height());
+    match (left, right) {
+        (
+            Node::Branch {
+                left: a, right: b, ..
+            },
+            right,
+        ) if hl > hr + 1 => match *b {
+            Node::Branch {
+                left: c, right: d, ..
+            } if inner_taller(&a, &c, &d) => {
+                Node::branch(Node::branch(*a, *c), Node::branch(*d, right))
+            }
+            b => Node::branch(*a, Node::branch(b, right)),
+        },
+        (
+            left,
+            Node::Branch {
+                left: a, right: b, ..
+            },
+        ) if hr > hl + 1 => match *a {
+            Node::Branch {
+                left: c, right: d, ..
+            } if inner_taller(&b, &d, &c) => {
+                Node::branch(Node::branch(left, *c), Node::branch(*d, *b))
+            }
+            a => Node::branch(Node::branch(left, a), *b),
+        },
+        (left, right) => Node::branch(left, right),
+    }
+}
+
+/// Whether the inner child, made of `c` and `d`, is taller than its
+/// sibling `outer`, which calls for a double rotation.
+fn inner_taller(outer: &Node, c: &Node, d: &Node) -> bool {
+    c.height().max(d.height()) + 1 > outer.height()
+}
+
+fn split(node: Node, offset: usize) -> (Node, Node) {
+    match node {
+        Node::Leaf(mut v) => {
+            let right: String = v.split_off(offset.min(v.len()));
+            (Node::Leaf(v), Node::Leaf(right))
+        }
+        Node::Branch { left, right, .. } => {
+            let len: usize = left.len();
+            match offset.cmp(&len) {
+                std::cmp::Ordering::Less => {
+                    let (a, b) = split(*left, offset);
+                    (a, join(b, *right))
+                }
+                std::cmp::Ordering::Greater => {
+                    let (a, b) = split(*right, offset - len);
+                    (join(*left, a), b)
+                }
+                std::cmp::Ordering::Equal => (*left, *right),
+            }
+        }
+    }
+}
+
+fn collect(node: &Node, start: usize, end: usize, out: &mut String) {
+    if start >= end {
+        return;
+    }
+    match node {
+        Node::Leaf(v) => out.push_str(&v[start.min(v.len())..end.min(v.len())]),
+        Node::Branch { left, right, .. } => {
+            let len: usize = left.len();
+            if start < len {
+                collect(left, start, end.min(len), out);
+            }
+            if end > len {
+                collect(right, start.saturating_sub(len), end - len, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_LEAF, Node, Rope};
+
+    /// Checks the cached sizes and that siblings differ in height by at most one.
+    fn check(node: &Node) -> usize {
+        match node {
+            Node::Leaf(v) => {
+                assert!(v.len() <= MAX_LEAF || v.is_empty());
+                0
+            }
+            Node::Branch {
+                left,
+                right,
+                len,
+                newlines,
+                height,
+            } => {
+                let (hl, hr): (usize, usize) = (check(left), check(right));
+                assert!(hl.abs_diff(hr) <= 1, "unbalanced: {} and {}", hl, hr);
+                assert_eq!(*height, hl.max(hr) + 1);
+                assert_eq!(*len, left.len() + right.len());
+                assert_eq!(*newlines, left.newlines() + right.newlines());
+                *height
+            }
+        }
+    }
+
+    #[test]
+    fn stays_balanced() {
+        let mut rope = Rope::default();
+        let mut model: String = String::new();
+
+        // Appending one character at a time is the worst case for a naive tree
+        for i in 0..20_000 {
+            let text: String = format!("{}\n", i % 10);
+            rope.insert(rope.len(), &text);
+            model.push_str(&text);
+        }
+        for i in 0..2_000 {
+            let offset: usize = (i * 7919) % model.len();
+            let offset: usize = (offset..).find(|v| model.is_char_boundary(*v)).unwrap_or(0);
+            rope.insert(offset, "λ");
+            model.insert(offset, 'λ');
+            let start: usize = (i * 104_729) % (model.len() - 4);
+            let start: usize = (start..).find(|v| model.is_char_boundary(*v)).unwrap_or(0);
+            let end: usize = (start + 3..)
+                .find(|v| model.is_char_boundary(*v))
+                .unwrap_or(model.len());
+            rope.delete(start, end);
+            model.replace_range(start..end, "");
+        }
+
+        check(&rope.root);
+        assert_eq!(rope.to_string(), model);
+        // A tree of 1 KiB leaves over this much text is only a few levels deep
+        assert!(rope.root.height() <= 12, "height {}", rope.root.height());
+    }
+
+    #[test]
+    fn lines() {
+        let rope = Rope::new("a\nbc\n\nd");
+        assert_eq!(rope.newlines(), 3);
+        assert_eq!(rope.after_newline(0), Some(2));
+        assert_eq!(rope.after_newline(2), Some(6));
+        assert_eq!(rope.after_newline(3), None);
+        assert_eq!(rope.newlines_before(5), 2);
+        assert_eq!(rope.slice(2, 4), "bc");
+    }
+}
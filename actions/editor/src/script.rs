@@ -0,0 +1,343 @@
+//! Runs `init.core` and the procedures it binds to keys.
+//!
+//! Procedures defined for the editor cannot borrow it while they run, so
+//! they read a [`Context`] captured before the call, and what they ask the
+//! editor to do is collected as [`Effect`]s which it applies afterwards.
+
+use crate::key::parse_keys;
+use crate::keymap::{Binding, Keymap, Keymaps};
+use core_lang::interpreter::Interpreter;
+use core_lang::object::{Arity, Object};
+use core_lang::sandbox::{Capability, Sandbox};
+use core_lang::syntax::Source;
+use std::cell::{Ref, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+pub struct Script {
+    interpreter: Interpreter,
+    host: Rc<RefCell<Host>>,
+}
+
+/// What the editor shares with `init.core`.
+#[derive(Default)]
+pub struct Host {
+    pub keymaps: Keymaps,
+    /// Commands from `define-command`, by name.
+    pub commands: BTreeMap<String, Object>,
+    pub context: Context,
+    effects: Vec<Effect>,
+}
+
+/// The state of the editor when a procedure was called.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Context {
+    pub file_name: Option<String>,
+    pub mode: String,
+    pub filetype: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    Insert(String),
+    Message(String),
+    Command(String),
+    /// Goes back to the text as it was this long ago.
+    UndoTo(Duration),
+}
+
+/// Makes the effect of a procedure which takes one string.
+type EffectFn = fn(String) -> Effect;
+
+/// The procedures the editor adds for `init.core`, with their arity, so that
+/// checks know about them.
+pub fn natives() -> Vec<(String, Arity)> {
+    let standard: Interpreter = Interpreter::new();
+
+    Script::new()
+        .interpreter
+        .global()
+        .bindings()
+        .into_iter()
+        .filter(|(name, _)| standard.global().get(name).is_none())
+        .filter_map(|(name, value)| match value {
+            Object::Native(v) => Some((name, v.arity)),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Script::new()
+    }
+}
+
+impl Script {
+    pub fn new() -> Self {
+        let mut interpreter: Interpreter = Interpreter::new();
+        interpreter.set_sandbox(sandbox());
+        let mut script = Script {
+            interpreter,
+            host: Rc::new(RefCell::new(Host::default())),
+        };
+        script.install();
+
+        script
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    pub fn host(&self) -> Ref<'_, Host> {
+        self.host.borrow()
+    }
+
+    /// Evaluates `source`, then calls its `main` procedure if it defines one.
+    /// Returns what both asked the editor to do.
+    pub fn load(
+        &mut self,
+        source: Source,
+        context: Context,
+    ) -> Result<Vec<Effect>, Box<dyn std::error::Error>> {
+        let name: String = source.name.clone();
+        self.host.borrow_mut().context = context.clone();
+        let result = self.interpreter.eval_source(source);
+        let mut effects: Vec<Effect> = std::mem::take(&mut self.host.borrow_mut().effects);
+        result.map_err(|e| format!("{}: {}", name, e))?;
+
+        if let Some(main) = self.interpreter.global().get("main") {
+            if main.is_procedure() {
+                let called: Vec<Effect> = self
+                    .call(&main, context)
+                    .map_err(|e| format!("{}: {}", name, e))?;
+                effects.extend(called);
+            }
+        }
+
+        Ok(effects)
+    }
+
+    /// Calls `procedure` without arguments.
+    pub fn call(
+        &mut self,
+        procedure: &Object,
+        context: Context,
+    ) -> Result<Vec<Effect>, Box<dyn std::error::Error>> {
+        self.host.borrow_mut().context = context;
+        let result = self.interpreter.apply(procedure, Vec::new());
+        let effects: Vec<Effect> = std::mem::take(&mut self.host.borrow_mut().effects);
+        result?;
+
+        Ok(effects)
+    }
+
+    fn install(&mut self) {
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "bind-key",
+            Arity::exact(2),
+            "Binds keys such as \"C-x C-s\" to a command name or a procedure.",
+            move |_, args| bind(&mut host.borrow_mut().keymaps.global, &args[0], &args[1]),
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "bind-mode-key",
+            Arity::exact(3),
+            "Binds keys in the keymap of an editing mode, such as \"insert\".",
+            move |_, args| {
+                let mode: String = args[0].convert()?;
+                let mut host = host.borrow_mut();
+                bind(
+                    host.keymaps.modes.entry(mode).or_default(),
+                    &args[1],
+                    &args[2],
+                )
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "bind-filetype-key",
+            Arity::exact(3),
+            "Binds keys in the keymap of a file extension, such as \"core\".",
+            move |_, args| {
+                let filetype: String = args[0].convert()?;
+                let mut host = host.borrow_mut();
+                let keymap: &mut Keymap = host.keymaps.filetypes.entry(filetype).or_default();
+                bind(keymap, &args[1], &args[2])
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "unbind-key",
+            Arity::exact(1),
+            "Removes a binding from the global keymap.",
+            move |_, args| {
+                let keys = parse_keys(&args[0].convert::<String>()?)?;
+                Ok(Object::Boolean(
+                    host.borrow_mut().keymaps.global.unbind(&keys),
+                ))
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "set-prefix-timeout",
+            Arity::exact(1),
+            "Sets how many milliseconds to wait after a prefix key before listing what can follow.",
+            move |_, args| {
+                let milliseconds: i64 = args[0].convert()?;
+                let milliseconds: u64 =
+                    u64::try_from(milliseconds).map_err(|_| "the timeout must not be negative")?;
+                host.borrow_mut().keymaps.timeout = Duration::from_millis(milliseconds);
+                Ok(Object::Unspecified)
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "define-command",
+            Arity::exact(2),
+            "Defines a named command which runs a procedure.",
+            move |_, args| {
+                let name: String = args[0].convert()?;
+                if !args[1].is_procedure() {
+                    return Err(format!("expected a procedure, got {}", args[1].type_name()).into());
+                }
+                host.borrow_mut().commands.insert(name, args[1].clone());
+                Ok(Object::Unspecified)
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "set-editing-style",
+            Arity::exact(1),
+            "Chooses \"modal\" editing, in the manner of vi, or \"modeless\" editing.",
+            move |_, args| {
+                let command: &str = match args[0].convert::<String>()?.as_str() {
+                    "modal" => "modal-editing",
+                    "modeless" => "modeless-editing",
+                    v => {
+                        return Err(
+                            format!("expected \"modal\" or \"modeless\", got \"{}\"", v).into()
+                        );
+                    }
+                };
+                host.borrow_mut()
+                    .effects
+                    .push(Effect::Command(command.to_string()));
+                Ok(Object::Unspecified)
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "set-persistent-undo",
+            Arity::exact(1),
+            "Keeps the undo history of saved files in the data directory, so that undo works after a restart.",
+            move |_, args| {
+                let command: &str = match args[0].convert::<bool>()? {
+                    true => "enable-persistent-undo",
+                    false => "disable-persistent-undo",
+                };
+                host.borrow_mut()
+                    .effects
+                    .push(Effect::Command(command.to_string()));
+                Ok(Object::Unspecified)
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "undo-to",
+            Arity::exact(1),
+            "Changes the buffer back to how it was this many seconds ago.",
+            move |_, args| {
+                let seconds: i64 = args[0].convert()?;
+                let seconds: u64 =
+                    u64::try_from(seconds).map_err(|_| "the time must not be negative")?;
+                host.borrow_mut()
+                    .effects
+                    .push(Effect::UndoTo(Duration::from_secs(seconds)));
+                Ok(Object::Unspecified)
+            },
+        );
+
+        let effects: [(&str, &str, EffectFn); 3] = [
+            ("run-command", "Runs a command by name.", Effect::Command),
+            ("insert", "Inserts a string at the cursor.", Effect::Insert),
+            (
+                "message",
+                "Shows a string in the message area.",
+                Effect::Message,
+            ),
+        ];
+        for (name, doc, effect) in effects {
+            let host: Rc<RefCell<Host>> = self.host.clone();
+            self.interpreter
+                .define_native(name, Arity::exact(1), doc, move |_, args| {
+                    host.borrow_mut().effects.push(effect(args[0].convert()?));
+                    Ok(Object::Unspecified)
+                });
+        }
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "buffer-file-name",
+            Arity::exact(0),
+            "The file name of the current buffer, or nil.",
+            move |_, _| {
+                Ok(match &host.borrow().context.file_name {
+                    Some(v) => Object::string(v),
+                    None => Object::nil(),
+                })
+            },
+        );
+
+        let host: Rc<RefCell<Host>> = self.host.clone();
+        self.interpreter.define_native(
+            "current-mode",
+            Arity::exact(0),
+            "The name of the current editing mode.",
+            move |_, _| Ok(Object::string(&host.borrow().context.mode)),
+        );
+    }
+}
+
+/// What `init.core` and the procedures it binds may do. A runaway loop runs
+/// out of budget instead of hanging the editor, and files may be read and
+/// written, but no programs started.
+fn sandbox() -> Sandbox {
+    Sandbox {
+        capabilities: vec![Capability::Filesystem],
+        ..Sandbox::restricted()
+    }
+}
+
+fn bind(
+    keymap: &mut Keymap,
+    keys: &Object,
+    binding: &Object,
+) -> Result<Object, Box<dyn std::error::Error>> {
+    let keys = parse_keys(&keys.convert::<String>()?)?;
+    let binding: Binding = match binding {
+        Object::String(v) | Object::Symbol(v) => Binding::Command(v.to_string()),
+        v if v.is_procedure() => Binding::Procedure(v.clone()),
+        v => {
+            return Err(format!(
+                "expected a command name or a procedure, got {}",
+                v.type_name()
+            )
+            .into());
+        }
+    };
+    keymap.bind(keys, binding);
+
+    Ok(Object::Unspecified)
+}
@@ -0,0 +1,190 @@
+//! Searching the buffer for literal text or a regular expression.
+//!
+//! Matches are looked for one line at a time, so a search copies no more of
+//! the rope at once than the longest line it reads. A match cannot span
+//! lines, and `^` and `$` match at the start and end of each line. The
+//! empty line after a final line break is not searched.
+
+use crate::buffer::Buffer;
+use crate::modal;
+use regex::{Captures, Regex, RegexBuilder};
+use std::ops::Range;
+
+/// Whether `query` has an uppercase letter to match. In a regular expression
+/// the letters of escapes such as `\S`, `\p{Lu}` or `\x4A`, and of flags and
+/// group names, are not matched, so they do not count.
+fn has_uppercase(query: &str, regex: bool) -> bool {
+    if !regex {
+        return query.chars().any(char::is_uppercase);
+    }
+
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('p' | 'P' | 'x' | 'u' | 'U') if chars.peek() == Some(&'{') => {
+                    chars.find(|v| *v == '}');
+                }
+                // `\pL` names a class with one letter
+                Some('p' | 'P') => {
+                    chars.next();
+                }
+                Some('x' | 'u' | 'U') => while chars.next_if(char::is_ascii_hexdigit).is_some() {},
+                _ => (),
+            },
+            '(' if chars.next_if_eq(&'?').is_some() => match chars.peek() {
+                Some('P' | '<') => {
+                    chars.find(|v| *v == '>');
+                }
+                _ => {
+                    chars.find(|v| *v == ')' || *v == ':');
+                }
+            },
+            c if c.is_uppercase() => return true,
+            _ => (),
+        }
+    }
+
+    false
+}
+
+/// A compiled search query.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    query: String,
+    regex: bool,
+    compiled: Regex,
+}
+
+/// Where a search found its match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub range: Range<usize>,
+    /// The search went past the end of the text, or the start going
+    /// backward, and went on from the other end.
+    pub wrapped: bool,
+}
+
+impl Pattern {
+    /// Compiles `query`, as a regular expression if `regex` and as literal
+    /// text otherwise. Case is ignored unless the query has an uppercase
+    /// letter.
+    pub fn new(query: &str, regex: bool) -> Result<Pattern, Box<dyn std::error::Error>> {
+        let source: String = match regex {
+            true => query.to_string(),
+            false => regex::escape(query),
+        };
+        let compiled: Regex = RegexBuilder::new(&source)
+            .case_insensitive(!has_uppercase(query, regex))
+            .build()
+            .map_err(|e| match e {
+                regex::Error::Syntax(_) => format!("SEARCH_ERROR: invalid pattern {}", query),
+                e => format!("SEARCH_ERROR: {}", e),
+            })?;
+
+        Ok(Pattern {
+            query: query.to_string(),
+            regex,
+            compiled,
+        })
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn is_regex(&self) -> bool {
+        self.regex
+    }
+
+    /// The first match starting at or after `offset`, or else the first one
+    /// from the start of the text.
+    pub fn find_forward(&self, buffer: &Buffer, offset: usize) -> Option<Match> {
+        let count: usize = modal::last_line(buffer) + 1;
+        let first: usize = line_of(buffer, offset).min(count - 1);
+        for (i, line) in (first..count).chain(0..=first).enumerate() {
+            let wrapped: bool = i >= count - first;
+            let found: Option<Range<usize>> = self
+                .line_matches(buffer, line)
+                .find(|v| wrapped || v.start >= offset);
+            if let Some(range) = found {
+                return Some(Match { range, wrapped });
+            }
+        }
+
+        None
+    }
+
+    /// The last match starting before `offset`, or else the last one in the
+    /// text.
+    pub fn find_backward(&self, buffer: &Buffer, offset: usize) -> Option<Match> {
+        let count: usize = modal::last_line(buffer) + 1;
+        let first: usize = line_of(buffer, offset).min(count - 1);
+        let lines = (0..=first).rev().chain((first..count).rev());
+        for (i, line) in lines.enumerate() {
+            let wrapped: bool = i > first;
+            let found: Option<Range<usize>> = self
+                .line_matches(buffer, line)
+                .filter(|v| wrapped || v.start < offset)
+                .last();
+            if let Some(range) = found {
+                return Some(Match { range, wrapped });
+            }
+        }
+
+        None
+    }
+
+    /// The matches on `line` which are not empty, to be highlighted.
+    pub fn matches(&self, buffer: &Buffer, line: usize) -> Vec<Range<usize>> {
+        self.line_matches(buffer, line)
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
+    /// What the match at `range` is replaced with. For a regular expression
+    /// `$1`, `$name` and `${name}` in `with` stand for the groups it
+    /// captured, and `$$` for `$`; literal text is replaced as it is.
+    pub fn replacement(
+        &self,
+        buffer: &Buffer,
+        range: Range<usize>,
+        with: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        if !self.regex {
+            return Ok(with.to_string());
+        }
+
+        let line: usize = line_of(buffer, range.start);
+        let start: usize = buffer.line_start(line).unwrap_or(0);
+        let text: String = buffer.line(line).unwrap_or_default();
+        let captures: Captures = self
+            .compiled
+            .captures_at(&text, range.start - start)
+            .filter(|v| v.get_match().range() == (range.start - start..range.end - start))
+            .ok_or_else(|| format!("SEARCH_ERROR: {} no longer matches there", self.query))?;
+        let mut result: String = String::new();
+        captures.expand(with, &mut result);
+
+        Ok(result)
+    }
+
+    /// The matches on `line`, as offsets into the buffer.
+    fn line_matches(&self, buffer: &Buffer, line: usize) -> impl Iterator<Item = Range<usize>> {
+        let start: usize = buffer.line_start(line).unwrap_or(0);
+        let text: String = buffer.line(line).unwrap_or_default();
+        let ranges: Vec<Range<usize>> = self
+            .compiled
+            .find_iter(&text)
+            .map(|v| start + v.start()..start + v.end())
+            .collect();
+
+        ranges.into_iter()
+    }
+}
+
+fn line_of(buffer: &Buffer, offset: usize) -> usize {
+    buffer
+        .offset_to_position(offset.min(buffer.len()))
+        .map_or(0, |v| v.line)
+}
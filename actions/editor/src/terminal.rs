@@ -0,0 +1,63 @@
+//! Draws frames on a [`Backend`], sending only the cells which changed since
+//! the last frame.
+
+use crate::backend::Backend;
+use crate::grid::{Cell, Grid};
+
+pub struct Terminal<B: Backend> {
+    backend: B,
+    /// What the backend is showing.
+    previous: Grid,
+}
+
+impl<B: Backend> Terminal<B> {
+    pub fn new(mut backend: B) -> Result<Self, Box<dyn std::error::Error>> {
+        let (width, height) = backend.size()?;
+        backend.clear()?;
+
+        Ok(Terminal {
+            backend,
+            previous: Grid::new(width, height),
+        })
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.previous.width(), self.previous.height())
+    }
+
+    /// Starts over from a blank screen of the new size, since the terminal
+    /// may have rewrapped or dropped what it showed.
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<(), Box<dyn std::error::Error>> {
+        self.backend.clear()?;
+        self.previous = Grid::new(width, height);
+
+        Ok(())
+    }
+
+    /// Shows `frame`, and returns how many cells had to be drawn.
+    pub fn draw(
+        &mut self,
+        frame: &Grid,
+        cursor: Option<(u16, u16)>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        if (frame.width(), frame.height()) != self.size() {
+            self.resize(frame.width(), frame.height())?;
+        }
+
+        let changes: Vec<(u16, u16, &Cell)> = self.previous.diff(frame);
+        self.backend.draw(&changes)?;
+        self.backend.set_cursor(cursor)?;
+        self.backend.flush()?;
+        self.previous = frame.clone();
+
+        Ok(changes.len())
+    }
+}
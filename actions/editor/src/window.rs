@@ -0,0 +1,317 @@
+//! How the screen is shared between windows: a tree of splits, each dividing
+//! its area in two, with a window at each leaf.
+//!
+//! Windows are known by a number. Side by side windows have a column
+//! between them for the separator, and each window has its own status line
+//! at the bottom.
+
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// One window above the other.
+    Below,
+    /// Windows side by side.
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Part of the screen, in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    Window(usize),
+    Split {
+        direction: Direction,
+        /// The share of the area the first part gets, in percent.
+        percent: u16,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    /// The windows, from the top left.
+    pub fn windows(&self) -> Vec<usize> {
+        match self {
+            Layout::Window(v) => vec![*v],
+            Layout::Split { first, second, .. } => {
+                let mut windows: Vec<usize> = first.windows();
+                windows.extend(second.windows());
+                windows
+            }
+        }
+    }
+
+    /// Divides `window` in two, with `new` below or to the right of it.
+    /// Returns `false` if there is no such window.
+    pub fn split(&mut self, window: usize, new: usize, direction: Direction) -> bool {
+        match self {
+            Layout::Window(v) if *v == window => {
+                *self = Layout::Split {
+                    direction,
+                    percent: 50,
+                    first: Box::new(Layout::Window(window)),
+                    second: Box::new(Layout::Window(new)),
+                };
+                true
+            }
+            Layout::Window(_) => false,
+            Layout::Split { first, second, .. } => {
+                first.split(window, new, direction) || second.split(window, new, direction)
+            }
+        }
+    }
+
+    /// Takes `window` out, giving its area to the window or split next to
+    /// it. The last window cannot be taken out, and returns `false`.
+    pub fn remove(&mut self, window: usize) -> bool {
+        let Layout::Split { first, second, .. } = self else {
+            return false;
+        };
+        let rest: Layout = match (&**first, &**second) {
+            (Layout::Window(v), _) if *v == window => (**second).clone(),
+            (_, Layout::Window(v)) if *v == window => (**first).clone(),
+            _ => return first.remove(window) || second.remove(window),
+        };
+        *self = rest;
+
+        true
+    }
+
+    /// Exchanges the places of windows `a` and `b`.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        match self {
+            Layout::Window(v) if *v == a => *v = b,
+            Layout::Window(v) if *v == b => *v = a,
+            Layout::Window(_) => (),
+            Layout::Split { first, second, .. } => {
+                first.swap(a, b);
+                second.swap(a, b);
+            }
+        }
+    }
+
+    /// Where each window is drawn in `area`.
+    pub fn rects(&self, area: Rect) -> Vec<(usize, Rect)> {
+        match self {
+            Layout::Window(v) => vec![(*v, area)],
+            Layout::Split {
+                direction,
+                percent,
+                first,
+                second,
+            } => {
+                let (a, b) = divide(area, *direction, *percent);
+                let mut rects: Vec<(usize, Rect)> = first.rects(a);
+                rects.extend(second.rects(b));
+                rects
+            }
+        }
+    }
+
+    /// The window next to `window` on `side`, picking the one beside its
+    /// top or left edge when there are several.
+    pub fn neighbor(&self, window: usize, side: Side, area: Rect) -> Option<usize> {
+        let rects: Vec<(usize, Rect)> = self.rects(area);
+        let (_, rect) = *rects.iter().find(|(v, _)| *v == window)?;
+        let beside = |other: &Rect| match side {
+            Side::Left => other.x + other.width + 1 == rect.x,
+            Side::Right => rect.x + rect.width + 1 == other.x,
+            Side::Up => other.y + other.height == rect.y,
+            Side::Down => rect.y + rect.height == other.y,
+        };
+        let own: Range<u16> = along(&rect, side);
+        let candidates: Vec<&(usize, Rect)> = rects
+            .iter()
+            .filter(|(_, v)| {
+                let other: Range<u16> = along(v, side);
+                beside(v) && other.start < own.end && own.start < other.end
+            })
+            .collect();
+
+        candidates
+            .iter()
+            .find(|(_, v)| along(v, side).contains(&own.start))
+            .or(candidates.first())
+            .map(|(v, _)| *v)
+    }
+
+    /// Makes `window` `cells` taller, or wider if `direction` is `Right`,
+    /// or smaller if `cells` is negative, by moving the edge of the
+    /// innermost split in that direction which it is in. Returns `false`
+    /// if there is no such split.
+    pub fn resize(&mut self, window: usize, direction: Direction, cells: i32, area: Rect) -> bool {
+        let Layout::Split {
+            direction: own,
+            percent,
+            first,
+            second,
+        } = self
+        else {
+            return false;
+        };
+        let (a, b) = divide(area, *own, *percent);
+        if first.resize(window, direction, cells, a) || second.resize(window, direction, cells, b) {
+            return true;
+        }
+        let in_first: bool = first.windows().contains(&window);
+        if *own != direction || !(in_first || second.windows().contains(&window)) {
+            return false;
+        }
+
+        let size: i32 = match own {
+            Direction::Below => area.height as i32,
+            Direction::Right => area.width as i32 - 1,
+        };
+        let cells: i32 = if in_first { cells } else { -cells };
+        // Each cell is at least a percent, so that small areas still change
+        let change: i32 = match cells * 100 / size.max(1) {
+            0 => cells.signum(),
+            v => v,
+        };
+        *percent = (*percent as i32 + change).clamp(1, 99) as u16;
+
+        true
+    }
+}
+
+/// Layouts are written as window numbers and splits, such as
+/// `(right 50 0 (below 30 1 2))`.
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Layout::Window(v) => write!(f, "{}", v),
+            Layout::Split {
+                direction,
+                percent,
+                first,
+                second,
+            } => {
+                let direction: &str = match direction {
+                    Direction::Below => "below",
+                    Direction::Right => "right",
+                };
+                write!(f, "({} {} {} {})", direction, percent, first, second)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Layout {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let spaced: String = text.replace('(', " ( ").replace(')', " ) ");
+        let mut words = spaced.split_whitespace();
+        let layout: Layout = parse(&mut words)?;
+        if let Some(word) = words.next() {
+            return Err(format!("LAYOUT_ERROR: unexpected {}", word).into());
+        }
+        let windows: Vec<usize> = layout.windows();
+        if (1..windows.len()).any(|i| windows[i..].contains(&windows[i - 1])) {
+            return Err("LAYOUT_ERROR: a window appears twice".into());
+        }
+
+        Ok(layout)
+    }
+}
+
+fn parse<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+) -> Result<Layout, Box<dyn std::error::Error>> {
+    let word: &str = words.next().ok_or("LAYOUT_ERROR: the layout ends early")?;
+    if word != "(" {
+        let window: usize = word
+            .parse()
+            .map_err(|_| format!("LAYOUT_ERROR: expected a window, found {}", word))?;
+        return Ok(Layout::Window(window));
+    }
+
+    let direction: Direction = match words.next() {
+        Some("below") => Direction::Below,
+        Some("right") => Direction::Right,
+        v => {
+            return Err(format!("LAYOUT_ERROR: unknown direction {}", v.unwrap_or("")).into());
+        }
+    };
+    let percent: u16 = words
+        .next()
+        .and_then(|v| v.parse().ok())
+        .filter(|v| (1..100).contains(v))
+        .ok_or("LAYOUT_ERROR: expected a percent from 1 to 99")?;
+    let first: Layout = parse(words)?;
+    let second: Layout = parse(words)?;
+    if words.next() != Some(")") {
+        return Err("LAYOUT_ERROR: expected )".into());
+    }
+
+    Ok(Layout::Split {
+        direction,
+        percent,
+        first: Box::new(first),
+        second: Box::new(second),
+    })
+}
+
+/// Where `rect` starts and ends along the edges on `side` of it.
+fn along(rect: &Rect, side: Side) -> Range<u16> {
+    match side {
+        Side::Left | Side::Right => rect.y..rect.y + rect.height,
+        Side::Up | Side::Down => rect.x..rect.x + rect.width,
+    }
+}
+
+/// The two parts of `area` a split makes, leaving a column between them
+/// when they are side by side. Each part keeps a cell where there is room.
+fn divide(area: Rect, direction: Direction, percent: u16) -> (Rect, Rect) {
+    let size: u16 = match direction {
+        Direction::Below => area.height,
+        Direction::Right => area.width.saturating_sub(1),
+    };
+    let first: u16 = match size {
+        0 | 1 => size,
+        _ => ((size as u32 * percent as u32 / 100) as u16).clamp(1, size - 1),
+    };
+    let second: u16 = size - first;
+
+    match direction {
+        Direction::Below => (
+            Rect {
+                height: first,
+                ..area
+            },
+            Rect {
+                y: area.y + first,
+                height: second,
+                ..area
+            },
+        ),
+        Direction::Right => (
+            Rect {
+                width: first,
+                ..area
+            },
+            Rect {
+                x: area.x + first + 1,
+                width: second,
+                ..area
+            },
+        ),
+    }
+}
@@ -0,0 +1,178 @@
+use core_editor::buffer::{Buffer, Position};
+use proptest::prelude::*;
+
+#[test]
+fn lines_and_positions() -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = Buffer::from_text("(define λ 1)\r\n\nlast");
+
+    assert_eq!(buffer.len_lines(), 3);
+    assert_eq!(
+        buffer.lines().collect::<Vec<String>>(),
+        vec!["(define λ 1)", "", "last"]
+    );
+    assert_eq!(buffer.line_range(0), Some(0..13));
+    assert_eq!(buffer.line(3), None);
+
+    // Columns count chars, so `1` is column 10 although it is byte 11
+    let position = Position {
+        line: 0,
+        column: 10,
+    };
+    assert_eq!(buffer.position_to_offset(position)?, 11);
+    assert_eq!(buffer.offset_to_position(11)?, position);
+    assert_eq!(
+        buffer.offset_to_position(buffer.len())?,
+        Position { line: 2, column: 4 }
+    );
+
+    // Columns past the end of a line stop at the line ending
+    let position = Position {
+        line: 1,
+        column: 80,
+    };
+    assert_eq!(buffer.position_to_offset(position)?, 15);
+    assert!(
+        buffer
+            .position_to_offset(Position { line: 3, column: 0 })
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn edits() -> Result<(), Box<dyn std::error::Error>> {
+    let mut buffer = Buffer::from_text("(define x 1)");
+    assert!(!buffer.modified());
+
+    buffer.replace(8..9, "λ")?;
+    buffer.insert(buffer.len(), "\n")?;
+    assert_eq!(buffer.text(), "(define λ 1)\n");
+    assert!(buffer.modified());
+
+    // Offsets inside a character or past the end are rejected
+    let error = buffer.insert(9, "x").err().ok_or("insert succeeded")?;
+    assert_eq!(
+        error.to_string(),
+        "BUFFER_ERROR: offset 9 is inside a character"
+    );
+    assert!(buffer.delete(0..100).is_err());
+    assert!(buffer.slice(8..9).is_err());
+    assert_eq!(buffer.slice(8..10)?, "λ");
+    assert_eq!(buffer.text(), "(define λ 1)\n");
+
+    Ok(())
+}
+
+#[test]
+fn graphemes() -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = Buffer::from_text("e\u{301}🇯🇵\r\nx");
+
+    assert_eq!(
+        buffer.graphemes().collect::<Vec<String>>(),
+        vec!["e\u{301}", "🇯🇵", "\r\n", "x"]
+    );
+    assert_eq!(buffer.next_grapheme_boundary(0)?, 3);
+    assert_eq!(buffer.next_grapheme_boundary(3)?, 11);
+    assert_eq!(buffer.next_grapheme_boundary(11)?, 13);
+    assert_eq!(buffer.prev_grapheme_boundary(13)?, 11);
+    assert_eq!(buffer.prev_grapheme_boundary(3)?, 0);
+    assert_eq!(buffer.next_grapheme_boundary(buffer.len())?, buffer.len());
+
+    Ok(())
+}
+
+#[test]
+fn open() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("core-editor-buffer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let path = dir.join("init.core");
+    std::fs::write(&path, "(define x 1)\n")?;
+    let buffer = Buffer::open(&path)?;
+    assert_eq!(buffer.text(), "(define x 1)\n");
+    assert_eq!(buffer.path(), Some(path.as_path()));
+
+    // A file which does not exist yet is empty until it is saved
+    let buffer = Buffer::open(&dir.join("new.core"))?;
+    assert!(buffer.is_empty());
+    assert_eq!(buffer.len_lines(), 1);
+
+    let path = dir.join("binary");
+    std::fs::write(&path, b"ok\xff")?;
+    let error = Buffer::open(&path).err().ok_or("opened invalid UTF-8")?;
+    assert!(
+        error
+            .to_string()
+            .starts_with("BUFFER_ERROR: invalid UTF-8 at byte 2 in ")
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Insert(usize, String),
+    Delete(usize, usize),
+}
+
+fn edit() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        (any::<usize>(), "(\\PC|\n|\r\n){0,40}").prop_map(|(at, text)| Edit::Insert(at, text)),
+        (any::<usize>(), 0..2000usize).prop_map(|(at, len)| Edit::Delete(at, len)),
+    ]
+}
+
+/// Moves `offset` into `text` and back onto a char boundary.
+fn boundary(text: &str, offset: usize) -> usize {
+    let mut offset: usize = offset % (text.len() + 1);
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+proptest! {
+    /// Edits to a buffer give the same text as edits to a `String`.
+    #[test]
+    fn edits_match_string(initial in "\\PC{0,3000}", edits in prop::collection::vec(edit(), 0..60)) {
+        let mut buffer = Buffer::from_text(&initial);
+        let mut model: String = initial;
+
+        for edit in edits {
+            match edit {
+                Edit::Insert(at, text) => {
+                    let at: usize = boundary(&model, at);
+                    buffer.insert(at, &text).unwrap();
+                    model.insert_str(at, &text);
+                }
+                Edit::Delete(at, len) => {
+                    let start: usize = boundary(&model, at);
+                    let end: usize = boundary(&model, (start + len).min(model.len()));
+                    buffer.delete(start..end).unwrap();
+                    model.replace_range(start..end, "");
+                }
+            }
+        }
+
+        prop_assert_eq!(buffer.text(), model.clone());
+        prop_assert_eq!(buffer.len_lines(), model.split('\n').count());
+        let mut lines: Vec<String> = model
+            .split_inclusive('\n')
+            .map(|v| v.strip_suffix("\r\n").or(v.strip_suffix('\n')).unwrap_or(v).to_string())
+            .collect();
+        if model.is_empty() || model.ends_with('\n') {
+            lines.push(String::new());
+        }
+        prop_assert_eq!(buffer.lines().collect::<Vec<String>>(), lines);
+
+        // Offsets between `\r` and `\n` have no position of their own
+        for (offset, _) in model.char_indices().filter(|(_, v)| *v != '\n').step_by(37) {
+            let position: Position = buffer.offset_to_position(offset).unwrap();
+            prop_assert_eq!(buffer.position_to_offset(position).unwrap(), offset);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+mod common;
+
+use common::{directory, press, type_str};
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use std::path::PathBuf;
+
+/// Files `a.txt`, `b.txt` and `notes.md` in a new directory, each holding
+/// its own name.
+fn files(name: &str) -> Result<(PathBuf, Vec<PathBuf>), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory(name)?;
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for file in ["a.txt", "b.txt", "notes.md"] {
+        let path: PathBuf = directory.join(file);
+        std::fs::write(&path, format!("{}\n", file))?;
+        paths.push(path);
+    }
+
+    Ok((directory, paths))
+}
+
+fn name(path: &std::path::Path) -> String {
+    path.display().to_string()
+}
+
+#[test]
+fn open() -> Result<(), Box<dyn std::error::Error>> {
+    let (directory, paths) = files("open")?;
+    let [a, b, notes] = &paths[..] else {
+        return Err("expected three files".into());
+    };
+
+    let mut editor = Editor::new(Buffer::open(a)?);
+    editor.open(b)?;
+    editor.open(notes)?;
+    assert_eq!(editor.buffer_names(), vec![name(notes), name(b), name(a)]);
+    assert_eq!(editor.buffer().text(), "notes.md\n");
+
+    // A file which is open already is shown rather than read again
+    press(&mut editor, "<end>")?;
+    type_str(&mut editor, "!");
+    editor.open(a)?;
+    assert_eq!(editor.buffer_names(), vec![name(a), name(notes), name(b)]);
+    editor.open(&directory.join(".").join("notes.md"))?;
+    assert_eq!(editor.buffer_names(), vec![name(notes), name(a), name(b)]);
+    assert_eq!(editor.buffer().text(), "notes.md!\n");
+    assert_eq!(editor.cursor(), 9);
+
+    editor.new_buffer();
+    editor.new_buffer();
+    assert_eq!(editor.buffer_name(), "*scratch*<2>");
+    assert_eq!(editor.buffer_names()[1], "*scratch*");
+
+    // Files are opened by name too
+    press(&mut editor, "C-x C-f")?;
+    type_str(&mut editor, &name(&directory.join("new.txt")));
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.buffer_name(), name(&directory.join("new.txt")));
+    assert_eq!(editor.buffer().text(), "");
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn switching() -> Result<(), Box<dyn std::error::Error>> {
+    let (directory, paths) = files("switching")?;
+    let [a, b, notes] = &paths[..] else {
+        return Err("expected three files".into());
+    };
+
+    let mut editor = Editor::new(Buffer::new());
+    press(&mut editor, "C-x b")?;
+    assert_eq!(editor.message(), Some("There is no other buffer"));
+
+    editor.open(a)?;
+    editor.open(b)?;
+    editor.open(notes)?;
+    // With nothing typed, the buffer shown before comes first
+    press(&mut editor, "C-x b RET")?;
+    assert_eq!(editor.buffer_name(), name(b));
+
+    press(&mut editor, "C-x b")?;
+    type_str(&mut editor, "ntmd");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.buffer_name(), name(notes));
+
+    press(&mut editor, "C-x b")?;
+    type_str(&mut editor, "txt");
+    press(&mut editor, "<down> RET")?;
+    assert_eq!(editor.buffer_name(), name(a));
+
+    press(&mut editor, "C-x b")?;
+    type_str(&mut editor, "zz");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.message(), Some("No buffer matches zz"));
+    editor.switch_to("*scratch*")?;
+    assert_eq!(editor.buffer().text(), "");
+    let e = editor
+        .switch_to("*nothing*")
+        .err()
+        .ok_or("expected an error")?;
+    assert_eq!(e.to_string(), "there is no buffer named *nothing*");
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn listing() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("one\n"));
+    editor.new_buffer();
+    type_str(&mut editor, "two");
+    press(&mut editor, "C-x C-b")?;
+
+    let (grid, _) = editor.render(30, 6);
+    let screen: String = grid.to_string();
+    let lines: Vec<&str> = screen.lines().map(str::trim_end).collect();
+    assert_eq!(lines[2], " .* *scratch*<2>");
+    assert_eq!(lines[3], "    *scratch*");
+
+    // The list goes away with the next key
+    press(&mut editor, "<left>")?;
+    let (grid, _) = editor.render(30, 6);
+    assert!(!grid.to_string().contains("*scratch*\n"));
+
+    Ok(())
+}
+
+#[test]
+fn closing() -> Result<(), Box<dyn std::error::Error>> {
+    let (directory, paths) = files("closing")?;
+    let [a, b, notes] = &paths[..] else {
+        return Err("expected three files".into());
+    };
+
+    let mut editor = Editor::new(Buffer::open(a)?);
+    editor.open(b)?;
+    editor.open(notes)?;
+    press(&mut editor, "C-x k")?;
+    assert_eq!(
+        editor.message(),
+        Some(format!("Closed {}", name(notes)).as_str())
+    );
+    assert_eq!(editor.buffer_names(), vec![name(b), name(a)]);
+
+    // A buffer with unsaved changes is only closed once asked
+    type_str(&mut editor, "x");
+    press(&mut editor, "C-x k")?;
+    assert_eq!(
+        editor.message(),
+        Some(format!("{} is modified; save (s), discard (d) or cancel?", name(b)).as_str())
+    );
+    type_str(&mut editor, "c");
+    assert_eq!(editor.message(), Some("Cancelled"));
+    assert_eq!(editor.buffer_names().len(), 2);
+    press(&mut editor, "C-x k")?;
+    type_str(&mut editor, "s");
+    assert_eq!(std::fs::read_to_string(b)?, "xb.txt\n");
+    assert_eq!(editor.buffer_names(), vec![name(a)]);
+
+    type_str(&mut editor, "y");
+    press(&mut editor, "C-x k")?;
+    type_str(&mut editor, "d");
+    assert_eq!(std::fs::read_to_string(a)?, "a.txt\n");
+    // Closing the last buffer leaves an empty one
+    assert_eq!(editor.buffer_names(), vec!["*scratch*"]);
+    assert_eq!(editor.buffer().text(), "");
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn quitting() -> Result<(), Box<dyn std::error::Error>> {
+    let (directory, paths) = files("quitting")?;
+    let [a, b, _] = &paths[..] else {
+        return Err("expected three files".into());
+    };
+
+    let mut editor = Editor::new(Buffer::open(a)?);
+    editor.open(b)?;
+    press(&mut editor, "C-q")?;
+    assert!(editor.should_quit());
+
+    // Changes in any buffer, shown or not, are only discarded once asked
+    let mut editor = Editor::new(Buffer::open(a)?);
+    type_str(&mut editor, "x");
+    editor.open(b)?;
+    press(&mut editor, "C-x C-c")?;
+    assert_eq!(
+        editor.message(),
+        Some(
+            format!(
+                "{} is modified; quit without saving (d) or cancel?",
+                name(a)
+            )
+            .as_str()
+        )
+    );
+    type_str(&mut editor, "c");
+    assert_eq!(editor.message(), Some("Cancelled"));
+    assert!(!editor.should_quit());
+
+    type_str(&mut editor, "y");
+    press(&mut editor, "C-q")?;
+    assert_eq!(
+        editor.message(),
+        Some(
+            format!(
+                "{}, {} are modified; quit without saving (d) or cancel?",
+                name(b),
+                name(a)
+            )
+            .as_str()
+        )
+    );
+    type_str(&mut editor, "d");
+    assert!(editor.should_quit());
+    assert_eq!(std::fs::read_to_string(a)?, "a.txt\n");
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
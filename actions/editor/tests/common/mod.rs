@@ -0,0 +1,39 @@
+//! Helpers shared by the editor's tests. Each test file uses only some.
+#![allow(dead_code)]
+
+use core_editor::backend::Event;
+use core_editor::editor::Editor;
+use core_editor::key::{Key, KeyCode, parse_keys};
+use std::path::PathBuf;
+
+/// An empty directory for one test.
+pub fn directory(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("core-editor-{}-{}", name, std::process::id()));
+    if path.exists() {
+        std::fs::remove_dir_all(&path)?;
+    }
+    std::fs::create_dir_all(&path)?;
+
+    Ok(path)
+}
+
+/// Presses keys written as in `bind-key`, such as `"C-x C-s"`.
+pub fn press(editor: &mut Editor, keys: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for key in parse_keys(keys)? {
+        editor.handle(&Event::Key(key));
+    }
+
+    Ok(())
+}
+
+/// Types each char of `text` as a key, with `⎋` for escape.
+pub fn type_str(editor: &mut Editor, text: &str) {
+    for c in text.chars() {
+        let key: Key = match c {
+            '⎋' => Key::new(KeyCode::Esc),
+            c => Key::new(KeyCode::Char(c)),
+        };
+        editor.handle(&Event::Key(key));
+    }
+}
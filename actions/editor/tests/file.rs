@@ -0,0 +1,188 @@
+mod common;
+
+use common::directory;
+use core_editor::backend::Event;
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::file::SaveOptions;
+use core_editor::key::{Key, KeyCode};
+use std::path::{Path, PathBuf};
+
+fn names(directory: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut result: Vec<String> = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        result.push(entry?.file_name().to_string_lossy().into_owned());
+    }
+    result.sort();
+
+    Ok(result)
+}
+
+#[test]
+fn save() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory("save")?;
+    let path: PathBuf = directory.join("init.core");
+    std::fs::write(&path, "(define x 1)\n")?;
+
+    let mut buffer = Buffer::open(&path)?;
+    buffer.replace(10..11, "2")?;
+    buffer.save(&SaveOptions::default())?;
+    assert!(!buffer.modified());
+    assert_eq!(std::fs::read_to_string(&path)?, "(define x 2)\n");
+    // The temporary file was renamed away, and no backup was asked for
+    assert_eq!(names(&directory)?, vec!["init.core"]);
+
+    buffer.insert(0, ";; config\n")?;
+    buffer.save(&SaveOptions {
+        backup: true,
+        ..SaveOptions::default()
+    })?;
+    assert_eq!(names(&directory)?, vec!["init.core", "init.core~"]);
+    assert_eq!(
+        std::fs::read_to_string(directory.join("init.core~"))?,
+        "(define x 2)\n"
+    );
+
+    // A buffer for a new file creates it
+    let mut buffer = Buffer::open(&directory.join("new.core"))?;
+    buffer.insert(0, "λ")?;
+    buffer.save(&SaveOptions {
+        backup: true,
+        ..SaveOptions::default()
+    })?;
+    assert_eq!(std::fs::read_to_string(directory.join("new.core"))?, "λ");
+
+    let error = Buffer::new()
+        .save(&SaveOptions::default())
+        .err()
+        .ok_or("saved a buffer without a file")?;
+    assert_eq!(
+        error.to_string(),
+        "BUFFER_ERROR: the buffer has no file name"
+    );
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn save_keeps_permissions_and_links() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory: PathBuf = directory("permissions")?;
+    let path: PathBuf = directory.join("run.sh");
+    std::fs::write(&path, "echo 1\n")?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o750))?;
+    let link: PathBuf = directory.join("link.sh");
+    std::os::unix::fs::symlink(&path, &link)?;
+
+    let mut buffer = Buffer::open(&link)?;
+    buffer.replace(5..6, "2")?;
+    buffer.save(&SaveOptions::default())?;
+
+    assert_eq!(std::fs::read_to_string(&path)?, "echo 2\n");
+    assert!(std::fs::symlink_metadata(&link)?.file_type().is_symlink());
+    assert_eq!(
+        std::fs::metadata(&path)?.permissions().mode() & 0o777,
+        0o750
+    );
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn external_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory("external")?;
+    let path: PathBuf = directory.join("init.core");
+    std::fs::write(&path, "(define x 1)\n")?;
+
+    let mut buffer = Buffer::open(&path)?;
+    assert!(!buffer.changed_on_disk()?);
+
+    // Touching the file is not a change, since its contents are the same
+    let file = std::fs::File::options().write(true).open(&path)?;
+    file.set_modified(std::time::SystemTime::UNIX_EPOCH)?;
+    assert!(!buffer.changed_on_disk()?);
+
+    std::fs::write(&path, "(define yy 1)\n")?;
+    assert!(buffer.changed_on_disk()?);
+    buffer.insert(0, ";; mine\n")?;
+    let error = buffer
+        .save(&SaveOptions::default())
+        .err()
+        .ok_or("saved over an external change")?;
+    assert!(
+        error
+            .to_string()
+            .ends_with("changed on disk since it was read")
+    );
+
+    buffer.overwrite(&SaveOptions::default())?;
+    assert!(!buffer.changed_on_disk()?);
+    assert_eq!(std::fs::read_to_string(&path)?, ";; mine\n(define x 1)\n");
+
+    std::fs::remove_file(&path)?;
+    assert!(buffer.changed_on_disk()?);
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn prompt() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory("prompt")?;
+    let path: PathBuf = directory.join("init.core");
+    std::fs::write(&path, "(define x 1)\n(define y 2)\n")?;
+    let save: Event = Event::Key(Key::ctrl(KeyCode::Char('s')));
+
+    let mut editor = Editor::new(Buffer::open(&path)?);
+    editor.move_lines(1);
+    editor.insert(";")?;
+    std::fs::write(&path, "(define x 10)\n(define y 20)\n")?;
+
+    editor.handle(&save);
+    assert_eq!(
+        editor.message(),
+        Some(
+            format!(
+                "{} changed on disk; overwrite (o), reload (r) or cancel?",
+                path.display()
+            )
+            .as_str()
+        )
+    );
+    editor.handle(&Event::Key(Key::new(KeyCode::Char('r'))));
+    assert_eq!(editor.buffer().text(), "(define x 10)\n(define y 20)\n");
+    assert!(!editor.buffer().modified());
+    // The cursor stays where it was, after the `;` which was reverted
+    assert_eq!(editor.position().line, 1);
+    assert_eq!(editor.position().column, 1);
+
+    editor.move_to_line_start();
+    editor.insert(";")?;
+    std::fs::write(&path, "(define z 3)\n")?;
+    editor.handle(&save);
+    editor.handle(&Event::Key(Key::new(KeyCode::Esc)));
+    assert_eq!(editor.message(), Some("Cancelled"));
+    assert_eq!(std::fs::read_to_string(&path)?, "(define z 3)\n");
+
+    editor.handle(&save);
+    editor.handle(&Event::Key(Key::new(KeyCode::Char('o'))));
+    assert_eq!(
+        editor.message(),
+        Some(format!("Wrote {}", path.display()).as_str())
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        "(define x 10)\n;(define y 20)\n"
+    );
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use core_editor::fuzzy::{rank, score};
+
+#[test]
+fn scores() {
+    assert_eq!(score("xyz", "new-buffer"), None);
+    assert_eq!(score("bn", "new-buffer"), None);
+    assert!(score("", "anything").is_some());
+    assert!(score("NB", "new-buffer").is_some());
+
+    // Word starts and runs of chars count for more than chars in between
+    assert!(score("nb", "new-buffer") > score("nb", "unbind"));
+    assert!(score("buf", "buffer") > score("buf", "bluff"));
+    assert!(score("sb", "switchBuffer") > score("sb", "sibling"));
+}
+
+#[test]
+fn ranking() {
+    let names: [&str; 4] = ["list-buffers", "switch-buffer", "save", "close-buffer"];
+    assert_eq!(rank("sb", &names), vec![1, 0, 3]);
+    assert_eq!(rank("buffer", &names), vec![0, 3, 1]);
+    assert_eq!(rank("", &names), vec![0, 1, 2, 3]);
+    assert_eq!(rank("q", &names), Vec::<usize>::new());
+}
@@ -0,0 +1,311 @@
+mod common;
+
+use common::{directory, press, type_str};
+use core_editor::backend::Event;
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::file::SaveOptions;
+use core_editor::history::History;
+use core_editor::key::{Key, KeyCode};
+use core_lang::syntax::Source;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[test]
+fn undo_tree() -> Result<(), Box<dyn std::error::Error>> {
+    let mut buffer = Buffer::from_text("one");
+    buffer.insert(3, " two")?;
+    buffer.commit(3, 7);
+    buffer.delete(0..4)?;
+    buffer.insert(0, "2:")?;
+    buffer.commit(0, 2);
+    assert_eq!(buffer.text(), "2:two");
+    assert!(buffer.modified());
+
+    assert_eq!(buffer.undo(), Some(0));
+    assert_eq!(buffer.text(), "one two");
+    assert_eq!(buffer.undo(), Some(3));
+    assert_eq!(buffer.text(), "one");
+    assert!(!buffer.modified());
+    assert_eq!(buffer.undo(), None);
+    assert_eq!(buffer.redo(), Some(7));
+    assert_eq!(buffer.text(), "one two");
+
+    // Editing after an undo starts a branch, and the old one is kept
+    buffer.insert(7, " three")?;
+    buffer.commit(7, 13);
+    assert_eq!(buffer.history().len(), 4);
+    assert_eq!(buffer.earlier(), Some(2));
+    assert_eq!(buffer.text(), "2:two");
+    assert_eq!(buffer.earlier(), Some(0));
+    assert_eq!(buffer.text(), "one two");
+    assert_eq!(buffer.later(), Some(2));
+    assert_eq!(buffer.later(), Some(13));
+    assert_eq!(buffer.text(), "one two three");
+    assert_eq!(buffer.later(), None);
+
+    // Redo follows the branch visited last
+    buffer.undo();
+    buffer.undo();
+    buffer.redo();
+    assert_eq!(buffer.redo(), Some(13));
+
+    assert_eq!(buffer.travel_back(Duration::from_secs(3600)), 3);
+    assert_eq!(buffer.text(), "one");
+    assert_eq!(buffer.travel_back(Duration::ZERO), 13);
+    assert_eq!(buffer.text(), "one two three");
+
+    Ok(())
+}
+
+#[test]
+fn transactions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("(a)\n"));
+    type_str(&mut editor, "hello world");
+    press(&mut editor, "DEL DEL RET")?;
+    type_str(&mut editor, "x");
+    assert_eq!(editor.buffer().text(), "hello wor\nx(a)\n");
+
+    // Typing is undone in runs, other commands one at a time
+    for expected in [
+        "hello wor\n(a)\n",
+        "hello wor(a)\n",
+        "hello worl(a)\n",
+        "hello world(a)\n",
+        "(a)\n",
+    ] {
+        press(&mut editor, "C-/")?;
+        assert_eq!(editor.buffer().text(), expected);
+    }
+    assert_eq!(editor.cursor(), 0);
+    press(&mut editor, "C-_")?;
+    assert_eq!(editor.message(), Some("Already at the oldest change"));
+
+    press(&mut editor, "M-_ M-_")?;
+    assert_eq!(editor.buffer().text(), "hello worl(a)\n");
+    assert_eq!(editor.cursor(), 10);
+
+    Ok(())
+}
+
+#[test]
+fn modal() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("one two\n"));
+    editor.load_config(Source::new("init.core", r#"(set-editing-style "modal")"#))?;
+
+    // A change and the text typed after it are one transaction
+    type_str(&mut editor, "cwuno⎋wdw");
+    assert_eq!(editor.buffer().text(), "uno \n");
+    type_str(&mut editor, "u");
+    assert_eq!(editor.buffer().text(), "uno two\n");
+    assert_eq!(editor.cursor(), 4);
+    type_str(&mut editor, "u");
+    assert_eq!(editor.buffer().text(), "one two\n");
+
+    editor.handle(&Event::Key(Key::ctrl(KeyCode::Char('r'))));
+    assert_eq!(editor.buffer().text(), "uno two\n");
+    type_str(&mut editor, "uxg-");
+    assert_eq!(editor.buffer().text(), "uno \n");
+    type_str(&mut editor, "2g+");
+    assert_eq!(editor.buffer().text(), "ne two\n");
+    type_str(&mut editor, "2u");
+    assert_eq!(editor.buffer().text(), "one two\n");
+
+    Ok(())
+}
+
+#[test]
+fn undo_to() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(""));
+    editor.load_config(Source::new(
+        "init.core",
+        r#"(bind-key "<f2>" (lambda () (undo-to 600)))"#,
+    ))?;
+    type_str(&mut editor, "abc");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "def");
+
+    press(&mut editor, "<f2>")?;
+    assert_eq!(editor.buffer().text(), "");
+    press(&mut editor, "M-_")?;
+    assert_eq!(editor.buffer().text(), "abc");
+
+    let e = editor
+        .load_config(Source::new("init.core", "(undo-to (- 1))"))
+        .err()
+        .ok_or("expected an error")?;
+    assert!(e.to_string().contains("must not be negative"), "{}", e);
+
+    Ok(())
+}
+
+#[test]
+fn persistence() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory("undo")?;
+    let path: PathBuf = directory.join("notes.txt");
+    std::fs::write(&path, "first\n")?;
+    let config: &str = "(set-persistent-undo t)";
+
+    let mut editor = Editor::new(Buffer::open(&path)?);
+    let e = editor
+        .load_config(Source::new("init.core", config))
+        .err()
+        .ok_or("expected an error")?;
+    assert!(e.to_string().contains("no data directory"), "{}", e);
+
+    editor.set_data_directory(directory.join("data"));
+    editor.load_config(Source::new("init.core", config))?;
+    type_str(&mut editor, "new ");
+    press(&mut editor, "C-s")?;
+    let saved: Vec<std::fs::DirEntry> =
+        std::fs::read_dir(directory.join("data/undo"))?.collect::<Result<_, _>>()?;
+    assert_eq!(saved.len(), 1);
+    // The contents are identified by their FNV-1a hash, which does not change
+    // between builds
+    let text: String = std::fs::read_to_string(saved[0].path())?;
+    assert_eq!(
+        text.lines().nth(1).and_then(|v| v.split(' ').next()),
+        Some("6949947297172005223")
+    );
+
+    // The next session can undo what the last one did
+    let mut editor = Editor::new(Buffer::open(&path)?);
+    editor.set_data_directory(directory.join("data"));
+    editor.load_config(Source::new("init.core", config))?;
+    assert!(!editor.buffer().modified());
+    press(&mut editor, "C-/")?;
+    assert_eq!(editor.buffer().text(), "first\n");
+    assert!(editor.buffer().modified());
+
+    // History saved for other text than the file has now is not used
+    std::fs::write(&path, "changed elsewhere\n")?;
+    let mut buffer = Buffer::open(&path)?;
+    assert!(!buffer.read_history(&directory.join("data/undo"))?);
+    assert_eq!(buffer.undo(), None);
+
+    // An unreadable history is reported, and later saves still keep one
+    std::fs::write(saved[0].path(), "something else\n")?;
+    let mut editor = Editor::new(Buffer::open(&path)?);
+    editor.set_data_directory(directory.join("data"));
+    editor.load_config(Source::new("init.core", config))?;
+    assert!(
+        editor
+            .message()
+            .is_some_and(|v| v.contains("HISTORY_ERROR: not an undo history file")),
+        "{:?}",
+        editor.message()
+    );
+    type_str(&mut editor, "again ");
+    press(&mut editor, "C-s")?;
+    let text: String = std::fs::read_to_string(saved[0].path())?;
+    assert!(text.starts_with("core-undo 1\n"), "{}", text);
+
+    // Without the option nothing is written
+    let mut buffer = Buffer::open(&directory.join("other.txt"))?;
+    buffer.insert(0, "x")?;
+    buffer.save(&SaveOptions::default())?;
+    let saved: usize = std::fs::read_dir(directory.join("data/undo"))?.count();
+    assert_eq!(saved, 1);
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn history_names() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory("undo-names")?;
+    let config: &str = "(set-persistent-undo t)";
+    let open = |path: &Path| -> Result<Editor, Box<dyn std::error::Error>> {
+        let mut editor = Editor::new(Buffer::open(path)?);
+        editor.set_data_directory(directory.join("data"));
+        editor.load_config(Source::new("init.core", config))?;
+
+        Ok(editor)
+    };
+
+    // Paths which only differ in their separators, and one too long to be
+    // a file name
+    let long: PathBuf = ["x", "y", "z"]
+        .iter()
+        .fold(directory.clone(), |path, v| path.join(v.repeat(120)));
+    let paths: [PathBuf; 3] = [
+        directory.join("a%b"),
+        directory.join("a").join("b"),
+        long.join("notes.txt"),
+    ];
+    for path in &paths {
+        std::fs::create_dir_all(path.parent().ok_or("expected a directory")?)?;
+        std::fs::write(path, "text\n")?;
+        let mut editor = open(path)?;
+        type_str(&mut editor, "new ");
+        press(&mut editor, "C-s")?;
+        assert_eq!(
+            editor.message(),
+            Some(format!("Wrote {}", path.display()).as_str())
+        );
+    }
+    for path in &paths {
+        let mut editor = open(path)?;
+        press(&mut editor, "C-/")?;
+        assert_eq!(editor.buffer().text(), "text\n", "{}", path.display());
+    }
+    assert_eq!(std::fs::read_dir(directory.join("data/undo"))?.count(), 3);
+
+    // A history which cannot be written does not fail the save
+    std::fs::remove_dir_all(directory.join("data/undo"))?;
+    std::fs::write(directory.join("data/undo"), "")?;
+    let mut editor = open(&paths[0])?;
+    type_str(&mut editor, "more ");
+    press(&mut editor, "C-s")?;
+    let message: &str = editor.message().ok_or("expected a message")?;
+    assert!(
+        message.starts_with(&format!(
+            "Wrote {}, but not its undo history: ",
+            paths[0].display()
+        )),
+        "{}",
+        message
+    );
+    assert_eq!(std::fs::read_to_string(&paths[0])?, "more new text\n");
+    assert!(!editor.buffer().modified());
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn history_files() -> Result<(), Box<dyn std::error::Error>> {
+    let mut buffer = Buffer::from_text("a\n");
+    buffer.insert(2, "λ 日本語\r\n")?;
+    buffer.commit(2, 2);
+    buffer.delete(0..1)?;
+    buffer.commit(0, 0);
+    buffer.undo();
+
+    let text: String = buffer.history().to_text(42);
+    let history: History = History::from_text(&text, 42)?.ok_or("expected a history")?;
+    assert_eq!(history.to_text(42), text);
+    assert_eq!(history.current(), 1);
+    assert!(History::from_text(&text, 43)?.is_none());
+
+    for broken in [
+        "",
+        "core-undo 1\n42 0 1\n",
+        "something else\n",
+        &text[..text.len() - 4],
+        // Corrupt or hostile files are errors, never panics
+        "core-undo 1\n42 0 1\n0 0 0 0 1\n0 18446744073709551615 0\nx\n",
+        "core-undo 1\n42 0 1\n0 0 0 0 1\n0 1 18446744073709551615\nx\n",
+        "core-undo 1\n42 0 1\n0 0 0 0 1\n0 1 0\nλ\n",
+        "core-undo 1\n42 0 2\n0 0 0 0 0\n7 0 0 0 0\n",
+        "core-undo 1\n42 5 1\n0 0 0 0 0\n",
+        "core-undo 1\n42 0 1\n0 x 0 0 0\n",
+    ] {
+        let e = History::from_text(broken, 42).err().ok_or(broken)?;
+        assert!(e.to_string().starts_with("HISTORY_ERROR:"), "{}", e);
+    }
+
+    Ok(())
+}
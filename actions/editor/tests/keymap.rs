@@ -0,0 +1,283 @@
+mod common;
+
+use common::press;
+use core_editor::backend::HeadlessBackend;
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::key::{Key, KeyCode, keys_to_string, parse_keys};
+use core_editor::terminal::Terminal;
+use core_lang::syntax::Source;
+use std::time::Duration;
+
+fn configured(config: &str) -> Result<Editor, Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("hello\n"));
+    editor.load_config(Source::new("init.core", config))?;
+
+    Ok(editor)
+}
+
+fn run(editor: &mut Editor, backend: HeadlessBackend) -> Result<(), Box<dyn std::error::Error>> {
+    let mut terminal = Terminal::new(backend)?;
+
+    core_editor::run(editor, &mut terminal)
+}
+
+#[test]
+fn keys() -> Result<(), Box<dyn std::error::Error>> {
+    for text in [
+        "a",
+        "C-x",
+        "M-<left>",
+        "C-M-S-<f5>",
+        "SPC",
+        "RET",
+        "C--",
+        "-",
+        "λ",
+    ] {
+        assert_eq!(text.parse::<Key>()?.to_string(), text);
+    }
+    assert_eq!("S-a".parse::<Key>()?, Key::new(KeyCode::Char('A')));
+    assert_eq!(
+        parse_keys("C-x  C-s")?,
+        vec![Key::ctrl(KeyCode::Char('x')), Key::ctrl(KeyCode::Char('s'))]
+    );
+    assert_eq!(keys_to_string(&parse_keys("C-c TAB")?), "C-c TAB");
+
+    for text in ["", "C-", "<nope>", "<fx>", "ab"] {
+        let e = parse_keys(text).err().ok_or(text)?;
+        assert!(e.to_string().starts_with("KEYMAP_ERROR:"), "{}", e);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bind_keys() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = configured(
+        r#"
+(bind-key "C-c h" (lambda () (insert "hi ")))
+(bind-key "C-c q" "quit")
+(bind-key "C-x C-c" "keyboard-quit")
+(unbind-key "C-q")
+"#,
+    )?;
+
+    press(&mut editor, "C-c")?;
+    assert_eq!(editor.message(), Some("C-c-"));
+    press(&mut editor, "h")?;
+    assert_eq!(editor.buffer().text(), "hi hello\n");
+    assert!(editor.pending().is_empty());
+
+    press(&mut editor, "C-q")?;
+    assert_eq!(editor.message(), Some("C-q is undefined"));
+    press(&mut editor, "C-x C-c")?;
+    assert_eq!(editor.message(), Some("Quit"));
+    press(&mut editor, "C-c z")?;
+    assert_eq!(editor.message(), Some("C-c z is undefined"));
+    press(&mut editor, "C-c C-g")?;
+    assert_eq!(editor.message(), Some("Quit"));
+    assert!(!editor.should_quit());
+
+    // The inserted text is not saved, so quitting asks first
+    press(&mut editor, "C-c q")?;
+    assert!(
+        editor
+            .message()
+            .is_some_and(|v| v.ends_with("is modified; quit without saving (d) or cancel?"))
+    );
+    press(&mut editor, "d")?;
+    assert!(editor.should_quit());
+
+    Ok(())
+}
+
+#[test]
+fn commands() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = configured(
+        r#"
+(define-command "greet"
+  (lambda () (message (string-append "editing " (current-mode)))))
+(define-command "twice"
+  (lambda () (run-command "forward-char") (run-command "forward-char")))
+(define-command "forever" (lambda () (run-command "forever")))
+(bind-key "<f2>" "greet")
+(bind-key "<f3>" "twice")
+(bind-key "<f4>" "forever")
+(bind-key "<f5>" "missing")
+"#,
+    )?;
+
+    press(&mut editor, "<f2>")?;
+    assert_eq!(editor.message(), Some("editing insert"));
+    press(&mut editor, "<f3>")?;
+    assert_eq!(editor.cursor(), 2);
+    press(&mut editor, "<f4>")?;
+    assert_eq!(editor.message(), Some("commands are nested too deeply"));
+    press(&mut editor, "<f5>")?;
+    assert_eq!(editor.message(), Some("unknown command missing"));
+
+    Ok(())
+}
+
+#[test]
+fn layers() -> Result<(), Box<dyn std::error::Error>> {
+    let config: &str = r#"
+(bind-key "<f2>" (lambda () (message "global")))
+(bind-mode-key "normal" "<f2>" (lambda () (message "normal")))
+(bind-filetype-key "core" "<f2>" (lambda () (message "core")))
+(bind-mode-key "normal" "x" "delete-char")
+"#;
+    let mut editor = configured(config)?;
+    press(&mut editor, "<f2>")?;
+    assert_eq!(editor.message(), Some("global"));
+    editor.set_mode("normal");
+    press(&mut editor, "<f2>")?;
+    assert_eq!(editor.message(), Some("normal"));
+    press(&mut editor, "x y")?;
+    assert_eq!(editor.buffer().text(), "ello\n");
+    assert_eq!(editor.message(), Some("y is undefined"));
+
+    let path = std::env::temp_dir().join(format!("core-editor-layers-{}.core", std::process::id()));
+    let mut editor = Editor::new(Buffer::open(&path)?);
+    editor.load_config(Source::new("init.core", config))?;
+    assert_eq!(editor.filetype().as_deref(), Some("core"));
+    editor.set_mode("normal");
+    press(&mut editor, "<f2>")?;
+    assert_eq!(editor.message(), Some("core"));
+
+    Ok(())
+}
+
+#[test]
+fn prefix_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = configured(
+        r#"
+(set-prefix-timeout 250)
+(bind-key "C-c" (lambda () (message "alone")))
+(bind-key "C-c a" (lambda () (message "sequence")))
+"#,
+    )?;
+    let c: Key = Key::ctrl(KeyCode::Char('c'));
+
+    let mut backend = HeadlessBackend::new(20, 6);
+    backend.push_keys([c, Key::new(KeyCode::Char('a'))]);
+    run(&mut editor, backend)?;
+    assert_eq!(editor.message(), Some("sequence"));
+
+    // An ambiguous prefix runs its own binding once nothing follows it
+    let mut backend = HeadlessBackend::new(20, 6);
+    backend.push_keys([c]);
+    assert_eq!(editor.timeout(), None);
+    backend.pause();
+    run(&mut editor, backend)?;
+    assert_eq!(editor.message(), Some("alone"));
+    assert!(editor.pending().is_empty());
+
+    // Otherwise the continuations are listed, and the keys still wait
+    let mut backend = HeadlessBackend::new(20, 6);
+    backend.push_keys([Key::ctrl(KeyCode::Char('x'))]);
+    backend.pause();
+    run(&mut editor, backend)?;
+    assert_eq!(keys_to_string(editor.pending()), "C-x");
+    assert_eq!(editor.timeout(), None);
+    press(&mut editor, "C-g")?;
+    assert!(editor.pending().is_empty());
+
+    press(&mut editor, "C-x")?;
+    assert_eq!(editor.timeout(), Some(Duration::from_millis(250)));
+    assert_eq!(
+        editor.continuations(),
+        vec![
+            (Key::new(KeyCode::Char('-')), String::from("shrink-window")),
+            (Key::new(KeyCode::Char('0')), String::from("close-window")),
+            (Key::new(KeyCode::Char('1')), String::from("only-window")),
+            (
+                Key::new(KeyCode::Char('2')),
+                String::from("split-window-below")
+            ),
+            (
+                Key::new(KeyCode::Char('3')),
+                String::from("split-window-right")
+            ),
+            (Key::new(KeyCode::Char('^')), String::from("enlarge-window")),
+            (Key::new(KeyCode::Char('b')), String::from("switch-buffer")),
+            (Key::ctrl(KeyCode::Char('b')), String::from("list-buffers")),
+            (Key::ctrl(KeyCode::Char('c')), String::from("quit")),
+            (Key::ctrl(KeyCode::Char('f')), String::from("find-file")),
+            (Key::new(KeyCode::Char('k')), String::from("close-buffer")),
+            (Key::new(KeyCode::Char('o')), String::from("other-window")),
+            (Key::ctrl(KeyCode::Char('s')), String::from("save")),
+            (Key::new(KeyCode::Char('t')), String::from("+prefix")),
+            (Key::new(KeyCode::Char('x')), String::from("swap-window")),
+            (
+                Key::new(KeyCode::Char('z')),
+                String::from("maximize-window")
+            ),
+            (
+                Key::new(KeyCode::Char('{')),
+                String::from("shrink-window-horizontally")
+            ),
+            (
+                Key::new(KeyCode::Char('}')),
+                String::from("enlarge-window-horizontally")
+            ),
+            (Key::new(KeyCode::Left), String::from("window-left")),
+            (Key::new(KeyCode::Right), String::from("window-right")),
+            (Key::new(KeyCode::Up), String::from("window-up")),
+            (Key::new(KeyCode::Down), String::from("window-down")),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> Result<(), Box<dyn std::error::Error>> {
+    for (config, expected) in [
+        (r#"(bind-key "C-" "quit")"#, "KEYMAP_ERROR: unknown key C-"),
+        (
+            r#"(bind-key "a" 1)"#,
+            "expected a command name or a procedure",
+        ),
+        (r#"(define (main) (message 1))"#, "init.core: "),
+    ] {
+        let e = configured(config).err().ok_or(config)?;
+        assert!(e.to_string().starts_with("init.core: "), "{}", e);
+        assert!(e.to_string().contains(expected), "{}", e);
+    }
+
+    let editor = configured(r#"(define (main) (message "ready"))"#)?;
+    assert_eq!(editor.message(), Some("ready"));
+    let mut editor = configured(r#"(bind-key "<f2>" (lambda () (car 1)))"#)?;
+    press(&mut editor, "<f2>")?;
+    assert!(editor.message().is_some_and(|v| v.contains("car")));
+
+    Ok(())
+}
+
+#[test]
+fn runaway_scripts() -> Result<(), Box<dyn std::error::Error>> {
+    let slow: &str = "(define (slow n) (if (eq? n 0) 0 (+ (slow (- n 1)) (slow (- n 1)))))";
+
+    let e = configured(&format!("{} (define (main) (slow 40))", slow))
+        .err()
+        .ok_or("expected an error")?;
+    assert!(e.to_string().contains("RUNTIME_ERROR: evaluation"), "{}", e);
+
+    // Bound procedures are stopped too, and the editor carries on
+    let mut editor = configured(&format!(
+        "{} (bind-key \"<f2>\" (lambda () (slow 40)))",
+        slow
+    ))?;
+    press(&mut editor, "<f2>")?;
+    assert!(
+        editor
+            .message()
+            .is_some_and(|v| v.contains("RUNTIME_ERROR: evaluation"))
+    );
+    press(&mut editor, "a")?;
+    assert_eq!(editor.buffer().text(), "ahello\n");
+
+    Ok(())
+}
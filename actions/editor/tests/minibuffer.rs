@@ -0,0 +1,163 @@
+mod common;
+
+use common::{directory, press, type_str};
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::key::parse_keys;
+use core_editor::minibuffer::{self, Input, Minibuffer};
+use core_lang::syntax::Source;
+use std::path::PathBuf;
+
+/// The lines on screen, without the spaces after them.
+fn screen(editor: &mut Editor, width: u16, height: u16) -> Vec<String> {
+    let (grid, _) = editor.render(width, height);
+
+    grid.to_string().lines().map(|v| v.to_string()).collect()
+}
+
+#[test]
+fn keys() -> Result<(), Box<dyn std::error::Error>> {
+    let candidates: Vec<String> = vec![String::from("one"), String::from("two")];
+    let answers: Vec<String> = vec![String::from("first"), String::from("second")];
+    let mut minibuffer: Minibuffer = Minibuffer::new();
+    let mut press = |keys: &str| -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let mut done: Option<String> = None;
+        for key in parse_keys(keys)? {
+            done = match minibuffer.key(key, &candidates, &answers) {
+                Input::Done(v) => Some(v),
+                Input::Cancelled => Some(String::from("cancelled")),
+                Input::Editing(_) => None,
+            };
+        }
+        Ok(done)
+    };
+
+    // Picking goes no further than the candidates, and TAB completes
+    assert_eq!(press("<down> <down> TAB RET")?, Some(String::from("two")));
+    assert_eq!(
+        press("DEL DEL DEL C-n C-p TAB RET")?,
+        Some(String::from("one"))
+    );
+
+    // Earlier answers are recalled from the last
+    assert_eq!(press("M-p RET")?, Some(String::from("second")));
+    assert_eq!(press("M-p M-p M-p RET")?, Some(String::from("first")));
+    assert_eq!(press("M-n RET")?, Some(String::from("second")));
+    assert_eq!(press("M-n RET")?, Some(String::new()));
+    assert_eq!(press("a C-g")?, Some(String::from("cancelled")));
+
+    Ok(())
+}
+
+#[test]
+fn palette() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("one\n"));
+    editor.load_config(Source::new(
+        "init.core",
+        r#"(define-command "greet" (lambda () (message "hello")))"#,
+    ))?;
+    assert!(editor.command_names().contains(&String::from("greet")));
+    assert!(
+        editor
+            .command_names()
+            .contains(&String::from("execute-command"))
+    );
+
+    press(&mut editor, "M-x")?;
+    type_str(&mut editor, "swr");
+    let lines: Vec<String> = screen(&mut editor, 60, 8);
+    assert_eq!(lines[7], "M-x swr");
+    // The best match is listed first, with the keys which run it
+    assert_eq!(lines[0], " split-window-right (C-x 3)");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.window_buffers().len(), 2);
+
+    press(&mut editor, "M-x")?;
+    type_str(&mut editor, "greet");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.message(), Some("hello"));
+
+    // Commands run before come first, the last first
+    press(&mut editor, "M-x")?;
+    let lines: Vec<String> = screen(&mut editor, 30, 8);
+    assert_eq!(lines[0], " greet");
+    assert_eq!(lines[1], " split-window-right (C-x 3)");
+    press(&mut editor, "<down> RET")?;
+    assert_eq!(editor.window_buffers().len(), 3);
+    press(&mut editor, "M-x M-p M-p RET")?;
+    assert_eq!(editor.message(), Some("hello"));
+
+    press(&mut editor, "M-x")?;
+    type_str(&mut editor, "zzz");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.message(), Some("No command matches zzz"));
+    press(&mut editor, "M-x ESC")?;
+    assert_eq!(editor.message(), Some("Quit"));
+
+    Ok(())
+}
+
+#[test]
+fn symbols() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::new());
+    editor.load_config(Source::new("init.core", "(define answer 42)"))?;
+
+    press(&mut editor, "C-h o")?;
+    type_str(&mut editor, "bind-key");
+    press(&mut editor, "RET")?;
+    assert_eq!(
+        editor.message(),
+        Some("bind-key: Binds keys such as \"C-x C-s\" to a command name or a procedure.")
+    );
+
+    press(&mut editor, "C-h o")?;
+    type_str(&mut editor, "answer");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.message(), Some("answer is not documented"));
+
+    Ok(())
+}
+
+#[test]
+fn files() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory("minibuffer")?;
+    std::fs::create_dir_all(directory.join("src"))?;
+    for file in ["notes.md", "new.txt", ".hidden"] {
+        std::fs::write(directory.join(file), "text\n")?;
+    }
+    let prefix: String = format!("{}/", directory.display());
+
+    assert_eq!(
+        minibuffer::files(&prefix),
+        vec![
+            format!("{}new.txt", prefix),
+            format!("{}notes.md", prefix),
+            format!("{}src/", prefix),
+        ]
+    );
+    assert_eq!(
+        minibuffer::files(&format!("{}nmd", prefix)),
+        vec![format!("{}notes.md", prefix)]
+    );
+    assert_eq!(
+        minibuffer::files(&format!("{}.h", prefix)),
+        vec![format!("{}.hidden", prefix)]
+    );
+    assert!(minibuffer::files(&format!("{}missing/", prefix)).is_empty());
+
+    // TAB completes the name, and RET opens what was typed
+    let mut editor = Editor::new(Buffer::new());
+    press(&mut editor, "C-x C-f")?;
+    type_str(&mut editor, &format!("{}nmd", prefix));
+    press(&mut editor, "TAB RET")?;
+    assert_eq!(editor.buffer_name(), format!("{}notes.md", prefix));
+    press(&mut editor, "C-x C-f")?;
+    type_str(&mut editor, &format!("{}nmd", prefix));
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.buffer_name(), format!("{}nmd", prefix));
+    assert_eq!(editor.buffer().text(), "");
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
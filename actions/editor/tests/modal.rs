@@ -0,0 +1,308 @@
+use core_editor::backend::{Event, HeadlessBackend};
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::key::{Key, KeyCode, parse_keys};
+use core_editor::modal::Register;
+use core_editor::terminal::Terminal;
+use core_lang::syntax::Source;
+
+const TEXT: &str = "one two three\n  four five\n\nsix seven\n";
+
+fn modal(text: &str) -> Result<Editor, Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(text));
+    editor.load_config(Source::new("init.core", r#"(set-editing-style "modal")"#))?;
+
+    Ok(editor)
+}
+
+/// Types each char of `keys` as a key, with `⎋` for escape.
+fn vi(editor: &mut Editor, keys: &str) {
+    for c in keys.chars() {
+        let key: Key = match c {
+            '⎋' => Key::new(KeyCode::Esc),
+            '\n' => Key::new(KeyCode::Enter),
+            c => Key::new(KeyCode::Char(c)),
+        };
+        editor.handle(&Event::Key(key));
+    }
+}
+
+fn text(editor: &Editor) -> String {
+    editor.buffer().text()
+}
+
+#[test]
+fn motions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = modal(TEXT)?;
+    assert_eq!(editor.mode(), "normal");
+
+    for (keys, offset) in [
+        ("w", 4),
+        ("2w", 16),
+        ("b", 8),
+        ("e", 12),
+        ("W", 16),
+        ("$", 24),
+        ("0", 14),
+        ("^", 16),
+        ("j", 26),
+        ("}", 35),
+        ("{", 26),
+        ("gg", 0),
+        ("G", 27),
+        ("3G", 26),
+        ("1gg", 0),
+        ("ft", 4),
+        (";", 8),
+        (",", 4),
+        ("2l", 6),
+        ("Fn", 1),
+        ("te", 1),
+        ("tr", 9),
+        ("w", 16),
+        ("3x", 16),
+    ] {
+        vi(&mut editor, keys);
+        assert_eq!(editor.cursor(), offset, "after {}", keys);
+    }
+    assert_eq!(text(&editor), "one two three\n  r five\n\nsix seven\n");
+    // Typing in normal mode does not insert
+    vi(&mut editor, "z");
+    assert_eq!(editor.message(), Some("z is undefined"));
+
+    Ok(())
+}
+
+#[test]
+fn operators() -> Result<(), Box<dyn std::error::Error>> {
+    for (keys, expected, cursor) in [
+        ("dw", "two three\n  four five\n\nsix seven\n", 0),
+        ("2dw", "three\n  four five\n\nsix seven\n", 0),
+        ("d2w", "three\n  four five\n\nsix seven\n", 0),
+        ("wwdw", "one two \n  four five\n\nsix seven\n", 7),
+        ("de", " two three\n  four five\n\nsix seven\n", 0),
+        ("d$", "\n  four five\n\nsix seven\n", 0),
+        ("wD", "one \n  four five\n\nsix seven\n", 3),
+        ("dd", "  four five\n\nsix seven\n", 2),
+        ("2dd", "\nsix seven\n", 0),
+        ("dj", "\nsix seven\n", 0),
+        ("jdk", "\nsix seven\n", 0),
+        ("Gdd", "one two three\n  four five\n\n", 26),
+        ("wdt ", "one  three\n  four five\n\nsix seven\n", 4),
+        ("$dFt", "one two e\n  four five\n\nsix seven\n", 8),
+        ("wd2l", "one o three\n  four five\n\nsix seven\n", 4),
+        ("ywP", "one one two three\n  four five\n\nsix seven\n", 3),
+        (
+            "yyjp",
+            "one two three\n  four five\none two three\n\nsix seven\n",
+            26,
+        ),
+        ("ddp", "  four five\none two three\n\nsix seven\n", 12),
+        (
+            "Gyyp",
+            "one two three\n  four five\n\nsix seven\nsix seven\n",
+            37,
+        ),
+        ("xp", "noe two three\n  four five\n\nsix seven\n", 1),
+        ("3rx", "xxx two three\n  four five\n\nsix seven\n", 2),
+        ("20rx", TEXT, 0),
+    ] {
+        let mut editor = modal(TEXT)?;
+        vi(&mut editor, keys);
+        assert_eq!(text(&editor), expected, "after {}", keys);
+        assert_eq!(editor.cursor(), cursor, "after {}", keys);
+        assert_eq!(editor.mode(), "normal", "after {}", keys);
+    }
+
+    let mut editor = modal("a\nb\nc\n")?;
+    vi(&mut editor, "2yy");
+    assert_eq!(editor.register(), &Register::Lines(String::from("a\nb\n")));
+
+    Ok(())
+}
+
+#[test]
+fn operator_pending() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = modal(TEXT)?;
+    vi(&mut editor, "2d");
+    assert_eq!(editor.mode(), "operator-pending");
+    vi(&mut editor, "⎋");
+    assert_eq!(editor.mode(), "normal");
+    vi(&mut editor, "w");
+    assert_eq!((text(&editor).as_str(), editor.cursor()), (TEXT, 4));
+
+    // An unknown motion cancels the operator
+    vi(&mut editor, "dz");
+    assert_eq!(editor.mode(), "normal");
+    vi(&mut editor, "l");
+    assert_eq!((text(&editor).as_str(), editor.cursor()), (TEXT, 5));
+
+    // Keys bound for the mode come before the built-in motions
+    editor.load_config(Source::new(
+        "init.core",
+        r#"(bind-mode-key "operator-pending" "w" "forward-char")"#,
+    ))?;
+    vi(&mut editor, "dw");
+    assert_eq!(editor.mode(), "normal");
+    assert_eq!(text(&editor), TEXT);
+
+    Ok(())
+}
+
+#[test]
+fn insert() -> Result<(), Box<dyn std::error::Error>> {
+    for (keys, expected, cursor) in [
+        ("iX⎋", "Xone two three\n", 0),
+        ("aX⎋", "oXne two three\n", 1),
+        ("wIX⎋", "Xone two three\n", 0),
+        ("AX⎋", "one two threeX\n", 13),
+        ("oX⎋", "one two three\nX\n", 14),
+        ("OX⎋", "X\none two three\n", 0),
+        ("cwX⎋", "X two three\n", 0),
+        ("ccX⎋", "X\n", 0),
+        ("wCX⎋", "one X\n", 4),
+        ("sX⎋", "Xne two three\n", 0),
+        ("i\n⎋", "\none two three\n", 1),
+    ] {
+        let mut editor = modal("one two three\n")?;
+        vi(&mut editor, keys);
+        assert_eq!(text(&editor), expected, "after {}", keys);
+        assert_eq!(editor.cursor(), cursor, "after {}", keys);
+        assert_eq!(editor.mode(), "normal", "after {}", keys);
+    }
+
+    let mut editor = modal("one\n")?;
+    vi(&mut editor, "A");
+    assert_eq!(editor.mode(), "insert");
+    for key in parse_keys("DEL C-s")? {
+        editor.handle(&Event::Key(key));
+    }
+    assert_eq!(text(&editor), "on\n");
+
+    Ok(())
+}
+
+#[test]
+fn visual() -> Result<(), Box<dyn std::error::Error>> {
+    let text: &str = "abcd\nefgh\nijkl\n";
+    for (keys, expected, register) in [
+        (
+            "lvld",
+            "ad\nefgh\nijkl\n",
+            Register::Chars(String::from("bc")),
+        ),
+        (
+            "lvjd",
+            "agh\nijkl\n",
+            Register::Chars(String::from("bcd\nef")),
+        ),
+        (
+            "lvlohd",
+            "d\nefgh\nijkl\n",
+            Register::Chars(String::from("abc")),
+        ),
+        (
+            "jVd",
+            "abcd\nijkl\n",
+            Register::Lines(String::from("efgh\n")),
+        ),
+        ("Vjy", text, Register::Lines(String::from("abcd\nefgh\n"))),
+        (
+            "vVjd",
+            "ijkl\n",
+            Register::Lines(String::from("abcd\nefgh\n")),
+        ),
+    ] {
+        let mut editor = modal(text)?;
+        vi(&mut editor, keys);
+        assert_eq!(editor.buffer().text(), expected, "after {}", keys);
+        assert_eq!(editor.register(), &register, "after {}", keys);
+        assert_eq!(editor.mode(), "normal", "after {}", keys);
+    }
+
+    let mut editor = modal(text)?;
+    vi(&mut editor, "l");
+    editor.handle(&Event::Key(Key::ctrl(KeyCode::Char('v'))));
+    assert_eq!(editor.mode(), "visual-block");
+    vi(&mut editor, "jld");
+    assert_eq!(editor.buffer().text(), "ad\neh\nijkl\n");
+    let block: Register = Register::Block(vec![String::from("bc"), String::from("fg")]);
+    assert_eq!(editor.register(), &block);
+    vi(&mut editor, "jjP");
+    assert_eq!(editor.buffer().text(), "ad\neh\nibcjkl\n fg\n");
+
+    // A block which ends on the last line needs no line break after it
+    let mut editor = modal("ab\ncd")?;
+    editor.handle(&Event::Key(Key::ctrl(KeyCode::Char('v'))));
+    vi(&mut editor, "jyggp");
+    assert_eq!(editor.buffer().text(), "aab\nccd");
+    let mut editor = modal("ab\ncd")?;
+    editor.handle(&Event::Key(Key::ctrl(KeyCode::Char('v'))));
+    vi(&mut editor, "jygglp");
+    assert_eq!(editor.buffer().text(), "aba\ncdc");
+    vi(&mut editor, "jP");
+    assert_eq!(editor.buffer().text(), "aba\ncdac\n  c");
+
+    vi(&mut editor, "vv");
+    assert_eq!(editor.mode(), "normal");
+    vi(&mut editor, "v⎋");
+    assert_eq!(editor.mode(), "normal");
+
+    Ok(())
+}
+
+#[test]
+fn styles() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = modal("abc\n")?;
+    editor.load_config(Source::new(
+        "init.core",
+        r#"(set-editing-style "modeless")"#,
+    ))?;
+    assert_eq!(editor.mode(), "insert");
+    vi(&mut editor, "dw⎋");
+    assert_eq!(editor.buffer().text(), "dwabc\n");
+
+    let e = editor
+        .load_config(Source::new("init.core", r#"(set-editing-style "emacs")"#))
+        .err()
+        .ok_or("expected an error")?;
+    assert!(
+        e.to_string().contains("expected \"modal\" or \"modeless\""),
+        "{}",
+        e
+    );
+
+    // Modal editing can be switched on by a key, too
+    editor.load_config(Source::new(
+        "init.core",
+        r#"(bind-key "<f2>" "modal-editing")"#,
+    ))?;
+    editor.handle(&Event::Key(Key::new(KeyCode::F(2))));
+    assert!(editor.modal_editing());
+    assert_eq!((editor.mode(), editor.cursor()), ("normal", 2));
+
+    Ok(())
+}
+
+#[test]
+fn screen() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = modal("abcd\nefgh\n")?;
+    vi(&mut editor, "lvj");
+    let mut terminal = Terminal::new(HeadlessBackend::new(24, 5))?;
+    core_editor::run(&mut editor, &mut terminal)?;
+    let screen = terminal.backend().screen();
+
+    assert!(screen.to_string().contains(" VISUAL  *scratch* "));
+    let reversed: Vec<bool> = (0..5)
+        .map(|x| screen.get(x, 0).is_some_and(|v| v.style.reverse))
+        .chain((0..5).map(|x| screen.get(x, 1).is_some_and(|v| v.style.reverse)))
+        .collect();
+    assert_eq!(
+        reversed,
+        [
+            false, true, true, true, false, true, true, false, false, false
+        ]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,301 @@
+mod common;
+
+use common::{press, type_str};
+use core_editor::backend::HeadlessBackend;
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::grid::{Color, Style};
+use core_editor::key::parse_keys;
+use core_editor::search::{Match, Pattern};
+use core_editor::terminal::Terminal;
+use core_lang::syntax::Source;
+use std::ops::Range;
+
+/// The line shown in the message area.
+fn message_line(editor: &mut Editor) -> String {
+    let (grid, _) = editor.render(60, 6);
+    let screen: String = grid.to_string();
+
+    screen
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim_end()
+        .to_string()
+}
+
+fn found(range: Range<usize>, wrapped: bool) -> Option<Match> {
+    Some(Match { range, wrapped })
+}
+
+#[test]
+fn patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = Buffer::from_text("One two one\nthree ONE 日本\n");
+
+    // Without an uppercase letter, case is ignored
+    let pattern: Pattern = Pattern::new("one", false)?;
+    assert_eq!(pattern.find_forward(&buffer, 0), found(0..3, false));
+    assert_eq!(pattern.find_forward(&buffer, 1), found(8..11, false));
+    assert_eq!(pattern.find_forward(&buffer, 9), found(18..21, false));
+    assert_eq!(pattern.find_forward(&buffer, 19), found(0..3, true));
+    assert_eq!(pattern.find_backward(&buffer, 8), found(0..3, false));
+    assert_eq!(pattern.find_backward(&buffer, 0), found(18..21, true));
+    assert_eq!(pattern.matches(&buffer, 0), vec![0..3, 8..11]);
+
+    let pattern: Pattern = Pattern::new("One", false)?;
+    assert_eq!(pattern.find_forward(&buffer, 1), found(0..3, true));
+    assert_eq!(pattern.matches(&buffer, 1), Vec::<Range<usize>>::new());
+
+    // Literal text is not a regular expression, and `$` is the end of a line
+    assert_eq!(Pattern::new("t.o", false)?.find_forward(&buffer, 0), None);
+    let pattern: Pattern = Pattern::new("t.o", true)?;
+    assert_eq!(pattern.find_forward(&buffer, 0), found(4..7, false));
+    let pattern: Pattern = Pattern::new("o(n)e$", true)?;
+    assert_eq!(pattern.find_forward(&buffer, 0), found(8..11, false));
+    assert_eq!(
+        Pattern::new("本", false)?.find_forward(&buffer, 0),
+        found(25..28, false)
+    );
+    // The empty line after the last line break is not searched
+    assert_eq!(
+        Pattern::new("^", true)?.find_forward(&buffer, 13),
+        found(0..0, true)
+    );
+    assert_eq!(
+        Pattern::new("x*", true)?.matches(&buffer, 0),
+        Vec::<Range<usize>>::new()
+    );
+
+    // Only the letters a regular expression matches count towards the case
+    let buffer = Buffer::from_text("one ONE\n");
+    for query in [r"on\S", r"\bo\w\pL", r"\p{L}n\x45", r"(?U)(?P<Word>o)ne"] {
+        let pattern: Pattern = Pattern::new(query, true)?;
+        assert_eq!(
+            pattern.find_forward(&buffer, 1),
+            found(4..7, false),
+            "{}",
+            query
+        );
+    }
+    for (query, range) in [(r"O\S+", 4..7), (r"\bO", 4..5), (r"(?P<w>O)NE", 4..7)] {
+        let pattern: Pattern = Pattern::new(query, true)?;
+        assert_eq!(
+            pattern.find_forward(&buffer, 0),
+            found(range, false),
+            "{}",
+            query
+        );
+    }
+
+    let e = Pattern::new("(", true).err().ok_or("expected an error")?;
+    assert_eq!(e.to_string(), "SEARCH_ERROR: invalid pattern (");
+
+    Ok(())
+}
+
+#[test]
+fn replacements() -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = Buffer::from_text("One two\nthree\n");
+
+    let pattern: Pattern = Pattern::new(r"(\w+) (\w+)", true)?;
+    assert_eq!(pattern.replacement(&buffer, 0..7, "$2 $1")?, "two One");
+    let pattern: Pattern = Pattern::new(r"(?P<word>t\w+)", true)?;
+    assert_eq!(
+        pattern.replacement(&buffer, 8..13, "[${word}] $$")?,
+        "[three] $"
+    );
+    assert_eq!(pattern.replacement(&buffer, 4..7, "${word}s")?, "twos");
+    let e = pattern
+        .replacement(&buffer, 0..3, "x")
+        .err()
+        .ok_or("expected an error")?;
+    assert!(e.to_string().starts_with("SEARCH_ERROR:"), "{}", e);
+
+    // Literal replacements are inserted as they are
+    let pattern: Pattern = Pattern::new("two", false)?;
+    assert_eq!(pattern.replacement(&buffer, 4..7, "$1")?, "$1");
+
+    Ok(())
+}
+
+#[test]
+fn incremental() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("alpha beta\ngamma beta\nbeta\n"));
+    press(&mut editor, "C-f")?;
+    type_str(&mut editor, "bet");
+    assert_eq!(editor.cursor(), 6);
+    assert_eq!(message_line(&mut editor), "Search: bet");
+
+    for (keys, cursor) in [("C-f", 17), ("C-f", 22), ("C-f", 6), ("C-r", 22)] {
+        press(&mut editor, keys)?;
+        assert_eq!(editor.cursor(), cursor, "after {}", keys);
+    }
+    assert_eq!(message_line(&mut editor), "Wrapped search backward: bet");
+
+    // Changing the query searches again from where the search started
+    press(&mut editor, "C-f DEL")?;
+    assert_eq!(editor.cursor(), 6);
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.cursor(), 6);
+    assert_eq!(message_line(&mut editor), "");
+    editor.run_command("search-next")?;
+    assert_eq!(editor.cursor(), 17);
+    editor.run_command("search-previous")?;
+    assert_eq!(editor.cursor(), 6);
+
+    // Cancelling goes back to where the search started
+    press(&mut editor, "C-r")?;
+    type_str(&mut editor, "gamma");
+    assert_eq!(editor.cursor(), 11);
+    press(&mut editor, "ESC")?;
+    assert_eq!(editor.cursor(), 6);
+
+    // Another key ends the search, then does what it usually does
+    press(&mut editor, "C-f")?;
+    type_str(&mut editor, "zzz");
+    assert_eq!(editor.cursor(), 6);
+    assert_eq!(message_line(&mut editor), "Failing search: zzz");
+    press(&mut editor, "DEL DEL DEL")?;
+    type_str(&mut editor, "l.h");
+    assert_eq!(message_line(&mut editor), "Failing search: l.h");
+    press(&mut editor, "M-r")?;
+    assert_eq!(editor.cursor(), 1);
+    assert_eq!(message_line(&mut editor), "Wrapped regex search: l.h");
+    press(&mut editor, "<right>")?;
+    assert_eq!(editor.cursor(), 2);
+    editor.run_command("search-next")?;
+    assert_eq!(editor.cursor(), 1);
+    assert_eq!(editor.message(), Some("Search wrapped around"));
+
+    press(&mut editor, "C-f M-r")?;
+    type_str(&mut editor, "(");
+    assert_eq!(
+        message_line(&mut editor),
+        "Regex search: ( [SEARCH_ERROR: invalid pattern (]"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn highlighting() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("a ab\nab\n"));
+    let mut backend = HeadlessBackend::new(12, 4);
+    backend.push_keys(parse_keys("C-f a b")?);
+    let mut terminal = Terminal::new(backend)?;
+    core_editor::run(&mut editor, &mut terminal)?;
+
+    let screen = terminal.backend().screen();
+    let matched: Style = Style {
+        fg: Some(Color::Black),
+        bg: Some(Color::Yellow),
+        ..Style::default()
+    };
+    let styles: Vec<Style> = [(0, 0), (2, 0), (3, 0), (0, 1), (1, 1), (2, 1)]
+        .into_iter()
+        .map(|(x, y)| screen.get(x, y).map_or(Style::default(), |v| v.style))
+        .collect();
+    assert_eq!(
+        styles,
+        [
+            Style::default(),
+            Style::reverse(),
+            Style::reverse(),
+            matched,
+            matched,
+            Style::default()
+        ]
+    );
+    // The cursor is in the message area while the query is typed
+    assert_eq!(terminal.backend().cursor(), Some((10, 3)));
+
+    Ok(())
+}
+
+#[test]
+fn query_replace() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("cat hat cat\ncat\n"));
+    press(&mut editor, "M-%")?;
+    type_str(&mut editor, "cat");
+    assert_eq!(message_line(&mut editor), "Query replace: cat");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "dog");
+    assert_eq!(message_line(&mut editor), "Query replace cat with: dog");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.message(), Some("Replace cat with dog? (y, n, !, q)"));
+    assert_eq!(editor.cursor(), 0);
+
+    type_str(&mut editor, "y");
+    assert_eq!(editor.cursor(), 8);
+    type_str(&mut editor, "n");
+    assert_eq!(editor.cursor(), 12);
+    type_str(&mut editor, "y");
+    assert_eq!(editor.buffer().text(), "dog hat cat\ndog\n");
+    assert_eq!(editor.message(), Some("Replaced 2 occurrences"));
+    // Each replacement is undone on its own
+    press(&mut editor, "C-/")?;
+    assert_eq!(editor.buffer().text(), "dog hat cat\ncat\n");
+
+    // Capture groups, with all the rest replaced at once
+    let mut editor = Editor::new(Buffer::from_text("key=value\nname=core\n"));
+    press(&mut editor, "C-M-%")?;
+    type_str(&mut editor, r"(\w+)=(\w+)");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "$2: $1");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "!");
+    assert_eq!(editor.buffer().text(), "value: key\ncore: name\n");
+    assert_eq!(editor.message(), Some("Replaced 2 occurrences"));
+    press(&mut editor, "C-/")?;
+    assert_eq!(editor.buffer().text(), "key=value\nname=core\n");
+
+    // Replacements do not match again, and empty matches are stepped over
+    let mut editor = Editor::new(Buffer::from_text("a a\nb\n"));
+    press(&mut editor, "C-M-%")?;
+    type_str(&mut editor, "^");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "^");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "!");
+    assert_eq!(editor.buffer().text(), "^a a\n^b\n");
+    press(&mut editor, "<up> <home> M-%")?;
+    type_str(&mut editor, "a");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "aa");
+    press(&mut editor, "RET")?;
+    type_str(&mut editor, "yq");
+    assert_eq!(editor.buffer().text(), "^aa a\n^b\n");
+    assert_eq!(editor.message(), Some("Replaced 1 occurrence"));
+
+    press(&mut editor, "C-M-%")?;
+    type_str(&mut editor, "(");
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.message(), Some("SEARCH_ERROR: invalid pattern ("));
+    press(&mut editor, "M-% C-g")?;
+    assert_eq!(editor.message(), Some("Quit"));
+
+    Ok(())
+}
+
+#[test]
+fn modal() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("one two\nthree two\n"));
+    editor.load_config(Source::new("init.core", r#"(set-editing-style "modal")"#))?;
+
+    type_str(&mut editor, "/tw");
+    assert_eq!(editor.cursor(), 4);
+    press(&mut editor, "RET")?;
+    for (keys, cursor) in [("n", 14), ("n", 4), ("N", 14), ("2n", 14), ("?e", 12)] {
+        type_str(&mut editor, keys);
+        assert_eq!(editor.cursor(), cursor, "after {}", keys);
+    }
+    press(&mut editor, "RET")?;
+    assert_eq!(editor.mode(), "normal");
+    // `?` searches for a regular expression, and `n` keeps its direction
+    type_str(&mut editor, "n");
+    assert_eq!(editor.cursor(), 11);
+    type_str(&mut editor, "x");
+    assert_eq!(editor.buffer().text(), "one two\nthre two\n");
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use core_editor::backend::HeadlessBackend;
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::key::{Key, KeyCode, parse_keys};
+use core_editor::terminal::Terminal;
+use std::path::{Path, PathBuf};
+
+const TEXT: &str = "(define (main)\n\t(cli))\n;; λ 日本語\n";
+
+/// Compares a screen with `tests/snapshots/ui/<name>.snap`, or writes it
+/// there when run with `BLESS=1`.
+fn check(name: &str, screen: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path: PathBuf = Path::new("./tests/snapshots/ui").join(format!("{}.snap", name));
+    if std::env::var_os("BLESS").is_some_and(|v| !v.is_empty() && v != "0") {
+        std::fs::create_dir_all(path.parent().ok_or("no parent")?)?;
+        std::fs::write(&path, screen)?;
+        return Ok(());
+    }
+
+    let expected: String = std::fs::read_to_string(&path)
+        .map_err(|_| format!("{} is missing, run with BLESS=1", path.display()))?;
+    assert_eq!(screen, expected, "{} does not match", path.display());
+
+    Ok(())
+}
+
+fn run(
+    editor: &mut Editor,
+    backend: HeadlessBackend,
+) -> Result<Terminal<HeadlessBackend>, Box<dyn std::error::Error>> {
+    let mut terminal = Terminal::new(backend)?;
+    core_editor::run(editor, &mut terminal)?;
+
+    Ok(terminal)
+}
+
+#[test]
+fn open_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(TEXT));
+    let terminal = run(&mut editor, HeadlessBackend::new(24, 6))?;
+
+    check("open-file", &terminal.backend().screen().to_string())?;
+    assert_eq!(terminal.backend().cursor(), Some((0, 0)));
+
+    Ok(())
+}
+
+#[test]
+fn typing() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(TEXT));
+    let mut backend = HeadlessBackend::new(24, 6);
+    backend.push_keys([Key::new(KeyCode::Down), Key::new(KeyCode::End)]);
+    backend.type_str(" ; ok");
+    backend.push_keys([Key::ctrl(KeyCode::Char('t'))]);
+    let terminal = run(&mut editor, backend)?;
+
+    check("typing", &terminal.backend().screen().to_string())?;
+    assert_eq!(editor.buffer().line(1).as_deref(), Some("\t(cli)) ; ok"));
+    assert_eq!(editor.message(), Some("C-t is undefined"));
+    assert_eq!(terminal.backend().cursor(), Some((15, 1)));
+
+    Ok(())
+}
+
+#[test]
+fn which_key() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(TEXT));
+    let mut backend = HeadlessBackend::new(32, 6);
+    backend.push_keys([Key::ctrl(KeyCode::Char('x'))]);
+    backend.pause();
+    let terminal = run(&mut editor, backend)?;
+
+    check("which-key", &terminal.backend().screen().to_string())?;
+
+    Ok(())
+}
+
+#[test]
+fn scrolling() -> Result<(), Box<dyn std::error::Error>> {
+    let text: String = (1..=20)
+        .map(|v| format!("line {} {}\n", v, "-".repeat(v * 2)))
+        .collect();
+    let mut editor = Editor::new(Buffer::from_text(&text));
+    let mut backend = HeadlessBackend::new(16, 6);
+    backend.push_keys([Key::new(KeyCode::PageDown), Key::new(KeyCode::PageDown)]);
+    backend.push_keys([Key::new(KeyCode::Down), Key::new(KeyCode::End)]);
+    let terminal = run(&mut editor, backend)?;
+
+    check("scrolling", &terminal.backend().screen().to_string())?;
+    assert_eq!(terminal.backend().cursor(), Some((15, 3)));
+
+    Ok(())
+}
+
+#[test]
+fn resize() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(TEXT));
+    let mut backend = HeadlessBackend::new(24, 6);
+    backend.resize(12, 4);
+    let terminal = run(&mut editor, backend)?;
+
+    check("resize", &terminal.backend().screen().to_string())?;
+
+    Ok(())
+}
+
+#[test]
+fn windows() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(TEXT));
+    let mut backend = HeadlessBackend::new(40, 10);
+    backend.push_keys(parse_keys("C-x 3 C-x o C-x 2 <down> <down> <end>")?);
+    backend.type_str("!");
+    let terminal = run(&mut editor, backend)?;
+
+    check("windows", &terminal.backend().screen().to_string())?;
+    assert_eq!(terminal.backend().cursor(), Some((32, 2)));
+
+    Ok(())
+}
+
+#[test]
+fn redraws_changed_cells() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text(TEXT));
+    let mut terminal = Terminal::new(HeadlessBackend::new(24, 6))?;
+
+    let (grid, cursor) = editor.render(24, 6);
+    terminal.draw(&grid, cursor)?;
+    let first: usize = terminal.backend_mut().take_drawn();
+
+    // Moving the cursor only changes the position in the status line
+    editor.move_right()?;
+    let (grid, cursor) = editor.render(24, 6);
+    assert_eq!(terminal.draw(&grid, cursor)?, 1);
+    assert_eq!(terminal.backend().cursor(), Some((1, 0)));
+
+    // Nothing changed, so nothing is drawn
+    assert_eq!(terminal.draw(&grid, cursor)?, 0);
+    assert!(first > 24);
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+mod common;
+
+use common::{directory, press, type_str};
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::window::{Direction, Layout, Rect, Side};
+use std::path::PathBuf;
+
+const AREA: Rect = Rect {
+    x: 0,
+    y: 0,
+    width: 41,
+    height: 20,
+};
+
+fn rect(x: u16, y: u16, width: u16, height: u16) -> Rect {
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+#[test]
+fn layouts() -> Result<(), Box<dyn std::error::Error>> {
+    let mut layout: Layout = Layout::Window(0);
+    assert!(layout.split(0, 1, Direction::Right));
+    assert!(layout.split(1, 2, Direction::Below));
+    assert!(!layout.split(7, 8, Direction::Below));
+    assert_eq!(layout.to_string(), "(right 50 0 (below 50 1 2))");
+    assert_eq!(layout.windows(), vec![0, 1, 2]);
+
+    // Side by side windows leave a column for the separator
+    assert_eq!(
+        layout.rects(AREA),
+        vec![
+            (0, rect(0, 0, 20, 20)),
+            (1, rect(21, 0, 20, 10)),
+            (2, rect(21, 10, 20, 10)),
+        ]
+    );
+    assert_eq!(layout.neighbor(0, Side::Right, AREA), Some(1));
+    assert_eq!(layout.neighbor(2, Side::Left, AREA), Some(0));
+    assert_eq!(layout.neighbor(2, Side::Up, AREA), Some(1));
+    assert_eq!(layout.neighbor(0, Side::Up, AREA), None);
+
+    // The innermost split in the direction asked for moves
+    assert!(layout.resize(2, Direction::Below, 2, AREA));
+    assert!(layout.resize(2, Direction::Right, -4, AREA));
+    assert!(!layout.resize(0, Direction::Below, 1, AREA));
+    assert_eq!(layout.to_string(), "(right 60 0 (below 40 1 2))");
+
+    layout.swap(0, 2);
+    assert_eq!(layout.to_string(), "(right 60 2 (below 40 1 0))");
+    assert!(layout.remove(1));
+    assert_eq!(layout.to_string(), "(right 60 2 0)");
+    assert!(layout.remove(2));
+    assert!(!layout.remove(0));
+    assert_eq!(layout, Layout::Window(0));
+
+    let text: &str = "(below 25 (right 60 3 4) 5)";
+    assert_eq!(text.parse::<Layout>()?.to_string(), text);
+    for (text, expected) in [
+        ("(below 25 3)", "LAYOUT_ERROR: expected a window, found )"),
+        ("(up 50 1 2)", "LAYOUT_ERROR: unknown direction up"),
+        (
+            "(right 100 1 2)",
+            "LAYOUT_ERROR: expected a percent from 1 to 99",
+        ),
+        ("(right 50 1 1)", "LAYOUT_ERROR: a window appears twice"),
+        ("1 2", "LAYOUT_ERROR: unexpected 2"),
+    ] {
+        let e = text.parse::<Layout>().err().ok_or("expected an error")?;
+        assert_eq!(e.to_string(), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn splitting() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("one\ntwo\nthree\n"));
+    press(&mut editor, "C-x 0")?;
+    assert_eq!(editor.message(), Some("Cannot close the only window"));
+
+    // Both windows show the buffer, each with its own cursor
+    press(&mut editor, "C-x 2 <down>")?;
+    assert_eq!(editor.window(), 0);
+    press(&mut editor, "C-x o")?;
+    assert_eq!(editor.window(), 1);
+    assert_eq!(editor.cursor(), 0);
+    type_str(&mut editor, "zero ");
+    press(&mut editor, "C-x <up>")?;
+    assert_eq!(editor.window(), 0);
+    assert_eq!(editor.buffer().text(), "zero one\ntwo\nthree\n");
+    assert_eq!(editor.position().line, 1);
+    press(&mut editor, "C-x <left>")?;
+    assert_eq!(editor.message(), Some("There is no window to the left"));
+
+    editor.new_buffer();
+    press(&mut editor, "C-x 3")?;
+    assert_eq!(
+        editor.window_buffers(),
+        vec![
+            (0, String::from("*scratch*<2>")),
+            (2, String::from("*scratch*<2>")),
+            (1, String::from("*scratch*")),
+        ]
+    );
+    press(&mut editor, "C-x x")?;
+    assert_eq!(editor.layout().to_string(), "(below 50 (right 50 2 0) 1)");
+    press(&mut editor, "C-x ^ C-x }")?;
+    assert_eq!(editor.layout().to_string(), "(below 54 (right 49 2 0) 1)");
+
+    // Maximizing hides the other windows until it is undone
+    press(&mut editor, "C-x z")?;
+    assert_eq!(editor.layout(), &Layout::Window(0));
+    press(&mut editor, "C-x z")?;
+    assert_eq!(editor.window_buffers().len(), 3);
+    press(&mut editor, "C-x 0")?;
+    assert_eq!(editor.window(), 1);
+    assert_eq!(editor.buffer_name(), "*scratch*");
+    assert_eq!(editor.layout().to_string(), "(below 54 2 1)");
+    press(&mut editor, "C-x 1")?;
+    assert_eq!(editor.layout(), &Layout::Window(1));
+    assert_eq!(editor.buffer_names().len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn closing_buffers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("one\n"));
+    editor.new_buffer();
+    press(&mut editor, "C-x 2 C-x k")?;
+    // The other window showed the closed buffer, so it shows another
+    assert_eq!(
+        editor.window_buffers(),
+        vec![
+            (0, String::from("*scratch*")),
+            (1, String::from("*scratch*"))
+        ]
+    );
+    press(&mut editor, "C-x o")?;
+    assert_eq!(editor.buffer().text(), "one\n");
+
+    Ok(())
+}
+
+#[test]
+fn tabs() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = Editor::new(Buffer::from_text("one\n"));
+    press(&mut editor, "C-x t o")?;
+    assert_eq!(editor.message(), Some("There is only one tab"));
+
+    press(&mut editor, "C-x 3 C-x t 2")?;
+    assert_eq!(editor.tab(), (1, 2));
+    assert_eq!(editor.window_buffers().len(), 1);
+    editor.new_buffer();
+    press(&mut editor, "C-x t 2")?;
+    assert_eq!(editor.tab(), (2, 3));
+
+    let (grid, _) = editor.render(60, 6);
+    let screen: String = grid.to_string();
+    assert_eq!(
+        screen.lines().next(),
+        Some(" 1 *scratch*  2 *scratch*<2>  3 *scratch*<2>")
+    );
+
+    press(&mut editor, "C-x t o")?;
+    assert_eq!(editor.tab(), (0, 3));
+    assert_eq!(editor.buffer_name(), "*scratch*");
+    assert_eq!(editor.window_buffers().len(), 2);
+    press(&mut editor, "C-x t O C-x t 0")?;
+    assert_eq!(editor.tab(), (1, 2));
+    assert_eq!(editor.buffer_name(), "*scratch*<2>");
+    press(&mut editor, "C-x t 0")?;
+    assert_eq!(editor.tab(), (0, 1));
+    assert_eq!(editor.window_buffers().len(), 2);
+    press(&mut editor, "C-x t 0")?;
+    assert_eq!(editor.message(), Some("Cannot close the only tab"));
+
+    Ok(())
+}
+
+#[test]
+fn saved_layouts() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf = directory("layouts")?;
+    let path: PathBuf = directory.join("a.txt");
+    std::fs::write(&path, "first\nsecond\n")?;
+
+    let mut editor = Editor::new(Buffer::open(&path)?);
+    editor.set_data_directory(directory.join("data"));
+    press(&mut editor, "C-x 3 <down> <right> C-x o")?;
+    editor.new_buffer();
+    let text: String = editor.layout_text();
+    assert_eq!(
+        text,
+        format!(
+            "(right 50 0 1)\n1 0:0 0 0 *scratch*\n0 1:1 0 0 {}\n",
+            path.display()
+        )
+    );
+    editor.run_command("save-layout")?;
+
+    // A later session opens the files the windows showed
+    let mut editor = Editor::new(Buffer::new());
+    press(&mut editor, "C-x 2")?;
+    editor.set_data_directory(directory.join("data"));
+    editor.run_command("restore-layout")?;
+    assert_eq!(editor.layout_text(), text);
+    press(&mut editor, "C-x o")?;
+    assert_eq!(editor.buffer().text(), "first\nsecond\n");
+    assert_eq!(editor.cursor(), 7);
+
+    let e = editor
+        .apply_layout("(right 50 0 1)\n0 0:0 0 0 *scratch*\n")
+        .err()
+        .ok_or("expected an error")?;
+    assert_eq!(
+        e.to_string(),
+        "LAYOUT_ERROR: the windows do not match the layout"
+    );
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+//! Just enough JSON for the Language Server Protocol.
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys keep their order, so output is stable.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, Box<dyn std::error::Error>> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            offset: 0,
+            depth: 0,
+        };
+        let value: Json = parser.value()?;
+        parser.whitespace();
+        if parser.offset != text.len() {
+            return Err("JSON_ERROR: trailing characters".into());
+        }
+
+        Ok(value)
+    }
+
+    /// Builds an object from `(key, value)` pairs.
+    pub fn object<const N: usize>(entries: [(&str, Json); N]) -> Json {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(v) => v.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Follows a path of object keys.
+    pub fn pointer(&self, path: &[&str]) -> Option<&Json> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(v) if *v >= 0.0 && v.fract() == 0.0 => Some(*v as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(v) => write!(f, "{}", v),
+            Json::Number(v) if v.is_finite() => write!(f, "{}", v),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(v) => write_string(f, v),
+            Json::Array(v) => {
+                f.write_char('[')?;
+                for (i, item) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(v) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// How deeply arrays and objects may be nested, so that parsing cannot run
+/// out of stack.
+pub const MAX_NESTING: usize = 256;
+
+struct Parser<'a> {
+    text: &'a [u8],
+    offset: usize,
+    /// The arrays and objects the parser is inside.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while self
+            .text
+            .get(self.offset)
+            .is_some_and(|v| matches!(v, b' ' | b'\n' | b'\r' | b'\t'))
+        {
+            self.offset += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match self.text[self.offset..].starts_with(literal.as_bytes()) {
+            true => {
+                self.offset += literal.len();
+                Ok(())
+            }
+            false => Err(format!("JSON_ERROR: expected {} at {}", literal, self.offset).into()),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        self.whitespace();
+        match self.text.get(self.offset) {
+            Some(b'n') => self.expect("null").map(|_| Json::Null),
+            Some(b't') => self.expect("true").map(|_| Json::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[' | b'{') => {
+                if self.depth == MAX_NESTING {
+                    return Err(format!(
+                        "JSON_ERROR: nested deeper than {} levels at {}",
+                        MAX_NESTING, self.offset
+                    )
+                    .into());
+                }
+                self.depth += 1;
+                let result: Result<Json, Box<dyn std::error::Error>> = match self.text[self.offset]
+                {
+                    b'[' => self.array(),
+                    _ => self.object(),
+                };
+                self.depth -= 1;
+                result
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                let start: usize = self.offset;
+                while self
+                    .text
+                    .get(self.offset)
+                    .is_some_and(|v| matches!(v, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+                {
+                    self.offset += 1;
+                }
+                let number: &str = std::str::from_utf8(&self.text[start..self.offset])?;
+                Ok(Json::Number(number.parse()?))
+            }
+            _ => Err(format!("JSON_ERROR: unexpected input at {}", self.offset).into()),
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        self.offset += 1;
+        let mut result: Vec<Json> = Vec::new();
+        self.whitespace();
+        if self.text.get(self.offset) == Some(&b']') {
+            self.offset += 1;
+            return Ok(Json::Array(result));
+        }
+        loop {
+            result.push(self.value()?);
+            self.whitespace();
+            match self.text.get(self.offset) {
+                Some(b',') => self.offset += 1,
+                _ => break,
+            }
+        }
+        self.expect("]")?;
+        Ok(Json::Array(result))
+    }
+
+    fn object(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        self.offset += 1;
+        let mut result: Vec<(String, Json)> = Vec::new();
+        self.whitespace();
+        if self.text.get(self.offset) == Some(&b'}') {
+            self.offset += 1;
+            return Ok(Json::Object(result));
+        }
+        loop {
+            self.whitespace();
+            let key: String = self.string()?;
+            self.whitespace();
+            self.expect(":")?;
+            result.push((key, self.value()?));
+            self.whitespace();
+            match self.text.get(self.offset) {
+                Some(b',') => self.offset += 1,
+                _ => break,
+            }
+        }
+        self.expect("}")?;
+        Ok(Json::Object(result))
+    }
+
+    fn string(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.expect("\"")?;
+        let mut result: Vec<u8> = Vec::new();
+
+        loop {
+            let Some(&byte) = self.text.get(self.offset) else {
+                return Err("JSON_ERROR: unterminated string".into());
+            };
+            self.offset += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.text.get(self.offset) else {
+                        return Err("JSON_ERROR: unterminated string".into());
+                    };
+                    self.offset += 1;
+                    let c: char = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err("JSON_ERROR: invalid escape".into()),
+                    };
+                    let mut buffer: [u8; 4] = [0; 4];
+                    result.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                byte => result.push(byte),
+            }
+        }
+
+        Ok(String::from_utf8(result)?)
+    }
+
+    /// Reads the `XXXX` of `\uXXXX`, and a following low surrogate if needed.
+    fn unicode_escape(&mut self) -> Result<char, Box<dyn std::error::Error>> {
+        let high: u32 = self.hex()?;
+        let code: u32 = match high {
+            0xD800..=0xDBFF => {
+                self.expect("\\u")?;
+                let low: u32 = self.hex()?;
+                0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+            }
+            v => v,
+        };
+
+        char::from_u32(code).ok_or_else(|| "JSON_ERROR: invalid unicode escape".into())
+    }
+
+    fn hex(&mut self) -> Result<u32, Box<dyn std::error::Error>> {
+        let digits: &[u8] = self
+            .text
+            .get(self.offset..self.offset + 4)
+            .ok_or("JSON_ERROR: invalid unicode escape")?;
+        self.offset += 4;
+
+        Ok(u32::from_str_radix(std::str::from_utf8(digits)?, 16)?)
+    }
+}
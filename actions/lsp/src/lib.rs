@@ -0,0 +1,75 @@
+//! A Language Server Protocol server for `.core` files, run by `core lsp`.
+
+pub mod json;
+pub mod server;
+
+use json::Json;
+use server::Server;
+use std::io::{BufRead, Write};
+
+/// Serves one client over `reader` and `writer` until it exits, returning
+/// the exit code.
+pub fn run<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut server = Server::new();
+
+    while let Some(body) = read_body(&mut reader)? {
+        for response in server.handle_body(&body) {
+            write_message(&mut writer, &response)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+
+    // The client went away without asking
+    Ok(1)
+}
+
+/// Reads one `Content-Length` framed message, or `None` at the end of input.
+pub fn read_message<R: BufRead>(
+    reader: &mut R,
+) -> Result<Option<Json>, Box<dyn std::error::Error>> {
+    match read_body(reader)? {
+        Some(body) => Ok(Some(Json::parse(std::str::from_utf8(&body)?)?)),
+        None => Ok(None),
+    }
+}
+
+/// Reads the body of one `Content-Length` framed message, without parsing
+/// it, or `None` at the end of input.
+pub fn read_body<R: BufRead>(
+    reader: &mut R,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut length: Option<usize> = None;
+
+    loop {
+        let mut line: String = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line: &str = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse()?);
+            }
+        }
+    }
+
+    let length: usize = length.ok_or("LSP_ERROR: missing Content-Length")?;
+    let mut body: Vec<u8> = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(body))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Json) -> std::io::Result<()> {
+    let body: String = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
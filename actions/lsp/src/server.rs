@@ -0,0 +1,475 @@
+use crate::json::Json;
+use core_lang::checker::{self, Diagnostic, Severity};
+use core_lang::cst::{TokenKind, Tree, is_word_char};
+use core_lang::formatter::{FormatOptions, format};
+use core_lang::help::{self, Help, HelpKind};
+use core_lang::interpreter::Interpreter;
+use core_lang::object::Arity;
+use core_lang::parser;
+use core_lang::syntax::{Source, Span, Syntax, SyntaxKind};
+use std::collections::HashMap;
+
+/// The state of a language server session.
+///
+/// Feed it every message from the client with [`Server::handle`], and send
+/// back what it returns. This is all in memory, so tests can drive it
+/// directly instead of going through stdio.
+pub struct Server {
+    documents: HashMap<String, String>,
+    /// Special forms, standard procedures and other standard values.
+    builtins: Vec<Help>,
+    /// Procedures the editor defines for `init.core`, for the checker.
+    natives: Vec<(String, Arity)>,
+    shutdown: bool,
+    exit: Option<i32>,
+}
+
+/// Error codes of JSON-RPC.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+// Kinds of completion items
+const FUNCTION: usize = 3;
+const VARIABLE: usize = 6;
+const KEYWORD: usize = 14;
+const CONSTANT: usize = 21;
+
+/// A top-level definition of a document.
+struct Definition {
+    name: String,
+    /// The name being defined.
+    span: Span,
+    /// The whole `(define ...)` form.
+    form: Span,
+    procedure: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        let builtins: Vec<Help> = help::apropos(&Interpreter::new(), "");
+
+        Server {
+            documents: HashMap::new(),
+            builtins,
+            natives: core_editor::script::natives(),
+            shutdown: false,
+            exit: None,
+        }
+    }
+
+    /// The exit code, once the client has sent `exit`.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit
+    }
+
+    /// Like [`Server::handle`], for the body of a message as it was read. A
+    /// body which is not JSON gets a parse error, and the session goes on.
+    pub fn handle_body(&mut self, body: &[u8]) -> Vec<Json> {
+        let message: Result<Json, Box<dyn std::error::Error>> = std::str::from_utf8(body)
+            .map_err(Into::into)
+            .and_then(Json::parse);
+
+        match message {
+            Ok(message) => self.handle(&message),
+            Err(e) => vec![error(&Json::Null, PARSE_ERROR, &e.to_string())],
+        }
+    }
+
+    /// Handles one message, returning the responses and notifications to send.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method: &str = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params: &Json = message.get("params").unwrap_or(&Json::Null);
+
+        // Notifications have no id and get no response
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+        if self.shutdown {
+            return vec![error(id, INVALID_REQUEST, "the server is shutting down")];
+        }
+
+        let result: Result<Json, (i32, String)> = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => self.with_position(params, Server::hover),
+            "textDocument/definition" => self.with_position(params, Server::definition),
+            "textDocument/completion" => self.with_position(params, Server::completion),
+            "textDocument/formatting" => self.formatting(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        };
+
+        match result {
+            Ok(v) => vec![Json::object([
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id.clone()),
+                ("result", v),
+            ])],
+            Err((code, message)) => vec![error(id, code, &message)],
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri: String = params
+            .pointer(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        match method {
+            "exit" => {
+                self.exit = Some(if self.shutdown { 0 } else { 1 });
+                vec![]
+            }
+            "textDocument/didOpen" => {
+                let text: &str = params
+                    .pointer(&["textDocument", "text"])
+                    .and_then(Json::as_str)
+                    .unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didChange" => {
+                // Only full synchronisation is announced, so the last change is the text
+                let text: Option<&str> = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(<[Json]>::last)
+                    .and_then(|v| v.get("text"))
+                    .and_then(Json::as_str);
+                match text {
+                    Some(text) => {
+                        self.documents.insert(uri.clone(), text.to_string());
+                        vec![self.diagnostics(&uri)]
+                    }
+                    None => vec![],
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, vec![])]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn diagnostics(&self, uri: &str) -> Json {
+        let text: &str = self.documents.get(uri).map_or("", String::as_str);
+        let source: Source = Source::new(uri, text);
+        let diagnostics: Vec<Json> = checker::check_with(&source, &self.natives)
+            .iter()
+            .map(|v: &Diagnostic| {
+                Json::object([
+                    ("range", range(text, v.span)),
+                    (
+                        "severity",
+                        Json::from(match v.severity() {
+                            Severity::Error => 1,
+                            Severity::Warning => 2,
+                        }),
+                    ),
+                    ("code", Json::from(v.lint.code())),
+                    ("source", Json::from("core")),
+                    ("message", Json::from(v.message.as_str())),
+                ])
+            })
+            .collect();
+
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    /// Resolves the document and offset of a `TextDocumentPositionParams`.
+    fn with_position(
+        &self,
+        params: &Json,
+        f: fn(&Server, &str, &str, usize) -> Json,
+    ) -> Result<Json, (i32, String)> {
+        let uri: &str = params
+            .pointer(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .ok_or((INVALID_PARAMS, String::from("missing textDocument")))?;
+        let text: &str = self
+            .documents
+            .get(uri)
+            .ok_or((INVALID_PARAMS, format!("unknown document {}", uri)))?;
+        let line: Option<usize> = params
+            .pointer(&["position", "line"])
+            .and_then(Json::as_usize);
+        let character: Option<usize> = params
+            .pointer(&["position", "character"])
+            .and_then(Json::as_usize);
+        let (Some(line), Some(character)) = (line, character) else {
+            return Err((INVALID_PARAMS, String::from("missing position")));
+        };
+
+        Ok(f(self, uri, text, offset(text, line, character)))
+    }
+
+    fn hover(&self, _: &str, text: &str, offset: usize) -> Json {
+        let Some((name, span)) = word_at(text, offset) else {
+            return Json::Null;
+        };
+
+        let contents: String = if let Some(v) = definitions(text).iter().find(|v| v.name == name) {
+            let form: &str = &text[v.form.start..v.form.end];
+            format!("```core\n{}\n```", form.lines().next().unwrap_or_default())
+        } else {
+            let Some(help) = self.builtins.iter().find(|v| v.name == name) else {
+                return Json::Null;
+            };
+            let kind: String = match help.kind {
+                HelpKind::SpecialForm => "special form".to_string(),
+                HelpKind::Native(arity) => format!(
+                    "builtin procedure taking {} argument{}",
+                    arity,
+                    if arity == Arity::exact(1) { "" } else { "s" }
+                ),
+                _ => "builtin value".to_string(),
+            };
+            match &help.doc {
+                Some(doc) => format!("`{}`: {}\n\n{}", name, kind, doc),
+                None => format!("`{}`: {}", name, kind),
+            }
+        };
+
+        Json::object([
+            (
+                "contents",
+                Json::object([
+                    ("kind", Json::from("markdown")),
+                    ("value", Json::from(contents)),
+                ]),
+            ),
+            ("range", range(text, span)),
+        ])
+    }
+
+    fn definition(&self, uri: &str, text: &str, offset: usize) -> Json {
+        let Some((name, _)) = word_at(text, offset) else {
+            return Json::Null;
+        };
+
+        match definitions(text).iter().find(|v| v.name == name) {
+            Some(v) => Json::object([("uri", Json::from(uri)), ("range", range(text, v.span))]),
+            None => Json::Null,
+        }
+    }
+
+    fn completion(&self, _: &str, text: &str, offset: usize) -> Json {
+        // The part of the word before the cursor
+        let start: usize = text[..offset]
+            .rfind(|c: char| !is_word_char(c))
+            .map_or(0, |v| v + 1);
+        let prefix: &str = &text[start..offset];
+
+        let mut items: Vec<(String, usize)> = Vec::new();
+        items.extend(self.builtins.iter().map(|v| match v.kind {
+            HelpKind::SpecialForm => (v.name.clone(), KEYWORD),
+            HelpKind::Native(_) => (v.name.clone(), FUNCTION),
+            _ => (v.name.clone(), CONSTANT),
+        }));
+        items.extend(definitions(text).into_iter().map(|v| match v.procedure {
+            true => (v.name, FUNCTION),
+            false => (v.name, VARIABLE),
+        }));
+        items.retain(|(name, _)| name.starts_with(prefix));
+        items.sort();
+        items.dedup_by(|a, b| a.0 == b.0);
+
+        Json::Array(
+            items
+                .into_iter()
+                .map(|(label, kind)| {
+                    Json::object([("label", Json::from(label)), ("kind", Json::from(kind))])
+                })
+                .collect(),
+        )
+    }
+
+    fn formatting(&self, params: &Json) -> Result<Json, (i32, String)> {
+        let uri: &str = params
+            .pointer(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .ok_or((INVALID_PARAMS, String::from("missing textDocument")))?;
+        let text: &str = self
+            .documents
+            .get(uri)
+            .ok_or((INVALID_PARAMS, format!("unknown document {}", uri)))?;
+
+        let options = FormatOptions {
+            indent: params
+                .pointer(&["options", "tabSize"])
+                .and_then(Json::as_usize)
+                .unwrap_or(FormatOptions::default().indent),
+            ..FormatOptions::default()
+        };
+        // Nothing to do for text which does not parse; diagnostics report it
+        let Ok(formatted) = format(text, &options) else {
+            return Ok(Json::Null);
+        };
+        if formatted == text {
+            return Ok(Json::Array(vec![]));
+        }
+
+        Ok(Json::Array(vec![Json::object([
+            ("range", range(text, Span::new(0, text.len()))),
+            ("newText", Json::from(formatted)),
+        ])]))
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                // Full text on every change
+                ("textDocumentSync", Json::from(1)),
+                ("hoverProvider", Json::from(true)),
+                ("definitionProvider", Json::from(true)),
+                ("completionProvider", Json::object([])),
+                ("documentFormattingProvider", Json::from(true)),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", Json::from("core-lsp")),
+                ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+    ])
+}
+
+fn error(id: &Json, code: i32, message: &str) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("id", id.clone()),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code as f64)),
+                ("message", Json::from(message)),
+            ]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Json::object([
+                ("uri", Json::from(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+/// Converts an LSP position, counted in UTF-16 code units, into a byte offset.
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let start: usize = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((v, _)) => v + 1,
+            None => return text.len(),
+        },
+    };
+
+    let mut units: usize = 0;
+    for (i, c) in text[start..].char_indices() {
+        if units >= character || c == '\n' {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+/// Converts a byte offset into an LSP position.
+fn position(text: &str, offset: usize) -> Json {
+    let before: &str = &text[..offset.min(text.len())];
+    let line: usize = before.matches('\n').count();
+    let start: usize = before.rfind('\n').map_or(0, |v| v + 1);
+    let character: usize = before[start..].encode_utf16().count();
+
+    Json::object([
+        ("line", Json::from(line)),
+        ("character", Json::from(character)),
+    ])
+}
+
+fn range(text: &str, span: Span) -> Json {
+    Json::object([
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end)),
+    ])
+}
+
+/// The word touching `offset`, with its span.
+fn word_at(text: &str, offset: usize) -> Option<(String, Span)> {
+    let tree: Tree = Tree::parse(text);
+    tree.tokens()
+        .into_iter()
+        .find(|(span, token)| {
+            token.kind == TokenKind::Word && span.start <= offset && offset <= span.end
+        })
+        .map(|(span, token)| (token.text.clone(), span))
+}
+
+/// The top-level definitions of a document, if it parses.
+fn definitions(text: &str) -> Vec<Definition> {
+    let Ok(program) = parser::parse_spanned(text) else {
+        return vec![];
+    };
+
+    let mut result: Vec<Definition> = Vec::new();
+    for form in &program {
+        let Some([head, target, rest @ ..]) = form.as_sexpr() else {
+            continue;
+        };
+        if head.as_word().as_deref() != Some("define") {
+            continue;
+        }
+
+        let (name, procedure): (&Syntax, bool) = match &target.kind {
+            SyntaxKind::SExpression(v) => match v.first() {
+                Some(name) => (name, true),
+                None => continue,
+            },
+            _ => {
+                let lambda: bool = rest
+                    .first()
+                    .and_then(Syntax::as_sexpr)
+                    .and_then(<[Syntax]>::first)
+                    .and_then(Syntax::as_word)
+                    .is_some_and(|v| v == "lambda");
+                (target, lambda)
+            }
+        };
+        if let Some(v) = name.as_word() {
+            result.push(Definition {
+                name: v,
+                span: name.span,
+                form: form.span,
+                procedure,
+            });
+        }
+    }
+
+    result
+}
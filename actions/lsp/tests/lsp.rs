@@ -0,0 +1,299 @@
+use core_lsp::json::Json;
+use core_lsp::server::Server;
+use std::io::Write;
+
+const URI: &str = "file:///init.core";
+
+/// A scripted client, numbering its requests.
+struct Client {
+    server: Server,
+    next_id: usize,
+}
+
+impl Client {
+    fn new() -> Self {
+        let mut client = Client {
+            server: Server::new(),
+            next_id: 0,
+        };
+        let result: Json = client.request("initialize", Json::object([]));
+        assert!(result.pointer(&["capabilities", "hoverProvider"]).is_some());
+        client.notify("initialized", Json::object([]));
+
+        client
+    }
+
+    fn request(&mut self, method: &str, params: Json) -> Json {
+        self.next_id += 1;
+        let mut responses: Vec<Json> = self.server.handle(&Json::object([
+            ("jsonrpc", Json::from("2.0")),
+            ("id", Json::from(self.next_id)),
+            ("method", Json::from(method)),
+            ("params", params),
+        ]));
+        assert_eq!(responses.len(), 1);
+        let response: Json = responses.remove(0);
+        assert_eq!(response.get("id"), Some(&Json::from(self.next_id)));
+
+        match response.get("error") {
+            Some(error) => error.clone(),
+            None => response.get("result").cloned().unwrap_or(Json::Null),
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> Vec<Json> {
+        self.server.handle(&Json::object([
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from(method)),
+            ("params", params),
+        ]))
+    }
+
+    fn open(&mut self, text: &str) -> Vec<Json> {
+        self.notify(
+            "textDocument/didOpen",
+            Json::object([(
+                "textDocument",
+                Json::object([
+                    ("uri", Json::from(URI)),
+                    ("languageId", Json::from("core")),
+                    ("version", Json::from(1)),
+                    ("text", Json::from(text)),
+                ]),
+            )]),
+        )
+    }
+
+    fn at(&mut self, method: &str, line: usize, character: usize) -> Json {
+        self.request(
+            method,
+            Json::object([
+                ("textDocument", Json::object([("uri", Json::from(URI))])),
+                (
+                    "position",
+                    Json::object([
+                        ("line", Json::from(line)),
+                        ("character", Json::from(character)),
+                    ]),
+                ),
+            ]),
+        )
+    }
+}
+
+fn position(line: usize, character: usize) -> Json {
+    Json::object([
+        ("line", Json::from(line)),
+        ("character", Json::from(character)),
+    ])
+}
+
+#[test]
+fn diagnostics() {
+    let mut client = Client::new();
+
+    let notifications: Vec<Json> = client.open("(define (main)\n  (foo))");
+    assert_eq!(notifications.len(), 1);
+    let diagnostics: &Json = notifications[0]
+        .pointer(&["params", "diagnostics"])
+        .unwrap();
+    assert_eq!(
+        diagnostics,
+        &Json::Array(vec![Json::object([
+            (
+                "range",
+                Json::object([("start", position(1, 3)), ("end", position(1, 6))])
+            ),
+            ("severity", Json::from(1)),
+            ("code", Json::from("C001")),
+            ("source", Json::from("core")),
+            ("message", Json::from("unbound word `foo`")),
+        ])])
+    );
+
+    let notifications: Vec<Json> = client.notify(
+        "textDocument/didChange",
+        Json::object([
+            ("textDocument", Json::object([("uri", Json::from(URI))])),
+            (
+                "contentChanges",
+                Json::Array(vec![Json::object([(
+                    "text",
+                    // Procedures of the editor are known too
+                    Json::from(
+                        "(define (main) 1)\n(bind-key \"C-c h\" (lambda () (insert \"hi\")))",
+                    ),
+                )])]),
+            ),
+        ]),
+    );
+    assert_eq!(
+        notifications[0].pointer(&["params", "diagnostics"]),
+        Some(&Json::Array(vec![]))
+    );
+}
+
+#[test]
+fn hover_and_definition() {
+    let mut client = Client::new();
+    client.open("(define (twice x) (* 2 x))\n(define (main) (twice (car '(1))))");
+
+    let hover: Json = client.at("textDocument/hover", 1, 17);
+    assert_eq!(
+        hover.pointer(&["contents", "value"]),
+        Some(&Json::from("```core\n(define (twice x) (* 2 x))\n```"))
+    );
+
+    let hover: Json = client.at("textDocument/hover", 1, 23);
+    assert_eq!(
+        hover.pointer(&["contents", "value"]),
+        Some(&Json::from(
+            "`car`: builtin procedure taking 1 argument\n\nThe first element of a list."
+        ))
+    );
+
+    let definition: Json = client.at("textDocument/definition", 1, 17);
+    assert_eq!(definition.get("uri"), Some(&Json::from(URI)));
+    assert_eq!(
+        definition.get("range"),
+        Some(&Json::object([
+            ("start", position(0, 9)),
+            ("end", position(0, 14))
+        ]))
+    );
+
+    // Nothing to find on numbers or whitespace
+    assert_eq!(client.at("textDocument/definition", 0, 21), Json::Null);
+    assert_eq!(client.at("textDocument/hover", 0, 17), Json::Null);
+}
+
+#[test]
+fn completion() {
+    let mut client = Client::new();
+    client.open("(define (display-all xs) xs)\n(define (main) (di))");
+
+    let items: Json = client.at("textDocument/completion", 1, 18);
+    let labels: Vec<&str> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|v| v.get("label").and_then(Json::as_str))
+        .collect();
+    assert_eq!(labels, vec!["display-all"]);
+
+    let items: Json = client.at("textDocument/completion", 1, 16);
+    assert!(items.as_array().unwrap().len() > 20);
+}
+
+#[test]
+fn formatting() {
+    let mut client = Client::new();
+    client.open("(define   foo\n 1)");
+
+    let edits: Json = client.request(
+        "textDocument/formatting",
+        Json::object([
+            ("textDocument", Json::object([("uri", Json::from(URI))])),
+            (
+                "options",
+                Json::object([
+                    ("tabSize", Json::from(2)),
+                    ("insertSpaces", Json::from(true)),
+                ]),
+            ),
+        ]),
+    );
+    assert_eq!(
+        edits,
+        Json::Array(vec![Json::object([
+            (
+                "range",
+                Json::object([("start", position(0, 0)), ("end", position(1, 3))])
+            ),
+            ("newText", Json::from("(define foo 1)\n")),
+        ])])
+    );
+}
+
+#[test]
+fn lifecycle() {
+    let mut client = Client::new();
+
+    let error: Json = client.request("workspace/symbol", Json::object([]));
+    assert_eq!(error.get("code"), Some(&Json::Number(-32601.0)));
+
+    assert_eq!(client.request("shutdown", Json::Null), Json::Null);
+    let error: Json = client.at("textDocument/hover", 0, 0);
+    assert_eq!(error.get("code"), Some(&Json::Number(-32600.0)));
+
+    client.notify("exit", Json::Null);
+    assert_eq!(client.server.exit_code(), Some(0));
+}
+
+#[test]
+fn stdio() -> Result<(), Box<dyn std::error::Error>> {
+    let mut input: Vec<u8> = Vec::new();
+    let deep: String = "[".repeat(100_000);
+    for message in [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        // Bodies which are not JSON are answered, and the session goes on
+        r#"{"jsonrpc":"#,
+        &deep,
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ] {
+        write!(
+            input,
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        )?;
+    }
+
+    let mut output: Vec<u8> = Vec::new();
+    let code: i32 = core_lsp::run(input.as_slice(), &mut output)?;
+    assert_eq!(code, 0);
+
+    let mut reader: &[u8] = &output;
+    let first: Json = core_lsp::read_message(&mut reader)?.ok_or("no response")?;
+    assert_eq!(first.get("id"), Some(&Json::from(1)));
+    for _ in 0..2 {
+        let error: Json = core_lsp::read_message(&mut reader)?.ok_or("no response")?;
+        assert_eq!(error.get("id"), Some(&Json::Null));
+        assert_eq!(
+            error.pointer(&["error", "code"]),
+            Some(&Json::Number(-32700.0))
+        );
+    }
+    let second: Json = core_lsp::read_message(&mut reader)?.ok_or("no response")?;
+    assert_eq!(
+        second,
+        Json::parse(r#"{"jsonrpc":"2.0","id":2,"result":null}"#)?
+    );
+    assert!(core_lsp::read_message(&mut reader)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn json() -> Result<(), Box<dyn std::error::Error>> {
+    let text: &str = r#"{"a":[1,-2.5,true,null],"b":"\"\\\né😀"}"#;
+    let value: Json = Json::parse(text)?;
+    assert_eq!(value.pointer(&["b"]), Some(&Json::from("\"\\\né😀")));
+    assert_eq!(Json::parse(&value.to_string())?, value);
+    assert!(Json::parse("{\"a\":}").is_err());
+
+    let nested: String = format!("{}{}", "[".repeat(256), "]".repeat(256));
+    assert!(Json::parse(&nested).is_ok());
+    let e = Json::parse(&format!("[{}]", nested))
+        .err()
+        .ok_or("expected an error")?;
+    assert!(
+        e.to_string()
+            .starts_with("JSON_ERROR: nested deeper than 256 levels"),
+        "{}",
+        e
+    );
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use core_lang::evaluator;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = evaluator::eval(text);
+});
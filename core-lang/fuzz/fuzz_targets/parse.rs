@@ -0,0 +1,14 @@
+#![no_main]
+
+use core_lang::cst::Tree;
+use core_lang::parser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = parser::parse(text);
+    assert_eq!(Tree::parse(text).print(), text);
+});
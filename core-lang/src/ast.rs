@@ -1,3 +1,5 @@
+use crate::syntax::escape_word;
+
 #[derive(Debug)]
 pub struct AST(pub Vec<Constant>);
 
@@ -55,16 +57,17 @@ impl Constant {
 impl Value {
     pub fn car(&self) -> Result<Value, Box<dyn std::error::Error>> {
         match self {
-            Value::SExpression(v) => Ok(v[0].clone()),
-            Value::List(v) => Ok(v[0].clone()),
-            _ => panic!(),
+            Value::SExpression(v) | Value::List(v) => {
+                Ok(v.first().ok_or("car: empty list")?.clone())
+            }
+            _ => Err("car: expected a list".into()),
         }
     }
 
     pub fn cdr(&self) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
         let mut value: Vec<Value> = match self {
             Value::SExpression(v) => v.clone(),
-            _ => todo!(),
+            _ => return Err("cdr: expected an S-expression".into()),
         };
 
         value.reverse();
@@ -81,3 +84,71 @@ impl Value {
         )
     }
 }
+
+impl std::fmt::Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(define {} {})", self.name, self.value)
+    }
+}
+
+impl std::fmt::Display for Value {
+    /// Prints the value as source text, which parses back into the same
+    /// value. Strings holding a `"` are the exception, as strings have no
+    /// escapes to write one with.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn join(f: &mut std::fmt::Formatter<'_>, values: &[Value]) -> std::fmt::Result {
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{}", v)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Value::SExpression(v) => {
+                write!(f, "(")?;
+                join(f, v)?;
+                write!(f, ")")
+            }
+            Value::Constant(v) => write!(f, "{}", v),
+            Value::List(v) => {
+                write!(f, "'(")?;
+                join(f, v)?;
+                write!(f, ")")
+            }
+            Value::Number(v) => write!(f, "{}", v),
+            Value::Lambda((args, expr)) => {
+                write!(f, "(lambda ")?;
+                join(f, args)?;
+                write!(f, " ")?;
+                join(f, expr)?;
+                write!(f, ")")
+            }
+            Value::String(v) => write!(f, "\"{}\"", v),
+            Value::Boolean(Boolean::T) => write!(f, "t"),
+            Value::Boolean(Boolean::Nil) => write!(f, "nil"),
+            Value::Word(v) => write!(f, "{}", escape_word(v)),
+            Value::BuiltinWord(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl std::fmt::Display for BuiltinWord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuiltinWord::Main => write!(f, "main"),
+            BuiltinWord::Cli => write!(f, "cli"),
+            BuiltinWord::Cons => write!(f, "cons"),
+            BuiltinWord::Car => write!(f, "car"),
+            BuiltinWord::Cdr => write!(f, "cdr"),
+            BuiltinWord::If => write!(f, "if"),
+            BuiltinWord::Lambda => write!(f, "lambda"),
+            BuiltinWord::Begin => write!(f, "begin"),
+            BuiltinWord::Define => write!(f, "define"),
+            BuiltinWord::DefineSyntax => write!(f, "define-syntax"),
+            BuiltinWord::CallCc => write!(f, "call/cc"),
+        }
+    }
+}
@@ -0,0 +1,637 @@
+use crate::debugger::Debugger;
+use crate::help::{self, Help};
+use crate::interpreter::Interpreter;
+use crate::object::{Arity, Condition, Object};
+use crate::sandbox::Capability;
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
+
+type NativeResult = Result<Object, Box<dyn std::error::Error>>;
+
+/// Defines the standard procedures in the global environment of `interpreter`.
+pub fn install(interpreter: &mut Interpreter) {
+    interpreter.global().define("t", Object::Boolean(true));
+    interpreter.global().define("nil", Object::nil());
+
+    install_numbers(interpreter);
+    install_lists(interpreter);
+    install_predicates(interpreter);
+    install_strings(interpreter);
+    install_exceptions(interpreter);
+    install_testing(interpreter);
+    install_system(interpreter);
+    install_debugging(interpreter);
+    install_help(interpreter);
+
+    interpreter.define_native(
+        "apply",
+        Arity::at_least(2),
+        "Calls a procedure with the given arguments, the last of which is a list.",
+        |interp, mut args| {
+            let list: Vec<Object> = list(&args.pop().unwrap_or(Object::Unspecified))?.to_vec();
+            let function: Object = args.remove(0);
+            args.extend(list);
+
+            Ok(interp.apply(&function, args)?)
+        },
+    );
+}
+
+fn install_numbers(interpreter: &mut Interpreter) {
+    interpreter.define_native("+", Arity::at_least(0), "Adds numbers.", |_, args| {
+        fold(&args, 0, |a, b| a.checked_add(b))
+    });
+    interpreter.define_native("*", Arity::at_least(0), "Multiplies numbers.", |_, args| {
+        fold(&args, 1, |a, b| a.checked_mul(b))
+    });
+    interpreter.define_native(
+        "-",
+        Arity::at_least(1),
+        "Subtracts the other numbers from the first, or negates a single one.",
+        |_, args| match args.as_slice() {
+            [v] => Ok(Object::Number(
+                number(v)?.checked_neg().ok_or("integer overflow")?,
+            )),
+            [first, rest @ ..] => fold(rest, number(first)?, |a, b| a.checked_sub(b)),
+            [] => unreachable!(),
+        },
+    );
+    interpreter.define_native(
+        "/",
+        Arity::at_least(2),
+        "Divides the first number by the others, rounding towards zero.",
+        |_, args| {
+            let first: i64 = number(&args[0])?;
+            fold(&args[1..], first, |a, b| a.checked_div(b))
+        },
+    );
+    interpreter.define_native(
+        "modulo",
+        Arity::exact(2),
+        "The remainder of dividing two numbers, with the sign of the divisor.",
+        |_, args| {
+            let value: i64 = number(&args[0])?
+                .checked_rem_euclid(number(&args[1])?)
+                .ok_or("division by zero")?;
+
+            Ok(Object::Number(value))
+        },
+    );
+
+    compare(
+        interpreter,
+        "=",
+        "Whether all numbers are equal.",
+        |a, b| a == b,
+    );
+    compare(
+        interpreter,
+        "<",
+        "Whether the numbers are increasing.",
+        |a, b| a < b,
+    );
+    compare(
+        interpreter,
+        ">",
+        "Whether the numbers are decreasing.",
+        |a, b| a > b,
+    );
+    compare(
+        interpreter,
+        "<=",
+        "Whether the numbers are non-decreasing.",
+        |a, b| a <= b,
+    );
+    compare(
+        interpreter,
+        ">=",
+        "Whether the numbers are non-increasing.",
+        |a, b| a >= b,
+    );
+}
+
+fn install_lists(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "list",
+        Arity::at_least(0),
+        "Makes a list of its arguments.",
+        |_, args| Ok(Object::list(args)),
+    );
+    interpreter.define_native(
+        "cons",
+        Arity::exact(2),
+        "Makes a list of a value followed by the elements of a list.",
+        |_, args| {
+            let mut result: Vec<Object> = vec![args[0].clone()];
+            result.extend_from_slice(list(&args[1])?);
+
+            Ok(Object::list(result))
+        },
+    );
+    interpreter.define_native(
+        "car",
+        Arity::exact(1),
+        "The first element of a list.",
+        |_, args| {
+            let list: &[Object] = list(&args[0])?;
+
+            Ok(list.first().ok_or("empty list")?.clone())
+        },
+    );
+    interpreter.define_native(
+        "cdr",
+        Arity::exact(1),
+        "A list without its first element.",
+        |_, args| {
+            let list: &[Object] = list(&args[0])?;
+            if list.is_empty() {
+                return Err("empty list".into());
+            }
+
+            Ok(Object::list(list[1..].to_vec()))
+        },
+    );
+    interpreter.define_native(
+        "length",
+        Arity::exact(1),
+        "The number of elements of a list.",
+        |_, args| Ok(Object::Number(list(&args[0])?.len() as i64)),
+    );
+    interpreter.define_native("append", Arity::at_least(0), "Joins lists.", |_, args| {
+        let mut result: Vec<Object> = Vec::new();
+        for arg in &args {
+            result.extend_from_slice(list(arg)?);
+        }
+
+        Ok(Object::list(result))
+    });
+    interpreter.define_native(
+        "reverse",
+        Arity::exact(1),
+        "A list in the opposite order.",
+        |_, args| {
+            let mut result: Vec<Object> = list(&args[0])?.to_vec();
+            result.reverse();
+
+            Ok(Object::list(result))
+        },
+    );
+    interpreter.define_native(
+        "map",
+        Arity::exact(2),
+        "Calls a procedure on each element of a list, collecting the results.",
+        |interp, args| {
+            let mut result: Vec<Object> = Vec::new();
+            for v in list(&args[1])? {
+                result.push(interp.apply(&args[0], vec![v.clone()])?);
+            }
+
+            Ok(Object::list(result))
+        },
+    );
+}
+
+fn install_predicates(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "not",
+        Arity::exact(1),
+        "Whether the argument is nil.",
+        |_, args| Ok(Object::Boolean(!args[0].is_true())),
+    );
+    interpreter.define_native(
+        "eq?",
+        Arity::exact(2),
+        "Whether two values are the same object.",
+        |_, args| Ok(Object::Boolean(args[0].is_eq(&args[1]))),
+    );
+    interpreter.define_native(
+        "equal?",
+        Arity::exact(2),
+        "Whether two values have the same structure.",
+        |_, args| Ok(Object::Boolean(args[0] == args[1])),
+    );
+
+    predicate(
+        interpreter,
+        "null?",
+        "Whether the argument is the empty list.",
+        |v| matches!(v, Object::List(v) if v.is_empty()),
+    );
+    predicate(
+        interpreter,
+        "pair?",
+        "Whether the argument is a non-empty list.",
+        |v| matches!(v, Object::List(v) if !v.is_empty()),
+    );
+    predicate(
+        interpreter,
+        "list?",
+        "Whether the argument is a list.",
+        |v| matches!(v, Object::List(_)),
+    );
+    predicate(
+        interpreter,
+        "number?",
+        "Whether the argument is a number.",
+        |v| matches!(v, Object::Number(_)),
+    );
+    predicate(
+        interpreter,
+        "string?",
+        "Whether the argument is a string.",
+        |v| matches!(v, Object::String(_)),
+    );
+    predicate(
+        interpreter,
+        "symbol?",
+        "Whether the argument is a symbol.",
+        |v| matches!(v, Object::Symbol(_)),
+    );
+    predicate(
+        interpreter,
+        "boolean?",
+        "Whether the argument is t or nil.",
+        |v| matches!(v, Object::Boolean(_)),
+    );
+    predicate(
+        interpreter,
+        "procedure?",
+        "Whether the argument can be called.",
+        Object::is_procedure,
+    );
+}
+
+fn install_strings(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "string-append",
+        Arity::at_least(0),
+        "Joins strings.",
+        |_, args| {
+            let mut result: String = String::new();
+            for arg in &args {
+                result.push_str(string(arg)?);
+            }
+
+            Ok(Object::string(&result))
+        },
+    );
+    interpreter.define_native(
+        "number->string",
+        Arity::exact(1),
+        "The decimal representation of a number.",
+        |_, args| Ok(Object::string(&number(&args[0])?.to_string())),
+    );
+    interpreter.define_native(
+        "symbol->string",
+        Arity::exact(1),
+        "The name of a symbol.",
+        |_, args| match &args[0] {
+            Object::Symbol(v) => Ok(Object::String(v.clone())),
+            v => Err(format!("expected symbol, got {}", v.type_name()).into()),
+        },
+    );
+    interpreter.define_native(
+        "string->symbol",
+        Arity::exact(1),
+        "The symbol with a string as its name.",
+        |_, args| Ok(Object::symbol(string(&args[0])?)),
+    );
+
+    // Lengths and indices count Unicode scalar values, as in R7RS
+    interpreter.define_native(
+        "string-length",
+        Arity::range(1, 2),
+        "The number of characters in a string, or of graphemes with (quote graphemes).",
+        |_, args| {
+            let s: &str = string(&args[0])?;
+            let length: usize = match args.get(1).map(name).transpose()? {
+                None | Some("characters") => s.chars().count(),
+                Some("graphemes") => s.graphemes(true).count(),
+                Some(v) => {
+                    return Err(format!("expected characters or graphemes, got {}", v).into());
+                }
+            };
+
+            Ok(Object::Number(length as i64))
+        },
+    );
+    interpreter.define_native(
+        "substring",
+        Arity::range(2, 3),
+        "The characters of a string from a start index up to an end index.",
+        |_, args| {
+            let s: &str = string(&args[0])?;
+            let start: usize = index(&args[1])?;
+            let end: usize = match args.get(2) {
+                Some(v) => index(v)?,
+                None => s.chars().count(),
+            };
+            if start > end || end > s.chars().count() {
+                return Err(format!("indices {} to {} are out of range", start, end).into());
+            }
+
+            Ok(Object::string(
+                &s.chars().skip(start).take(end - start).collect::<String>(),
+            ))
+        },
+    );
+    interpreter.define_native(
+        "string-upcase",
+        Arity::exact(1),
+        "A string in upper case.",
+        |_, args| Ok(Object::string(&string(&args[0])?.to_uppercase())),
+    );
+    interpreter.define_native(
+        "string-downcase",
+        Arity::exact(1),
+        "A string in lower case.",
+        |_, args| Ok(Object::string(&string(&args[0])?.to_lowercase())),
+    );
+    interpreter.define_native(
+        "string-graphemes",
+        Arity::exact(1),
+        "The user-perceived characters of a string, as a list of strings.",
+        |_, args| {
+            let graphemes: Vec<Object> = string(&args[0])?
+                .graphemes(true)
+                .map(Object::string)
+                .collect();
+
+            Ok(Object::list(graphemes))
+        },
+    );
+}
+
+fn install_testing(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "assert-equal",
+        Arity::exact(2),
+        "Fails the running test unless both arguments are equal?.",
+        |_, args| match args[0] == args[1] {
+            true => Ok(Object::Unspecified),
+            false => Err(format!("expected {}, got {}", args[0], args[1]).into()),
+        },
+    );
+}
+
+fn install_exceptions(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "raise",
+        Arity::exact(1),
+        "Raises an object. Handlers may not return to the raise.",
+        |interp, mut args| Ok(interp.raise(args.remove(0), false)?),
+    );
+    interpreter.define_native(
+        "raise-continuable",
+        Arity::exact(1),
+        "Raises an object. The value of the handler is returned.",
+        |interp, mut args| Ok(interp.raise(args.remove(0), true)?),
+    );
+    interpreter.define_native(
+        "error",
+        Arity::at_least(1),
+        "Raises an error object with a message and irritants.",
+        |interp, mut args| {
+            let message: String = string(&args.remove(0))?.to_string();
+            let condition: Condition = Condition::new(&message, args, Some(interp.span()));
+
+            Ok(interp.raise(Object::Condition(Rc::new(condition)), false)?)
+        },
+    );
+    interpreter.define_native(
+        "with-exception-handler",
+        Arity::exact(2),
+        "Calls the second procedure with the first installed as a handler.",
+        |interp, args| {
+            if !args[0].is_procedure() || !args[1].is_procedure() {
+                return Err("expected two procedures".into());
+            }
+
+            Ok(interp.with_exception_handler(args[0].clone(), &args[1])?)
+        },
+    );
+
+    predicate(
+        interpreter,
+        "error-object?",
+        "Whether the argument was created by error.",
+        |v| matches!(v, Object::Condition(_)),
+    );
+    interpreter.define_native(
+        "error-object-message",
+        Arity::exact(1),
+        "The message of an error object.",
+        |_, args| Ok(Object::string(&condition(&args[0])?.message)),
+    );
+    interpreter.define_native(
+        "error-object-irritants",
+        Arity::exact(1),
+        "The irritants of an error object.",
+        |_, args| Ok(Object::list(condition(&args[0])?.irritants.clone())),
+    );
+    interpreter.define_native(
+        "error-object-span",
+        Arity::exact(1),
+        "The start and end offsets of where an error object was raised.",
+        |_, args| match condition(&args[0])?.span {
+            Some(v) => Ok(Object::list(vec![
+                Object::Number(v.start as i64),
+                Object::Number(v.end as i64),
+            ])),
+            None => Ok(Object::nil()),
+        },
+    );
+}
+
+fn install_system(interpreter: &mut Interpreter) {
+    let fs: Capability = Capability::Filesystem;
+    interpreter.define_privileged(
+        "read-file",
+        Arity::exact(1),
+        fs,
+        "The contents of a file. Needs the filesystem capability.",
+        |_, args| Ok(Object::string(&std::fs::read_to_string(string(&args[0])?)?)),
+    );
+    interpreter.define_privileged(
+        "write-file",
+        Arity::exact(2),
+        fs,
+        "Replaces the contents of a file. Needs the filesystem capability.",
+        |_, args| {
+            std::fs::write(string(&args[0])?, string(&args[1])?)?;
+
+            Ok(Object::Unspecified)
+        },
+    );
+    interpreter.define_privileged(
+        "file-exists?",
+        Arity::exact(1),
+        fs,
+        "Whether a path exists. Needs the filesystem capability.",
+        |_, args| {
+            let path = std::path::Path::new(string(&args[0])?);
+
+            Ok(Object::Boolean(path.exists()))
+        },
+    );
+
+    // (run-process "git" '("status" "--short")) returns what the program printed
+    interpreter.define_privileged(
+        "run-process",
+        Arity::range(1, 2),
+        Capability::Process,
+        "Runs a program with a list of arguments and returns its output. Needs the process capability.",
+        |_, args| {
+            let mut command = std::process::Command::new(string(&args[0])?);
+            if let Some(v) = args.get(1) {
+                for arg in list(v)? {
+                    command.arg(string(arg)?);
+                }
+            }
+
+            let output = command.output()?;
+            if !output.status.success() {
+                return Err(format!("exited with {}", output.status).into());
+            }
+
+            Ok(Object::string(&String::from_utf8_lossy(&output.stdout)))
+        },
+    );
+}
+
+fn install_debugging(interpreter: &mut Interpreter) {
+    // (trace (quote name) ...) logs every call to and return from the named procedures
+    interpreter.define_native(
+        "trace",
+        Arity::at_least(0),
+        "Logs every call to and return from the named procedures.",
+        |interp, args| {
+            if interp.debugger_mut().is_none() {
+                interp.attach_debugger(Debugger::default());
+            }
+
+            let mut names: Vec<&str> = Vec::new();
+            for arg in &args {
+                names.push(name(arg)?);
+            }
+            if let Some(debugger) = interp.debugger_mut() {
+                names.iter().for_each(|v| debugger.trace(v));
+            }
+
+            Ok(Object::Unspecified)
+        },
+    );
+    interpreter.define_native(
+        "untrace",
+        Arity::at_least(0),
+        "Stops tracing the named procedures.",
+        |interp, args| {
+            let mut names: Vec<&str> = Vec::new();
+            for arg in &args {
+                names.push(name(arg)?);
+            }
+            if let Some(debugger) = interp.debugger_mut() {
+                names.iter().for_each(|v| debugger.untrace(v));
+            }
+
+            Ok(Object::Unspecified)
+        },
+    );
+}
+
+fn install_help(interpreter: &mut Interpreter) {
+    // (describe (quote map)) returns the documentation of map
+    interpreter.define_native(
+        "describe",
+        Arity::exact(1),
+        "The documentation of a name, with where it was defined.",
+        |interp, args| {
+            let name: &str = name(&args[0])?;
+            let help: Help =
+                help::describe(interp, name).ok_or(format!("unbound variable {}", name))?;
+
+            Ok(Object::string(&help.to_string()))
+        },
+    );
+    interpreter.define_native(
+        "apropos",
+        Arity::exact(1),
+        "The names of bindings and special forms containing a string.",
+        |interp, args| {
+            let names: Vec<Object> = help::apropos(interp, name(&args[0])?)
+                .iter()
+                .map(|v| Object::symbol(&v.name))
+                .collect();
+
+            Ok(Object::list(names))
+        },
+    );
+}
+
+fn predicate(interpreter: &mut Interpreter, name: &str, doc: &str, test: fn(&Object) -> bool) {
+    interpreter.define_native(name, Arity::exact(1), doc, move |_, args| {
+        Ok(Object::Boolean(test(&args[0])))
+    });
+}
+
+fn compare(interpreter: &mut Interpreter, name: &str, doc: &str, test: fn(i64, i64) -> bool) {
+    interpreter.define_native(name, Arity::at_least(1), doc, move |_, args| {
+        for pair in args.windows(2) {
+            if !test(number(&pair[0])?, number(&pair[1])?) {
+                return Ok(Object::nil());
+            }
+        }
+
+        Ok(Object::Boolean(true))
+    });
+}
+
+fn fold(args: &[Object], init: i64, f: fn(i64, i64) -> Option<i64>) -> NativeResult {
+    let mut result: i64 = init;
+    for arg in args {
+        result = f(result, number(arg)?).ok_or("integer overflow or division by zero")?;
+    }
+
+    Ok(Object::Number(result))
+}
+
+fn number(value: &Object) -> Result<i64, Box<dyn std::error::Error>> {
+    match value {
+        Object::Number(v) => Ok(*v),
+        v => Err(format!("expected number, got {}", v.type_name()).into()),
+    }
+}
+
+fn index(value: &Object) -> Result<usize, Box<dyn std::error::Error>> {
+    let n: i64 = number(value)?;
+    usize::try_from(n).map_err(|_| format!("expected an index, got {}", n).into())
+}
+
+fn string(value: &Object) -> Result<&str, Box<dyn std::error::Error>> {
+    match value {
+        Object::String(v) => Ok(v),
+        v => Err(format!("expected string, got {}", v.type_name()).into()),
+    }
+}
+
+/// Accepts a symbol or a string naming something.
+fn name(value: &Object) -> Result<&str, Box<dyn std::error::Error>> {
+    match value {
+        Object::Symbol(v) | Object::String(v) => Ok(v),
+        v => Err(format!("expected symbol, got {}", v.type_name()).into()),
+    }
+}
+
+fn list(value: &Object) -> Result<&[Object], Box<dyn std::error::Error>> {
+    match value {
+        Object::List(v) => Ok(v),
+        v => Err(format!("expected list, got {}", v.type_name()).into()),
+    }
+}
+
+fn condition(value: &Object) -> Result<&Condition, Box<dyn std::error::Error>> {
+    match value {
+        Object::Condition(v) => Ok(v),
+        v => Err(format!("expected error-object, got {}", v.type_name()).into()),
+    }
+}
@@ -0,0 +1,920 @@
+//! Static checks for `.core` files, run by `core check` before the editor
+//! ever evaluates them.
+//!
+//! Every finding belongs to a [`Lint`] with a stable code. A file can silence
+//! lints for itself with a comment such as `; core-check: allow(C003, unbound-word)`.
+
+use crate::cst::{TokenKind, Tree};
+use crate::interpreter::{Interpreter, SPECIAL_FORMS};
+use crate::object::{Arity, Object};
+use crate::parser;
+use crate::record::RecordSpec;
+use crate::syntax::{Source, Span, Syntax, SyntaxKind};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Lint {
+    ParseError,
+    UnboundWord,
+    WrongArity,
+    UnreachableDefinition,
+    ShadowedBuiltin,
+    BuiltinMisuse,
+    UnsupportedForm,
+    NonExhaustiveMatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub message: String,
+    pub span: Span,
+}
+
+/// The options accepted in the body of `(define cli (lambda () ...))`.
+const CLI_OPTIONS: &[&str] = &["enable"];
+
+const ALLOW_PREFIX: &str = "core-check: allow(";
+
+impl Lint {
+    pub const ALL: [Lint; 8] = [
+        Lint::ParseError,
+        Lint::UnboundWord,
+        Lint::WrongArity,
+        Lint::UnreachableDefinition,
+        Lint::ShadowedBuiltin,
+        Lint::BuiltinMisuse,
+        Lint::UnsupportedForm,
+        Lint::NonExhaustiveMatch,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::ParseError => "C000",
+            Lint::UnboundWord => "C001",
+            Lint::WrongArity => "C002",
+            Lint::UnreachableDefinition => "C003",
+            Lint::ShadowedBuiltin => "C004",
+            Lint::BuiltinMisuse => "C005",
+            Lint::UnsupportedForm => "C006",
+            Lint::NonExhaustiveMatch => "C007",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::ParseError => "parse-error",
+            Lint::UnboundWord => "unbound-word",
+            Lint::WrongArity => "wrong-arity",
+            Lint::UnreachableDefinition => "unreachable-definition",
+            Lint::ShadowedBuiltin => "shadowed-builtin",
+            Lint::BuiltinMisuse => "builtin-misuse",
+            Lint::UnsupportedForm => "unsupported-form",
+            Lint::NonExhaustiveMatch => "non-exhaustive-match",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::ParseError | Lint::UnboundWord | Lint::WrongArity | Lint::BuiltinMisuse => {
+                Severity::Error
+            }
+            Lint::UnreachableDefinition
+            | Lint::ShadowedBuiltin
+            | Lint::UnsupportedForm
+            | Lint::NonExhaustiveMatch => Severity::Warning,
+        }
+    }
+
+    /// Finds a lint by its code or name.
+    pub fn find(name: &str) -> Option<Lint> {
+        Lint::ALL
+            .into_iter()
+            .find(|v| v.code() == name || v.name() == name)
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        self.lint.severity()
+    }
+
+    /// Formats this as `name:line:column: severity[code]: message`.
+    pub fn render(&self, source: &Source) -> String {
+        let (line, column) = source.line_col(self.span.start);
+        format!(
+            "{}:{}:{}: {}[{}]: {}",
+            source.name,
+            line,
+            column,
+            self.severity(),
+            self.lint.code(),
+            self.message
+        )
+    }
+}
+
+/// Checks a whole file, returning its diagnostics in source order.
+pub fn check(source: &Source) -> Vec<Diagnostic> {
+    check_with(source, &[])
+}
+
+/// Like [`check`], for a file run by a host which defines more procedures,
+/// given with their arity.
+pub fn check_with(source: &Source, natives: &[(String, Arity)]) -> Vec<Diagnostic> {
+    let tree: Tree = Tree::parse(&source.text);
+    let allowed: HashSet<Lint> = allowed(&tree);
+
+    let mut diagnostics: Vec<Diagnostic> = tree
+        .errors()
+        .iter()
+        .map(|v| Diagnostic {
+            lint: Lint::ParseError,
+            message: v.message.clone(),
+            span: v.span,
+        })
+        .collect();
+
+    if diagnostics.is_empty() {
+        match parser::parse_spanned(&source.text) {
+            Ok(program) => diagnostics = Checker::new(natives).run(&program),
+            Err(e) => diagnostics.push(Diagnostic {
+                lint: Lint::ParseError,
+                message: e.to_string(),
+                span: Span::default(),
+            }),
+        }
+    }
+
+    diagnostics.retain(|v| !allowed.contains(&v.lint));
+    diagnostics.sort_by_key(|v| (v.span, v.lint));
+
+    diagnostics
+}
+
+/// The lints silenced by `core-check: allow(...)` comments.
+fn allowed(tree: &Tree) -> HashSet<Lint> {
+    let mut result: HashSet<Lint> = HashSet::new();
+
+    for (_, token) in tree.tokens() {
+        if token.kind != TokenKind::Comment {
+            continue;
+        }
+        let text: &str = token.text.trim_start_matches(';').trim();
+        let Some(list) = text
+            .strip_prefix(ALLOW_PREFIX)
+            .and_then(|v| v.strip_suffix(')'))
+        else {
+            continue;
+        };
+        result.extend(list.split(',').filter_map(|v| Lint::find(v.trim())));
+    }
+
+    result
+}
+
+/// A top-level definition.
+struct Global {
+    span: Span,
+    /// Known when bound to a `lambda` and never `set!`.
+    arity: Option<Arity>,
+    /// The other top-level definitions it refers to.
+    references: HashSet<String>,
+}
+
+struct Checker {
+    /// Standard procedures with their arity, and other standard values.
+    builtins: HashMap<String, Option<Arity>>,
+    globals: HashMap<String, Global>,
+    /// Local scopes, innermost last.
+    scopes: Vec<HashSet<String>>,
+    /// Top-level definitions referred to by the form being checked.
+    references: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn new(natives: &[(String, Arity)]) -> Self {
+        let interpreter = Interpreter::new();
+        let mut builtins: HashMap<String, Option<Arity>> = interpreter
+            .global()
+            .bindings()
+            .into_iter()
+            .map(|(name, value)| match value {
+                Object::Native(v) => (name, Some(v.arity)),
+                _ => (name, None),
+            })
+            .collect();
+        builtins.extend(
+            natives
+                .iter()
+                .map(|(name, arity)| (name.clone(), Some(*arity))),
+        );
+
+        Checker {
+            builtins,
+            globals: HashMap::new(),
+            scopes: Vec::new(),
+            references: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, lint: Lint, span: Span, message: String) {
+        self.diagnostics.push(Diagnostic {
+            lint,
+            message,
+            span,
+        });
+    }
+
+    fn run(mut self, program: &[Syntax]) -> Vec<Diagnostic> {
+        // Definitions may refer to ones further down, so collect them first
+        let mut assigned: HashSet<String> = HashSet::new();
+        for form in program {
+            collect_assignments(form, &mut assigned);
+        }
+        for form in program {
+            // Record types are only checked for misuse, never reported as unused
+            if let Some(spec) = record_type(form) {
+                for (name, arity) in spec.procedures() {
+                    self.globals.insert(
+                        name.name.clone(),
+                        Global {
+                            span: name.span,
+                            arity: Some(arity).filter(|_| !assigned.contains(&name.name)),
+                            references: HashSet::new(),
+                        },
+                    );
+                }
+                continue;
+            }
+            let Some((name, span, value)) = definition(form) else {
+                continue;
+            };
+            let arity: Option<Arity> = match value {
+                Definition::Procedure(params) => params_arity(params),
+                Definition::Value(Some(params)) => match &params.kind {
+                    SyntaxKind::Word(_) => Some(Arity::at_least(0)),
+                    SyntaxKind::SExpression(v) | SyntaxKind::List(v) => params_arity(v),
+                    _ => None,
+                },
+                Definition::Value(None) => None,
+            };
+            self.globals.insert(
+                name.clone(),
+                Global {
+                    span,
+                    arity: arity.filter(|_| !assigned.contains(&name)),
+                    references: HashSet::new(),
+                },
+            );
+        }
+
+        let mut roots: HashSet<String> = HashSet::new();
+        let mut entry_points: bool = false;
+        for form in program {
+            if let Some(spec) = record_type(form) {
+                roots.extend(spec.procedures().into_iter().map(|(v, _)| v.name.clone()));
+                continue;
+            }
+            self.references.clear();
+            let defined: Option<String> = self.top_level(form);
+
+            let references: HashSet<String> = std::mem::take(&mut self.references);
+            match defined {
+                Some(name) => {
+                    if name == "main" || name == "cli" {
+                        entry_points = true;
+                        roots.insert(name.clone());
+                    }
+                    if let Some(global) = self.globals.get_mut(&name) {
+                        global.references.extend(references);
+                    }
+                }
+                None => {
+                    entry_points = true;
+                    roots.extend(references);
+                }
+            }
+        }
+
+        if entry_points {
+            self.unreachable(roots);
+        }
+
+        self.diagnostics
+    }
+
+    /// Checks a top-level form, returning the name it defines.
+    fn top_level(&mut self, form: &Syntax) -> Option<String> {
+        let Some((name, span, _)) = definition(form) else {
+            self.expression(form);
+            return None;
+        };
+        let items: &[Syntax] = form.as_sexpr()?;
+
+        if self.is_builtin(&name) {
+            self.report(
+                Lint::ShadowedBuiltin,
+                span,
+                format!("`{}` shadows a builtin", name),
+            );
+        }
+
+        match name.as_str() {
+            "main" => {
+                if let Some(body) = self.entry_point(&name, items, form.span) {
+                    self.scopes.push(HashSet::new());
+                    self.body(body);
+                    self.scopes.pop();
+                }
+            }
+            "cli" => {
+                if let Some(body) = self.entry_point(&name, items, form.span) {
+                    self.cli_options(body);
+                }
+            }
+            _ => self.define(&items[1..], form.span, true),
+        }
+
+        Some(name)
+    }
+
+    /// Checks that `main` or `cli` is a procedure without parameters, and
+    /// returns its body.
+    fn entry_point<'a>(
+        &mut self,
+        name: &str,
+        items: &'a [Syntax],
+        span: Span,
+    ) -> Option<&'a [Syntax]> {
+        let misuse = |checker: &mut Checker| {
+            checker.report(
+                Lint::BuiltinMisuse,
+                span,
+                format!("`{}` must be defined as (lambda () ...)", name),
+            );
+            None
+        };
+
+        match &items[1].kind {
+            SyntaxKind::SExpression(v) if v.len() == 1 => Some(&items[2..]),
+            SyntaxKind::SExpression(_) => misuse(self),
+            _ => match items.get(2).and_then(Syntax::as_sexpr) {
+                Some([head, params, body @ ..])
+                    if head.as_word().as_deref() == Some("lambda")
+                        && params.as_sexpr().is_some_and(<[Syntax]>::is_empty) =>
+                {
+                    Some(body)
+                }
+                _ => misuse(self),
+            },
+        }
+    }
+
+    /// Checks the `(option value)` forms of `cli`.
+    fn cli_options(&mut self, body: &[Syntax]) {
+        for option in body {
+            match option.as_sexpr() {
+                Some([name, value]) => match name.as_word() {
+                    Some(v) if CLI_OPTIONS.contains(&v.as_str()) => {
+                        if !matches!(value.as_word().as_deref(), Some("t" | "nil")) {
+                            self.report(
+                                Lint::BuiltinMisuse,
+                                value.span,
+                                format!("`{}` expects t or nil", v),
+                            );
+                        }
+                    }
+                    _ => self.report(
+                        Lint::BuiltinMisuse,
+                        name.span,
+                        String::from("unknown cli option"),
+                    ),
+                },
+                _ => self.report(
+                    Lint::BuiltinMisuse,
+                    option.span,
+                    String::from("`cli` options take the form (option value)"),
+                ),
+            }
+        }
+    }
+
+    fn is_builtin(&self, name: &str) -> bool {
+        self.builtins.contains_key(name) || SPECIAL_FORMS.contains(&name)
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes.iter().any(|v| v.contains(name))
+    }
+
+    /// Binds `name` in the innermost local scope.
+    fn bind(&mut self, name: &str, span: Span) {
+        if self.is_builtin(name) {
+            self.report(
+                Lint::ShadowedBuiltin,
+                span,
+                format!("`{}` shadows a builtin", name),
+            );
+        } else if name == "main" || name == "cli" {
+            self.report(
+                Lint::ShadowedBuiltin,
+                span,
+                format!("`{}` shadows the entry point of the config", name),
+            );
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string());
+        }
+    }
+
+    fn body(&mut self, forms: &[Syntax]) {
+        for form in forms {
+            self.expression(form);
+        }
+    }
+
+    fn expression(&mut self, syntax: &Syntax) {
+        match &syntax.kind {
+            SyntaxKind::SExpression(items) => self.sexpr(items, syntax.span),
+            SyntaxKind::Word(_) | SyntaxKind::Literal(_) => {
+                let name: String = syntax.as_word().unwrap_or_default();
+                self.reference(&name, syntax.span);
+            }
+            // Quoted data and self-evaluating atoms
+            _ => (),
+        }
+    }
+
+    fn reference(&mut self, name: &str, span: Span) {
+        if self.is_local(name) {
+            return;
+        }
+        if self.globals.contains_key(name) {
+            self.references.insert(name.to_string());
+        } else if SPECIAL_FORMS.contains(&name) {
+            self.report(
+                Lint::BuiltinMisuse,
+                span,
+                format!("`{}` is a special form, not a value", name),
+            );
+        } else if !self.builtins.contains_key(name) {
+            self.report(Lint::UnboundWord, span, format!("unbound word `{}`", name));
+        }
+    }
+
+    fn sexpr(&mut self, items: &[Syntax], span: Span) {
+        let Some(head) = items.first() else {
+            return;
+        };
+        let args: &[Syntax] = &items[1..];
+        let name: Option<String> = head.as_word();
+
+        // Special forms take precedence over any binding, as in the interpreter
+        if let Some(name) = name.as_deref() {
+            if SPECIAL_FORMS.contains(&name) {
+                self.special_form(name, args, span);
+                return;
+            }
+            if !self.is_local(name) {
+                self.arity(name, args.len(), span);
+            }
+        }
+
+        self.expression(head);
+        self.body(args);
+    }
+
+    /// Checks the argument count of a call to a known procedure.
+    fn arity(&mut self, name: &str, count: usize, span: Span) {
+        let arity: Option<Arity> = match self.globals.get(name) {
+            Some(v) => v.arity,
+            None => self.builtins.get(name).copied().flatten(),
+        };
+
+        if let Some(arity) = arity.filter(|v| !v.accepts(count)) {
+            self.report(
+                Lint::WrongArity,
+                span,
+                format!(
+                    "`{}` expects {} argument{}, got {}",
+                    name,
+                    arity,
+                    if arity == Arity::exact(1) { "" } else { "s" },
+                    count
+                ),
+            );
+        }
+    }
+
+    fn misuse(&mut self, span: Span, message: &str) {
+        self.report(Lint::BuiltinMisuse, span, message.to_string());
+    }
+
+    fn special_form(&mut self, name: &str, args: &[Syntax], span: Span) {
+        match name {
+            "quote" => {
+                if args.len() != 1 {
+                    self.report(
+                        Lint::WrongArity,
+                        span,
+                        String::from("`quote` expects 1 argument"),
+                    );
+                }
+            }
+            "if" => {
+                if !(2..=3).contains(&args.len()) {
+                    self.report(
+                        Lint::WrongArity,
+                        span,
+                        format!("`if` expects 2 to 3 arguments, got {}", args.len()),
+                    );
+                }
+                self.body(args);
+            }
+            "define" => self.define(args, span, false),
+            "set!" => match args {
+                [target, value] => {
+                    match target.as_word() {
+                        Some(v) => self.reference(&v, target.span),
+                        None => self.misuse(target.span, "set!: expected a name"),
+                    }
+                    self.expression(value);
+                }
+                _ => self.report(
+                    Lint::WrongArity,
+                    span,
+                    format!("`set!` expects 2 arguments, got {}", args.len()),
+                ),
+            },
+            "lambda" => match args.first() {
+                Some(params) => self.lambda(params, &args[1..], span),
+                None => self.misuse(span, "lambda: expected parameters"),
+            },
+            "begin" | "and" | "or" => self.body(args),
+            "when" | "unless" => match args.is_empty() {
+                true => self.misuse(span, &format!("{}: expected a test", name)),
+                false => self.body(args),
+            },
+            "let" | "let*" => self.let_form(name, args, span),
+            "cond" => {
+                for clause in args {
+                    self.clause(clause, "cond");
+                }
+            }
+            "guard" => match args.first().and_then(Syntax::as_sexpr) {
+                Some([var, clauses @ ..]) if var.as_word().is_some() => {
+                    self.body(&args[1..]);
+
+                    self.scopes.push(HashSet::new());
+                    self.bind(&var.as_word().unwrap_or_default(), var.span);
+                    for clause in clauses {
+                        self.clause(clause, "guard");
+                    }
+                    self.scopes.pop();
+                }
+                _ => self.misuse(span, "guard: expected (var clause ...)"),
+            },
+            "define-test" => match args.first().map(|v| &v.kind) {
+                Some(SyntaxKind::String(_) | SyntaxKind::Word(_)) => {
+                    self.scopes.push(HashSet::new());
+                    self.body(&args[1..]);
+                    self.scopes.pop();
+                }
+                _ => self.misuse(span, "define-test: expected a name"),
+            },
+            "assert-error" => match args {
+                [expr] => self.expression(expr),
+                _ => self.report(
+                    Lint::WrongArity,
+                    span,
+                    format!("`assert-error` expects 1 argument, got {}", args.len()),
+                ),
+            },
+            "define-record-type" => match RecordSpec::parse(args) {
+                Ok(spec) => {
+                    for (name, _) in spec.procedures() {
+                        self.bind(&name.name, name.span);
+                    }
+                }
+                Err(e) => self.misuse(span, &format!("define-record-type: {}", e)),
+            },
+            "match" => match args.split_first() {
+                Some((value, clauses)) => {
+                    self.expression(value);
+                    self.match_clauses(clauses, span);
+                }
+                None => self.misuse(span, "match: expected a value"),
+            },
+            _ => self.report(
+                Lint::UnsupportedForm,
+                span,
+                format!("`{}` is not supported by the interpreter yet", name),
+            ),
+        }
+    }
+
+    /// `(test body ...)`, where `test` may be `else`.
+    fn clause(&mut self, clause: &Syntax, form: &str) {
+        let Some([test, body @ ..]) = clause.as_sexpr() else {
+            self.misuse(clause.span, &format!("{}: malformed clause", form));
+            return;
+        };
+
+        if test.as_word().as_deref() != Some("else") {
+            self.expression(test);
+        }
+        self.body(body);
+    }
+
+    /// Checks the clauses of `match`, warning unless an unguarded clause
+    /// matches every value.
+    fn match_clauses(&mut self, clauses: &[Syntax], span: Span) {
+        let mut exhaustive: bool = false;
+
+        for clause in clauses {
+            let Some([pattern, body @ ..]) = clause.as_sexpr() else {
+                self.misuse(clause.span, "match: malformed clause");
+                continue;
+            };
+
+            self.scopes.push(HashSet::new());
+            let irrefutable: bool = self.pattern(pattern);
+            let body: &[Syntax] = match body.first().and_then(Syntax::as_sexpr) {
+                Some([head, test]) if head.as_word().as_deref() == Some("guard") => {
+                    self.expression(test);
+                    &body[1..]
+                }
+                _ => {
+                    exhaustive |= irrefutable;
+                    body
+                }
+            };
+            self.body(body);
+            self.scopes.pop();
+        }
+
+        if !exhaustive {
+            self.report(
+                Lint::NonExhaustiveMatch,
+                span,
+                String::from("`match` may not match every value; add a `_` clause"),
+            );
+        }
+    }
+
+    /// Binds the variables of a `match` pattern. Returns whether it matches
+    /// every value.
+    fn pattern(&mut self, pattern: &Syntax) -> bool {
+        match &pattern.kind {
+            SyntaxKind::Word(v) if v == "_" => true,
+            SyntaxKind::Word(v) if v == "t" || v == "nil" => false,
+            SyntaxKind::Word(_) | SyntaxKind::Literal(_) => {
+                self.bind(&pattern.as_word().unwrap_or_default(), pattern.span);
+                true
+            }
+            SyntaxKind::SExpression(items) => {
+                match items.split_first() {
+                    Some((head, [_])) if head.as_word().as_deref() == Some("quote") => (),
+                    Some((head, patterns)) if head.as_word().as_deref() == Some("list") => {
+                        for v in patterns {
+                            if v.as_word().as_deref() != Some(".") {
+                                self.pattern(v);
+                            }
+                        }
+                    }
+                    Some((head, [predicate, patterns @ ..]))
+                        if head.as_word().as_deref() == Some("?") =>
+                    {
+                        self.expression(predicate);
+                        for v in patterns {
+                            self.pattern(v);
+                        }
+                    }
+                    _ => self.misuse(pattern.span, "match: unknown pattern"),
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks the operands of `define`, binding the name locally unless it
+    /// is a top-level definition.
+    fn define(&mut self, args: &[Syntax], span: Span, top_level: bool) {
+        let Some(target) = args.first() else {
+            self.misuse(span, "define: expected a name");
+            return;
+        };
+
+        let name: Option<(String, Span)> = match &target.kind {
+            SyntaxKind::SExpression(v) => v.first().and_then(|v| Some((v.as_word()?, v.span))),
+            _ => target.as_word().map(|v| (v, target.span)),
+        };
+        let Some((name, name_span)) = name else {
+            self.misuse(target.span, "define: expected a name");
+            return;
+        };
+        if !top_level {
+            // Bound before the value, so procedures can call themselves
+            self.bind(&name, name_span);
+        }
+
+        match &target.kind {
+            SyntaxKind::SExpression(v) => {
+                let params = Syntax::new(SyntaxKind::SExpression(v[1..].into()), target.span);
+                self.lambda(&params, &args[1..], span);
+            }
+            _ => match &args[1..] {
+                [] => (),
+                [value] => self.expression(value),
+                _ => self.misuse(span, "define: expected 1 value"),
+            },
+        }
+    }
+
+    fn lambda(&mut self, params: &Syntax, body: &[Syntax], span: Span) {
+        self.scopes.push(HashSet::new());
+
+        match &params.kind {
+            SyntaxKind::Word(v) => self.bind(v, params.span),
+            SyntaxKind::SExpression(v) | SyntaxKind::List(v) => {
+                for param in v.iter() {
+                    match param.as_word() {
+                        Some(v) if v == "." => (),
+                        Some(v) => self.bind(&v, param.span),
+                        None => self.misuse(param.span, "lambda: parameters must be names"),
+                    }
+                }
+            }
+            _ => self.misuse(params.span, "lambda: expected parameters"),
+        }
+        if body.is_empty() {
+            self.misuse(span, "lambda: expected a body");
+        }
+        self.body(body);
+
+        self.scopes.pop();
+    }
+
+    fn let_form(&mut self, name: &str, args: &[Syntax], span: Span) {
+        // (let name ((var init) ...) body ...)
+        let (loop_name, args): (Option<&Syntax>, &[Syntax]) = match args.first() {
+            Some(v) if name == "let" && matches!(v.kind, SyntaxKind::Word(_)) => {
+                (Some(v), &args[1..])
+            }
+            _ => (None, args),
+        };
+
+        let Some(bindings) = args.first().and_then(Syntax::as_sexpr) else {
+            self.misuse(span, &format!("{}: expected a list of bindings", name));
+            return;
+        };
+
+        let mut vars: Vec<(String, Span)> = Vec::new();
+        let depth: usize = self.scopes.len();
+        for binding in bindings {
+            match binding.as_sexpr() {
+                Some([var, init]) if var.as_word().is_some() => {
+                    self.expression(init);
+                    let var: (String, Span) = (var.as_word().unwrap_or_default(), var.span);
+                    // Each init of let* sees the variables before it
+                    if name == "let*" {
+                        self.scopes.push(HashSet::new());
+                        self.bind(&var.0, var.1);
+                    } else {
+                        vars.push(var);
+                    }
+                }
+                _ => self.misuse(binding.span, &format!("{}: malformed binding", name)),
+            }
+        }
+
+        self.scopes.push(HashSet::new());
+        if let Some(v) = loop_name {
+            self.bind(&v.as_word().unwrap_or_default(), v.span);
+        }
+        for (v, span) in vars {
+            self.bind(&v, span);
+        }
+        self.body(&args[1..]);
+        self.scopes.truncate(depth);
+    }
+
+    /// Reports top-level definitions which nothing run by the config uses.
+    fn unreachable(&mut self, roots: HashSet<String>) {
+        let mut reached: HashSet<String> = HashSet::new();
+        let mut pending: Vec<String> = roots.into_iter().collect();
+        while let Some(name) = pending.pop() {
+            if !reached.insert(name.clone()) {
+                continue;
+            }
+            if let Some(global) = self.globals.get(&name) {
+                pending.extend(global.references.iter().cloned());
+            }
+        }
+
+        let mut unreachable: Vec<(Span, String)> = self
+            .globals
+            .iter()
+            .filter(|(name, _)| !reached.contains(*name))
+            .map(|(name, v)| (v.span, name.clone()))
+            .collect();
+        unreachable.sort();
+
+        for (span, name) in unreachable {
+            self.report(
+                Lint::UnreachableDefinition,
+                span,
+                format!("`{}` is never used by main or cli", name),
+            );
+        }
+    }
+}
+
+enum Definition<'a> {
+    /// `(define (name params ...) body ...)`
+    Procedure(&'a [Syntax]),
+    /// `(define name value)`, with the parameters if `value` is a `lambda`.
+    Value(Option<&'a Syntax>),
+}
+
+/// Splits `(define ...)` into its name, the span of the name and its value.
+fn definition(form: &Syntax) -> Option<(String, Span, Definition<'_>)> {
+    let [head, target, rest @ ..] = form.as_sexpr()? else {
+        return None;
+    };
+    if head.as_word()? != "define" {
+        return None;
+    }
+
+    match &target.kind {
+        SyntaxKind::SExpression(v) => {
+            let name: &Syntax = v.first()?;
+            Some((name.as_word()?, name.span, Definition::Procedure(&v[1..])))
+        }
+        _ => {
+            let params: Option<&Syntax> = match rest.first().and_then(Syntax::as_sexpr) {
+                Some([head, params, ..]) if head.as_word().as_deref() == Some("lambda") => {
+                    Some(params)
+                }
+                _ => None,
+            };
+            Some((target.as_word()?, target.span, Definition::Value(params)))
+        }
+    }
+}
+
+/// Parses a well-formed `(define-record-type ...)`.
+fn record_type(form: &Syntax) -> Option<RecordSpec> {
+    let [head, args @ ..] = form.as_sexpr()? else {
+        return None;
+    };
+    if head.as_word()? != "define-record-type" {
+        return None;
+    }
+
+    RecordSpec::parse(args).ok()
+}
+
+/// The arity of a `lambda` with this parameter list.
+fn params_arity(items: &[Syntax]) -> Option<Arity> {
+    let words: Vec<String> = items.iter().map(Syntax::as_word).collect::<Option<_>>()?;
+
+    match words.iter().position(|v| v == ".") {
+        Some(v) => Some(Arity::at_least(v)),
+        None => Some(Arity::exact(words.len())),
+    }
+}
+
+fn collect_assignments(syntax: &Syntax, result: &mut HashSet<String>) {
+    let Some(items) = syntax.as_sexpr() else {
+        return;
+    };
+    if let [head, target, ..] = items {
+        if head.as_word().as_deref() == Some("set!") {
+            result.extend(target.as_word());
+        }
+    }
+    for item in items {
+        collect_assignments(item, result);
+    }
+}
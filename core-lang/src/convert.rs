@@ -0,0 +1,110 @@
+//! Conversion of core-lang values into Rust types, for programs embedding
+//! the interpreter.
+//!
+//! Records convert field by field:
+//!
+//! ```
+//! use core_lang::convert::FromObject;
+//! use core_lang::interpreter::Interpreter;
+//! use core_lang::object::Object;
+//!
+//! struct Theme {
+//!     name: String,
+//!     tab_width: i64,
+//! }
+//!
+//! impl FromObject for Theme {
+//!     fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+//!         let record = object.as_record("theme")?;
+//!         Ok(Theme {
+//!             name: record.field("name")?,
+//!             tab_width: record.field("tab-width")?,
+//!         })
+//!     }
+//! }
+//!
+//! let mut interpreter = Interpreter::new();
+//! let theme: Theme = interpreter
+//!     .eval_str(
+//!         r#"(define-record-type theme
+//!              (make-theme name tab-width)
+//!              theme?
+//!              (name theme-name)
+//!              (tab-width theme-tab-width))
+//!            (make-theme "dark" 4)"#,
+//!     )?
+//!     .convert()?;
+//! assert_eq!((theme.name.as_str(), theme.tab_width), ("dark", 4));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::object::Object;
+use crate::record::Record;
+
+pub trait FromObject: Sized {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>>;
+}
+
+impl Object {
+    pub fn convert<T: FromObject>(&self) -> Result<T, Box<dyn std::error::Error>> {
+        T::from_object(self)
+    }
+
+    /// The record inside this value, if it is one of type `kind`.
+    pub fn as_record(&self, kind: &str) -> Result<&Record, Box<dyn std::error::Error>> {
+        match self {
+            Object::Record(v) if v.kind.name == kind => Ok(v),
+            v => Err(format!("expected {}, got {}", kind, v.type_name()).into()),
+        }
+    }
+}
+
+impl FromObject for Object {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(object.clone())
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+        match object {
+            Object::Number(v) => Ok(*v),
+            v => Err(format!("expected number, got {}", v.type_name()).into()),
+        }
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(object.is_true())
+    }
+}
+
+/// Accepts strings and symbols.
+impl FromObject for String {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+        match object {
+            Object::String(v) | Object::Symbol(v) => Ok(v.to_string()),
+            v => Err(format!("expected string, got {}", v.type_name()).into()),
+        }
+    }
+}
+
+/// `nil` becomes `None`.
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+        match object {
+            Object::Boolean(false) => Ok(None),
+            v => Ok(Some(T::from_object(v)?)),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+        match object {
+            Object::List(v) => v.iter().map(T::from_object).collect(),
+            v => Err(format!("expected list, got {}", v.type_name()).into()),
+        }
+    }
+}
@@ -0,0 +1,459 @@
+//! A lossless concrete syntax tree for editing `.core` files.
+//!
+//! Unlike [`crate::parser::parse`], this never fails: whitespace and comments
+//! are kept as trivia tokens, and malformed input such as unbalanced
+//! parentheses is recorded as [`ParseError`]s next to a best-effort tree.
+//! Printing the tree gives back the input exactly.
+
+use crate::parser::MAX_NESTING;
+use crate::syntax::Span;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tree {
+    text: String,
+    root: Node,
+    errors: Vec<ParseError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+    /// Length of the text covered by this node, in bytes.
+    len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Root,
+    /// `( ... )`
+    SExpression,
+    /// `'( ... )`
+    List,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    Token(Token),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    LeftParenthesis,
+    QuoteParenthesis,
+    RightParenthesis,
+    Word,
+    Number,
+    String,
+    Whitespace,
+    Comment,
+    /// Text which cannot start any token.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+impl Element {
+    pub fn len(&self) -> usize {
+        match self {
+            Element::Node(v) => v.len,
+            Element::Token(v) => v.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn write(&self, out: &mut String) {
+        match self {
+            Element::Node(v) => v.children.iter().for_each(|v| v.write(out)),
+            Element::Token(v) => out.push_str(&v.text),
+        }
+    }
+}
+
+impl Node {
+    fn new(kind: NodeKind, children: Vec<Element>) -> Self {
+        let len: usize = children.iter().map(Element::len).sum();
+        Node {
+            kind,
+            children,
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the closing parenthesis is present.
+    pub fn is_closed(&self) -> bool {
+        match self.children.last() {
+            Some(Element::Token(v)) => v.kind == TokenKind::RightParenthesis,
+            _ => self.kind == NodeKind::Root,
+        }
+    }
+
+    /// The children which are not whitespace or comments.
+    pub fn significant(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter(|v| match v {
+            Element::Token(v) => {
+                !v.is_trivia()
+                    && !matches!(
+                        v.kind,
+                        TokenKind::LeftParenthesis
+                            | TokenKind::QuoteParenthesis
+                            | TokenKind::RightParenthesis
+                    )
+            }
+            Element::Node(_) => true,
+        })
+    }
+}
+
+impl Tree {
+    pub fn parse(text: &str) -> Self {
+        let mut parser = Parser {
+            text,
+            offset: 0,
+            depth: 0,
+            errors: Vec::new(),
+        };
+        let root: Node = parser.root();
+
+        Tree {
+            text: text.to_string(),
+            root,
+            errors: parser.errors,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Prints the tree back, which always gives the text it was parsed from.
+    pub fn print(&self) -> String {
+        let mut out: String = String::with_capacity(self.text.len());
+        self.root.children.iter().for_each(|v| v.write(&mut out));
+
+        out
+    }
+
+    /// Every token with its position, in order, e.g. for syntax highlighting.
+    pub fn tokens(&self) -> Vec<(Span, &Token)> {
+        fn walk<'a>(node: &'a Node, offset: &mut usize, out: &mut Vec<(Span, &'a Token)>) {
+            for child in &node.children {
+                match child {
+                    Element::Node(v) => walk(v, offset, out),
+                    Element::Token(v) => {
+                        out.push((Span::new(*offset, *offset + v.text.len()), v));
+                        *offset += v.text.len();
+                    }
+                }
+            }
+        }
+
+        let mut out: Vec<(Span, &Token)> = Vec::new();
+        walk(&self.root, &mut 0, &mut out);
+
+        out
+    }
+
+    /// The spans of the lists containing `offset`, innermost first. Useful for
+    /// structural editing, like selecting the enclosing form.
+    pub fn enclosing(&self, offset: usize) -> Vec<Span> {
+        let mut result: Vec<Span> = Vec::new();
+        let mut node: &Node = &self.root;
+        let mut start: usize = 0;
+
+        'descend: loop {
+            let mut child_start: usize = start;
+            for child in &node.children {
+                let span = Span::new(child_start, child_start + child.len());
+                if let Element::Node(v) = child {
+                    if span.contains(offset) {
+                        result.push(span);
+                        node = v;
+                        start = child_start;
+                        continue 'descend;
+                    }
+                }
+                child_start = span.end;
+            }
+            break;
+        }
+        result.reverse();
+
+        result
+    }
+
+    /// Replaces `range` of the text with `replacement`, reparsing as little as
+    /// possible. Returns the span of the new text which was reparsed.
+    pub fn edit(&mut self, range: Span, replacement: &str) -> Span {
+        let mut text: String = self.text.clone();
+        text.replace_range(range.start..range.end, replacement);
+        let delta: isize = replacement.len() as isize - (range.end - range.start) as isize;
+
+        if let Some(span) = self.reparse_list(range, &text, delta) {
+            self.text = text;
+            return span;
+        }
+
+        *self = Tree::parse(&text);
+        Span::new(0, self.text.len())
+    }
+
+    /// Reparses only the innermost list strictly enclosing `range`, if the
+    /// result is again a single well-formed list.
+    fn reparse_list(&mut self, range: Span, text: &str, delta: isize) -> Option<Span> {
+        // Find the innermost list and the path of child indices leading to it
+        let mut path: Vec<usize> = Vec::new();
+        let mut found: Option<(Vec<usize>, Span)> = None;
+        let mut node: &Node = &self.root;
+        let mut start: usize = 0;
+
+        'descend: loop {
+            let mut child_start: usize = start;
+            for (i, child) in node.children.iter().enumerate() {
+                let end: usize = child_start + child.len();
+                if let Element::Node(v) = child {
+                    // The edit must not touch the parentheses themselves
+                    let open: usize = v.children.first().map_or(0, Element::len);
+                    if child_start + open <= range.start && range.end < end && v.is_closed() {
+                        path.push(i);
+                        found = Some((path.clone(), Span::new(child_start, end)));
+                        node = v;
+                        start = child_start;
+                        continue 'descend;
+                    }
+                }
+                child_start = end;
+            }
+            break;
+        }
+
+        let (path, old) = found?;
+        let new: Span = Span::new(old.start, (old.end as isize + delta) as usize);
+        let reparsed: Tree = Tree::parse(text.get(new.start..new.end)?);
+        if !reparsed.errors.is_empty() || reparsed.root.children.len() != 1 {
+            return None;
+        }
+        let Some(Element::Node(replacement)) = reparsed.root.children.into_iter().next() else {
+            return None;
+        };
+
+        // The reparsed list has no errors left, and those after it move
+        self.errors
+            .retain(|v| !(old.start..old.end).contains(&v.span.start));
+        for error in &mut self.errors {
+            if error.span.start >= old.end {
+                error.span.start = (error.span.start as isize + delta) as usize;
+                error.span.end = (error.span.end as isize + delta) as usize;
+            }
+        }
+
+        let mut node: &mut Node = &mut self.root;
+        for (depth, i) in path.iter().enumerate() {
+            node.len = (node.len as isize + delta) as usize;
+            if depth + 1 == path.len() {
+                node.children[*i] = Element::Node(replacement);
+                break;
+            }
+            let Element::Node(child) = &mut node.children[*i] else {
+                unreachable!()
+            };
+            node = child;
+        }
+
+        Some(new)
+    }
+}
+
+/// Characters allowed in words by the grammar in `sexpr.pest`, outside of
+/// `|escaped words|`.
+pub fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !c.is_control() && !matches!(c, '(' | ')' | '"' | ';' | '\'' | '|')
+}
+
+/// Whitespace allowed between forms by `punct` in `sexpr.pest`. Other
+/// whitespace, such as a no-break space, is an error.
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\n')
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+    /// The number of lists being read.
+    depth: usize,
+    errors: Vec<ParseError>,
+}
+
+impl Parser<'_> {
+    fn root(&mut self) -> Node {
+        let mut children: Vec<Element> = Vec::new();
+        while self.offset < self.text.len() {
+            match self.element() {
+                Some(v) => children.push(v),
+                None => {
+                    // A stray closing parenthesis
+                    let start: usize = self.offset;
+                    self.offset += 1;
+                    self.error("unexpected ')'", Span::new(start, self.offset));
+                    children.push(Element::Token(Token {
+                        kind: TokenKind::Error,
+                        text: String::from(")"),
+                    }));
+                }
+            }
+        }
+
+        Node::new(NodeKind::Root, children)
+    }
+
+    /// Reads one token or list. Returns `None` at a closing parenthesis.
+    fn element(&mut self) -> Option<Element> {
+        let rest: &str = &self.text[self.offset..];
+        let c: char = rest.chars().next()?;
+        let start: usize = self.offset;
+
+        let kind: TokenKind = match c {
+            ')' => return None,
+            // Give up on the rest rather than overflow the stack
+            '(' | '\'' if self.depth >= MAX_NESTING && (c == '(' || rest.starts_with("'(")) => {
+                self.offset = self.text.len();
+                self.error("lists are nested too deeply", Span::new(start, self.offset));
+                TokenKind::Error
+            }
+            '(' => return Some(Element::Node(self.list(NodeKind::SExpression, 1))),
+            '\'' if rest.starts_with("'(") => {
+                return Some(Element::Node(self.list(NodeKind::List, 2)));
+            }
+            ';' => {
+                self.offset += rest.find('\n').unwrap_or(rest.len());
+                TokenKind::Comment
+            }
+            '"' => {
+                match rest[1..].find('"') {
+                    Some(v) => self.offset += v + 2,
+                    None => {
+                        self.offset = self.text.len();
+                        self.error("unterminated string", Span::new(start, self.offset));
+                    }
+                }
+                TokenKind::String
+            }
+            '|' => {
+                let mut escaped: bool = false;
+                let end: Option<usize> = rest[1..].find(|c: char| {
+                    let end: bool = c == '|' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    end
+                });
+                match end {
+                    Some(v) => self.offset += v + 2,
+                    None => {
+                        self.offset = self.text.len();
+                        self.error("unterminated word", Span::new(start, self.offset));
+                    }
+                }
+                TokenKind::Word
+            }
+            c if is_whitespace(c) => {
+                self.offset += rest.find(|c: char| !is_whitespace(c)).unwrap_or(rest.len());
+                TokenKind::Whitespace
+            }
+            c if is_word_char(c) => {
+                let len: usize = rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+                self.offset += len;
+                match rest[..len].bytes().all(|v| v.is_ascii_digit()) {
+                    true => TokenKind::Number,
+                    false => TokenKind::Word,
+                }
+            }
+            c => {
+                self.offset += c.len_utf8();
+                self.error("unexpected character", Span::new(start, self.offset));
+                TokenKind::Error
+            }
+        };
+
+        Some(Element::Token(Token {
+            kind,
+            text: self.text[start..self.offset].to_string(),
+        }))
+    }
+
+    fn list(&mut self, kind: NodeKind, open: usize) -> Node {
+        let start: usize = self.offset;
+        self.offset += open;
+        self.depth += 1;
+
+        let mut children: Vec<Element> = vec![Element::Token(Token {
+            kind: match kind {
+                NodeKind::List => TokenKind::QuoteParenthesis,
+                _ => TokenKind::LeftParenthesis,
+            },
+            text: self.text[start..self.offset].to_string(),
+        })];
+
+        loop {
+            match self.element() {
+                Some(v) => children.push(v),
+                None if self.offset < self.text.len() => {
+                    self.offset += 1;
+                    children.push(Element::Token(Token {
+                        kind: TokenKind::RightParenthesis,
+                        text: String::from(")"),
+                    }));
+                    break;
+                }
+                None => {
+                    self.error("unclosed parenthesis", Span::new(start, start + open));
+                    break;
+                }
+            }
+        }
+
+        self.depth -= 1;
+        Node::new(kind, children)
+    }
+
+    fn error(&mut self, message: &str, span: Span) {
+        self.errors.push(ParseError {
+            message: message.to_string(),
+            span,
+        });
+    }
+}
@@ -0,0 +1,242 @@
+use crate::environment::Environment;
+use crate::interpreter::{Frame, Interpreter};
+use crate::object::Object;
+use crate::syntax::{Source, Span};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+pub type PauseHandler = dyn FnMut(&mut Interpreter, &Pause) -> Resume;
+pub type TraceHandler = dyn FnMut(&TraceEvent);
+
+/// Breakpoints, stepping and tracing for an [`Interpreter`].
+///
+/// Attach one with [`Interpreter::attach_debugger`]. Whenever evaluation
+/// reaches a breakpoint, or the next form while stepping, the pause handler
+/// is called with the paused state and decides how to go on. The handler gets
+/// the interpreter itself, so it may evaluate expressions in [`Pause::env`].
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    traced: HashSet<String>,
+    mode: Mode,
+    on_pause: Box<PauseHandler>,
+    on_trace: Option<Box<TraceHandler>>,
+    /// Trace events waiting for [`Debugger::take_trace`], when there is no tracer.
+    events: Vec<TraceEvent>,
+    /// The line of the innermost form being evaluated in the current frame.
+    pub(crate) line: Option<usize>,
+}
+
+/// Pauses before evaluating the first form starting on `line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Only break in the source with this name, or in any if `None`.
+    pub source: Option<String>,
+    pub line: usize,
+}
+
+/// What to do after a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Run until the next breakpoint.
+    Continue,
+    /// Pause at the next form, including inside called procedures.
+    StepIn,
+    /// Pause at the next form of the current procedure or its callers.
+    StepOver,
+    /// Pause once the current procedure has returned.
+    StepOut,
+    /// Stop evaluation with [`crate::interpreter::Unwind::Interrupted`].
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Run,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// Index of the breakpoint in [`Debugger::breakpoints`].
+    Breakpoint(usize),
+    Step,
+}
+
+/// The state of a paused evaluation.
+pub struct Pause {
+    pub reason: PauseReason,
+    pub source: Rc<Source>,
+    /// The form about to be evaluated.
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+    pub env: Environment,
+    /// The procedure calls leading here, outermost first.
+    pub frames: Vec<Frame>,
+}
+
+/// A call to or return from a traced procedure.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    Call {
+        name: String,
+        args: Vec<Object>,
+        depth: usize,
+    },
+    Return {
+        name: String,
+        value: Object,
+        depth: usize,
+    },
+}
+
+impl Debugger {
+    pub fn new<F>(on_pause: F) -> Self
+    where
+        F: FnMut(&mut Interpreter, &Pause) -> Resume + 'static,
+    {
+        Debugger {
+            breakpoints: Vec::new(),
+            traced: HashSet::new(),
+            mode: Mode::Run,
+            on_pause: Box::new(on_pause),
+            on_trace: None,
+            events: Vec::new(),
+            line: None,
+        }
+    }
+
+    /// Hands trace events to `on_trace` as they happen, instead of
+    /// collecting them for [`Debugger::take_trace`].
+    pub fn set_tracer<F>(&mut self, on_trace: F)
+    where
+        F: FnMut(&TraceEvent) + 'static,
+    {
+        self.on_trace = Some(Box::new(on_trace));
+    }
+
+    /// Takes the trace events collected so far.
+    pub fn take_trace(&mut self) -> Vec<TraceEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) {
+        self.breakpoints.retain(|v| v != breakpoint);
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Pauses at the very next form evaluated.
+    pub fn break_next(&mut self) {
+        self.mode = Mode::StepIn;
+    }
+
+    pub fn trace(&mut self, name: &str) {
+        self.traced.insert(name.to_string());
+    }
+
+    pub fn untrace(&mut self, name: &str) {
+        self.traced.remove(name);
+    }
+
+    pub fn is_traced(&self, name: &str) -> bool {
+        self.traced.contains(name)
+    }
+
+    /// Decides whether to pause before a form on `line` of `source`, with
+    /// `depth` procedure calls active.
+    pub(crate) fn should_pause(
+        &self,
+        source: &Source,
+        line: usize,
+        outer_line: Option<usize>,
+        depth: usize,
+    ) -> Option<PauseReason> {
+        // Only the outermost form on a line triggers its breakpoint
+        if outer_line != Some(line) {
+            let breakpoint = self.breakpoints.iter().position(|v| {
+                v.line == line && v.source.as_ref().is_none_or(|v| *v == source.name)
+            });
+            if let Some(v) = breakpoint {
+                return Some(PauseReason::Breakpoint(v));
+            }
+        }
+
+        let step: bool = match self.mode {
+            Mode::Run => false,
+            Mode::StepIn => true,
+            Mode::StepOver(v) => depth <= v,
+            Mode::StepOut(v) => depth < v,
+        };
+        step.then_some(PauseReason::Step)
+    }
+
+    pub(crate) fn pause(&mut self, interpreter: &mut Interpreter, pause: &Pause) -> Resume {
+        let resume: Resume = (self.on_pause)(interpreter, pause);
+        let depth: usize = pause.frames.len();
+        self.mode = match resume {
+            Resume::Continue | Resume::Abort => Mode::Run,
+            Resume::StepIn => Mode::StepIn,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+        };
+
+        resume
+    }
+
+    pub(crate) fn emit(&mut self, event: &TraceEvent) {
+        match &mut self.on_trace {
+            Some(on_trace) => on_trace(event),
+            None => self.events.push(event.clone()),
+        }
+    }
+}
+
+impl Default for Debugger {
+    /// A debugger which never pauses, for tracing only.
+    fn default() -> Self {
+        Debugger::new(|_, _| Resume::Continue)
+    }
+}
+
+impl Pause {
+    /// The bindings visible at the pause, innermost scope first, without the
+    /// global environment.
+    pub fn locals(&self) -> Vec<(String, Object)> {
+        let mut result: Vec<(String, Object)> = Vec::new();
+        let mut env: Environment = self.env.clone();
+        while let Some(parent) = env.parent() {
+            result.extend(env.bindings());
+            env = parent;
+        }
+
+        result
+    }
+}
+
+impl std::fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceEvent::Call { name, args, depth } => {
+                write!(f, "{}({}", "| ".repeat(*depth), name)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            TraceEvent::Return { value, depth, .. } => {
+                write!(f, "{}{}", "| ".repeat(*depth), value)
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::object::Object;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A chain of scopes mapping names to values.
+#[derive(Clone, Default)]
+pub struct Environment(Rc<RefCell<Scope>>);
+
+#[derive(Default)]
+struct Scope {
+    bindings: HashMap<String, Object>,
+    parent: Option<Environment>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    /// Creates a new scope whose lookups fall back to this one.
+    pub fn child(&self) -> Self {
+        Environment(Rc::new(RefCell::new(Scope {
+            bindings: HashMap::new(),
+            parent: Some(self.clone()),
+        })))
+    }
+
+    pub fn define(&self, name: &str, value: Object) {
+        self.0.borrow_mut().bindings.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        let scope = self.0.borrow();
+        match scope.bindings.get(name) {
+            Some(v) => Some(v.clone()),
+            None => scope.parent.as_ref()?.get(name),
+        }
+    }
+
+    /// Updates an existing binding. Returns `false` if `name` is unbound.
+    pub fn set(&self, name: &str, value: Object) -> bool {
+        let mut scope = self.0.borrow_mut();
+        if let Some(v) = scope.bindings.get_mut(name) {
+            *v = value;
+            return true;
+        }
+
+        match &scope.parent {
+            Some(parent) => parent.set(name, value),
+            None => false,
+        }
+    }
+
+    /// Returns the bindings of this scope only, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut result: Vec<(String, Object)> = self
+            .0
+            .borrow()
+            .bindings
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+
+        result
+    }
+
+    /// Replaces the bindings of this scope only, e.g. with ones taken from
+    /// [`Environment::bindings`] earlier.
+    pub fn restore(&self, bindings: Vec<(String, Object)>) {
+        self.0.borrow_mut().bindings = bindings.into_iter().collect();
+    }
+
+    pub fn parent(&self) -> Option<Environment> {
+        self.0.borrow().parent.clone()
+    }
+}
@@ -1,24 +1,44 @@
 use crate::{
-    ast::AST, ast::Boolean, ast::BuiltinWord, ast::Constant, ast::Value, parser, token::Literal,
-    token::Token,
+    ast::AST,
+    ast::Boolean,
+    ast::BuiltinWord,
+    ast::Constant,
+    ast::Value,
+    parser,
+    syntax::{Span, Syntax, SyntaxKind},
+    token::Literal,
 };
 
 pub fn eval(program: &str) -> Result<AST, Box<dyn std::error::Error>> {
+    let parser_result: Vec<Syntax> = parser::parse_spanned(program)?;
+
+    eval_syntax(parser_result)
+}
+
+/// Like [`eval`], for top-level forms which have already been parsed.
+pub fn eval_syntax(forms: Vec<Syntax>) -> Result<AST, Box<dyn std::error::Error>> {
     let mut context: Vec<Constant> = Vec::new();
-    let parser_result: Vec<Token> = parser::parse(program)?;
 
-    for token in parser_result {
-        match token {
-            Token::SExpression(mut value) => {
+    for syntax in forms {
+        let span: Span = syntax.span;
+        match syntax.kind {
+            SyntaxKind::SExpression(value) => {
+                let mut value: Vec<Syntax> = value.to_vec();
                 // Reverses the order of tokens because I want to use 'tokens.pop()'
                 value.reverse();
 
-                let function_t: Token = value.pop().ok_or("Failed to read function")?;
-                match function_t {
-                    Token::Literal(v) => match v {
+                let function_t: Syntax = value
+                    .pop()
+                    .ok_or_else(|| error("Failed to read function", span))?;
+                match function_t.kind {
+                    SyntaxKind::Literal(v) => match v {
                         Literal::Define => {
-                            let name_t: Token = value.pop().ok_or("Failed to read name")?;
-                            let value_t: Token = value.pop().ok_or("Failed to read value")?;
+                            let name_t: Syntax = value
+                                .pop()
+                                .ok_or_else(|| error("Failed to read name", span))?;
+                            let value_t: Syntax = value
+                                .pop()
+                                .ok_or_else(|| error("Failed to read value", span))?;
 
                             let result: Constant = Constant {
                                 name: eval_name(name_t)?,
@@ -27,39 +47,51 @@ pub fn eval(program: &str) -> Result<AST, Box<dyn std::error::Error>> {
 
                             context.push(result);
                         }
-                        _ => todo!(),
+                        v => {
+                            let message: String = format!("Unsupported top-level form: {}", v);
+                            return Err(error(&message, function_t.span));
+                        }
                     },
-                    _ => todo!(),
+                    _ => {
+                        return Err(error("Only definitions are allowed at the top level", span));
+                    }
                 }
             }
-            _ => unreachable!(),
+            _ => {
+                return Err(error("Only definitions are allowed at the top level", span));
+            }
         }
     }
 
     Ok(AST(context))
 }
 
-fn eval_name(token: Token) -> Result<Value, Box<dyn std::error::Error>> {
-    match token {
-        Token::Word(v) => match v.as_str() {
+/// Locates `message` at `span` of the program.
+fn error(message: &str, span: Span) -> Box<dyn std::error::Error> {
+    format!("{} at {}", message, span).into()
+}
+
+fn eval_name(syntax: Syntax) -> Result<Value, Box<dyn std::error::Error>> {
+    match syntax.kind {
+        SyntaxKind::Word(v) => match v.as_str() {
             "main" => Ok(Value::BuiltinWord(BuiltinWord::Main)),
             "cli" => Ok(Value::BuiltinWord(BuiltinWord::Cli)),
             "t" => Ok(Value::Boolean(Boolean::T)),
             "nil" => Ok(Value::Boolean(Boolean::Nil)),
             v => Ok(Value::Word(v.to_string())),
         },
-        _ => panic!(),
+        _ => Err(error("Failed to read name: expected a word", syntax.span)),
     }
 }
 
-fn eval_value(token: Token) -> Result<Value, Box<dyn std::error::Error>> {
-    match token {
-        Token::String(v) => Ok(Value::String(v)),
-        Token::Number(v) => Ok(Value::Number(v)),
-        Token::List(v) => eval_list(v),
-        Token::SExpression(v) => eval_sexpr(v),
-        Token::Word(_) => eval_name(token),
-        Token::Literal(v) => eval_literal(v),
+fn eval_value(syntax: Syntax) -> Result<Value, Box<dyn std::error::Error>> {
+    match syntax.kind {
+        SyntaxKind::String(v) => Ok(Value::String(v)),
+        SyntaxKind::Number(v) => Ok(Value::Number(v)),
+        SyntaxKind::List(v) => eval_list(&v),
+        SyntaxKind::SExpression(v) => eval_sexpr(v.to_vec(), syntax.span),
+        SyntaxKind::Word(_) => eval_name(syntax),
+        SyntaxKind::Literal(v) => eval_literal(v),
     }
 }
 
@@ -77,35 +109,45 @@ fn eval_literal(literal: Literal) -> Result<Value, Box<dyn std::error::Error>> {
     }
 }
 
-fn eval_list(tokens: Vec<Token>) -> Result<Value, Box<dyn std::error::Error>> {
+fn eval_list(syntax: &[Syntax]) -> Result<Value, Box<dyn std::error::Error>> {
     let mut result: Vec<Value> = Vec::new();
 
-    for token in tokens {
-        match token {
-            Token::SExpression(v) => result.push(eval_sexpr(v)?),
-            Token::List(v) => result.push(eval_list(v)?),
-            Token::Word(_) => result.push(eval_name(token)?),
-            Token::Literal(v) => result.push(eval_literal(v)?),
-            Token::String(v) => result.push(Value::String(v)),
-            Token::Number(v) => result.push(Value::Number(v)),
+    for s in syntax {
+        match &s.kind {
+            SyntaxKind::SExpression(v) => result.push(eval_sexpr(v.to_vec(), s.span)?),
+            SyntaxKind::List(v) => result.push(eval_list(v)?),
+            SyntaxKind::Word(_) => result.push(eval_name(s.clone())?),
+            SyntaxKind::Literal(v) => result.push(eval_literal(*v)?),
+            SyntaxKind::String(v) => result.push(Value::String(v.clone())),
+            SyntaxKind::Number(v) => result.push(Value::Number(*v)),
         }
     }
 
     Ok(Value::List(result))
 }
 
-fn eval_sexpr(mut tokens: Vec<Token>) -> Result<Value, Box<dyn std::error::Error>> {
+fn eval_sexpr(mut tokens: Vec<Syntax>, span: Span) -> Result<Value, Box<dyn std::error::Error>> {
+    // `()` is empty, such as the parameters of `(lambda () ...)`
+    if tokens.is_empty() {
+        return Ok(Value::List(Vec::new()));
+    }
     let mut result: Vec<Value> = Vec::new();
 
     // Reverses the order of tokens because I want to use 'tokens.pop()'
     tokens.reverse();
 
-    let function_t: Token = tokens.pop().ok_or("Failed to read function")?;
-    match function_t {
-        Token::Literal(v) => match v {
+    let function_t: Syntax = tokens
+        .pop()
+        .ok_or_else(|| error("Failed to read function", span))?;
+    match function_t.kind {
+        SyntaxKind::Literal(v) => match v {
             Literal::Define => {
-                let name_t: Token = tokens.pop().ok_or("Failed to read name")?;
-                let value_t: Token = tokens.pop().ok_or("Failed to read value")?;
+                let name_t: Syntax = tokens
+                    .pop()
+                    .ok_or_else(|| error("Failed to read name", span))?;
+                let value_t: Syntax = tokens
+                    .pop()
+                    .ok_or_else(|| error("Failed to read value", span))?;
 
                 let constant: Constant = Constant {
                     name: eval_name(name_t)?,
@@ -115,23 +157,36 @@ fn eval_sexpr(mut tokens: Vec<Token>) -> Result<Value, Box<dyn std::error::Error
                 result.push(Value::Constant(Box::new(constant)));
             }
             Literal::Lambda => {
-                let args_t: Token = tokens.pop().ok_or("Failed to read arguments")?;
-                let expr_t: Token = tokens.pop().ok_or("Failed to read expr")?;
+                let args_t: Syntax = tokens
+                    .pop()
+                    .ok_or_else(|| error("Failed to read arguments", span))?;
+                let expr_t: Syntax = tokens
+                    .pop()
+                    .ok_or_else(|| error("Failed to read expr", span))?;
 
                 let args: Vec<Value> = vec![eval_value(args_t)?];
                 let expr: Vec<Value> = vec![eval_value(expr_t)?];
 
                 result.push(Value::Lambda((args, expr)));
             }
-            _ => todo!(),
+            v => {
+                let message: String = format!("Unsupported form: {}", v);
+                return Err(error(&message, function_t.span));
+            }
         },
-        Token::Word(v) => {
+        SyntaxKind::Word(v) => {
             result.push(Value::Word(v));
-            for token in tokens {
+            // Undoes the reversal above, so arguments keep their order
+            for token in tokens.into_iter().rev() {
                 result.push(eval_value(token)?);
             }
         }
-        _ => todo!(),
+        _ => {
+            return Err(error(
+                "Failed to read function: expected a word",
+                function_t.span,
+            ));
+        }
     }
 
     Ok(Value::SExpression(result))
@@ -0,0 +1,277 @@
+//! The canonical layout of `.core` files.
+//!
+//! A list is printed on one line when it fits within [`FormatOptions::max_width`]
+//! and holds no comments. Otherwise `define`, `lambda`, `let` and the other
+//! forms with a body keep their leading operands on the first line and indent
+//! the body by [`FormatOptions::indent`], while calls align their arguments
+//! under the first one. Comments are kept, as are single blank lines between
+//! forms. Formatting a formatted text gives it back unchanged.
+
+use crate::cst::{Element, Node, NodeKind, TokenKind, Tree};
+use crate::syntax::Source;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub max_width: usize,
+    /// Indentation of bodies, relative to their opening parenthesis.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            max_width: 80,
+            indent: 2,
+        }
+    }
+}
+
+/// Formats a whole file. Fails if the text does not parse.
+pub fn format(text: &str, options: &FormatOptions) -> Result<String, Box<dyn std::error::Error>> {
+    let tree: Tree = Tree::parse(text);
+    if let Some(error) = tree.errors().first() {
+        let (line, column) = Source::new("", text).line_col(error.span.start);
+        return Err(format!("PARSE_ERROR: {} at {}:{}", error.message, line, column).into());
+    }
+
+    let entries: Vec<Entry> = entries(tree.root());
+    let mut printer = Printer {
+        options,
+        out: String::with_capacity(text.len()),
+        column: 0,
+    };
+    for (i, entry) in entries.iter().enumerate() {
+        match &entry.kind {
+            EntryKind::Comment {
+                text,
+                trailing: true,
+            } if i > 0 => {
+                printer.push(" ");
+                printer.push(text);
+                continue;
+            }
+            _ => (),
+        }
+        if i > 0 {
+            if entry.blank_before {
+                printer.out.push('\n');
+            }
+            printer.newline(0);
+        }
+        printer.entry(entry);
+    }
+    if !printer.out.is_empty() {
+        printer.out.push('\n');
+    }
+
+    Ok(printer.out)
+}
+
+/// The number of operands kept on the first line of forms with a body.
+fn distinguished(head: &str, list: &List) -> Option<usize> {
+    match head {
+        "begin" => Some(0),
+        "define" | "define-syntax" | "define-test" | "lambda" | "let*" | "when" | "unless"
+        | "guard" | "syntax-rules" | "match" | "define-record-type" => Some(1),
+        // A named let has the name before its bindings
+        "let" => match list.forms().nth(1) {
+            Some(Form::Atom(_)) => Some(2),
+            _ => Some(1),
+        },
+        _ => None,
+    }
+}
+
+enum Form {
+    Atom(String),
+    List(List),
+}
+
+struct List {
+    open: &'static str,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    kind: EntryKind,
+    /// Whether an empty line separated this from the previous entry.
+    blank_before: bool,
+}
+
+enum EntryKind {
+    Form(Form),
+    /// `trailing` comments share the line of the previous entry.
+    Comment {
+        text: String,
+        trailing: bool,
+    },
+}
+
+impl List {
+    fn forms(&self) -> impl Iterator<Item = &Form> {
+        self.entries.iter().filter_map(|v| match &v.kind {
+            EntryKind::Form(v) => Some(v),
+            EntryKind::Comment { .. } => None,
+        })
+    }
+}
+
+impl Form {
+    /// The single-line text of this form, unless it has to span lines.
+    fn flat(&self) -> Option<String> {
+        match self {
+            Form::Atom(v) if v.contains('\n') => None,
+            Form::Atom(v) => Some(v.clone()),
+            Form::List(list) => {
+                let mut result: String = list.open.to_string();
+                for (i, entry) in list.entries.iter().enumerate() {
+                    let EntryKind::Form(form) = &entry.kind else {
+                        return None;
+                    };
+                    if i > 0 {
+                        result.push(' ');
+                    }
+                    result.push_str(&form.flat()?);
+                }
+                result.push(')');
+
+                Some(result)
+            }
+        }
+    }
+}
+
+fn entries(node: &Node) -> Vec<Entry> {
+    let mut result: Vec<Entry> = Vec::new();
+    let mut newlines: usize = 0;
+
+    for child in &node.children {
+        let kind: EntryKind = match child {
+            Element::Token(v) => match v.kind {
+                TokenKind::Whitespace => {
+                    newlines += v.text.matches('\n').count();
+                    continue;
+                }
+                TokenKind::LeftParenthesis
+                | TokenKind::QuoteParenthesis
+                | TokenKind::RightParenthesis => continue,
+                TokenKind::Comment => EntryKind::Comment {
+                    text: v.text.trim_end().to_string(),
+                    trailing: newlines == 0 && !result.is_empty(),
+                },
+                _ => EntryKind::Form(Form::Atom(v.text.clone())),
+            },
+            Element::Node(v) => EntryKind::Form(Form::List(List {
+                open: match v.kind {
+                    NodeKind::List => "'(",
+                    _ => "(",
+                },
+                entries: entries(v),
+            })),
+        };
+
+        result.push(Entry {
+            kind,
+            blank_before: newlines >= 2 && !result.is_empty(),
+        });
+        newlines = 0;
+    }
+
+    result
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    out: String,
+    column: usize,
+}
+
+impl Printer<'_> {
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.column = match text.rfind('\n') {
+            Some(v) => text[v + 1..].chars().count(),
+            None => self.column + text.chars().count(),
+        };
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.out.extend(std::iter::repeat_n(' ', indent));
+        self.column = indent;
+    }
+
+    fn entry(&mut self, entry: &Entry) {
+        match &entry.kind {
+            EntryKind::Form(v) => self.form(v),
+            EntryKind::Comment { text, .. } => self.push(text),
+        }
+    }
+
+    fn form(&mut self, form: &Form) {
+        match form {
+            Form::Atom(v) => self.push(v),
+            Form::List(list) => match form.flat() {
+                Some(v) if self.column + v.chars().count() <= self.options.max_width => {
+                    self.push(&v)
+                }
+                _ => self.broken(list),
+            },
+        }
+    }
+
+    fn broken(&mut self, list: &List) {
+        let start: usize = self.column;
+        self.push(list.open);
+
+        // How many forms stay on the opening line, and where the rest go
+        let inner: usize = self.column;
+        let (same_line, indent): (usize, usize) = match list.forms().next() {
+            Some(Form::Atom(head)) if list.open == "(" => match distinguished(head, list) {
+                Some(v) => (v + 1, start + self.options.indent),
+                None => (2, inner + head.chars().count() + 1),
+            },
+            _ => (1, inner),
+        };
+
+        let mut forms: usize = 0;
+        let mut last_comment: bool = false;
+        for (i, entry) in list.entries.iter().enumerate() {
+            match &entry.kind {
+                EntryKind::Comment {
+                    text,
+                    trailing: true,
+                } => {
+                    self.push(" ");
+                    self.push(text);
+                    // Whatever follows a comment starts a new line
+                    forms = forms.max(same_line);
+                }
+                kind => {
+                    if i > 0 {
+                        match forms < same_line {
+                            true => self.push(" "),
+                            false => {
+                                if entry.blank_before {
+                                    self.out.push('\n');
+                                }
+                                self.newline(indent);
+                            }
+                        }
+                    }
+                    self.entry(entry);
+                    forms = match kind {
+                        EntryKind::Form(_) => forms + 1,
+                        EntryKind::Comment { .. } => forms.max(same_line),
+                    };
+                }
+            }
+            last_comment = matches!(entry.kind, EntryKind::Comment { .. });
+        }
+
+        if last_comment {
+            self.newline(indent);
+        }
+        self.push(")");
+    }
+}
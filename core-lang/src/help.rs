@@ -0,0 +1,172 @@
+//! Documentation of bindings, as shown by `describe` and `apropos`.
+//!
+//! Procedures written in core-lang are documented by a string at the start of
+//! their body, and native procedures by the string given to
+//! [`Interpreter::define_native`].
+
+use crate::interpreter::{Interpreter, SPECIAL_FORMS};
+use crate::object::{Arity, Object};
+
+/// What is known about a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Help {
+    pub name: String,
+    pub kind: HelpKind,
+    pub doc: Option<String>,
+    /// `file:line:column` of the definition, for procedures written in core-lang.
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HelpKind {
+    SpecialForm,
+    Procedure {
+        params: Vec<String>,
+        rest: Option<String>,
+    },
+    Native(Arity),
+    Variable,
+}
+
+/// Describes `name` as the global environment of `interpreter` sees it.
+pub fn describe(interpreter: &Interpreter, name: &str) -> Option<Help> {
+    if SPECIAL_FORMS.contains(&name) {
+        return Some(Help {
+            name: name.to_string(),
+            kind: HelpKind::SpecialForm,
+            doc: special_form_doc(name).map(str::to_string),
+            location: None,
+        });
+    }
+
+    let help: Help = match interpreter.global().get(name)? {
+        Object::Procedure(v) => {
+            let (line, column) = v.source.line_col(v.span.start);
+            Help {
+                name: name.to_string(),
+                kind: HelpKind::Procedure {
+                    params: v.params.clone(),
+                    rest: v.rest.clone(),
+                },
+                doc: v.doc.clone(),
+                location: Some(format!("{}:{}:{}", v.source.name, line, column)),
+            }
+        }
+        Object::Native(v) => Help {
+            name: name.to_string(),
+            kind: HelpKind::Native(v.arity),
+            doc: v.doc.clone(),
+            location: None,
+        },
+        _ => Help {
+            name: name.to_string(),
+            kind: HelpKind::Variable,
+            doc: None,
+            location: None,
+        },
+    };
+
+    Some(help)
+}
+
+/// Describes every global binding and special form whose name contains
+/// `pattern`, sorted by name.
+pub fn apropos(interpreter: &Interpreter, pattern: &str) -> Vec<Help> {
+    let mut names: Vec<String> = interpreter
+        .global()
+        .bindings()
+        .into_iter()
+        .map(|(name, _)| name)
+        .chain(SPECIAL_FORMS.iter().map(|v| v.to_string()))
+        .filter(|v| v.contains(pattern))
+        .collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .iter()
+        .filter_map(|v| describe(interpreter, v))
+        .collect()
+}
+
+impl Help {
+    /// The first line of the documentation.
+    pub fn summary(&self) -> Option<&str> {
+        self.doc.as_deref().and_then(|v| v.lines().next())
+    }
+}
+
+impl std::fmt::Display for Help {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            HelpKind::SpecialForm => write!(f, "{}\n  special form", self.name)?,
+            HelpKind::Procedure { params, rest } => {
+                write!(f, "({}", self.name)?;
+                for param in params {
+                    write!(f, " {}", param)?;
+                }
+                if let Some(rest) = rest {
+                    write!(f, " . {}", rest)?;
+                }
+                write!(f, ")\n  procedure")?;
+                if let Some(location) = &self.location {
+                    write!(f, " defined at {}", location)?;
+                }
+            }
+            HelpKind::Native(arity) => write!(
+                f,
+                "{}\n  built-in procedure taking {} argument{}",
+                self.name,
+                arity,
+                if *arity == Arity::exact(1) { "" } else { "s" }
+            )?,
+            HelpKind::Variable => write!(f, "{}\n  variable", self.name)?,
+        }
+
+        if let Some(doc) = &self.doc {
+            writeln!(f)?;
+            for line in doc.lines() {
+                match line.trim() {
+                    "" => writeln!(f)?,
+                    v => write!(f, "\n  {}", v)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn special_form_doc(name: &str) -> Option<&'static str> {
+    let doc: &str = match name {
+        "quote" => "Returns its operand without evaluating it.",
+        "if" => "Evaluates the second operand if the first is true, and the third otherwise.",
+        "define" => {
+            "Binds a name to a value, or to a procedure with (define (name params ...) body ...)."
+        }
+        "set!" => "Changes the value of an existing binding.",
+        "lambda" => "Creates a procedure. A string starting a longer body documents it.",
+        "begin" => "Evaluates its operands in order and returns the last value.",
+        "let" => {
+            "Binds names for the duration of its body. A name before the bindings makes a loop."
+        }
+        "let*" => "Like let, but each binding sees the ones before it.",
+        "and" => "Returns the first false operand, or the last one.",
+        "or" => "Returns the first true operand, or nil.",
+        "when" => "Evaluates its body if the condition is true.",
+        "unless" => "Evaluates its body if the condition is false.",
+        "cond" => "Evaluates the body of the first clause whose condition is true.",
+        "guard" => "Evaluates its body, handling raised objects with the given clauses.",
+        "define-syntax" => "Defines a macro from syntax-rules.",
+        "call/cc" => "Calls a procedure with the current continuation.",
+        "define-test" => "Registers a test for core test.",
+        "assert-error" => "Fails unless evaluating its operand raises an error.",
+        "match" => "Evaluates the body of the first clause whose pattern matches a value.",
+        "define-record-type" => {
+            "Defines a record type with its constructor, predicate, accessors and modifiers."
+        }
+        _ => return None,
+    };
+
+    Some(doc)
+}
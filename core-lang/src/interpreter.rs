@@ -0,0 +1,1291 @@
+use crate::builtins;
+use crate::debugger::{Debugger, Pause, PauseReason, Resume, TraceEvent};
+use crate::environment::Environment;
+use crate::object::{Arity, Condition, ConditionKind, Native, NativeFn, Object, Procedure};
+use crate::parser;
+use crate::profiler::Profiler;
+use crate::record::{Record, RecordSpec, RecordType};
+use crate::sandbox::{Capability, Limit, Sandbox, Usage};
+use crate::syntax::{Source, Span, Syntax, SyntaxKind};
+use crate::testing::Test;
+use std::rc::Rc;
+
+/// Runs core-lang programs.
+///
+/// Unlike [`crate::evaluator::eval`], which only collects the top-level
+/// definitions of a config file, this actually evaluates expressions.
+pub struct Interpreter {
+    global: Environment,
+    handlers: Vec<Handler>,
+    span: Span,
+    sandbox: Sandbox,
+    usage: Usage,
+    source: Rc<Source>,
+    frames: Vec<Frame>,
+    debugger: Option<Debugger>,
+    profiler: Option<Profiler>,
+    /// Registered by `define-test`, waiting to be run.
+    tests: Vec<Test>,
+}
+
+/// An active call of a [`Procedure`].
+#[derive(Clone)]
+pub struct Frame {
+    pub procedure: Rc<Procedure>,
+    /// Where the procedure was called from.
+    pub call: Span,
+    pub env: Environment,
+}
+
+/// How much stack must be left before a procedure call, and how much more to
+/// allocate when there is not.
+const STACK_RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT: usize = 2 * 1024 * 1024;
+
+/// Names evaluated by the interpreter itself rather than bound to procedures.
+pub const SPECIAL_FORMS: &[&str] = &[
+    "quote",
+    "if",
+    "define",
+    "set!",
+    "lambda",
+    "begin",
+    "let",
+    "let*",
+    "and",
+    "or",
+    "when",
+    "unless",
+    "cond",
+    "guard",
+    "define-syntax",
+    "call/cc",
+    "define-test",
+    "assert-error",
+    "match",
+    "define-record-type",
+];
+
+/// An entry of the exception handler stack.
+#[derive(Clone)]
+enum Handler {
+    /// Installed by `with-exception-handler`.
+    Procedure(Object),
+    /// Installed by `guard`. Raising unwinds the Rust stack up to the guard.
+    Guard,
+}
+
+/// Why evaluation stopped before producing a value.
+#[derive(Debug)]
+pub enum Unwind {
+    /// An object was raised and no handler took care of it.
+    Raise(Object),
+    /// A budget of the [`Sandbox`] ran out. This cannot be caught by the script.
+    Limit(Limit),
+    /// The debugger was told to abort.
+    Interrupted,
+}
+
+impl std::fmt::Display for Unwind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unwind::Raise(Object::Condition(v)) => write!(f, "RUNTIME_ERROR: {}", v),
+            Unwind::Raise(v) => write!(f, "RUNTIME_ERROR: uncaught exception: {}", v),
+            Unwind::Limit(v) => write!(f, "RUNTIME_ERROR: evaluation {}", v),
+            Unwind::Interrupted => write!(f, "RUNTIME_ERROR: evaluation was interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Unwind {}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut interpreter = Interpreter {
+            global: Environment::new(),
+            handlers: Vec::new(),
+            span: Span::default(),
+            sandbox: Sandbox::default(),
+            usage: Usage::default(),
+            source: Rc::new(Source::new("<eval>", "")),
+            frames: Vec::new(),
+            debugger: None,
+            profiler: None,
+            tests: Vec::new(),
+        };
+        builtins::install(&mut interpreter);
+
+        interpreter
+    }
+
+    pub fn global(&self) -> &Environment {
+        &self.global
+    }
+
+    /// The span of the call currently being evaluated.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.sandbox = sandbox;
+    }
+
+    /// The resources used by the current, or last, evaluation.
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    /// The program text currently being evaluated.
+    pub fn source(&self) -> &Rc<Source> {
+        &self.source
+    }
+
+    /// The procedure calls currently active, outermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn attach_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(debugger);
+    }
+
+    pub fn detach_debugger(&mut self) -> Option<Debugger> {
+        self.debugger.take()
+    }
+
+    pub fn debugger_mut(&mut self) -> Option<&mut Debugger> {
+        self.debugger.as_mut()
+    }
+
+    pub fn attach_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
+    pub fn detach_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    /// Registers a procedure implemented in Rust in the global environment,
+    /// with `doc` as its documentation.
+    ///
+    /// An `Err` returned by `function` is raised as an error object, so it
+    /// can be caught by `guard` or `with-exception-handler`.
+    pub fn define_native<F>(&mut self, name: &str, arity: Arity, doc: &str, function: F)
+    where
+        F: Fn(&mut Interpreter, Vec<Object>) -> Result<Object, Box<dyn std::error::Error>>
+            + 'static,
+    {
+        self.define(name, arity, None, doc, Box::new(function));
+    }
+
+    /// Like [`Interpreter::define_native`], but calling the procedure raises an
+    /// error unless the sandbox grants `capability`.
+    pub fn define_privileged<F>(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        capability: Capability,
+        doc: &str,
+        function: F,
+    ) where
+        F: Fn(&mut Interpreter, Vec<Object>) -> Result<Object, Box<dyn std::error::Error>>
+            + 'static,
+    {
+        self.define(name, arity, Some(capability), doc, Box::new(function));
+    }
+
+    fn define(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        capability: Option<Capability>,
+        doc: &str,
+        function: Box<NativeFn>,
+    ) {
+        let native = Native {
+            name: name.to_string(),
+            arity,
+            capability,
+            function,
+            doc: Some(doc.to_string()),
+        };
+        self.global.define(name, Object::Native(Rc::new(native)));
+    }
+
+    /// Takes the tests registered by `define-test` so far.
+    pub fn take_tests(&mut self) -> Vec<Test> {
+        std::mem::take(&mut self.tests)
+    }
+
+    /// Runs the body of `test` in a scope of its own.
+    pub fn run_test(&mut self, test: &Test) -> Result<Object, Unwind> {
+        self.usage = Usage::default();
+        let saved: Rc<Source> = std::mem::replace(&mut self.source, test.source.clone());
+        let result: Result<Object, Unwind> = self.eval_body(&test.body, &test.env.child());
+        self.source = saved;
+
+        result
+    }
+
+    /// Evaluates every top-level expression of `program`, returning the last value.
+    pub fn eval_str(&mut self, program: &str) -> Result<Object, Box<dyn std::error::Error>> {
+        self.eval_source(Source::new("<eval>", program))
+    }
+
+    /// Like [`Interpreter::eval_str`], but procedures defined by `source`
+    /// remember its name, for breakpoints and error locations.
+    pub fn eval_source(&mut self, source: Source) -> Result<Object, Box<dyn std::error::Error>> {
+        let global: Environment = self.global.clone();
+        self.eval_source_in(source, &global)
+    }
+
+    /// Evaluates `program` in `env` instead of the global environment, e.g. in
+    /// the environment of a [`Pause`].
+    pub fn eval_in(
+        &mut self,
+        program: &str,
+        env: &Environment,
+    ) -> Result<Object, Box<dyn std::error::Error>> {
+        self.eval_source_in(Source::new("<eval>", program), env)
+    }
+
+    fn eval_source_in(
+        &mut self,
+        source: Source,
+        env: &Environment,
+    ) -> Result<Object, Box<dyn std::error::Error>> {
+        let syntax: Vec<Syntax> = parser::parse_spanned(&source.text)?;
+
+        // Nested calls from native functions share the budget of the outer one
+        if self.usage.depth == 0 {
+            self.usage = Usage::default();
+        }
+
+        let saved: Rc<Source> = std::mem::replace(&mut self.source, Rc::new(source));
+        let mut result: Result<Object, Unwind> = Ok(Object::Unspecified);
+        for s in syntax {
+            result = self.eval(&s, env);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.source = saved;
+
+        Ok(result?)
+    }
+
+    pub fn eval(&mut self, syntax: &Syntax, env: &Environment) -> Result<Object, Unwind> {
+        self.sandbox.step(&mut self.usage).map_err(Unwind::Limit)?;
+
+        if self.debugger.is_some() {
+            if let SyntaxKind::SExpression(_) = syntax.kind {
+                return self.eval_debug(syntax, env);
+            }
+        }
+
+        self.eval_unchecked(syntax, env)
+    }
+
+    fn eval_unchecked(&mut self, syntax: &Syntax, env: &Environment) -> Result<Object, Unwind> {
+        match &syntax.kind {
+            SyntaxKind::Number(v) => match i64::try_from(*v) {
+                Ok(v) => Ok(Object::Number(v)),
+                Err(_) => self.error_at(syntax.span, "number out of range", vec![]),
+            },
+            SyntaxKind::String(v) => self.allocate(Object::string(v)),
+            SyntaxKind::List(_) => self.quote(syntax),
+            SyntaxKind::Word(v) => self.lookup(v, syntax.span, env),
+            SyntaxKind::Literal(v) => self.lookup(&v.to_string(), syntax.span, env),
+            SyntaxKind::SExpression(v) => self.eval_sexpr(v, syntax.span, env),
+        }
+    }
+
+    /// Evaluates a form, pausing first if the debugger asks to.
+    fn eval_debug(&mut self, syntax: &Syntax, env: &Environment) -> Result<Object, Unwind> {
+        let Some(mut debugger) = self.debugger.take() else {
+            return self.eval_unchecked(syntax, env);
+        };
+
+        let (line, column) = self.source.line_col(syntax.span.start);
+        let outer: Option<usize> = debugger.line.replace(line);
+        let reason: Option<PauseReason> =
+            debugger.should_pause(&self.source, line, outer, self.frames.len());
+
+        let mut resume: Resume = Resume::Continue;
+        if let Some(reason) = reason {
+            let pause = Pause {
+                reason,
+                source: self.source.clone(),
+                span: syntax.span,
+                line,
+                column,
+                env: env.clone(),
+                frames: self.frames.clone(),
+            };
+            // The debugger is detached while its handler runs
+            resume = debugger.pause(self, &pause);
+        }
+        self.debugger = Some(debugger);
+
+        if resume == Resume::Abort {
+            return Err(Unwind::Interrupted);
+        }
+
+        let result: Result<Object, Unwind> = self.eval_unchecked(syntax, env);
+        if let Some(debugger) = &mut self.debugger {
+            debugger.line = outer;
+        }
+
+        result
+    }
+
+    /// Calls `function` with `args`.
+    ///
+    /// Called from outside any evaluation, e.g. by a host running a
+    /// procedure a script handed it, the call gets a fresh budget.
+    pub fn apply(&mut self, function: &Object, args: Vec<Object>) -> Result<Object, Unwind> {
+        if self.usage.depth == 0 {
+            self.usage = Usage::default();
+        }
+
+        self.call(function, args)
+    }
+
+    /// Calls `function` with `args`, sharing the budget of the evaluation.
+    fn call(&mut self, function: &Object, args: Vec<Object>) -> Result<Object, Unwind> {
+        let traced: Option<String> = match &self.debugger {
+            Some(debugger) => match function {
+                Object::Procedure(v) => v.name.clone(),
+                Object::Native(v) => Some(v.name.clone()),
+                _ => None,
+            }
+            .filter(|v| debugger.is_traced(v)),
+            None => None,
+        };
+        if let Some(name) = &traced {
+            self.trace(TraceEvent::Call {
+                name: name.clone(),
+                args: args.clone(),
+                depth: self.frames.len(),
+            });
+        }
+
+        let result: Result<Object, Unwind> = match self.sandbox.enter(&mut self.usage) {
+            // Grow the stack on demand, so the depth limit is what stops deep recursion
+            Ok(()) => stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+                self.apply_unchecked(function, args)
+            }),
+            Err(e) => Err(Unwind::Limit(e)),
+        };
+        self.usage.depth -= 1;
+
+        if let (Some(name), Ok(value)) = (traced, &result) {
+            self.trace(TraceEvent::Return {
+                name,
+                value: value.clone(),
+                depth: self.frames.len(),
+            });
+        }
+
+        result
+    }
+
+    fn trace(&mut self, event: TraceEvent) {
+        if let Some(debugger) = &mut self.debugger {
+            debugger.emit(&event);
+        }
+    }
+
+    fn apply_unchecked(&mut self, function: &Object, args: Vec<Object>) -> Result<Object, Unwind> {
+        match function {
+            Object::Procedure(v) => self.apply_procedure(v, args),
+            Object::Native(v) => {
+                if !v.arity.accepts(args.len()) {
+                    let message: String = format!(
+                        "{}: expected {} arguments, got {}",
+                        v.name,
+                        v.arity,
+                        args.len()
+                    );
+                    return self.error(&message, vec![]);
+                }
+                if let Some(capability) = v.capability {
+                    if !self.sandbox.allows(capability) {
+                        let message: String =
+                            format!("{}: permission denied, requires {}", v.name, capability);
+                        return self.error(&message, vec![]);
+                    }
+                }
+
+                match (v.function)(self, args) {
+                    Ok(v) => self.allocate(v),
+                    Err(e) => match e.downcast::<Unwind>() {
+                        Ok(e) => Err(*e),
+                        Err(e) => {
+                            let condition: Condition = Condition {
+                                kind: ConditionKind::Native,
+                                message: format!("{}: {}", v.name, e),
+                                irritants: vec![],
+                                span: Some(self.span),
+                            };
+                            self.raise(Object::Condition(Rc::new(condition)), false)
+                        }
+                    },
+                }
+            }
+            _ => self.error("not a procedure", vec![function.clone()]),
+        }
+    }
+
+    /// Hands `object` to the innermost exception handler.
+    ///
+    /// For a continuable raise the handler's return value is returned. For a
+    /// non-continuable one, returning from the handler raises a secondary error
+    /// in the context of the outer handler.
+    pub fn raise(&mut self, object: Object, continuable: bool) -> Result<Object, Unwind> {
+        let handler: Object = match self.handlers.last() {
+            Some(Handler::Procedure(v)) => v.clone(),
+            Some(Handler::Guard) | None => return Err(Unwind::Raise(object)),
+        };
+
+        // The handler runs with the outer handlers installed
+        let saved: Option<Handler> = self.handlers.pop();
+        let result: Result<Object, Unwind> = match self.call(&handler, vec![object.clone()]) {
+            Ok(v) if continuable => Ok(v),
+            Ok(_) => self.error("handler returned from non-continuable raise", vec![object]),
+            Err(e) => Err(e),
+        };
+        self.handlers.extend(saved);
+
+        result
+    }
+
+    /// Charges the memory held by `object` to the heap budget.
+    fn allocate(&mut self, object: Object) -> Result<Object, Unwind> {
+        let bytes: usize = object.heap_size();
+        if let Some(profiler) = &mut self.profiler {
+            if bytes != 0 {
+                profiler.allocate(bytes);
+            }
+        }
+        self.sandbox
+            .allocate(&mut self.usage, bytes)
+            .map_err(Unwind::Limit)?;
+
+        Ok(object)
+    }
+
+    /// Raises a new error object located at the current call.
+    pub fn error<T>(&mut self, message: &str, irritants: Vec<Object>) -> Result<T, Unwind> {
+        self.error_at(self.span, message, irritants)
+    }
+
+    fn error_at<T>(
+        &mut self,
+        span: Span,
+        message: &str,
+        irritants: Vec<Object>,
+    ) -> Result<T, Unwind> {
+        let condition: Condition = Condition::new(message, irritants, Some(span));
+        match self.raise(Object::Condition(Rc::new(condition)), false) {
+            Ok(_) => unreachable!("non-continuable raise returned a value"),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Calls `thunk` with `handler` installed as the innermost exception handler.
+    pub fn with_exception_handler(
+        &mut self,
+        handler: Object,
+        thunk: &Object,
+    ) -> Result<Object, Unwind> {
+        self.handlers.push(Handler::Procedure(handler));
+        let result: Result<Object, Unwind> = self.call(thunk, vec![]);
+        self.handlers.pop();
+
+        result
+    }
+
+    fn lookup(&mut self, name: &str, span: Span, env: &Environment) -> Result<Object, Unwind> {
+        match env.get(name) {
+            Some(v) => Ok(v),
+            None => self.error_at(span, "unbound variable", vec![Object::symbol(name)]),
+        }
+    }
+
+    fn eval_sexpr(
+        &mut self,
+        items: &[Syntax],
+        span: Span,
+        env: &Environment,
+    ) -> Result<Object, Unwind> {
+        let Some(head) = items.first() else {
+            return Ok(Object::empty_list());
+        };
+
+        if let Some(name) = head.as_word() {
+            let saved: Span = std::mem::replace(&mut self.span, span);
+            let result: Option<Result<Object, Unwind>> =
+                self.eval_special_form(&name, &items[1..], span, env);
+            self.span = saved;
+
+            if let Some(result) = result {
+                return result;
+            }
+        }
+
+        let function: Object = self.eval(head, env)?;
+        let mut args: Vec<Object> = Vec::with_capacity(items.len() - 1);
+        for item in &items[1..] {
+            args.push(self.eval(item, env)?);
+        }
+
+        let saved: Span = std::mem::replace(&mut self.span, span);
+        let result: Result<Object, Unwind> = self.call(&function, args);
+        self.span = saved;
+
+        result
+    }
+
+    /// Evaluates `(name args ...)` if `name` is a special form.
+    #[inline(never)]
+    fn eval_special_form(
+        &mut self,
+        name: &str,
+        args: &[Syntax],
+        span: Span,
+        env: &Environment,
+    ) -> Option<Result<Object, Unwind>> {
+        let result: Result<Object, Unwind> = match name {
+            "quote" => self.eval_quote(args),
+            "if" => self.eval_if(args, env),
+            "define" => self.eval_define(args, env),
+            "set!" => self.eval_set(args, env),
+            "lambda" => self.eval_lambda(None, args, span, env),
+            "begin" => self.eval_body(args, env),
+            "let" => self.eval_let(args, span, env),
+            "let*" => self.eval_let_star(args, env),
+            "and" => self.eval_and(args, env),
+            "or" => self.eval_or(args, env),
+            "when" => self.eval_when(args, env, true),
+            "unless" => self.eval_when(args, env, false),
+            "cond" => self.eval_cond(args, env),
+            "guard" => self.eval_guard(args, env),
+            "define-test" => self.eval_define_test(args, span, env),
+            "assert-error" => self.eval_assert_error(args, env),
+            "match" => self.eval_match(args, env),
+            "define-record-type" => self.eval_define_record_type(args, env),
+            "define-syntax" | "call/cc" => {
+                self.error("not supported yet", vec![Object::symbol(name)])
+            }
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    fn apply_procedure(
+        &mut self,
+        procedure: &Rc<Procedure>,
+        args: Vec<Object>,
+    ) -> Result<Object, Unwind> {
+        let count: usize = procedure.params.len();
+        if args.len() < count || (procedure.rest.is_none() && args.len() > count) {
+            let arity: Arity = match procedure.rest {
+                Some(_) => Arity::at_least(count),
+                None => Arity::exact(count),
+            };
+            let message: String = format!(
+                "{}: expected {} arguments, got {}",
+                procedure.name.as_deref().unwrap_or("lambda"),
+                arity,
+                args.len()
+            );
+            return self.error(&message, vec![]);
+        }
+
+        let scope: Environment = procedure.env.child();
+        let mut args = args.into_iter();
+        for param in &procedure.params {
+            scope.define(param, args.next().unwrap_or(Object::Unspecified));
+        }
+        if let Some(rest) = &procedure.rest {
+            scope.define(rest, Object::list(args.collect()));
+        }
+
+        self.frames.push(Frame {
+            procedure: procedure.clone(),
+            call: self.span,
+            env: scope.clone(),
+        });
+        let source: Rc<Source> = std::mem::replace(&mut self.source, procedure.source.clone());
+        let line: Option<usize> = self.debugger.as_mut().and_then(|v| v.line.take());
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(&procedure_name(procedure));
+        }
+
+        let result: Result<Object, Unwind> = self.eval_body(&procedure.body, &scope);
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
+        if let Some(debugger) = &mut self.debugger {
+            debugger.line = line;
+        }
+        self.source = source;
+        self.frames.pop();
+
+        result
+    }
+
+    fn eval_body(&mut self, body: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let mut result: Object = Object::Unspecified;
+        for s in body {
+            result = self.eval(s, env)?;
+        }
+
+        Ok(result)
+    }
+
+    fn eval_quote(&mut self, args: &[Syntax]) -> Result<Object, Unwind> {
+        match args {
+            [v] => self.quote(v),
+            _ => self.error("quote: expected 1 argument", vec![]),
+        }
+    }
+
+    fn eval_if(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let (test, consequent, alternative) = match args {
+            [test, consequent] => (test, consequent, None),
+            [test, consequent, alternative] => (test, consequent, Some(alternative)),
+            _ => return self.error("if: expected 2 or 3 arguments", vec![]),
+        };
+
+        if self.eval(test, env)?.is_true() {
+            self.eval(consequent, env)
+        } else {
+            match alternative {
+                Some(v) => self.eval(v, env),
+                None => Ok(Object::Unspecified),
+            }
+        }
+    }
+
+    fn eval_define(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let Some(target) = args.first() else {
+            return self.error("define: expected a name", vec![]);
+        };
+
+        match &target.kind {
+            // (define (name params ...) body ...)
+            SyntaxKind::SExpression(v) => {
+                let Some(name) = v.first().and_then(Syntax::as_word) else {
+                    return self.error("define: expected a name", vec![]);
+                };
+                let params: Syntax =
+                    Syntax::new(SyntaxKind::SExpression(v[1..].into()), target.span);
+                let mut lambda: Vec<Syntax> = vec![params];
+                lambda.extend_from_slice(&args[1..]);
+
+                let value: Object = self.eval_lambda(Some(&name), &lambda, self.span, env)?;
+                env.define(&name, value);
+            }
+            _ => {
+                let Some(name) = target.as_word() else {
+                    return self.error("define: expected a name", vec![]);
+                };
+                let value: Object = match &args[1..] {
+                    [] => Object::Unspecified,
+                    [v] => self.eval(v, env)?,
+                    _ => return self.error("define: expected 1 value", vec![]),
+                };
+
+                // Name anonymous procedures after the variable they are bound to
+                let value: Object = match value {
+                    Object::Procedure(v) if v.name.is_none() => {
+                        Object::Procedure(Rc::new(Procedure {
+                            name: Some(name.clone()),
+                            params: v.params.clone(),
+                            rest: v.rest.clone(),
+                            body: v.body.clone(),
+                            env: v.env.clone(),
+                            span: v.span,
+                            source: v.source.clone(),
+                            doc: v.doc.clone(),
+                        }))
+                    }
+                    v => v,
+                };
+                env.define(&name, value);
+            }
+        }
+
+        Ok(Object::Unspecified)
+    }
+
+    fn eval_set(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let [target, value] = args else {
+            return self.error("set!: expected 2 arguments", vec![]);
+        };
+        let Some(name) = target.as_word() else {
+            return self.error("set!: expected a name", vec![]);
+        };
+
+        let value: Object = self.eval(value, env)?;
+        if !env.set(&name, value) {
+            return self.error("set!: unbound variable", vec![Object::symbol(&name)]);
+        }
+
+        Ok(Object::Unspecified)
+    }
+
+    fn eval_lambda(
+        &mut self,
+        name: Option<&str>,
+        args: &[Syntax],
+        span: Span,
+        env: &Environment,
+    ) -> Result<Object, Unwind> {
+        let Some(formals) = args.first() else {
+            return self.error("lambda: expected parameters", vec![]);
+        };
+
+        let mut params: Vec<String> = Vec::new();
+        let mut rest: Option<String> = None;
+        match &formals.kind {
+            // (lambda args body ...)
+            SyntaxKind::Word(v) => rest = Some(v.clone()),
+            // (lambda (a b . rest) body ...)
+            SyntaxKind::SExpression(v) | SyntaxKind::List(v) => {
+                let mut words = v.iter();
+                while let Some(word) = words.next() {
+                    match word.as_word() {
+                        Some(v) if v == "." => {
+                            rest = words.next().and_then(Syntax::as_word);
+                            if rest.is_none() || words.next().is_some() {
+                                return self.error("lambda: malformed rest parameter", vec![]);
+                            }
+                        }
+                        Some(v) => params.push(v),
+                        None => return self.error("lambda: parameters must be names", vec![]),
+                    }
+                }
+            }
+            _ => return self.error("lambda: expected parameters", vec![]),
+        }
+
+        // (lambda (x) "docstring" body ...)
+        let doc: Option<String> = match &args[1..] {
+            [first, _, ..] => match &first.kind {
+                SyntaxKind::String(v) => Some(v.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        self.allocate(Object::Procedure(Rc::new(Procedure {
+            name: name.map(str::to_string),
+            params,
+            rest,
+            body: args[1..].into(),
+            env: env.clone(),
+            span,
+            source: self.source.clone(),
+            doc,
+        })))
+    }
+
+    fn eval_let(
+        &mut self,
+        args: &[Syntax],
+        span: Span,
+        env: &Environment,
+    ) -> Result<Object, Unwind> {
+        // (let name ((var init) ...) body ...)
+        if let Some(name) = args.first().and_then(|v| match v.kind {
+            SyntaxKind::Word(_) => v.as_word(),
+            _ => None,
+        }) {
+            let Some(bindings) = args.get(1) else {
+                return self.error("let: expected bindings", vec![]);
+            };
+            let (vars, inits) = self.let_bindings(bindings)?;
+
+            let mut values: Vec<Object> = Vec::new();
+            for init in &inits {
+                values.push(self.eval(init, env)?);
+            }
+
+            let scope: Environment = env.child();
+            let params: Vec<Syntax> = vars
+                .iter()
+                .map(|v| Syntax::new(SyntaxKind::Word(v.clone()), bindings.span))
+                .collect();
+            let mut lambda: Vec<Syntax> = vec![Syntax::new(
+                SyntaxKind::SExpression(params.into()),
+                bindings.span,
+            )];
+            lambda.extend_from_slice(&args[2..]);
+
+            let procedure: Object = self.eval_lambda(Some(&name), &lambda, span, &scope)?;
+            scope.define(&name, procedure.clone());
+
+            return self.call(&procedure, values);
+        }
+
+        let Some(bindings) = args.first() else {
+            return self.error("let: expected bindings", vec![]);
+        };
+        let (vars, inits) = self.let_bindings(bindings)?;
+
+        let scope: Environment = env.child();
+        for (var, init) in vars.iter().zip(inits.iter()) {
+            let value: Object = self.eval(init, env)?;
+            scope.define(var, value);
+        }
+
+        self.eval_body(&args[1..], &scope)
+    }
+
+    fn eval_let_star(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let Some(bindings) = args.first() else {
+            return self.error("let*: expected bindings", vec![]);
+        };
+        let (vars, inits) = self.let_bindings(bindings)?;
+
+        let mut scope: Environment = env.clone();
+        for (var, init) in vars.iter().zip(inits.iter()) {
+            let value: Object = self.eval(init, &scope)?;
+            scope = scope.child();
+            scope.define(var, value);
+        }
+
+        self.eval_body(&args[1..], &scope.child())
+    }
+
+    fn let_bindings(&mut self, bindings: &Syntax) -> Result<(Vec<String>, Vec<Syntax>), Unwind> {
+        let mut vars: Vec<String> = Vec::new();
+        let mut inits: Vec<Syntax> = Vec::new();
+
+        let Some(items) = bindings.as_sexpr() else {
+            return self.error("let: expected a list of bindings", vec![]);
+        };
+        for item in items {
+            match item.as_sexpr() {
+                Some([name, init]) if name.as_word().is_some() => {
+                    vars.push(name.as_word().unwrap_or_default());
+                    inits.push(init.clone());
+                }
+                _ => return self.error("let: malformed binding", vec![]),
+            }
+        }
+
+        Ok((vars, inits))
+    }
+
+    fn eval_and(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let mut result: Object = Object::Boolean(true);
+        for s in args {
+            result = self.eval(s, env)?;
+            if !result.is_true() {
+                break;
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn eval_or(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        for s in args {
+            let result: Object = self.eval(s, env)?;
+            if result.is_true() {
+                return Ok(result);
+            }
+        }
+
+        Ok(Object::nil())
+    }
+
+    fn eval_when(
+        &mut self,
+        args: &[Syntax],
+        env: &Environment,
+        expected: bool,
+    ) -> Result<Object, Unwind> {
+        let Some(test) = args.first() else {
+            return self.error("when: expected a test", vec![]);
+        };
+
+        if self.eval(test, env)?.is_true() == expected {
+            self.eval_body(&args[1..], env)
+        } else {
+            Ok(Object::Unspecified)
+        }
+    }
+
+    fn eval_cond(&mut self, clauses: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        for clause in clauses {
+            let Some([test, body @ ..]) = clause.as_sexpr() else {
+                return self.error("cond: malformed clause", vec![]);
+            };
+
+            let value: Object = match test.as_word().as_deref() {
+                Some("else") => Object::Boolean(true),
+                _ => self.eval(test, env)?,
+            };
+            if value.is_true() {
+                return match body {
+                    [] => Ok(value),
+                    _ => self.eval_body(body, env),
+                };
+            }
+        }
+
+        Ok(Object::Unspecified)
+    }
+
+    /// `(match value (pattern body ...) ...)` evaluates the body of the first
+    /// clause whose pattern matches, with the variables of the pattern bound.
+    /// A clause may start its body with `(guard test)` to also require `test`.
+    fn eval_match(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let Some((value, clauses)) = args.split_first() else {
+            return self.error("match: expected a value", vec![]);
+        };
+        let value: Object = self.eval(value, env)?;
+
+        for clause in clauses {
+            let Some([pattern, body @ ..]) = clause.as_sexpr() else {
+                return self.error("match: malformed clause", vec![]);
+            };
+
+            let scope: Environment = env.child();
+            if !self.match_pattern(pattern, &value, &scope)? {
+                continue;
+            }
+            let body: &[Syntax] = match body {
+                [first, rest @ ..] => match first.as_sexpr() {
+                    Some([head, test]) if head.as_word().as_deref() == Some("guard") => {
+                        if !self.eval(test, &scope)?.is_true() {
+                            continue;
+                        }
+                        rest
+                    }
+                    _ => body,
+                },
+                [] => body,
+            };
+
+            return self.eval_body(body, &scope);
+        }
+
+        self.error("match: no clause matched", vec![value])
+    }
+
+    /// Matches `value` against `pattern`, defining its variables in `scope`:
+    ///
+    /// - `_` matches anything, and any other name matches anything and binds it
+    /// - numbers, strings, `t`, `nil`, `'(...)` and `(quote datum)` match equal values
+    /// - `(list p ...)` matches lists of as many elements, and
+    ///   `(list p ... . rest)` lists of at least as many, binding the rest
+    /// - `(? predicate p ...)` matches values for which `predicate` is true and
+    ///   which match every `p`
+    fn match_pattern(
+        &mut self,
+        pattern: &Syntax,
+        value: &Object,
+        scope: &Environment,
+    ) -> Result<bool, Unwind> {
+        match &pattern.kind {
+            SyntaxKind::Word(v) if v == "_" => Ok(true),
+            SyntaxKind::Word(v) if v == "t" || v == "nil" => Ok(self.quote(pattern)? == *value),
+            SyntaxKind::Word(_) | SyntaxKind::Literal(_) => {
+                scope.define(&pattern.as_word().unwrap_or_default(), value.clone());
+                Ok(true)
+            }
+            SyntaxKind::Number(_) | SyntaxKind::String(_) | SyntaxKind::List(_) => {
+                Ok(self.quote(pattern)? == *value)
+            }
+            SyntaxKind::SExpression(items) => match items.split_first() {
+                Some((head, [datum])) if head.as_word().as_deref() == Some("quote") => {
+                    Ok(self.quote(datum)? == *value)
+                }
+                Some((head, patterns)) if head.as_word().as_deref() == Some("list") => {
+                    let Object::List(values) = value else {
+                        return Ok(false);
+                    };
+                    let (patterns, rest): (&[Syntax], Option<&Syntax>) = match patterns {
+                        [init @ .., dot, rest] if dot.as_word().as_deref() == Some(".") => {
+                            (init, Some(rest))
+                        }
+                        _ => (patterns, None),
+                    };
+                    if values.len() < patterns.len()
+                        || (rest.is_none() && values.len() > patterns.len())
+                    {
+                        return Ok(false);
+                    }
+
+                    for (pattern, value) in patterns.iter().zip(values.iter()) {
+                        if !self.match_pattern(pattern, value, scope)? {
+                            return Ok(false);
+                        }
+                    }
+                    match rest {
+                        Some(v) => {
+                            let rest: Object = Object::list(values[patterns.len()..].to_vec());
+                            self.match_pattern(v, &rest, scope)
+                        }
+                        None => Ok(true),
+                    }
+                }
+                Some((head, [predicate, patterns @ ..]))
+                    if head.as_word().as_deref() == Some("?") =>
+                {
+                    let predicate: Object = self.eval(predicate, scope)?;
+                    if !self.call(&predicate, vec![value.clone()])?.is_true() {
+                        return Ok(false);
+                    }
+                    for pattern in patterns {
+                        if !self.match_pattern(pattern, value, scope)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    Ok(true)
+                }
+                _ => self.error_at(pattern.span, "match: unknown pattern", vec![]),
+            },
+        }
+    }
+
+    /// Defines the constructor, predicate, accessors and modifiers of a new
+    /// record type, as described by [`RecordSpec`].
+    fn eval_define_record_type(
+        &mut self,
+        args: &[Syntax],
+        env: &Environment,
+    ) -> Result<Object, Unwind> {
+        let spec: RecordSpec = match RecordSpec::parse(args) {
+            Ok(v) => v,
+            Err(e) => return self.error(&format!("define-record-type: {}", e), vec![]),
+        };
+        let kind: Rc<RecordType> = Rc::new(RecordType {
+            name: spec.name.clone(),
+            fields: spec.fields.iter().map(|v| v.name.clone()).collect(),
+        });
+
+        if let Some((name, fields)) = &spec.constructor {
+            let indices: Vec<usize> = fields.iter().filter_map(|v| kind.index(v)).collect();
+            let record: Rc<RecordType> = kind.clone();
+            let native: Native = Native {
+                name: name.name.clone(),
+                arity: Arity::exact(indices.len()),
+                capability: None,
+                function: Box::new(move |_, args| {
+                    let mut values: Vec<Object> = vec![Object::Unspecified; record.fields.len()];
+                    for (index, value) in indices.iter().zip(args) {
+                        values[*index] = value;
+                    }
+
+                    Ok(Object::Record(Rc::new(Record::new(record.clone(), values))))
+                }),
+                doc: Some(format!("Makes a {} record.", kind.name)),
+            };
+            env.define(&name.name, Object::Native(Rc::new(native)));
+        }
+
+        let record: Rc<RecordType> = kind.clone();
+        let native: Native = Native {
+            name: spec.predicate.name.clone(),
+            arity: Arity::exact(1),
+            capability: None,
+            function: Box::new(move |_, args| {
+                let result: bool =
+                    matches!(&args[0], Object::Record(v) if Rc::ptr_eq(&v.kind, &record));
+                Ok(Object::Boolean(result))
+            }),
+            doc: Some(format!("Whether the argument is a {} record.", kind.name)),
+        };
+        env.define(&spec.predicate.name, Object::Native(Rc::new(native)));
+
+        for field in &spec.fields {
+            if let Some(accessor) = &field.accessor {
+                let (record, name): (Rc<RecordType>, String) = (kind.clone(), field.name.clone());
+                let native: Native = Native {
+                    name: accessor.name.clone(),
+                    arity: Arity::exact(1),
+                    capability: None,
+                    function: Box::new(move |_, args| {
+                        Ok(record_of(&record, &args[0])?
+                            .get(&name)
+                            .unwrap_or(Object::Unspecified))
+                    }),
+                    doc: Some(format!("The {} of a {} record.", field.name, kind.name)),
+                };
+                env.define(&accessor.name, Object::Native(Rc::new(native)));
+            }
+            if let Some(modifier) = &field.modifier {
+                let (record, name): (Rc<RecordType>, String) = (kind.clone(), field.name.clone());
+                let native: Native = Native {
+                    name: modifier.name.clone(),
+                    arity: Arity::exact(2),
+                    capability: None,
+                    function: Box::new(move |_, mut args| {
+                        let value: Object = args.pop().unwrap_or(Object::Unspecified);
+                        record_of(&record, &args[0])?.set(&name, value);
+                        Ok(Object::Unspecified)
+                    }),
+                    doc: Some(format!(
+                        "Changes the {} of a {} record.",
+                        field.name, kind.name
+                    )),
+                };
+                env.define(&modifier.name, Object::Native(Rc::new(native)));
+            }
+        }
+
+        Ok(Object::Unspecified)
+    }
+
+    /// `(define-test name body ...)`
+    fn eval_define_test(
+        &mut self,
+        args: &[Syntax],
+        span: Span,
+        env: &Environment,
+    ) -> Result<Object, Unwind> {
+        let name: String = match args.first().map(|v| &v.kind) {
+            Some(SyntaxKind::String(v) | SyntaxKind::Word(v)) => v.clone(),
+            _ => return self.error("define-test: expected a name", vec![]),
+        };
+
+        self.tests.push(Test {
+            name,
+            span,
+            body: args[1..].into(),
+            env: env.clone(),
+            source: self.source.clone(),
+        });
+
+        Ok(Object::Unspecified)
+    }
+
+    /// `(assert-error expr)` succeeds only if evaluating `expr` raises.
+    fn eval_assert_error(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let [expr] = args else {
+            return self.error("assert-error: expected 1 argument", vec![]);
+        };
+
+        let depth: usize = self.handlers.len();
+        self.handlers.push(Handler::Guard);
+        let result: Result<Object, Unwind> = self.eval(expr, env);
+        self.handlers.truncate(depth);
+
+        match result {
+            Ok(v) => self.error("assert-error: expected an error, got", vec![v]),
+            Err(Unwind::Raise(_)) => Ok(Object::Unspecified),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `(guard (var clause ...) body ...)`
+    fn eval_guard(&mut self, args: &[Syntax], env: &Environment) -> Result<Object, Unwind> {
+        let Some([var, clauses @ ..]) = args.first().and_then(Syntax::as_sexpr) else {
+            return self.error("guard: expected (var clause ...)", vec![]);
+        };
+        let Some(var) = var.as_word() else {
+            return self.error("guard: expected a variable name", vec![]);
+        };
+
+        let depth: usize = self.handlers.len();
+        self.handlers.push(Handler::Guard);
+        let result: Result<Object, Unwind> = self.eval_body(&args[1..], env);
+        self.handlers.truncate(depth);
+
+        match result {
+            Err(Unwind::Raise(object)) => {
+                let scope: Environment = env.child();
+                scope.define(&var, object.clone());
+
+                for clause in clauses {
+                    let Some([test, body @ ..]) = clause.as_sexpr() else {
+                        return self.error("guard: malformed clause", vec![]);
+                    };
+
+                    let value: Object = match test.as_word().as_deref() {
+                        Some("else") => Object::Boolean(true),
+                        _ => self.eval(test, &scope)?,
+                    };
+                    if value.is_true() {
+                        return match body {
+                            [] => Ok(value),
+                            _ => self.eval_body(body, &scope),
+                        };
+                    }
+                }
+
+                // No clause matched, so pass it on to the outer handler
+                self.raise(object, false)
+            }
+            result => result,
+        }
+    }
+
+    /// Converts syntax into the data it denotes, without evaluating it.
+    pub fn quote(&mut self, syntax: &Syntax) -> Result<Object, Unwind> {
+        match &syntax.kind {
+            SyntaxKind::SExpression(v) | SyntaxKind::List(v) => {
+                let mut result: Vec<Object> = Vec::with_capacity(v.len());
+                for s in v.iter() {
+                    result.push(self.quote(s)?);
+                }
+
+                self.allocate(Object::list(result))
+            }
+            SyntaxKind::Word(v) => match v.as_str() {
+                "t" => Ok(Object::Boolean(true)),
+                "nil" => Ok(Object::nil()),
+                v => Ok(Object::symbol(v)),
+            },
+            SyntaxKind::Literal(v) => Ok(Object::symbol(&v.to_string())),
+            SyntaxKind::Number(v) => match i64::try_from(*v) {
+                Ok(v) => Ok(Object::Number(v)),
+                Err(_) => self.error_at(syntax.span, "number out of range", vec![]),
+            },
+            SyntaxKind::String(v) => Ok(Object::string(v)),
+        }
+    }
+}
+
+/// The record in `value`, if it has type `kind`.
+fn record_of<'a>(
+    kind: &Rc<RecordType>,
+    value: &'a Object,
+) -> Result<&'a Record, Box<dyn std::error::Error>> {
+    match value {
+        Object::Record(v) if Rc::ptr_eq(&v.kind, kind) => Ok(v),
+        v => Err(format!("expected {}, got {}", kind.name, v.type_name()).into()),
+    }
+}
+
+/// Names a procedure after its binding, or after where it was written.
+fn procedure_name(procedure: &Procedure) -> String {
+    match &procedure.name {
+        Some(v) => v.clone(),
+        None => {
+            let (line, column) = procedure.source.line_col(procedure.span.start);
+            format!("lambda@{}:{}:{}", procedure.source.name, line, column)
+        }
+    }
+}
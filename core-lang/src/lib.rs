@@ -1,4 +1,19 @@
 pub mod ast;
+pub mod builtins;
+pub mod checker;
+pub mod convert;
+pub mod cst;
+pub mod debugger;
+pub mod environment;
 pub mod evaluator;
+pub mod formatter;
+pub mod help;
+pub mod interpreter;
+pub mod object;
 pub mod parser;
+pub mod profiler;
+pub mod record;
+pub mod sandbox;
+pub mod syntax;
+pub mod testing;
 pub mod token;
@@ -0,0 +1,268 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::record::Record;
+use crate::sandbox::Capability;
+use crate::syntax::{Source, Span, Syntax, escape_word};
+use std::rc::Rc;
+
+/// A value produced while running a core-lang program.
+#[derive(Clone)]
+pub enum Object {
+    Unspecified,
+    Boolean(bool),
+    Number(i64),
+    String(Rc<str>),
+    Symbol(Rc<str>),
+    List(Rc<[Object]>),
+    Procedure(Rc<Procedure>),
+    Native(Rc<Native>),
+    Condition(Rc<Condition>),
+    Record(Rc<Record>),
+}
+
+/// A procedure created by `lambda` or `define`.
+pub struct Procedure {
+    pub name: Option<String>,
+    pub params: Vec<String>,
+    pub rest: Option<String>,
+    pub body: Rc<[Syntax]>,
+    pub env: Environment,
+    pub span: Span,
+    pub source: Rc<Source>,
+    /// The string starting the body, if anything follows it.
+    pub doc: Option<String>,
+}
+
+pub type NativeFn =
+    dyn Fn(&mut Interpreter, Vec<Object>) -> Result<Object, Box<dyn std::error::Error>>;
+
+/// A procedure implemented in Rust.
+pub struct Native {
+    pub name: String,
+    pub arity: Arity,
+    pub capability: Option<Capability>,
+    pub function: Box<NativeFn>,
+    pub doc: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+/// An error object, as created by `error` or by a failing native function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub kind: ConditionKind,
+    pub message: String,
+    pub irritants: Vec<Object>,
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionKind {
+    /// Raised by `error` or by the interpreter itself.
+    Error,
+    /// Raised because a native function returned an `Err`.
+    Native,
+}
+
+impl Object {
+    pub fn nil() -> Object {
+        Object::Boolean(false)
+    }
+
+    pub fn empty_list() -> Object {
+        Object::List(Rc::from(Vec::new()))
+    }
+
+    pub fn string(s: &str) -> Object {
+        Object::String(Rc::from(s))
+    }
+
+    pub fn symbol(s: &str) -> Object {
+        Object::Symbol(Rc::from(s))
+    }
+
+    pub fn list(values: Vec<Object>) -> Object {
+        Object::List(Rc::from(values))
+    }
+
+    /// Everything except `nil` counts as true.
+    pub fn is_true(&self) -> bool {
+        !matches!(self, Object::Boolean(false))
+    }
+
+    pub fn is_procedure(&self) -> bool {
+        matches!(self, Object::Procedure(_) | Object::Native(_))
+    }
+
+    /// A short name for the type of this value, used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Unspecified => "unspecified",
+            Object::Boolean(_) => "boolean",
+            Object::Number(_) => "number",
+            Object::String(_) => "string",
+            Object::Symbol(_) => "symbol",
+            Object::List(_) => "list",
+            Object::Procedure(_) | Object::Native(_) => "procedure",
+            Object::Condition(_) => "error-object",
+            Object::Record(_) => "record",
+        }
+    }
+
+    /// The approximate number of bytes this value holds on the heap, not
+    /// counting values it shares with others.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Object::String(v) | Object::Symbol(v) => v.len(),
+            Object::List(v) => v.len() * std::mem::size_of::<Object>(),
+            Object::Procedure(_) => std::mem::size_of::<Procedure>(),
+            Object::Condition(v) => {
+                std::mem::size_of::<Condition>()
+                    + v.message.len()
+                    + v.irritants.len() * std::mem::size_of::<Object>()
+            }
+            Object::Record(v) => {
+                std::mem::size_of::<Record>() + v.kind.fields.len() * std::mem::size_of::<Object>()
+            }
+            Object::Unspecified | Object::Boolean(_) | Object::Number(_) | Object::Native(_) => 0,
+        }
+    }
+
+    /// Identity comparison, as done by `eq?`.
+    pub fn is_eq(&self, other: &Object) -> bool {
+        match (self, other) {
+            (Object::List(a), Object::List(b)) => {
+                (a.is_empty() && b.is_empty()) || Rc::ptr_eq(a, b)
+            }
+            (Object::String(a), Object::String(b)) => Rc::ptr_eq(a, b),
+            (Object::Procedure(a), Object::Procedure(b)) => Rc::ptr_eq(a, b),
+            (Object::Native(a), Object::Native(b)) => Rc::ptr_eq(a, b),
+            (Object::Condition(a), Object::Condition(b)) => Rc::ptr_eq(a, b),
+            (Object::Record(a), Object::Record(b)) => Rc::ptr_eq(a, b),
+            _ => self == other,
+        }
+    }
+}
+
+/// Structural comparison, as done by `equal?`.
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Unspecified, Object::Unspecified) => true,
+            (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::Number(a), Object::Number(b)) => a == b,
+            (Object::String(a), Object::String(b)) => a == b,
+            (Object::Symbol(a), Object::Symbol(b)) => a == b,
+            (Object::List(a), Object::List(b)) => a == b,
+            (Object::Procedure(a), Object::Procedure(b)) => Rc::ptr_eq(a, b),
+            (Object::Native(a), Object::Native(b)) => Rc::ptr_eq(a, b),
+            (Object::Condition(a), Object::Condition(b)) => a == b,
+            // Records are compared by identity, as in R7RS
+            (Object::Record(a), Object::Record(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Object::Unspecified => Ok(()),
+            Object::Boolean(true) => write!(f, "t"),
+            Object::Boolean(false) => write!(f, "nil"),
+            Object::Number(v) => write!(f, "{}", v),
+            Object::String(v) => write!(f, "\"{}\"", v),
+            Object::Symbol(v) => write!(f, "{}", escape_word(v)),
+            Object::List(v) => {
+                write!(f, "'(")?;
+                for (i, value) in v.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    match value {
+                        // Nested lists are already quoted by the outer one
+                        Object::List(_) => write!(f, "{}", &value.to_string()[1..])?,
+                        _ => write!(f, "{}", value)?,
+                    }
+                }
+                write!(f, ")")
+            }
+            Object::Procedure(v) => match &v.name {
+                Some(name) => write!(f, "#<procedure {}>", name),
+                None => write!(f, "#<procedure>"),
+            },
+            Object::Native(v) => write!(f, "#<procedure {}>", v.name),
+            Object::Condition(v) => write!(f, "#<error-object {}>", v),
+            Object::Record(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl std::fmt::Debug for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Arity {
+    pub fn exact(n: usize) -> Self {
+        Arity {
+            min: n,
+            max: Some(n),
+        }
+    }
+
+    pub fn at_least(n: usize) -> Self {
+        Arity { min: n, max: None }
+    }
+
+    pub fn range(min: usize, max: usize) -> Self {
+        Arity {
+            min,
+            max: Some(max),
+        }
+    }
+
+    pub fn accepts(&self, n: usize) -> bool {
+        self.min <= n && self.max.is_none_or(|max| n <= max)
+    }
+}
+
+impl std::fmt::Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", self.min),
+            Some(max) => write!(f, "{} to {}", self.min, max),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+impl Condition {
+    pub fn new(message: &str, irritants: Vec<Object>, span: Option<Span>) -> Self {
+        Condition {
+            kind: ConditionKind::Error,
+            message: message.to_string(),
+            irritants,
+            span,
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        for irritant in &self.irritants {
+            write!(f, " {}", irritant)?;
+        }
+        if let Some(span) = self.span {
+            write!(f, " (at {})", span)?;
+        }
+
+        Ok(())
+    }
+}
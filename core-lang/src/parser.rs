@@ -1,3 +1,4 @@
+use crate::syntax::{Span, Syntax, SyntaxKind};
 use crate::token::{Literal, Token};
 use pest::Parser;
 use pest::iterators::Pair;
@@ -39,67 +40,164 @@ impl std::fmt::Display for Literal {
             Literal::Lambda => write!(f, "lambda"),
             Literal::Begin => write!(f, "begin"),
             Literal::Define => write!(f, "define"),
-            Literal::DefineSyntax => write!(f, "define_syntax"),
-            Literal::CallCc => write!(f, "call_cc"),
+            Literal::DefineSyntax => write!(f, "define-syntax"),
+            Literal::CallCc => write!(f, "call/cc"),
         }
     }
 }
 
-fn parse_pair(pair: Pair<Rule>) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+/// How deeply lists may be nested. Parsing recurses once per level, so this
+/// keeps hostile input from overflowing the stack.
+pub const MAX_NESTING: usize = 256;
+
+fn span_of(pair: &Pair<Rule>) -> Span {
+    let span = pair.as_span();
+    Span::new(span.start(), span.end())
+}
+
+fn unexpected(pair: &Pair<Rule>) -> Box<dyn std::error::Error> {
+    format!(
+        "PARSE_ERROR: unexpected {:?} at {}",
+        pair.as_rule(),
+        span_of(pair)
+    )
+    .into()
+}
+
+/// Returns the deepest nesting of parentheses outside strings and comments.
+fn nesting(s: &str) -> usize {
+    let mut depth: usize = 0;
+    let mut deepest: usize = 0;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            ')' => depth = depth.saturating_sub(1),
+            '"' => {
+                chars.find(|v| *v == '"');
+            }
+            '|' => {
+                while let Some(v) = chars.next() {
+                    match v {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '|' => break,
+                        _ => (),
+                    }
+                }
+            }
+            ';' => {
+                chars.find(|v| *v == '\n');
+            }
+            _ => (),
+        }
+    }
+
+    deepest
+}
+
+fn parse_pair(pair: Pair<Rule>) -> Result<Vec<Syntax>, Box<dyn std::error::Error>> {
     match pair.as_rule() {
         Rule::EOI
         | Rule::punct
+        | Rule::word_char
+        | Rule::comment
         | Rule::sexpr
         | Rule::word
         | Rule::number
         | Rule::string
         | Rule::list
         | Rule::left_parenthesis
-        | Rule::right_parenthesis => unreachable!(),
+        | Rule::right_parenthesis => Err(unexpected(&pair)),
         Rule::program => {
-            let mut result: Vec<Token> = Vec::new();
-
-            let rule = pair.into_inner();
-            rule.clone().for_each(|w| match w.as_rule() {
-                Rule::sexpr => result.push(parse_sexpr(w).unwrap()),
-                Rule::list => result.push(parse_list(w).unwrap()),
-                Rule::word => {
-                    let str: String = String::from(w.as_span().as_str());
-                    result.push(Token::Word(str));
+            let mut result: Vec<Syntax> = Vec::new();
+
+            for w in pair.into_inner() {
+                match w.as_rule() {
+                    Rule::sexpr => result.push(parse_sexpr(w)?),
+                    Rule::list => result.push(parse_list(w)?),
+                    Rule::word => {
+                        let str: String = unescape_word(w.as_span().as_str());
+                        result.push(Syntax::new(SyntaxKind::Word(str), span_of(&w)));
+                    }
+                    Rule::string => result.push(parse_string(w)?),
+                    Rule::number => result.push(parse_number(w)?),
+                    Rule::program
+                    | Rule::punct
+                    | Rule::word_char
+                    | Rule::comment
+                    | Rule::left_parenthesis
+                    | Rule::right_parenthesis => return Err(unexpected(&w)),
+                    Rule::EOI => (),
                 }
-                Rule::string => result.push(parse_string(w).unwrap()),
-                Rule::number => {
-                    let number: u64 = w
-                        .as_span()
-                        .as_str()
-                        .parse::<u64>()
-                        .expect("PARSE_ERROR: Failed to parse number");
-                    result.push(Token::Number(number));
-                }
-                Rule::program | Rule::punct | Rule::left_parenthesis | Rule::right_parenthesis => {
-                    unreachable!()
-                }
-                Rule::EOI => (),
-            });
+            }
 
             Ok(result)
         }
     }
 }
 
-fn parse_word(word: Pair<Rule>) -> Result<Token, Box<dyn std::error::Error>> {
-    let s: String = String::from(word.as_span().as_str());
-    match Literal::from_str(&s) {
-        Ok(v) => Ok(Token::Literal(v)),
-        Err(_) => Ok(Token::Word(s)),
+fn parse_word(word: Pair<Rule>) -> Result<Syntax, Box<dyn std::error::Error>> {
+    let text: &str = word.as_span().as_str();
+    let s: String = unescape_word(text);
+    // A special form written between bars is only a word
+    let kind: SyntaxKind = match text.starts_with('|') {
+        true => SyntaxKind::Word(s),
+        false => match Literal::from_str(&s) {
+            Ok(v) => SyntaxKind::Literal(v),
+            Err(_) => SyntaxKind::Word(s),
+        },
+    };
+
+    Ok(Syntax::new(kind, span_of(&word)))
+}
+
+/// Removes the bars of `|escaped words|` and resolves their escapes.
+fn unescape_word(s: &str) -> String {
+    let Some(inner) = s.strip_prefix('|').and_then(|v| v.strip_suffix('|')) else {
+        return s.to_string();
+    };
+
+    let mut result: String = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            // \x3bb; is a code point in hexadecimal
+            Some('x') => {
+                let digits: String = chars.by_ref().take_while(|v| *v != ';').collect();
+                match u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    Some(v) => result.push(v),
+                    None => result.push(char::REPLACEMENT_CHARACTER),
+                }
+            }
+            Some(v) => result.push(v),
+            None => (),
+        }
     }
+
+    result
 }
 
-fn parse_string(string: Pair<Rule>) -> Result<Token, Box<dyn std::error::Error>> {
+fn parse_string(string: Pair<Rule>) -> Result<Syntax, Box<dyn std::error::Error>> {
     let s: &str = string.as_span().as_str();
     let result: String = strip_quotes(s).to_string();
 
-    Ok(Token::String(result))
+    Ok(Syntax::new(SyntaxKind::String(result), span_of(&string)))
 }
 
 fn strip_quotes(s: &str) -> &str {
@@ -112,17 +210,18 @@ fn strip_quotes(s: &str) -> &str {
     }
 }
 
-fn parse_number(word: Pair<Rule>) -> Result<Token, Box<dyn std::error::Error>> {
+fn parse_number(word: Pair<Rule>) -> Result<Syntax, Box<dyn std::error::Error>> {
     let w: &str = word.as_span().as_str().trim();
     let number: u64 = w
         .parse::<u64>()
-        .expect("PARSE_ERROR: Failed to parse number");
+        .map_err(|_| format!("PARSE_ERROR: number {} is too large", w))?;
 
-    Ok(Token::Number(number))
+    Ok(Syntax::new(SyntaxKind::Number(number), span_of(&word)))
 }
 
-fn parse_sexpr(sexpr: Pair<Rule>) -> Result<Token, Box<dyn std::error::Error>> {
-    let mut result: Vec<Token> = Vec::new();
+fn parse_sexpr(sexpr: Pair<Rule>) -> Result<Syntax, Box<dyn std::error::Error>> {
+    let mut result: Vec<Syntax> = Vec::new();
+    let span: Span = span_of(&sexpr);
 
     let rule = sexpr.into_inner();
     let mut words: Vec<Pair<Rule>> = rule.into_iter().collect();
@@ -139,18 +238,22 @@ fn parse_sexpr(sexpr: Pair<Rule>) -> Result<Token, Box<dyn std::error::Error>> {
             Rule::number => result.push(parse_number(w)?),
             Rule::string => result.push(parse_string(w)?),
             Rule::list => result.push(parse_list(w)?),
-            Rule::program | Rule::punct | Rule::left_parenthesis | Rule::right_parenthesis => {
-                unreachable!()
-            }
+            Rule::program
+            | Rule::punct
+            | Rule::word_char
+            | Rule::comment
+            | Rule::left_parenthesis
+            | Rule::right_parenthesis => return Err(unexpected(&w)),
             Rule::EOI => break,
         }
     }
 
-    Ok(Token::SExpression(result))
+    Ok(Syntax::new(SyntaxKind::SExpression(result.into()), span))
 }
 
-fn parse_list(list: Pair<Rule>) -> Result<Token, Box<dyn std::error::Error>> {
-    let mut result: Vec<Token> = Vec::new();
+fn parse_list(list: Pair<Rule>) -> Result<Syntax, Box<dyn std::error::Error>> {
+    let mut result: Vec<Syntax> = Vec::new();
+    let span: Span = span_of(&list);
 
     let rule = list.into_inner();
     let words: Vec<Pair<Rule>> = rule.into_iter().collect();
@@ -162,25 +265,43 @@ fn parse_list(list: Pair<Rule>) -> Result<Token, Box<dyn std::error::Error>> {
             Rule::number => result.push(parse_number(w)?),
             Rule::string => result.push(parse_string(w)?),
             Rule::list => result.push(parse_list(w)?),
-            Rule::program | Rule::punct | Rule::left_parenthesis | Rule::right_parenthesis => {
-                unreachable!()
-            }
+            Rule::program
+            | Rule::punct
+            | Rule::word_char
+            | Rule::comment
+            | Rule::left_parenthesis
+            | Rule::right_parenthesis => return Err(unexpected(&w)),
             Rule::EOI => break,
         }
     }
 
-    Ok(Token::List(result))
+    Ok(Syntax::new(SyntaxKind::List(result.into()), span))
 }
 
 pub fn parse(s: &str) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+    let syntax: Vec<Syntax> = parse_spanned(s)?;
+
+    Ok(syntax.into_iter().map(Token::from).collect())
+}
+
+/// Parses like [`parse`], but keeps the source span of every token.
+pub fn parse_spanned(s: &str) -> Result<Vec<Syntax>, Box<dyn std::error::Error>> {
+    if nesting(s) > MAX_NESTING {
+        return Err(format!(
+            "PARSE_ERROR: lists are nested deeper than {} levels",
+            MAX_NESTING
+        )
+        .into());
+    }
     let mut pairs = CoreLangParser::parse(Rule::program, s)?;
 
-    parse_pair(pairs.next().unwrap())
+    parse_pair(pairs.next().ok_or("PARSE_ERROR: empty parse result")?)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse;
+    use super::{MAX_NESTING, parse, parse_spanned};
+    use crate::syntax::{Span, Syntax, SyntaxKind};
     use crate::token::{Literal, Token};
 
     #[test]
@@ -336,4 +457,55 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn parse_spans() -> Result<(), Box<dyn std::error::Error>> {
+        let syntax: Vec<Syntax> = parse_spanned("(define main 1)\n(foo)")?;
+        assert_eq!(syntax.len(), 2);
+        assert_eq!(syntax[0].span, Span::new(0, 15));
+        assert_eq!(syntax[1].span, Span::new(16, 21));
+        assert_eq!(syntax[1].span.line_col("(define main 1)\n(foo)"), (2, 1));
+
+        let children: &[Syntax] = syntax[0].as_sexpr().unwrap();
+        assert_eq!(
+            children[1],
+            Syntax::new(SyntaxKind::Word(String::from("main")), Span::new(8, 12))
+        );
+        assert_eq!(children[0].kind, SyntaxKind::Literal(Literal::Define));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_unicode_words() -> Result<(), Box<dyn std::error::Error>> {
+        let token: Vec<Token> =
+            parse(r#"(λ <= ->string set-x:y= 名前 1+ 12 |hello world| |a\|b| |\x3bb;|)"#)?;
+        let words: Vec<Token> = ["λ", "<=", "->string", "set-x:y=", "名前", "1+"]
+            .into_iter()
+            .map(|v| Token::Word(String::from(v)))
+            .chain([Token::Number(12)])
+            .chain(
+                ["hello world", "a|b", "λ"]
+                    .into_iter()
+                    .map(|v| Token::Word(String::from(v))),
+            )
+            .collect();
+        assert_eq!(token, vec![Token::SExpression(words)]);
+
+        assert!(parse("(define x |unterminated)").is_err());
+        assert!(parse("(define x 'y)").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_limits() {
+        let deep = |n: usize| format!("{}{}", "(a ".repeat(n), ")".repeat(n));
+        assert!(parse(&deep(MAX_NESTING)).is_ok());
+        assert!(parse(&deep(MAX_NESTING + 1)).is_err());
+        assert!(parse(&deep(100_000)).is_err());
+
+        assert!(parse("(define main 18446744073709551615)").is_ok());
+        assert!(parse("(define main 18446744073709551616)").is_err());
+    }
 }
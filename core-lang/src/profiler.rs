@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Collects per-procedure timings and allocation counts of an [`crate::interpreter::Interpreter`].
+///
+/// Attach one with [`crate::interpreter::Interpreter::attach_profiler`] and
+/// take it back with `detach_profiler` when done. Procedures are named after
+/// their binding, or `lambda@source:line:column` for anonymous ones.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    stats: HashMap<String, ProcedureStats>,
+    stack: Vec<Entry>,
+    /// Exclusive time spent in each distinct call stack.
+    folded: HashMap<String, Duration>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcedureStats {
+    pub calls: u64,
+    /// Time spent in the procedure and everything it called.
+    pub inclusive: Duration,
+    /// Time spent in the procedure itself.
+    pub exclusive: Duration,
+    /// Number of values allocated by the procedure itself.
+    pub allocations: u64,
+    pub allocated_bytes: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    started: Instant,
+    children: Duration,
+    allocations: u64,
+    allocated_bytes: usize,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+
+    pub(crate) fn enter(&mut self, name: &str) {
+        self.stack.push(Entry {
+            name: name.to_string(),
+            started: Instant::now(),
+            children: Duration::ZERO,
+            allocations: 0,
+            allocated_bytes: 0,
+        });
+    }
+
+    pub(crate) fn exit(&mut self) {
+        let Some(entry) = self.stack.pop() else {
+            return;
+        };
+
+        let inclusive: Duration = entry.started.elapsed();
+        let exclusive: Duration = inclusive.saturating_sub(entry.children);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += inclusive;
+        }
+
+        let mut path: String = String::new();
+        for v in &self.stack {
+            path.push_str(&v.name);
+            path.push(';');
+        }
+        path.push_str(&entry.name);
+        *self.folded.entry(path).or_default() += exclusive;
+
+        // Recursive calls are already covered by the outermost one
+        let recursive: bool = self.stack.iter().any(|v| v.name == entry.name);
+        let stats: &mut ProcedureStats = self.stats.entry(entry.name).or_default();
+        stats.calls += 1;
+        if !recursive {
+            stats.inclusive += inclusive;
+        }
+        stats.exclusive += exclusive;
+        stats.allocations += entry.allocations;
+        stats.allocated_bytes += entry.allocated_bytes;
+    }
+
+    pub(crate) fn allocate(&mut self, bytes: usize) {
+        if let Some(entry) = self.stack.last_mut() {
+            entry.allocations += 1;
+            entry.allocated_bytes += bytes;
+        }
+    }
+
+    /// Returns the statistics of every procedure called, slowest first.
+    pub fn stats(&self) -> Vec<(String, ProcedureStats)> {
+        let mut result: Vec<(String, ProcedureStats)> =
+            self.stats.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        result.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(&b.0)));
+
+        result
+    }
+
+    /// Returns the statistics of one procedure.
+    pub fn get(&self, name: &str) -> Option<ProcedureStats> {
+        self.stats.get(name).copied()
+    }
+
+    /// Writes the call stacks in the folded format read by flamegraph tools:
+    /// one `outer;inner;innermost microseconds` line per distinct stack.
+    pub fn write_folded<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut stacks: Vec<(&String, &Duration)> = self.folded.iter().collect();
+        stacks.sort();
+
+        for (stack, time) in stacks {
+            writeln!(writer, "{} {}", stack, time.as_micros())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Profiler {
+    /// A table of [`Profiler::stats`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:>8} {:>12} {:>12} {:>8} {:>10}  name",
+            "calls", "incl (us)", "excl (us)", "allocs", "bytes"
+        )?;
+        for (name, v) in self.stats() {
+            writeln!(
+                f,
+                "{:>8} {:>12} {:>12} {:>8} {:>10}  {}",
+                v.calls,
+                v.inclusive.as_micros(),
+                v.exclusive.as_micros(),
+                v.allocations,
+                v.allocated_bytes,
+                name
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+//! Record types, as defined by `define-record-type`.
+
+use crate::convert::FromObject;
+use crate::object::{Arity, Object};
+use crate::syntax::{Span, Syntax, SyntaxKind};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+thread_local! {
+    /// Whether a record is being printed, so nested ones are printed shallowly.
+    static PRINTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The type shared by every record made by one `define-record-type`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RecordType {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+/// An instance of a [`RecordType`]. Fields can be changed through modifiers,
+/// so they sit behind a `RefCell`.
+///
+/// Fields hold strong references, so a record which refers back to itself
+/// is never freed.
+pub struct Record {
+    pub kind: Rc<RecordType>,
+    values: RefCell<Vec<Object>>,
+}
+
+/// The parsed operands of
+/// `(define-record-type name (constructor field ...) predicate (field accessor modifier) ...)`.
+///
+/// The constructor may also be a bare name, which takes every field in order,
+/// or `nil` for none. The accessor and modifier of a field may be left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordSpec {
+    pub name: String,
+    pub constructor: Option<(Name, Vec<String>)>,
+    pub predicate: Name,
+    pub fields: Vec<FieldSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: String,
+    pub accessor: Option<Name>,
+    pub modifier: Option<Name>,
+}
+
+/// A name introduced by `define-record-type`, with where it was written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Record {
+    /// Makes a record of `kind`. Missing values are left unspecified.
+    pub fn new(kind: Rc<RecordType>, mut values: Vec<Object>) -> Self {
+        values.resize(kind.fields.len(), Object::Unspecified);
+
+        Record {
+            kind,
+            values: RefCell::new(values),
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<Object> {
+        let index: usize = self.kind.index(field)?;
+
+        Some(self.values.borrow()[index].clone())
+    }
+
+    /// Changes a field. Returns `false` if the record has no such field.
+    pub fn set(&self, field: &str, value: Object) -> bool {
+        let Some(index) = self.kind.index(field) else {
+            return false;
+        };
+        self.values.borrow_mut()[index] = value;
+
+        true
+    }
+
+    /// The values of the fields, in the order they were declared.
+    pub fn values(&self) -> Vec<Object> {
+        self.values.borrow().clone()
+    }
+
+    /// Converts a field into a Rust value.
+    pub fn field<T: FromObject>(&self, field: &str) -> Result<T, Box<dyn std::error::Error>> {
+        let value: Object = self
+            .get(field)
+            .ok_or_else(|| format!("{} has no field {}", self.kind.name, field))?;
+
+        T::from_object(&value)
+            .map_err(|e| format!("{} field {}: {}", self.kind.name, field, e).into())
+    }
+}
+
+impl std::fmt::Display for Record {
+    /// Records inside the fields are printed as `#<name ...>`, since they
+    /// may refer back to this one.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if PRINTING.get() {
+            return write!(f, "#<{} ...>", self.kind.name);
+        }
+
+        PRINTING.set(true);
+        let result: std::fmt::Result = self.fields(f);
+        PRINTING.set(false);
+
+        result
+    }
+}
+
+impl Record {
+    fn fields(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#<{}", self.kind.name)?;
+        for (name, value) in self.kind.fields.iter().zip(self.values.borrow().iter()) {
+            write!(f, " {}: {}", name, value)?;
+        }
+        write!(f, ">")
+    }
+}
+
+impl RecordType {
+    pub fn index(&self, field: &str) -> Option<usize> {
+        self.fields.iter().position(|v| v == field)
+    }
+}
+
+impl RecordSpec {
+    pub fn parse(args: &[Syntax]) -> Result<RecordSpec, String> {
+        let [name, constructor, predicate, fields @ ..] = args else {
+            return Err(String::from(
+                "expected a name, a constructor and a predicate",
+            ));
+        };
+        let name: String = name.as_word().ok_or("expected a type name")?;
+
+        let mut specs: Vec<FieldSpec> = Vec::new();
+        for field in fields {
+            let spec: FieldSpec = match &field.kind {
+                SyntaxKind::SExpression(v) => match &v[..] {
+                    [field, rest @ ..] if rest.len() <= 2 => FieldSpec {
+                        name: field.as_word().ok_or("fields must be names")?,
+                        accessor: rest.first().map(Name::of).transpose()?,
+                        modifier: rest.get(1).map(Name::of).transpose()?,
+                    },
+                    _ => return Err(String::from("expected (field accessor modifier)")),
+                },
+                _ => FieldSpec {
+                    name: field.as_word().ok_or("fields must be names")?,
+                    accessor: None,
+                    modifier: None,
+                },
+            };
+            if specs.iter().any(|v| v.name == spec.name) {
+                return Err(format!("duplicate field {}", spec.name));
+            }
+            specs.push(spec);
+        }
+
+        let constructor: Option<(Name, Vec<String>)> = match &constructor.kind {
+            SyntaxKind::Word(v) if v == "nil" => None,
+            SyntaxKind::SExpression(v) => {
+                let [name, params @ ..] = &v[..] else {
+                    return Err(String::from("expected a constructor name"));
+                };
+                let mut fields: Vec<String> = Vec::new();
+                for param in params {
+                    let field: String = param.as_word().ok_or("fields must be names")?;
+                    if !specs.iter().any(|v| v.name == field) {
+                        return Err(format!("unknown field {}", field));
+                    }
+                    fields.push(field);
+                }
+                Some((Name::of(name)?, fields))
+            }
+            _ => Some((
+                Name::of(constructor)?,
+                specs.iter().map(|v| v.name.clone()).collect(),
+            )),
+        };
+
+        Ok(RecordSpec {
+            name,
+            constructor,
+            predicate: Name::of(predicate)?,
+            fields: specs,
+        })
+    }
+
+    /// Every procedure this defines, with its arity.
+    pub fn procedures(&self) -> Vec<(&Name, Arity)> {
+        let mut result: Vec<(&Name, Arity)> = Vec::new();
+        if let Some((name, fields)) = &self.constructor {
+            result.push((name, Arity::exact(fields.len())));
+        }
+        result.push((&self.predicate, Arity::exact(1)));
+        for field in &self.fields {
+            result.extend(field.accessor.iter().map(|v| (v, Arity::exact(1))));
+            result.extend(field.modifier.iter().map(|v| (v, Arity::exact(2))));
+        }
+
+        result
+    }
+}
+
+impl Name {
+    fn of(syntax: &Syntax) -> Result<Name, String> {
+        Ok(Name {
+            name: syntax.as_word().ok_or("expected a name")?,
+            span: syntax.span,
+        })
+    }
+}
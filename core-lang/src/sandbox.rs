@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+
+/// Limits on what a script may do, enforced by the [`crate::interpreter::Interpreter`].
+///
+/// `None` means unlimited. The fuel, heap and time budgets are counted from
+/// the start of each [`crate::interpreter::Interpreter::eval_str`] call, or of
+/// each [`crate::interpreter::Interpreter::apply`] call made by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    /// Number of evaluation steps a script may take.
+    pub fuel: Option<u64>,
+    /// Number of bytes a script may allocate for strings, lists and procedures.
+    pub max_heap: Option<usize>,
+    /// Number of nested procedure calls.
+    pub max_depth: Option<usize>,
+    /// Wall-clock time a script may run for.
+    pub timeout: Option<Duration>,
+    /// Native functions requiring a capability not listed here raise an error.
+    pub capabilities: Vec<Capability>,
+}
+
+/// Permission to use a group of native functions with side effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Reading and writing files.
+    Filesystem,
+    /// Spawning other programs.
+    Process,
+}
+
+/// Which budget of a [`Sandbox`] ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Fuel,
+    Heap,
+    Depth,
+    Timeout,
+}
+
+/// The resources used so far by the running script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub fuel: u64,
+    pub heap: usize,
+    pub depth: usize,
+    pub started: Instant,
+}
+
+/// Checking the clock on every step is too slow, so it is done every this many steps.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// The depth limit used unless told otherwise, so deep recursion in a script
+/// raises an error instead of overflowing the Rust stack.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+impl Sandbox {
+    /// No budgets other than the default depth limit, and every capability.
+    pub fn unrestricted() -> Self {
+        Sandbox {
+            fuel: None,
+            max_heap: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            timeout: None,
+            capabilities: vec![Capability::Filesystem, Capability::Process],
+        }
+    }
+
+    /// Default budgets suitable for untrusted code, without any capabilities.
+    pub fn restricted() -> Self {
+        Sandbox {
+            fuel: Some(10_000_000),
+            max_heap: Some(64 * 1024 * 1024),
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            timeout: Some(Duration::from_secs(1)),
+            capabilities: vec![],
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Counts one evaluation step.
+    pub fn step(&self, usage: &mut Usage) -> Result<(), Limit> {
+        usage.fuel += 1;
+
+        if self.fuel.is_some_and(|v| usage.fuel > v) {
+            return Err(Limit::Fuel);
+        }
+        if usage.fuel % CLOCK_INTERVAL == 0 {
+            if let Some(timeout) = self.timeout {
+                if usage.started.elapsed() > timeout {
+                    return Err(Limit::Timeout);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts `bytes` newly allocated bytes.
+    pub fn allocate(&self, usage: &mut Usage, bytes: usize) -> Result<(), Limit> {
+        usage.heap = usage.heap.saturating_add(bytes);
+
+        match self.max_heap {
+            Some(v) if usage.heap > v => Err(Limit::Heap),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts entering a procedure call.
+    pub fn enter(&self, usage: &mut Usage) -> Result<(), Limit> {
+        usage.depth += 1;
+
+        match self.max_depth {
+            Some(v) if usage.depth > v => Err(Limit::Depth),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Sandbox::unrestricted()
+    }
+}
+
+impl Default for Usage {
+    fn default() -> Self {
+        Usage {
+            fuel: 0,
+            heap: 0,
+            depth: 0,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Fuel => write!(f, "ran out of fuel"),
+            Limit::Heap => write!(f, "exceeded the heap limit"),
+            Limit::Depth => write!(f, "exceeded the maximum recursion depth"),
+            Limit::Timeout => write!(f, "exceeded the time limit"),
+        }
+    }
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Capability::Filesystem => write!(f, "filesystem"),
+            Capability::Process => write!(f, "process"),
+        }
+    }
+}
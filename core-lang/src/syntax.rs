@@ -0,0 +1,163 @@
+use crate::cst::is_word_char;
+use crate::token::{Literal, Token};
+use std::borrow::Cow;
+use std::rc::Rc;
+
+/// A region of the source text, as byte offsets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A named program text, with an index for turning offsets into lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    pub name: String,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+/// A token which remembers where it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Syntax {
+    pub kind: SyntaxKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxKind {
+    SExpression(Rc<[Syntax]>),
+    List(Rc<[Syntax]>),
+    Word(String),
+    Number(u64),
+    String(String),
+    Literal(Literal),
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Returns the 1-based line and column of the start of this span.
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        let before: &str = &source[..self.start.min(source.len())];
+        let line: usize = before.matches('\n').count() + 1;
+        let column: usize = match before.rfind('\n') {
+            Some(v) => before[v + 1..].chars().count() + 1,
+            None => before.chars().count() + 1,
+        };
+
+        (line, column)
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+impl Source {
+    pub fn new(name: &str, text: &str) -> Self {
+        let mut line_starts: Vec<usize> = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+
+        Source {
+            name: name.to_string(),
+            text: text.to_string(),
+            line_starts,
+        }
+    }
+
+    /// Returns the 1-based line and column of `offset`.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line: usize = match self.line_starts.binary_search(&offset) {
+            Ok(v) => v,
+            Err(v) => v - 1,
+        };
+        let start: usize = self.line_starts[line];
+        let column: usize = match self.text.get(start..offset.min(self.text.len())) {
+            Some(v) => v.chars().count() + 1,
+            None => offset - start + 1,
+        };
+
+        (line + 1, column)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+}
+
+impl Syntax {
+    pub fn new(kind: SyntaxKind, span: Span) -> Self {
+        Syntax { kind, span }
+    }
+
+    /// Returns the name of a word or literal, if this is one.
+    pub fn as_word(&self) -> Option<String> {
+        match &self.kind {
+            SyntaxKind::Word(v) => Some(v.clone()),
+            SyntaxKind::Literal(v) => Some(v.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns the children of an S-expression, if this is one.
+    pub fn as_sexpr(&self) -> Option<&[Syntax]> {
+        match &self.kind {
+            SyntaxKind::SExpression(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl From<Syntax> for Token {
+    fn from(syntax: Syntax) -> Self {
+        match syntax.kind {
+            SyntaxKind::SExpression(v) => {
+                Token::SExpression(v.iter().cloned().map(Token::from).collect())
+            }
+            SyntaxKind::List(v) => Token::List(v.iter().cloned().map(Token::from).collect()),
+            SyntaxKind::Word(v) => Token::Word(v),
+            SyntaxKind::Number(v) => Token::Number(v),
+            SyntaxKind::String(v) => Token::String(v),
+            SyntaxKind::Literal(v) => Token::Literal(v),
+        }
+    }
+}
+
+/// Writes a name so that it reads back as the same word, between `|` bars if
+/// it would not otherwise be one, or would be a boolean or special form.
+pub fn escape_word(name: &str) -> Cow<'_, str> {
+    let plain: bool = !name.is_empty()
+        && name.chars().all(is_word_char)
+        && !name.bytes().all(|v| v.is_ascii_digit())
+        && !matches!(name, "t" | "nil")
+        && name.parse::<Literal>().is_err();
+    if plain {
+        return Cow::Borrowed(name);
+    }
+
+    let mut result: String = String::from("|");
+    for c in name.chars() {
+        match c {
+            '|' => result.push_str("\\|"),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            c if c.is_control() => result.push_str(&format!("\\x{:x};", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('|');
+
+    Cow::Owned(result)
+}
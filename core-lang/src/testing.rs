@@ -0,0 +1,119 @@
+//! Unit tests written in core-lang itself.
+//!
+//! `(define-test name body ...)` registers a test instead of running it, and
+//! `assert-equal` and `assert-error` make it fail. Every file runs in a fresh
+//! [`Interpreter`], and every test in a scope of its own, with the top level
+//! as the file left it, whatever earlier tests changed.
+
+use crate::environment::Environment;
+use crate::interpreter::{Interpreter, Unwind};
+use crate::object::Object;
+use crate::syntax::{Source, Span, Syntax};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A test registered by `define-test`.
+pub struct Test {
+    pub name: String,
+    /// The whole `define-test` form.
+    pub span: Span,
+    pub(crate) body: Rc<[Syntax]>,
+    pub(crate) env: Environment,
+    pub(crate) source: Rc<Source>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub name: String,
+    pub span: Span,
+    pub failure: Option<Failure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub message: String,
+    /// Where the error was raised, or the test itself if unknown.
+    pub span: Span,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Loads `source` and runs the tests it defines, in order. Fails if loading
+/// the file itself fails.
+pub fn run_source(source: Source) -> Result<Vec<TestResult>, Box<dyn std::error::Error>> {
+    run_source_in(&mut Interpreter::new(), source)
+}
+
+/// Like [`run_source`], but in `interpreter`, which should be fresh. Lets the
+/// caller look at it afterwards, e.g. for what `trace` collected.
+pub fn run_source_in(
+    interpreter: &mut Interpreter,
+    source: Source,
+) -> Result<Vec<TestResult>, Box<dyn std::error::Error>> {
+    interpreter.eval_source(source)?;
+    let top: Vec<(String, Object)> = interpreter.global().bindings();
+
+    let mut results: Vec<TestResult> = Vec::new();
+    for test in interpreter.take_tests() {
+        interpreter.global().restore(top.clone());
+        let failure: Option<Failure> = match interpreter.run_test(&test) {
+            Ok(_) => None,
+            Err(e) => Some(failure(e, test.span)),
+        };
+        results.push(TestResult {
+            name: test.name,
+            span: test.span,
+            failure,
+        });
+    }
+
+    Ok(results)
+}
+
+fn failure(unwind: Unwind, span: Span) -> Failure {
+    match unwind {
+        Unwind::Raise(Object::Condition(v)) => {
+            let mut message: String = v.message.clone();
+            for irritant in &v.irritants {
+                message.push(' ');
+                message.push_str(&irritant.to_string());
+            }
+            Failure {
+                message,
+                span: v.span.unwrap_or(span),
+            }
+        }
+        Unwind::Raise(v) => Failure {
+            message: format!("raised {}", v),
+            span,
+        },
+        e => Failure {
+            message: e.to_string(),
+            span,
+        },
+    }
+}
+
+/// Finds the `*.core` files under `directory`, sorted by path, without
+/// entering symlinked directories.
+pub fn discover(directory: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut result: Vec<PathBuf> = Vec::new();
+
+    for entry in std::fs::read_dir(directory)? {
+        let entry: std::fs::DirEntry = entry?;
+        let path: PathBuf = entry.path();
+        // Links to directories are not followed, so a cycle cannot recurse forever
+        if entry.file_type()?.is_dir() {
+            result.extend(discover(&path)?);
+        } else if path.extension().is_some_and(|v| v == "core") {
+            result.push(path);
+        }
+    }
+    result.sort();
+
+    Ok(result)
+}
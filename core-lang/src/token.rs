@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
     SExpression(Vec<Token>),
     List(Vec<Token>),
@@ -8,7 +8,7 @@ pub enum Token {
     Literal(Literal),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Literal {
     Cons,
     Car,
@@ -0,0 +1,208 @@
+use core_lang::checker::{Diagnostic, Lint, Severity, check};
+use core_lang::syntax::Source;
+use std::path::PathBuf;
+
+/// The lint codes reported for `program`, with the line they are on.
+fn lints(program: &str) -> Vec<(&'static str, usize)> {
+    let source: Source = Source::new("init.core", program);
+    check(&source)
+        .iter()
+        .map(|v| (v.lint.code(), source.line_col(v.span.start).0))
+        .collect()
+}
+
+#[test]
+fn fixtures_are_clean() -> Result<(), Box<dyn std::error::Error>> {
+    let syntax = std::fs::read_dir("./tests/syntax-files")?;
+    for entry in syntax.chain(std::fs::read_dir("./tests/semantic-files")?) {
+        let path: PathBuf = entry?.path();
+        let source: Source = Source::new("", &std::fs::read_to_string(&path)?);
+        let errors: Vec<Diagnostic> = check(&source)
+            .into_iter()
+            .filter(|v| v.severity() == Severity::Error)
+            .collect();
+        assert_eq!(errors, vec![], "{}", path.display());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn unbound_words() {
+    let program: &str = r#"
+(define (main)
+  (let ((a 1))
+    (+ a b later)))
+(define later 2)
+"#;
+    assert_eq!(lints(program), vec![("C001", 4)]);
+
+    // Parameters, named let, let* and guard variables are bound
+    let program: &str = r#"
+(define (main) (run 1 2))
+(define (run . args)
+  (let loop ((i 0))
+    (let* ((a i) (b a))
+      (guard (e (t e))
+        (loop (+ a b (length args)))))))
+"#;
+    assert_eq!(lints(program), vec![]);
+}
+
+#[test]
+fn arity() {
+    let program: &str = r#"
+(define (add a b) (+ a b))
+(define (main)
+  (add 1)
+  (car '(1) '(2))
+  (if t)
+  (list)
+  (add 1 2))
+"#;
+    assert_eq!(lints(program), vec![("C002", 4), ("C002", 5), ("C002", 6)]);
+
+    // Redefined procedures are not checked
+    let program: &str = "(define (f a) a) (define (main) (set! f car) (f 1 2))";
+    assert_eq!(lints(program), vec![]);
+}
+
+#[test]
+fn unreachable_definitions() {
+    let program: &str = r#"
+(define (helper) (used))
+(define (used) 1)
+(define unused 2)
+(define (main) (helper))
+"#;
+    assert_eq!(lints(program), vec![("C003", 4)]);
+
+    // Without main or cli there is nothing to reach from
+    assert_eq!(lints("(define foo 1)"), vec![]);
+}
+
+#[test]
+fn shadowing() {
+    let program: &str = r#"
+(define (car x) x)
+(define (main)
+  (car (let ((cli 1) (list 2)) cli)))
+"#;
+    assert_eq!(lints(program), vec![("C004", 2), ("C004", 4), ("C004", 4)]);
+}
+
+#[test]
+fn builtin_misuse() {
+    let program: &str = r#"
+(define main 1)
+(define cli (lambda () (enable 1) (colour t) ((enable t))))
+(define (f) (map if '(1)) (lambda))
+(f)
+"#;
+    assert_eq!(
+        lints(program),
+        vec![
+            ("C005", 2),
+            ("C005", 3),
+            ("C005", 3),
+            ("C005", 3),
+            ("C005", 4),
+            ("C005", 4)
+        ]
+    );
+
+    assert_eq!(lints("(define-syntax foo 1)"), vec![("C006", 1)]);
+}
+
+#[test]
+fn match_exhaustiveness() {
+    let program: &str = r#"
+(define (main) (handle "q"))
+(define (handle key)
+  (match key
+    ("q" (quit))
+    ((list (quote prefix) . rest) rest)
+    ((? number? n) (guard (equal? n 0)) n)))
+(define (quit) 1)
+"#;
+    assert_eq!(lints(program), vec![("C007", 4)]);
+
+    // A catch-all clause covers every value
+    let program: &str = r#"
+(define (main) (handle 1))
+(define (handle key)
+  (match key
+    ((list first second) (+ first second))
+    (other other)))
+"#;
+    assert_eq!(lints(program), vec![]);
+
+    // Both booleans do not, since any other value is unmatched
+    let program: &str = "(define (f x) (match x (t 1) (nil 2)))";
+    assert_eq!(lints(program), vec![("C007", 1)]);
+
+    // Guarded catch-alls do not, and pattern variables are only bound in their clause
+    let program: &str = r#"
+(define (main) (handle 1))
+(define (handle key)
+  (match key
+    (x (guard (number? x)) x))
+  (match key
+    (_ x)))
+"#;
+    assert_eq!(lints(program), vec![("C007", 4), ("C001", 7)]);
+}
+
+#[test]
+fn record_types() {
+    let program: &str = r#"
+(define-record-type point (make-point x y) point? (x point-x set-point-x!) (y point-y))
+(define (main)
+  (let ((p (make-point 1)))
+    (set-point-x! p)
+    (point-z p)))
+"#;
+    assert_eq!(lints(program), vec![("C002", 4), ("C002", 5), ("C001", 6)]);
+
+    let program: &str = r#"
+(define (main)
+  (define-record-type cell (make-cell first) cell? (first cell-first))
+  (cell-first (make-cell 1))
+  (define-record-type broken (make-broken second) broken?))
+"#;
+    assert_eq!(lints(program), vec![("C005", 5)]);
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(
+        lints("(define main\n  (lambda ()"),
+        vec![("C000", 1), ("C000", 2)]
+    );
+    assert_eq!(lints("(foo))"), vec![("C000", 1)]);
+}
+
+#[test]
+fn silencing() {
+    let program: &str = r#"
+; core-check: allow(unreachable-definition, C001)
+(define unused 1)
+(define (main) (foo))
+"#;
+    assert_eq!(lints(program), vec![]);
+}
+
+#[test]
+fn rendering() {
+    let source: Source = Source::new("init.core", "(define (main)\n  (foo))");
+    let diagnostics: Vec<Diagnostic> = check(&source);
+    assert_eq!(
+        diagnostics[0].render(&source),
+        "init.core:2:4: error[C001]: unbound word `foo`"
+    );
+
+    for lint in Lint::ALL {
+        assert_eq!(Lint::find(lint.code()), Some(lint));
+        assert_eq!(Lint::find(lint.name()), Some(lint));
+    }
+}
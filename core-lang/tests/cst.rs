@@ -0,0 +1,178 @@
+use core_lang::cst::{Element, NodeKind, TokenKind, Tree};
+use core_lang::syntax::Span;
+use std::path::PathBuf;
+
+#[test]
+fn lossless() -> Result<(), Box<dyn std::error::Error>> {
+    for entry in std::fs::read_dir("./tests/syntax-files")? {
+        let path: PathBuf = entry?.path();
+        let data: String = std::fs::read_to_string(path).unwrap_or_default();
+
+        let tree: Tree = Tree::parse(&data);
+        assert!(tree.errors().is_empty());
+        assert_eq!(tree.print(), data);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tokens() {
+    let tree: Tree = Tree::parse("(define x '(1 \"a\")) ; hi\n");
+    let kinds: Vec<(Span, TokenKind)> = tree.tokens().iter().map(|(s, t)| (*s, t.kind)).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            (Span::new(0, 1), TokenKind::LeftParenthesis),
+            (Span::new(1, 7), TokenKind::Word),
+            (Span::new(7, 8), TokenKind::Whitespace),
+            (Span::new(8, 9), TokenKind::Word),
+            (Span::new(9, 10), TokenKind::Whitespace),
+            (Span::new(10, 12), TokenKind::QuoteParenthesis),
+            (Span::new(12, 13), TokenKind::Number),
+            (Span::new(13, 14), TokenKind::Whitespace),
+            (Span::new(14, 17), TokenKind::String),
+            (Span::new(17, 18), TokenKind::RightParenthesis),
+            (Span::new(18, 19), TokenKind::RightParenthesis),
+            (Span::new(19, 20), TokenKind::Whitespace),
+            (Span::new(20, 24), TokenKind::Comment),
+            (Span::new(24, 25), TokenKind::Whitespace),
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    let tree: Tree = Tree::parse("(define x (foo)\n) )");
+    assert_eq!(tree.print(), "(define x (foo)\n) )");
+    assert_eq!(tree.errors().len(), 1);
+    assert_eq!(tree.errors()[0].message, "unexpected ')'");
+    assert_eq!(tree.errors()[0].span, Span::new(18, 19));
+
+    let tree: Tree = Tree::parse("(define x (foo \"bar");
+    assert_eq!(tree.print(), "(define x (foo \"bar");
+    let messages: Vec<&str> = tree.errors().iter().map(|v| v.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "unterminated string",
+            "unclosed parenthesis",
+            "unclosed parenthesis"
+        ]
+    );
+
+    // The unclosed list still holds everything after it
+    let Some(Element::Node(node)) = tree.root().children.first() else {
+        panic!("expected a list");
+    };
+    assert_eq!(node.kind, NodeKind::SExpression);
+    assert!(!node.is_closed());
+    assert_eq!(node.significant().count(), 3);
+
+    // Only the whitespace `sexpr.pest` allows separates forms
+    let tree: Tree = Tree::parse("(define a\u{a0}1)\u{2003}");
+    assert_eq!(tree.print(), "(define a\u{a0}1)\u{2003}");
+    let errors: Vec<(&str, Span)> = tree
+        .errors()
+        .iter()
+        .map(|v| (v.message.as_str(), v.span))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ("unexpected character", Span::new(9, 11)),
+            ("unexpected character", Span::new(13, 16)),
+        ]
+    );
+}
+
+#[test]
+fn unicode_words() {
+    let text: &str = "(λ <= |a \\| b| 1+ 12)";
+    let tree: Tree = Tree::parse(text);
+    assert!(tree.errors().is_empty());
+    let words: Vec<(&str, TokenKind)> = tree
+        .tokens()
+        .iter()
+        .filter(|(_, t)| t.kind != TokenKind::Whitespace)
+        .map(|(s, t)| (&text[s.start..s.end], t.kind))
+        .collect();
+    assert_eq!(
+        words,
+        vec![
+            ("(", TokenKind::LeftParenthesis),
+            ("λ", TokenKind::Word),
+            ("<=", TokenKind::Word),
+            ("|a \\| b|", TokenKind::Word),
+            ("1+", TokenKind::Word),
+            ("12", TokenKind::Number),
+            (")", TokenKind::RightParenthesis),
+        ]
+    );
+
+    let tree: Tree = Tree::parse("(foo |bar)");
+    assert_eq!(tree.print(), "(foo |bar)");
+    let messages: Vec<&str> = tree.errors().iter().map(|v| v.message.as_str()).collect();
+    assert_eq!(messages, vec!["unterminated word", "unclosed parenthesis"]);
+}
+
+#[test]
+fn deep_nesting() {
+    let text: String = "(".repeat(100_000);
+    let tree: Tree = Tree::parse(&text);
+    assert_eq!(tree.print(), text);
+    assert!(
+        tree.errors()
+            .iter()
+            .any(|v| v.message == "lists are nested too deeply")
+    );
+}
+
+#[test]
+fn enclosing() {
+    let tree: Tree = Tree::parse("(a (b c) d)");
+    assert_eq!(tree.enclosing(5), vec![Span::new(3, 8), Span::new(0, 11)]);
+    assert_eq!(tree.enclosing(9), vec![Span::new(0, 11)]);
+    assert!(tree.enclosing(11).is_empty());
+}
+
+#[test]
+fn incremental() {
+    let text: &str = "(define a 1)\n(define b (list 1 2))\n(define c 3)";
+    let cases: Vec<(Span, &str, Option<Span>)> = vec![
+        // Inside (list 1 2), only that list is reparsed
+        (Span::new(29, 30), "20", Some(Span::new(23, 34))),
+        // Inside (define a 1)
+        (Span::new(10, 11), "one", Some(Span::new(0, 14))),
+        // Breaking the balance falls back to a full parse
+        (Span::new(29, 30), ")", None),
+        (Span::new(12, 13), "(", None),
+        // Edits touching the parentheses themselves
+        (Span::new(0, 1), "", None),
+    ];
+
+    for (range, replacement, expected) in cases {
+        let mut tree: Tree = Tree::parse(text);
+        let reparsed: Span = tree.edit(range, replacement);
+
+        let mut expected_text: String = text.to_string();
+        expected_text.replace_range(range.start..range.end, replacement);
+        assert_eq!(tree, Tree::parse(&expected_text));
+        assert_eq!(tree.print(), expected_text);
+        assert_eq!(
+            reparsed,
+            expected.unwrap_or(Span::new(0, expected_text.len()))
+        );
+    }
+}
+
+#[test]
+fn incremental_errors() {
+    // The error inside the reparsed list goes away with the bad character
+    let mut tree: Tree = Tree::parse("(a \u{1} b) )");
+    assert_eq!(tree.errors().len(), 2);
+    assert_eq!(tree.edit(Span::new(3, 4), "c"), Span::new(0, 7));
+    assert_eq!(tree, Tree::parse("(a c b) )"));
+    assert_eq!(tree.errors()[0].span, Span::new(8, 9));
+}
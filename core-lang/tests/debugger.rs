@@ -0,0 +1,159 @@
+use core_lang::debugger::{Breakpoint, Debugger, Pause, PauseReason, Resume, TraceEvent};
+use core_lang::interpreter::{Interpreter, Unwind};
+use core_lang::object::Object;
+use core_lang::syntax::Source;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Locals = Vec<(String, Object)>;
+
+const PROGRAM: &str = "(define (square x)
+  (* x x))
+(define (sum-squares a b)
+  (+ (square a)
+     (square b)))
+(sum-squares 2 3)";
+
+#[test]
+fn breakpoints() -> Result<(), Box<dyn std::error::Error>> {
+    let pauses: Rc<RefCell<Vec<(usize, Locals)>>> = Rc::default();
+    let recorded = pauses.clone();
+
+    let mut debugger = Debugger::new(move |_, pause: &Pause| {
+        assert_eq!(pause.reason, PauseReason::Breakpoint(0));
+        recorded.borrow_mut().push((pause.line, pause.locals()));
+        Resume::Continue
+    });
+    debugger.add_breakpoint(Breakpoint {
+        source: Some(String::from("init.core")),
+        line: 2,
+    });
+
+    let mut interpreter = Interpreter::new();
+    interpreter.attach_debugger(debugger);
+    let result: Object = interpreter.eval_source(Source::new("init.core", PROGRAM))?;
+    assert_eq!(result, Object::Number(13));
+
+    assert_eq!(
+        *pauses.borrow(),
+        vec![
+            (2, vec![(String::from("x"), Object::Number(2))]),
+            (2, vec![(String::from("x"), Object::Number(3))]),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn stepping() -> Result<(), Box<dyn std::error::Error>> {
+    let pauses: Rc<RefCell<Vec<(usize, usize)>>> = Rc::default();
+    let recorded = pauses.clone();
+
+    let mut commands = vec![
+        Resume::StepOut,
+        Resume::StepOver,
+        Resume::StepOver,
+        Resume::StepIn,
+    ];
+    let mut debugger = Debugger::new(move |_, pause: &Pause| {
+        recorded.borrow_mut().push((pause.line, pause.frames.len()));
+        commands.pop().unwrap_or(Resume::Continue)
+    });
+    debugger.add_breakpoint(Breakpoint {
+        source: None,
+        line: 6,
+    });
+
+    let mut interpreter = Interpreter::new();
+    interpreter.attach_debugger(debugger);
+    interpreter.eval_str(PROGRAM)?;
+
+    // Stepping over (square a) and (square b) never enters their bodies
+    assert_eq!(*pauses.borrow(), vec![(6, 0), (4, 1), (4, 1), (5, 1)]);
+
+    Ok(())
+}
+
+#[test]
+fn inspect_and_abort() -> Result<(), Box<dyn std::error::Error>> {
+    let seen: Rc<RefCell<Vec<Object>>> = Rc::default();
+    let recorded = seen.clone();
+
+    let mut debugger = Debugger::new(move |interp: &mut Interpreter, pause: &Pause| {
+        let value: Object = interp.eval_in("(+ x 100)", &pause.env).unwrap();
+        recorded.borrow_mut().push(value);
+        assert_eq!(
+            pause.frames[0].procedure.name.as_deref(),
+            Some("sum-squares")
+        );
+        Resume::Abort
+    });
+    debugger.add_breakpoint(Breakpoint {
+        source: None,
+        line: 2,
+    });
+
+    let mut interpreter = Interpreter::new();
+    interpreter.attach_debugger(debugger);
+    let error = interpreter.eval_str(PROGRAM).unwrap_err();
+
+    assert!(matches!(
+        error.downcast_ref::<Unwind>(),
+        Some(Unwind::Interrupted)
+    ));
+    assert_eq!(*seen.borrow(), vec![Object::Number(102)]);
+    assert!(interpreter.frames().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn trace() -> Result<(), Box<dyn std::error::Error>> {
+    let events: Rc<RefCell<Vec<String>>> = Rc::default();
+    let recorded = events.clone();
+
+    let mut interpreter = Interpreter::new();
+    let mut debugger = Debugger::default();
+    debugger.set_tracer(move |event: &TraceEvent| recorded.borrow_mut().push(event.to_string()));
+    interpreter.attach_debugger(debugger);
+
+    interpreter.eval_str(
+        "(define (len l) (if (null? l) 0 (+ 1 (len (cdr l)))))
+         (trace (quote len))
+         (len (list 1 2))
+         (untrace (quote len))
+         (len (list 1 2))",
+    )?;
+
+    assert_eq!(
+        *events.borrow(),
+        vec![
+            "(len '(1 2))",
+            "| (len '(2))",
+            "| | (len '())",
+            "| | 0",
+            "| 1",
+            "2",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn trace_is_collected_without_a_tracer() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define (id x) x) (trace (quote id)) (id 1)")?;
+
+    let debugger: &mut Debugger = interpreter.debugger_mut().ok_or("no debugger")?;
+    let events: Vec<String> = debugger
+        .take_trace()
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    assert_eq!(events, vec!["(id 1)", "1"]);
+    assert!(debugger.take_trace().is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use core_lang::interpreter::{Interpreter, Unwind};
+use core_lang::object::{Arity, ConditionKind, Object};
+use core_lang::syntax::Span;
+
+fn run(program: &str) -> Result<Object, Box<dyn std::error::Error>> {
+    Interpreter::new().eval_str(program)
+}
+
+#[test]
+fn guard_catches_error() -> Result<(), Box<dyn std::error::Error>> {
+    let result: Object = run(r#"
+        (guard (e ((error-object? e) (list (error-object-message e) (error-object-irritants e))))
+          (error "boom" 1 2))
+    "#)?;
+    assert_eq!(
+        result,
+        Object::list(vec![
+            Object::string("boom"),
+            Object::list(vec![Object::Number(1), Object::Number(2)])
+        ])
+    );
+
+    let result: Object = run("(guard (e ((number? e) (+ e 1))) (raise 41))")?;
+    assert_eq!(result, Object::Number(42));
+
+    let result: Object = run("(guard (e ((string? e) 1) (else 2)) (raise 41))")?;
+    assert_eq!(result, Object::Number(2));
+
+    Ok(())
+}
+
+#[test]
+fn guard_without_matching_clause_reraises() -> Result<(), Box<dyn std::error::Error>> {
+    let result: Object = run(r#"
+        (guard (outer (t (list (quote outer) outer)))
+          (guard (inner ((string? inner) (quote inner)))
+            (raise 1)))
+    "#)?;
+    assert_eq!(
+        result,
+        Object::list(vec![Object::symbol("outer"), Object::Number(1)])
+    );
+
+    let error = run("(guard (e ((string? e) 1)) (raise 41))").unwrap_err();
+    match error.downcast_ref::<Unwind>() {
+        Some(Unwind::Raise(v)) => assert_eq!(*v, Object::Number(41)),
+        _ => panic!("expected an uncaught raise"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn with_exception_handler() -> Result<(), Box<dyn std::error::Error>> {
+    let result: Object = run(r#"
+        (with-exception-handler
+          (lambda (e) (* e 2))
+          (lambda () (+ 1 (raise-continuable 5))))
+    "#)?;
+    assert_eq!(result, Object::Number(11));
+
+    // Returning from the handler of a non-continuable raise is an error
+    let result: Object = run(r#"
+        (guard (e ((error-object? e) (error-object-message e)))
+          (with-exception-handler
+            (lambda (e) 0)
+            (lambda () (raise (quote oops)))))
+    "#)?;
+    assert_eq!(
+        result,
+        Object::string("handler returned from non-continuable raise")
+    );
+
+    // Handlers may escape to an outer guard
+    let result: Object = run(r#"
+        (guard (e (t e))
+          (with-exception-handler
+            (lambda (e) (raise (list (quote wrapped) e)))
+            (lambda () (raise (quote oops)))))
+    "#)?;
+    assert_eq!(
+        result,
+        Object::list(vec![Object::symbol("wrapped"), Object::symbol("oops")])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn error_objects_carry_spans() -> Result<(), Box<dyn std::error::Error>> {
+    let program: &str =
+        "(define (f) (error \"inside\"))\n(guard (e (t (error-object-span e))) (f))";
+    let result: Object = run(program)?;
+    assert_eq!(
+        result,
+        Object::list(vec![Object::Number(12), Object::Number(28)])
+    );
+
+    let error = run("(define x 1)\n(car x)").unwrap_err();
+    match error.downcast_ref::<Unwind>() {
+        Some(Unwind::Raise(Object::Condition(v))) => {
+            assert_eq!(v.kind, ConditionKind::Native);
+            assert_eq!(v.message, "car: expected list, got number");
+            assert_eq!(v.span, Some(Span::new(13, 20)));
+        }
+        _ => panic!("expected an error object"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn native_errors_are_catchable() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_native("read-setting", Arity::exact(1), "A setting.", |_, _| {
+        Err("setting not found".into())
+    });
+
+    let result: Object = interpreter.eval_str(
+        r#"(guard (e ((error-object? e) (error-object-message e))) (read-setting "theme"))"#,
+    )?;
+    assert_eq!(result, Object::string("read-setting: setting not found"));
+
+    let result: Object =
+        interpreter.eval_str("(guard (e (t (error-object-irritants e))) (undefined-word))")?;
+    assert_eq!(result, Object::list(vec![Object::symbol("undefined-word")]));
+
+    let result: Object =
+        interpreter.eval_str("(guard (e (t (error-object-message e))) (car 1 2))")?;
+    assert_eq!(result, Object::string("car: expected 1 arguments, got 2"));
+
+    Ok(())
+}
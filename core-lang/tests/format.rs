@@ -0,0 +1,87 @@
+use core_lang::formatter::{FormatOptions, format};
+use core_lang::parser::parse;
+use core_lang::token::Token;
+use std::path::PathBuf;
+
+#[test]
+fn golden() -> Result<(), Box<dyn std::error::Error>> {
+    let options: FormatOptions = FormatOptions::default();
+
+    for entry in std::fs::read_dir("./tests/format-files/input")? {
+        let input: PathBuf = entry?.path();
+        let expected: PathBuf = PathBuf::from("./tests/format-files/expected")
+            .join(input.file_name().ok_or("no file name")?);
+
+        let formatted: String = format(&std::fs::read_to_string(&input)?, &options)?;
+        assert_eq!(
+            formatted,
+            std::fs::read_to_string(&expected)?,
+            "{}",
+            input.display()
+        );
+        assert_eq!(format(&formatted, &options)?, formatted);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn preserves_meaning() -> Result<(), Box<dyn std::error::Error>> {
+    for directory in ["./tests/syntax-files", "./tests/format-files/input"] {
+        for entry in std::fs::read_dir(directory)? {
+            let data: String = std::fs::read_to_string(entry?.path())?;
+
+            for max_width in [10, 40, 80] {
+                let options = FormatOptions {
+                    max_width,
+                    ..FormatOptions::default()
+                };
+                let formatted: String = format(&data, &options)?;
+                let before: Vec<Token> = parse(&data)?;
+                let after: Vec<Token> = parse(&formatted)?;
+                assert_eq!(before, after);
+                assert_eq!(format(&formatted, &options)?, formatted);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn max_width() -> Result<(), Box<dyn std::error::Error>> {
+    let text: &str = "(define (add a b) (+ a b))";
+    assert_eq!(
+        format(text, &FormatOptions::default())?,
+        "(define (add a b) (+ a b))\n"
+    );
+
+    let options = FormatOptions {
+        max_width: 20,
+        ..FormatOptions::default()
+    };
+    assert_eq!(format(text, &options)?, "(define (add a b)\n  (+ a b))\n");
+
+    let options = FormatOptions {
+        max_width: 20,
+        indent: 4,
+    };
+    assert_eq!(format(text, &options)?, "(define (add a b)\n    (+ a b))\n");
+
+    Ok(())
+}
+
+#[test]
+fn rejects_malformed_input() {
+    let error = format("(define x\n  (foo)", &FormatOptions::default()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "PARSE_ERROR: unclosed parenthesis at 1:1"
+    );
+    // Whitespace the grammar does not allow between forms
+    let error = format("(define a\u{a0}1)", &FormatOptions::default()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "PARSE_ERROR: unexpected character at 1:10"
+    );
+}
@@ -0,0 +1,122 @@
+use core_lang::help::{self, Help, HelpKind};
+use core_lang::interpreter::Interpreter;
+use core_lang::object::{Arity, Object};
+use core_lang::syntax::Source;
+
+const INIT: &str = r#"(define (square x)
+  "Multiplies x by itself.
+
+  Only numbers are accepted."
+  (* x x))
+
+(define cube (lambda (x) "Multiplies x by itself twice." (* x (square x))))
+
+(define (greeting) "hello")
+
+(define answer 42)
+"#;
+
+fn interpreter() -> Result<Interpreter, Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_source(Source::new("init.core", INIT))?;
+
+    Ok(interpreter)
+}
+
+#[test]
+fn docstrings() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter()?;
+
+    let help: Help = help::describe(&interpreter, "square").ok_or("square is undefined")?;
+    assert_eq!(
+        help.to_string(),
+        "(square x)\n  procedure defined at init.core:1:1\n\n  Multiplies x by itself.\n\n  Only numbers are accepted."
+    );
+    assert_eq!(help.summary(), Some("Multiplies x by itself."));
+
+    // Naming a lambda after its variable keeps the documentation
+    let help: Help = help::describe(&interpreter, "cube").ok_or("cube is undefined")?;
+    assert_eq!(help.doc.as_deref(), Some("Multiplies x by itself twice."));
+    assert_eq!(help.location.as_deref(), Some("init.core:7:14"));
+
+    // A string which is the whole body is the value, not documentation
+    let help: Help = help::describe(&interpreter, "greeting").ok_or("greeting is undefined")?;
+    assert_eq!(help.doc, None);
+    assert_eq!(interpreter.eval_str("(greeting)")?, Object::string("hello"));
+    assert_eq!(interpreter.eval_str("(square 3)")?, Object::Number(9));
+
+    let help: Help = help::describe(&interpreter, "answer").ok_or("answer is undefined")?;
+    assert_eq!(help.kind, HelpKind::Variable);
+    assert_eq!(help::describe(&interpreter, "undefined-word"), None);
+
+    Ok(())
+}
+
+#[test]
+fn native_docs() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+
+    let help: Help = help::describe(&interpreter, "car").ok_or("car is undefined")?;
+    assert_eq!(
+        help.to_string(),
+        "car\n  built-in procedure taking 1 argument\n\n  The first element of a list."
+    );
+
+    let help: Help = help::describe(&interpreter, "define").ok_or("define is undefined")?;
+    assert_eq!(help.kind, HelpKind::SpecialForm);
+    assert!(help.doc.is_some());
+
+    // Every standard procedure is documented
+    for help in help::apropos(&interpreter, "") {
+        if matches!(help.kind, HelpKind::Native(_)) {
+            assert!(help.doc.is_some(), "{} has no documentation", help.name);
+        }
+    }
+
+    interpreter.define_native(
+        "read-setting",
+        Arity::exact(1),
+        "The value of a setting.",
+        |_, _| Ok(Object::nil()),
+    );
+    let help: Help = help::describe(&interpreter, "read-setting").ok_or("undefined")?;
+    assert_eq!(help.doc.as_deref(), Some("The value of a setting."));
+
+    Ok(())
+}
+
+#[test]
+fn apropos() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter()?;
+
+    let names: Vec<String> = help::apropos(&interpreter, "error-object")
+        .into_iter()
+        .map(|v| v.name)
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "error-object-irritants",
+            "error-object-message",
+            "error-object-span",
+            "error-object?"
+        ]
+    );
+
+    let result: Object = interpreter.eval_str(r#"(apropos "squ")"#)?;
+    assert_eq!(result, Object::list(vec![Object::symbol("square")]));
+
+    let result: Object = interpreter.eval_str("(describe (quote cube))")?;
+    assert_eq!(
+        result,
+        Object::string(
+            "(cube x)\n  procedure defined at init.core:7:14\n\n  Multiplies x by itself twice."
+        )
+    );
+
+    let result: Object =
+        interpreter.eval_str("(guard (e (t (error-object-message e))) (describe \"nothing\"))")?;
+    assert_eq!(result, Object::string("describe: unbound variable nothing"));
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use core_lang::interpreter::Interpreter;
+use core_lang::object::Object;
+
+fn run(program: &str) -> Result<Object, Box<dyn std::error::Error>> {
+    Interpreter::new().eval_str(program)
+}
+
+#[test]
+fn literals_and_variables() -> Result<(), Box<dyn std::error::Error>> {
+    let program: &str = r#"
+(define (describe-key key)
+  (match key
+    ("q" "quit")
+    (1 "one")
+    (t "true")
+    ((quote save) "save")
+    ('(1 2) "one two")
+    (other (string-append "unknown " other))))
+(list (describe-key "q") (describe-key 1) (describe-key t) (describe-key (quote save))
+      (describe-key (list 1 2)) (describe-key "x"))
+"#;
+    assert_eq!(
+        run(program)?,
+        Object::list(
+            ["quit", "one", "true", "save", "one two", "unknown x"]
+                .into_iter()
+                .map(Object::string)
+                .collect()
+        )
+    );
+
+    assert_eq!(run("(match 5 (_ 1))")?, Object::Number(1));
+    assert_eq!(run("(match nil (t 1) (nil 2))")?, Object::Number(2));
+
+    Ok(())
+}
+
+#[test]
+fn lists() -> Result<(), Box<dyn std::error::Error>> {
+    let program: &str = r#"
+(define (dispatch command)
+  (match command
+    ((list) 0)
+    ((list (quote move) x y) (+ x y))
+    ((list (quote insert) . words) (length words))
+    ((list (list a b) c) (* a b c))
+    (_ (- 1))))
+(list (dispatch (list))
+      (dispatch (list (quote move) 1 2))
+      (dispatch (list (quote move) 1))
+      (dispatch (list (quote insert) "a" "b" "c"))
+      (dispatch (list (list 2 3) 4))
+      (dispatch 7))
+"#;
+    assert_eq!(
+        run(program)?,
+        Object::list(
+            [0, 3, -1, 3, 24, -1]
+                .into_iter()
+                .map(Object::Number)
+                .collect()
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn predicates_and_guards() -> Result<(), Box<dyn std::error::Error>> {
+    let program: &str = r#"
+(define (sign value)
+  (match value
+    ((? number? n) (guard (equal? n 0)) "zero")
+    ((? number?) "number")
+    ((? string? (? (lambda (s) (equal? s "")))) "empty")
+    (_ "other")))
+(list (sign 0) (sign 2) (sign "") (sign "x"))
+"#;
+    assert_eq!(
+        run(program)?,
+        Object::list(
+            ["zero", "number", "empty", "other"]
+                .into_iter()
+                .map(Object::string)
+                .collect()
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn errors() {
+    let result = run(
+        "(guard (e (t (list (error-object-message e) (error-object-irritants e)))) (match 3 (1 1)))",
+    );
+    assert_eq!(
+        result.ok(),
+        Some(Object::list(vec![
+            Object::string("match: no clause matched"),
+            Object::list(vec![Object::Number(3)])
+        ]))
+    );
+
+    assert!(run("(match 3 ((vector x) x))").is_err());
+    assert!(run("(match)").is_err());
+}
@@ -0,0 +1,64 @@
+use core_lang::interpreter::Interpreter;
+use core_lang::profiler::{ProcedureStats, Profiler};
+use core_lang::syntax::Source;
+
+const PROGRAM: &str = "(define (len l) (if (null? l) 0 (+ 1 (len (cdr l)))))
+(define (run)
+  (map (lambda (v) (list v v)) (list 1 2 3))
+  (len (list 1 2 3)))
+(run)";
+
+fn profile() -> Result<Profiler, Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.attach_profiler(Profiler::new());
+    interpreter.eval_source(Source::new("init.core", PROGRAM))?;
+
+    Ok(interpreter.detach_profiler().unwrap())
+}
+
+#[test]
+fn stats() -> Result<(), Box<dyn std::error::Error>> {
+    let profiler: Profiler = profile()?;
+
+    let len: ProcedureStats = profiler.get("len").unwrap();
+    assert_eq!(len.calls, 4);
+    assert!(len.inclusive >= len.exclusive);
+
+    let lambda: ProcedureStats = profiler.get("lambda@init.core:3:8").unwrap();
+    assert_eq!(lambda.calls, 3);
+    // Each call allocates one two-element list
+    assert_eq!(lambda.allocations, 3);
+
+    let run: ProcedureStats = profiler.get("run").unwrap();
+    assert_eq!(run.calls, 1);
+    assert!(run.inclusive >= len.inclusive);
+    assert_eq!(profiler.stats().len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn folded_stacks() -> Result<(), Box<dyn std::error::Error>> {
+    let profiler: Profiler = profile()?;
+
+    let mut output: Vec<u8> = Vec::new();
+    profiler.write_folded(&mut output)?;
+    let stacks: Vec<String> = String::from_utf8(output)?
+        .lines()
+        .map(|v| v.rsplit_once(' ').unwrap().0.to_string())
+        .collect();
+
+    assert_eq!(
+        stacks,
+        vec![
+            "run",
+            "run;lambda@init.core:3:8",
+            "run;len",
+            "run;len;len",
+            "run;len;len;len",
+            "run;len;len;len;len",
+        ]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use core_lang::ast::{AST, Boolean, Constant, Value};
+use core_lang::cst::Tree;
+use core_lang::formatter::{self, FormatOptions};
+use core_lang::syntax::Span;
+use core_lang::{evaluator, parser};
+use proptest::prelude::*;
+
+/// Words which the parser reads as something other than [`Value::Word`].
+const RESERVED: [&str; 4] = ["main", "cli", "t", "nil"];
+
+/// Mostly plain words, and some which have to be written between bars.
+fn word() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => "[a-zλ<=>:][a-z0-9_+*/!?.<=>:-]{0,8}",
+        1 => "\\PC{0,8}",
+    ]
+    .prop_filter("reserved word", |v| !RESERVED.contains(&v.as_str()))
+}
+
+/// Whether `value` holds a string with a `"`, which cannot be written in source text.
+fn has_quote(value: &Value) -> bool {
+    match value {
+        Value::String(v) => v.contains('"'),
+        Value::SExpression(v) | Value::List(v) => v.iter().any(has_quote),
+        _ => false,
+    }
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        any::<u64>().prop_map(Value::Number),
+        "[a-zA-Z0-9 ();'\"-]{0,12}".prop_map(Value::String),
+        word().prop_map(Value::Word),
+        Just(Value::Boolean(Boolean::T)),
+        Just(Value::Boolean(Boolean::Nil)),
+    ];
+
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::List),
+            (word(), prop::collection::vec(inner, 0..6)).prop_map(|(head, mut args)| {
+                args.insert(0, Value::Word(head));
+                Value::SExpression(args)
+            }),
+        ]
+    })
+}
+
+/// Printable-ish text which leans towards the characters the grammar cares about.
+fn source() -> impl Strategy<Value = String> {
+    prop::collection::vec(
+        prop_oneof![
+            Just("(".to_string()),
+            Just(")".to_string()),
+            Just("'(".to_string()),
+            Just("\"".to_string()),
+            Just("|".to_string()),
+            Just(";".to_string()),
+            Just("\n".to_string()),
+            Just(" ".to_string()),
+            Just("define".to_string()),
+            "[a-z0-9]{1,4}",
+            any::<char>().prop_map(String::from),
+        ],
+        0..64,
+    )
+    .prop_map(|v| v.concat())
+}
+
+/// Short lists with characters which cannot start a token, for editing.
+fn edited_source() -> impl Strategy<Value = String> {
+    prop::collection::vec(
+        prop_oneof![
+            Just("(".to_string()),
+            Just(")".to_string()),
+            Just(" ".to_string()),
+            Just("\u{1}".to_string()),
+            "[a-z]{1,2}",
+        ],
+        0..8,
+    )
+    .prop_map(|v| v.concat())
+}
+
+proptest! {
+    #[test]
+    fn printed_values_parse_back(name in word(), value in value()) {
+        let constant: Constant = Constant {
+            name: Value::Word(name),
+            value,
+        };
+        let text: String = constant.to_string();
+
+        let result = evaluator::eval(&text);
+        if has_quote(&constant.value) {
+            // Such a string cannot be written, so it never reads back
+            prop_assert!(result.map_or(true, |v| v.0 != vec![constant]));
+        } else {
+            let ast: AST = result.map_err(|e| TestCaseError::fail(e.to_string()))?;
+            prop_assert_eq!(ast.0, vec![constant]);
+        }
+    }
+
+    #[test]
+    fn parser_does_not_panic(text in source()) {
+        let _ = parser::parse(&text);
+        let _ = parser::parse_spanned(&text);
+        let _ = evaluator::eval(&text);
+    }
+
+    #[test]
+    fn concrete_tree_is_lossless(text in source()) {
+        prop_assert_eq!(Tree::parse(&text).print(), text);
+    }
+
+    #[test]
+    fn incremental_edits_match_a_full_parse(
+        text in edited_source(),
+        start in any::<prop::sample::Index>(),
+        len in 0..3usize,
+        replacement in prop_oneof![3 => "[a-z ]{0,2}", 1 => "[()\u{1}]"],
+    ) {
+        let boundaries: Vec<usize> = (0..=text.len()).filter(|v| text.is_char_boundary(*v)).collect();
+        let start: usize = start.index(boundaries.len());
+        let end: usize = boundaries[(start + len).min(boundaries.len() - 1)];
+        let range: Span = Span::new(boundaries[start], end);
+
+        let mut tree: Tree = Tree::parse(&text);
+        tree.edit(range, &replacement);
+        let mut edited: String = text.clone();
+        edited.replace_range(range.start..range.end, &replacement);
+        prop_assert_eq!(tree, Tree::parse(&edited));
+    }
+
+    #[test]
+    fn formatting_is_idempotent(name in word(), value in value()) {
+        prop_assume!(!has_quote(&value));
+        let options: FormatOptions = FormatOptions {
+            max_width: 20,
+            ..FormatOptions::default()
+        };
+        let text: String = Constant {
+            name: Value::Word(name),
+            value,
+        }
+        .to_string();
+
+        let once: String = formatter::format(&text, &options).map_err(|e| TestCaseError::fail(e.to_string()))?;
+        let twice: String = formatter::format(&once, &options).map_err(|e| TestCaseError::fail(e.to_string()))?;
+        prop_assert_eq!(once, twice);
+    }
+}
@@ -0,0 +1,167 @@
+use core_lang::convert::FromObject;
+use core_lang::interpreter::Interpreter;
+use core_lang::object::Object;
+
+const KEYMAP: &str = r#"
+(define-record-type binding
+  (make-binding key command)
+  binding?
+  (key binding-key)
+  (command binding-command set-binding-command!)
+  (description binding-description set-binding-description!))
+"#;
+
+fn interpreter() -> Result<Interpreter, Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(KEYMAP)?;
+
+    Ok(interpreter)
+}
+
+#[test]
+fn procedures() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter()?;
+
+    let program: &str = r#"
+(define save (make-binding "C-s" (quote save)))
+(set-binding-description! save "Save the buffer")
+(list (binding? save) (binding? 1) (binding-key save) (binding-command save) (binding-description save))
+"#;
+    assert_eq!(
+        interpreter.eval_str(program)?,
+        Object::list(vec![
+            Object::Boolean(true),
+            Object::nil(),
+            Object::string("C-s"),
+            Object::symbol("save"),
+            Object::string("Save the buffer"),
+        ])
+    );
+
+    let record: Object = interpreter.eval_str("(begin save)")?;
+    assert_eq!(
+        record.to_string(),
+        "#<binding key: \"C-s\" command: save description: \"Save the buffer\">"
+    );
+    assert_eq!(
+        interpreter.eval_str("(equal? save save)")?,
+        Object::Boolean(true)
+    );
+    assert_eq!(
+        interpreter.eval_str("(equal? save (make-binding \"C-s\" (quote save)))")?,
+        Object::nil()
+    );
+
+    // Another type with the same layout is still a different type
+    let program: &str = r#"
+(define-record-type other (make-other key command) other? (key other-key) command)
+(guard (e (t (error-object-message e))) (binding-key (make-other 1 2)))
+"#;
+    assert_eq!(
+        interpreter.eval_str(program)?,
+        Object::string("binding-key: expected binding, got record")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cycles() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+
+    let program: &str = r#"
+(define-record-type p (make-p x) p? (x p-x set-p-x!))
+(define r (make-p 1))
+(set-p-x! r r)
+(p-x r)
+"#;
+    let record: Object = interpreter.eval_str(program)?;
+    assert_eq!(record.to_string(), "#<p x: #<p ...>>");
+
+    let record: Object = interpreter.eval_str("(make-p (list 1 (make-p 2)))")?;
+    assert_eq!(record.to_string(), "#<p x: '(1 #<p ...>)>");
+
+    Ok(())
+}
+
+#[test]
+fn malformed() {
+    let errors: [&str; 4] = [
+        "(define-record-type point)",
+        "(define-record-type point (make-point x) point?)",
+        "(define-record-type point make-point point? (x point-x) (x point-y))",
+        "(define-record-type point make-point point? (x point-x set-point-x! extra))",
+    ];
+    for program in errors {
+        assert!(Interpreter::new().eval_str(program).is_err(), "{}", program);
+    }
+
+    // Without a constructor there is nothing to call, but the rest is defined
+    let result = Interpreter::new().eval_str("(define-record-type point nil point? x) (point? 1)");
+    assert_eq!(result.ok(), Some(Object::nil()));
+}
+
+struct Binding {
+    key: String,
+    command: String,
+    description: Option<String>,
+}
+
+impl FromObject for Binding {
+    fn from_object(object: &Object) -> Result<Self, Box<dyn std::error::Error>> {
+        let record = object.as_record("binding")?;
+        Ok(Binding {
+            key: record.field("key")?,
+            command: record.field("command")?,
+            description: record.field("description")?,
+        })
+    }
+}
+
+#[test]
+fn conversion() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = interpreter()?;
+
+    let bindings: Vec<Binding> = interpreter
+        .eval_str(
+            r#"
+(define quit (make-binding "C-q" (quote quit)))
+(set-binding-description! quit nil)
+(list quit)
+"#,
+        )?
+        .convert()?;
+    let bindings: Vec<(&str, &str, Option<&str>)> = bindings
+        .iter()
+        .map(|v| (v.key.as_str(), v.command.as_str(), v.description.as_deref()))
+        .collect();
+    assert_eq!(bindings, vec![("C-q", "quit", None)]);
+
+    // Fields left out of the constructor are unspecified, which is not nil
+    let result = interpreter
+        .eval_str("(make-binding \"C-s\" \"save\")")?
+        .convert::<Binding>();
+    assert_eq!(
+        result.err().map(|e| e.to_string()),
+        Some(String::from(
+            "binding field description: expected string, got unspecified"
+        ))
+    );
+
+    let result = interpreter
+        .eval_str("(make-binding 1 2)")?
+        .convert::<Binding>();
+    assert_eq!(
+        result.err().map(|e| e.to_string()),
+        Some(String::from(
+            "binding field key: expected string, got number"
+        ))
+    );
+    let result = Object::Number(1).convert::<Binding>();
+    assert_eq!(
+        result.err().map(|e| e.to_string()),
+        Some(String::from("expected binding, got number"))
+    );
+
+    Ok(())
+}
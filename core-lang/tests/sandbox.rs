@@ -0,0 +1,142 @@
+use core_lang::interpreter::{Interpreter, Unwind};
+use core_lang::object::Object;
+use core_lang::sandbox::{Capability, Limit, Sandbox};
+use std::time::Duration;
+
+fn limit(error: Box<dyn std::error::Error>) -> Option<Limit> {
+    match error.downcast_ref::<Unwind>() {
+        Some(Unwind::Limit(v)) => Some(*v),
+        _ => None,
+    }
+}
+
+const LOOP: &str = "(define (loop n) (if t (loop (+ n 1)) n))";
+
+#[test]
+fn fuel() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_sandbox(Sandbox {
+        fuel: Some(500),
+        max_depth: None,
+        ..Sandbox::unrestricted()
+    });
+
+    let result: Object = interpreter.eval_str("(+ 1 2)")?;
+    assert_eq!(result, Object::Number(3));
+
+    let error = interpreter
+        .eval_str(&format!("{} (loop 0)", LOOP))
+        .unwrap_err();
+    assert_eq!(limit(error), Some(Limit::Fuel));
+
+    // The budget is counted per evaluation
+    let result: Object = interpreter.eval_str("(+ 1 2)")?;
+    assert_eq!(result, Object::Number(3));
+
+    Ok(())
+}
+
+#[test]
+fn limits_cannot_be_caught() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_sandbox(Sandbox {
+        fuel: Some(1000),
+        ..Sandbox::unrestricted()
+    });
+
+    let program: String = format!("{} (guard (e (t 0)) (loop 0))", LOOP);
+    let error = interpreter.eval_str(&program).unwrap_err();
+    assert!(limit(error).is_some());
+}
+
+#[test]
+fn depth() {
+    let mut interpreter = Interpreter::new();
+    let program: &str = "(define (deep n) (+ 1 (deep n))) (deep 0)";
+
+    let error = interpreter.eval_str(program).unwrap_err();
+    assert_eq!(limit(error), Some(Limit::Depth));
+}
+
+#[test]
+fn heap() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_sandbox(Sandbox {
+        max_heap: Some(4096),
+        ..Sandbox::unrestricted()
+    });
+
+    let program: &str = r#"
+        (define (grow s) (grow (string-append s s)))
+        (grow "0123456789")
+    "#;
+    let error = interpreter.eval_str(program).unwrap_err();
+    assert_eq!(limit(error), Some(Limit::Heap));
+}
+
+#[test]
+fn timeout() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_sandbox(Sandbox {
+        timeout: Some(Duration::from_millis(50)),
+        max_depth: None,
+        ..Sandbox::unrestricted()
+    });
+
+    let error = interpreter
+        .eval_str(&format!("{} (loop 0)", LOOP))
+        .unwrap_err();
+    assert_eq!(limit(error), Some(Limit::Timeout));
+}
+
+#[test]
+fn host_calls_get_their_own_budget() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_sandbox(Sandbox {
+        timeout: Some(Duration::from_millis(50)),
+        max_depth: None,
+        ..Sandbox::restricted()
+    });
+
+    // Long enough for the clock to be checked
+    interpreter.eval_str("(define (count n) (if (eq? n 0) 0 (count (- n 1))))")?;
+    let count: Object = interpreter
+        .global()
+        .get("count")
+        .ok_or("count is not defined")?;
+    std::thread::sleep(Duration::from_millis(100));
+
+    let result: Object = interpreter.apply(&count, vec![Object::Number(1000)])?;
+    assert_eq!(result, Object::Number(0));
+
+    Ok(())
+}
+
+#[test]
+fn capabilities() -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_sandbox(Sandbox {
+        capabilities: vec![Capability::Filesystem],
+        ..Sandbox::restricted()
+    });
+
+    let result: Object = interpreter.eval_str(r#"(file-exists? "./tests/sandbox.rs")"#)?;
+    assert_eq!(result, Object::Boolean(true));
+
+    let result: Object = interpreter.eval_str(
+        r#"(guard (e ((error-object? e) (error-object-message e))) (run-process "true"))"#,
+    )?;
+    assert_eq!(
+        result,
+        Object::string("run-process: permission denied, requires process")
+    );
+
+    interpreter.set_sandbox(Sandbox::restricted());
+    assert!(
+        interpreter
+            .eval_str(r#"(read-file "./tests/sandbox.rs")"#)
+            .is_err()
+    );
+
+    Ok(())
+}
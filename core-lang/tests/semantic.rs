@@ -1,15 +1,51 @@
-use core_lang::ast::AST;
+mod snapshot;
+
+use core_lang::ast::{AST, Value};
 use core_lang::evaluator;
-use std::path::PathBuf;
+use snapshot::{Snapshots, fixtures};
 
 #[test]
 fn test() -> Result<(), Box<dyn std::error::Error>> {
-    for entry in std::fs::read_dir("./tests/semantic-files")? {
-        let path: PathBuf = entry?.path();
-        let data: String = std::fs::read_to_string(path).unwrap_or_default();
+    let mut snapshots = Snapshots::new("semantic");
 
-        let _result: AST = evaluator::eval(&data)?;
+    for (name, data) in fixtures("./tests/semantic-files")? {
+        match evaluator::eval(&data) {
+            Ok(result) => snapshots.check(&name, &format!("{:#?}\n", result))?,
+            Err(e) => snapshots.fail(&name, &format!("failed to evaluate: {}", e)),
+        }
     }
 
+    snapshots.finish()
+}
+
+#[test]
+fn errors() -> Result<(), Box<dyn std::error::Error>> {
+    let mut snapshots = Snapshots::new("semantic-errors");
+
+    for (name, data) in fixtures("./tests/semantic-errors")? {
+        match evaluator::eval(&data) {
+            Ok(result) => snapshots.fail(&name, &format!("evaluated unexpectedly: {:?}", result)),
+            Err(e) => snapshots.check(&name, &format!("{}\n", e))?,
+        }
+    }
+
+    snapshots.finish()
+}
+
+#[test]
+fn argument_order() -> Result<(), Box<dyn std::error::Error>> {
+    let ast: AST = evaluator::eval("(define x (f 1 \"two\" (g 3 4)))")?;
+    let value: Value = Value::SExpression(vec![
+        Value::Word(String::from("f")),
+        Value::Number(1),
+        Value::String(String::from("two")),
+        Value::SExpression(vec![
+            Value::Word(String::from("g")),
+            Value::Number(3),
+            Value::Number(4),
+        ]),
+    ]);
+    assert_eq!(ast.0.first().map(|v| v.value()), Some(value));
+
     Ok(())
 }
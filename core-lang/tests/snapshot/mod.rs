@@ -0,0 +1,105 @@
+//! Expected-output snapshots for the fixture tests.
+//!
+//! Each fixture `<dir>/<name>.txt` has its expected output stored in
+//! `tests/snapshots/<suite>/<name>.snap`. Run the tests with `BLESS=1` to
+//! write the current output as the new snapshots instead of comparing.
+
+use std::path::{Path, PathBuf};
+
+pub struct Snapshots {
+    directory: PathBuf,
+    bless: bool,
+    checked: Vec<String>,
+    failures: Vec<String>,
+}
+
+impl Snapshots {
+    pub fn new(suite: &str) -> Self {
+        Snapshots {
+            directory: Path::new("./tests/snapshots").join(suite),
+            bless: std::env::var_os("BLESS").is_some_and(|v| !v.is_empty() && v != "0"),
+            checked: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Compares `actual` with the snapshot called `name`.
+    pub fn check(&mut self, name: &str, actual: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path: PathBuf = self.directory.join(format!("{}.snap", name));
+        self.checked.push(name.to_string());
+
+        if self.bless {
+            std::fs::create_dir_all(&self.directory)?;
+            std::fs::write(&path, actual)?;
+            return Ok(());
+        }
+
+        match std::fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => (),
+            Ok(expected) => self.failures.push(format!(
+                "{} does not match\n--- expected\n{}\n+++ actual\n{}",
+                path.display(),
+                expected,
+                actual
+            )),
+            Err(_) => self.failures.push(format!(
+                "{} is missing, run with BLESS=1 to create it",
+                path.display()
+            )),
+        }
+
+        Ok(())
+    }
+
+    /// Records a fixture which did not behave as expected at all.
+    pub fn fail(&mut self, name: &str, message: &str) {
+        self.checked.push(name.to_string());
+        self.failures.push(format!("{}: {}", name, message));
+    }
+
+    /// Panics listing every mismatch, and snapshots left without a fixture.
+    pub fn finish(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.directory.exists() {
+            for entry in std::fs::read_dir(&self.directory)? {
+                let path: PathBuf = entry?.path();
+                let name: String = path
+                    .file_stem()
+                    .map(|v| v.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if self.checked.contains(&name) {
+                    continue;
+                }
+                match self.bless {
+                    true => std::fs::remove_file(&path)?,
+                    false => self
+                        .failures
+                        .push(format!("{} has no fixture", path.display())),
+                }
+            }
+        }
+
+        assert!(self.failures.is_empty(), "{}", self.failures.join("\n\n"));
+
+        Ok(())
+    }
+}
+
+/// The `.txt` fixtures of `directory` as `(name, text)`, sorted by name.
+pub fn fixtures(directory: &str) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let mut result: Vec<(String, String)> = Vec::new();
+
+    for entry in std::fs::read_dir(directory)? {
+        let path: PathBuf = entry?.path();
+        if path.extension().is_none_or(|v| v != "txt") {
+            continue;
+        }
+        let name: String = path
+            .file_stem()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        result.push((name, std::fs::read_to_string(&path)?));
+    }
+    result.sort();
+
+    Ok(result)
+}
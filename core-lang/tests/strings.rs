@@ -0,0 +1,89 @@
+use core_lang::interpreter::Interpreter;
+use core_lang::object::Object;
+
+fn run(program: &str) -> Result<Object, Box<dyn std::error::Error>> {
+    Interpreter::new().eval_str(program)
+}
+
+fn numbers(values: &[i64]) -> Object {
+    Object::list(values.iter().copied().map(Object::Number).collect())
+}
+
+#[test]
+fn unicode_names() -> Result<(), Box<dyn std::error::Error>> {
+    let program: &str = r#"
+(define (λ x) (* x x))
+(define (->string n) (number->string n))
+(define |tab width| 4)
+(list (λ 3) (->string 2) |tab width| (<= 1 2 2) (>= 3 1))
+"#;
+    assert_eq!(
+        run(program)?,
+        Object::list(vec![
+            Object::Number(9),
+            Object::string("2"),
+            Object::Number(4),
+            Object::Boolean(true),
+            Object::Boolean(true),
+        ])
+    );
+
+    // Symbols print so that they read back as the same word
+    assert_eq!(run("(quote |tab width|)")?.to_string(), "|tab width|");
+    assert_eq!(run("(quote 名前)")?.to_string(), "名前");
+    assert_eq!(run("(string->symbol \"12\")")?.to_string(), "|12|");
+    for (name, printed) in [("t", "|t|"), ("nil", "|nil|"), ("define", "|define|")] {
+        let program: String = format!("(string->symbol \"{}\")", name);
+        assert_eq!(run(&program)?.to_string(), printed);
+    }
+    let program: &str = "(symbol->string (quote |define|))";
+    assert_eq!(run(program)?, Object::string("define"));
+
+    Ok(())
+}
+
+#[test]
+fn lengths() -> Result<(), Box<dyn std::error::Error>> {
+    // "e" and a combining accent are two characters but one grapheme
+    let program: &str = "
+(list (string-length \"héllo\")
+      (string-length \"e\u{301}\")
+      (string-length \"🇯🇵\" (quote graphemes))
+      (string-length \"🇯🇵\")
+      (string-length \"\")
+      (length (string-graphemes \"ne\u{301}🇯🇵\")))
+";
+    assert_eq!(run(program)?, numbers(&[5, 2, 1, 2, 0, 3]));
+    assert_eq!(
+        run("(string-length \"e\u{301}\" (quote graphemes))")?,
+        Object::Number(1)
+    );
+    assert!(run("(string-length \"a\" (quote bytes))").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn operations() -> Result<(), Box<dyn std::error::Error>> {
+    let program: &str = r#"
+(list (substring "λx→y" 1 3)
+      (substring "λx→y" 2)
+      (string-upcase "straße")
+      (string-downcase "ΛΑΜΒΔΑ")
+      (symbol->string (string->symbol "名前")))
+"#;
+    assert_eq!(
+        run(program)?,
+        Object::list(
+            ["x→", "→y", "STRASSE", "λαμβδα", "名前"]
+                .into_iter()
+                .map(Object::string)
+                .collect()
+        )
+    );
+
+    assert!(run("(substring \"abc\" 2 4)").is_err());
+    assert!(run("(substring \"abc\" 2 1)").is_err());
+
+    Ok(())
+}
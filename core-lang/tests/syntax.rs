@@ -1,15 +1,36 @@
+mod snapshot;
+
 use core_lang::parser;
 use core_lang::token::Token;
-use std::path::PathBuf;
+use snapshot::{Snapshots, fixtures};
 
 #[test]
 fn test() -> Result<(), Box<dyn std::error::Error>> {
-    for entry in std::fs::read_dir("./tests/syntax-files")? {
-        let path: PathBuf = entry?.path();
-        let data: String = std::fs::read_to_string(path).unwrap_or_default();
+    let mut snapshots = Snapshots::new("syntax");
+
+    for (name, data) in fixtures("./tests/syntax-files")? {
+        match parser::parse(&data) {
+            Ok(result) => {
+                let result: Vec<Token> = result;
+                snapshots.check(&name, &format!("{:#?}\n", result))?;
+            }
+            Err(e) => snapshots.fail(&name, &format!("failed to parse: {}", e)),
+        }
+    }
+
+    snapshots.finish()
+}
+
+#[test]
+fn errors() -> Result<(), Box<dyn std::error::Error>> {
+    let mut snapshots = Snapshots::new("syntax-errors");
 
-        let _result: Vec<Token> = parser::parse(&data)?;
+    for (name, data) in fixtures("./tests/syntax-errors")? {
+        match parser::parse(&data) {
+            Ok(result) => snapshots.fail(&name, &format!("parsed unexpectedly: {:?}", result)),
+            Err(e) => snapshots.check(&name, &format!("{}\n", e))?,
+        }
     }
 
-    Ok(())
+    snapshots.finish()
 }
@@ -0,0 +1,105 @@
+use core_lang::syntax::{Source, Span};
+use core_lang::testing::{Failure, TestResult, discover, run_source};
+use std::path::{Path, PathBuf};
+
+fn run(path: &str) -> Result<(Source, Vec<TestResult>), Box<dyn std::error::Error>> {
+    let source: Source = Source::new(path, &std::fs::read_to_string(path)?);
+    let results: Vec<TestResult> = run_source(source.clone())?;
+
+    Ok((source, results))
+}
+
+#[test]
+fn passing() -> Result<(), Box<dyn std::error::Error>> {
+    let (_, results) = run("./tests/test-files/math.core")?;
+    let names: Vec<(&str, bool)> = results
+        .iter()
+        .map(|v| (v.name.as_str(), v.passed()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("square", true),
+            ("errors", true),
+            ("isolated", true),
+            ("still square", true),
+            ("set", true),
+            ("reset", true)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn failing() -> Result<(), Box<dyn std::error::Error>> {
+    let (source, results) = run("./tests/test-files/failing.core")?;
+    let failures: Vec<(String, (usize, usize))> = results
+        .into_iter()
+        .filter_map(|v| v.failure)
+        .map(|v: Failure| (v.message, source.line_col(v.span.start)))
+        .collect();
+    assert_eq!(
+        failures,
+        vec![
+            (String::from("assert-equal: expected 1, got 2"), (2, 3)),
+            (
+                String::from("assert-error: expected an error, got 2"),
+                (6, 3)
+            ),
+            (String::from("boom 1 2"), (9, 3)),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn limits_fail_the_test() -> Result<(), Box<dyn std::error::Error>> {
+    let program: &str = r#"
+(define (deep n) (+ 1 (deep n)))
+(define-test "deep" (assert-error (deep 0)))
+"#;
+    let results: Vec<TestResult> = run_source(Source::new("deep.core", program))?;
+    assert_eq!(results[0].span, Span::new(34, 78));
+    assert_eq!(
+        results[0].failure.as_ref().map(|v| v.message.as_str()),
+        Some("RUNTIME_ERROR: evaluation exceeded the maximum recursion depth")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn discovery() -> Result<(), Box<dyn std::error::Error>> {
+    let files: Vec<PathBuf> = discover(Path::new("./tests/test-files"))?;
+    assert_eq!(
+        files,
+        vec![
+            PathBuf::from("./tests/test-files/failing.core"),
+            PathBuf::from("./tests/test-files/math.core"),
+        ]
+    );
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn discovery_skips_linked_directories() -> Result<(), Box<dyn std::error::Error>> {
+    let directory: PathBuf =
+        std::env::temp_dir().join(format!("core-lang-discover-{}", std::process::id()));
+    if directory.exists() {
+        std::fs::remove_dir_all(&directory)?;
+    }
+    std::fs::create_dir_all(directory.join("sub"))?;
+    std::fs::write(directory.join("sub/a.core"), "")?;
+    // A link back to the top makes a cycle
+    std::os::unix::fs::symlink(&directory, directory.join("sub/loop"))?;
+
+    let files: Vec<PathBuf> = discover(&directory)?;
+    assert_eq!(files, vec![directory.join("sub/a.core")]);
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
@@ -1,23 +1,26 @@
-use core_lang::ast::AST;
-use core_lang::evaluator;
+use core_lang::syntax::Source;
 use directories::ProjectDirs;
 use std::path::PathBuf;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let ast = load_config()?;
-    let path = core_cli::cli(&ast)?;
-    core_editor::editor(ast, path)?;
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = core_cli::subcommand(&args)? {
+        std::process::exit(code);
+    }
 
-    Ok(())
-}
-
-fn load_config() -> Result<AST, Box<dyn std::error::Error>> {
     let proj_dirs = ProjectDirs::from("dev", "haruki7049", "Core")
         .ok_or("CONFIG_LOAD_ERROR: Failed to create Project Directories")?;
+    let config: Source = load_config(&proj_dirs);
+    let paths: Vec<PathBuf> = core_cli::cli(&config)?;
+    core_editor::editor(config, paths, PathBuf::from(proj_dirs.data_dir()))?;
+
+    Ok(())
+}
 
+/// Reads `init.core`, which is run by the editor.
+fn load_config(proj_dirs: &ProjectDirs) -> Source {
     let config_path: PathBuf = PathBuf::from(proj_dirs.config_dir()).join("init.core");
     let config: String = std::fs::read_to_string(&config_path).unwrap_or_default();
-    let result: AST = evaluator::eval(&config)?;
 
-    Ok(result)
+    Source::new("init.core", &config)
 }
@@ -0,0 +1,73 @@
+use core_editor::backend::Event;
+use core_editor::buffer::Buffer;
+use core_editor::editor::Editor;
+use core_editor::key::parse_keys;
+use core_lang::syntax::Source;
+use std::path::PathBuf;
+
+/// Loads `config` the way `main` does.
+fn start(config: &str) -> Result<Editor, Box<dyn std::error::Error>> {
+    let config: Source = Source::new("init.core", config);
+    let paths: Vec<PathBuf> = core_cli::cli(&config)?;
+    assert_eq!(paths, Vec::<PathBuf>::new());
+
+    let mut editor = Editor::new(Buffer::from_text("hello\n"));
+    editor.load_config(config)?;
+
+    Ok(editor)
+}
+
+const CONFIG: &str = r#"
+(define cli (lambda () (enable nil)))
+(define (greeting) "hi ")
+(bind-key "C-c h" (lambda () (insert (greeting))))
+(set-editing-style "modeless")
+"#;
+
+#[test]
+fn bind_key() -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = start(CONFIG)?;
+
+    for key in parse_keys("C-c h")? {
+        editor.handle(&Event::Key(key));
+    }
+    assert_eq!(editor.buffer().text(), "hi hello\n");
+
+    Ok(())
+}
+
+#[test]
+fn cli_errors() {
+    for config in ["(define cli 1)", "(define cli (lambda () (enable 1)))"] {
+        let config: Source = Source::new("init.core", config);
+        let e = core_cli::cli(&config).err().ok_or("no error");
+        assert!(
+            e.as_ref()
+                .is_ok_and(|v| v.to_string().starts_with("CONFIG_LOAD_ERROR:")),
+            "{:?}",
+            e.map(|v| v.to_string())
+        );
+    }
+
+    // Syntax errors are left to the editor
+    let config: Source = Source::new("init.core", "(bind-key");
+    assert!(core_cli::cli(&config).is_ok_and(|v| v.is_empty()));
+}
+
+#[test]
+fn check() -> Result<(), Box<dyn std::error::Error>> {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("core-config-{}.core", std::process::id()));
+    std::fs::write(&path, CONFIG)?;
+
+    let args: Vec<String> = vec![
+        String::from("core"),
+        String::from("check"),
+        path.display().to_string(),
+    ];
+    let code: Option<i32> = core_cli::subcommand(&args)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(code, Some(0));
+
+    Ok(())
+}